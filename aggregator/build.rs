@@ -0,0 +1,21 @@
+use std::process::Command;
+
+fn main() {
+    // Embed the commit the binary was built from, falling back to "unknown" outside a git checkout
+    let git_sha = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|sha| sha.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=GIT_SHA={}", git_sha);
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs/heads");
+
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/aggregator.proto")
+        .expect("Failed to compile proto/aggregator.proto");
+}
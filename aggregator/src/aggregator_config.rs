@@ -1,25 +1,885 @@
-use alloy::signers::local::PrivateKeySigner;
+use crate::{consensus::ConsensusKind, AggregatorError};
+use alloy::{eips::BlockNumberOrTag, signers::local::PrivateKeySigner};
+use contract_bindings::{response_signing_bytes, Chain, ContractAddresses, ResultTypes, TaskId};
 use dotenv::dotenv;
+use serde::Deserialize;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Default ceiling for `maxFeePerGas` on aggregator transactions (200 gwei).
+const DEFAULT_MAX_FEE_PER_GAS: u128 = 200_000_000_000;
+/// Default ceiling for `maxPriorityFeePerGas` on aggregator transactions (10 gwei).
+const DEFAULT_MAX_PRIORITY_FEE_PER_GAS: u128 = 10_000_000_000;
+/// Default location of the aggregator's persisted state.
+const DEFAULT_TASK_STORE_PATH: &str = "aggregator_store.json";
+/// Default number of tasks whose status is kept in memory.
+const DEFAULT_MAX_TASKS_IN_MEMORY: usize = 10_000;
+/// Default interval (in seconds) between two refreshes of the operator list.
+const DEFAULT_OPERATOR_REFRESH_INTERVAL_SECS: u64 = 60;
+/// Default time (in seconds) responses are still collected once every expected one arrived.
+const DEFAULT_RESPONSE_GRACE_PERIOD_SECS: u64 = 0;
+/// Default time (in seconds) a task waits for its operators before being aggregated anyway
+/// (disabled).
+const DEFAULT_TASK_TIMEOUT_SECS: u64 = 0;
+/// Default maximum number of task results submitted in one transaction (no batching).
+const DEFAULT_RESULT_BATCH_SIZE: usize = 1;
+/// Default time (in milliseconds) finalized task results are gathered into a batch.
+const DEFAULT_RESULT_BATCH_WINDOW_MS: u64 = 2_000;
+/// Default maximum size of a request body accepted by the server (64 KiB).
+const DEFAULT_MAX_REQUEST_BODY_SIZE: usize = 64 * 1024;
+/// Default maximum length of an operator result (16 KiB).
+const DEFAULT_MAX_RESULT_SIZE: usize = 16 * 1024;
+/// Default maximum number of task ids in a batch task status request.
+const DEFAULT_MAX_TASK_STATUS_BATCH_SIZE: usize = 100;
+/// Default clock skew (in seconds) tolerated between an operator and the aggregator.
+const DEFAULT_MAX_CLOCK_SKEW_SECS: u64 = 30;
+/// Default oldest response signature scheme accepted, the one covering task ids.
+const DEFAULT_MIN_RESPONSE_VERSION: u8 = 1;
+/// Default number of operators recovered from response signatures kept in cache.
+const DEFAULT_RECOVERY_CACHE_SIZE: usize = 1024;
+/// Default number of blocks covered by a single historical log query.
+const DEFAULT_LOG_QUERY_BLOCK_RANGE: u64 = 10_000;
+/// Key the aggregator signs with when no keystore is configured, only accepted on Anvil.
+const DEV_PRIVATE_KEY: &str = "6e7912cf57b1cd9df1b05712e92a082c8c06511f62432abdaad503060822bc72";
+/// Identifier of the deployment served when none is configured.
+pub const DEFAULT_AVS_ID: &str = "giza";
+
+/// An AVS deployment served by the aggregator, read from a `[[deployments]]` entry of the
+/// configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AvsDeployment {
+    /// Identifier of the deployment, used in the server paths (`/avs/<id>/...`).
+    pub id: String,
+
+    /// The chain the deployment's contracts live on.
+    pub chain: Chain,
+
+    /// Addresses of the deployment's contracts.
+    /// - Defaults to the GizaAVS ones when omitted, and must all be given otherwise.
+    #[serde(default)]
+    pub addresses: ContractAddresses,
+
+    /// Path of the JSON file persisting the deployment's state.
+    /// - Defaults to `task_store_path` prefixed with the deployment id, or to `task_store_path`
+    ///   itself when a single deployment is configured.
+    pub task_store_path: Option<PathBuf>,
+}
+
+impl AvsDeployment {
+    /// The GizaAVS deployment on `chain`, served when no deployment is configured.
+    pub fn default_for(chain: Chain) -> Self {
+        Self {
+            id: DEFAULT_AVS_ID.to_string(),
+            chain,
+            addresses: ContractAddresses::default(),
+            task_store_path: None,
+        }
+    }
+}
+
+/// The block contract state is read at, from the most recent to the most stable.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadBlockTag {
+    /// The latest block, which may still be reorged out.
+    #[default]
+    Latest,
+    /// The latest block considered safe from reorgs by the consensus layer.
+    Safe,
+    /// The latest finalized block, which can't be reverted.
+    Finalized,
+}
+
+impl From<ReadBlockTag> for BlockNumberOrTag {
+    fn from(tag: ReadBlockTag) -> Self {
+        match tag {
+            ReadBlockTag::Latest => BlockNumberOrTag::Latest,
+            ReadBlockTag::Safe => BlockNumberOrTag::Safe,
+            ReadBlockTag::Finalized => BlockNumberOrTag::Finalized,
+        }
+    }
+}
+
+impl FromStr for ReadBlockTag {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "latest" => Ok(ReadBlockTag::Latest),
+            "safe" => Ok(ReadBlockTag::Safe),
+            "finalized" => Ok(ReadBlockTag::Finalized),
+            other => Err(format!("Unknown block tag: {:?}", other)),
+        }
+    }
+}
+
+/// Values read from a TOML configuration file. Every field is optional: missing fields fall
+/// back to the built-in defaults, and environment variables override whatever is set here.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    keystore_path: Option<PathBuf>,
+    max_fee_per_gas: Option<u128>,
+    max_priority_fee_per_gas: Option<u128>,
+    task_store_path: Option<PathBuf>,
+    max_tasks_in_memory: Option<usize>,
+    operator_refresh_interval_secs: Option<u64>,
+    response_grace_period_secs: Option<u64>,
+    task_timeout_secs: Option<u64>,
+    result_batch_size: Option<usize>,
+    result_batch_window_ms: Option<u64>,
+    consensus: Option<ConsensusKind>,
+    max_request_body_size: Option<usize>,
+    max_result_size: Option<usize>,
+    max_task_status_batch_size: Option<usize>,
+    max_clock_skew_secs: Option<u64>,
+    min_response_version: Option<u8>,
+    recovery_cache_size: Option<usize>,
+    task_confirmations: Option<u64>,
+    log_query_block_range: Option<u64>,
+    read_block_tag: Option<ReadBlockTag>,
+    grpc_port: Option<u16>,
+    tls_cert_path: Option<PathBuf>,
+    tls_key_path: Option<PathBuf>,
+    ndjson_log_path: Option<PathBuf>,
+    debug_token: Option<String>,
+    admin_token: Option<String>,
+    result_types: Option<ResultTypes>,
+    deployments: Vec<AvsDeployment>,
+}
 
 #[derive(Debug)]
 pub struct AggregatorConfig {
     /// The ECDSA signer used for cryptographic operations.
-    /// - Currently initialized with a hardcoded private key.
-    /// - In production, this should be securely loaded from an environment variable
-    ///   or a secure key management system.
+    /// - Decrypted from the JSON keystore at `keystore_path` when one is set.
+    /// - Otherwise a built-in development key, which is only accepted on Anvil.
     pub ecdsa_signer: PrivateKeySigner,
+
+    /// Path of the JSON keystore (Web3 Secret Storage) holding the aggregator's key, unlocked
+    /// with the passphrase in the `AGGREGATOR_KEYSTORE_PASSWORD` environment variable.
+    /// - Required to serve deployments on chains other than Anvil.
+    /// - Can be overridden by the `AGGREGATOR_KEYSTORE_PATH` environment variable.
+    pub keystore_path: Option<PathBuf>,
+
+    /// Upper bound (in wei) for the `maxFeePerGas` of `respondToTask` transactions.
+    /// - Defaults to 200 gwei.
+    /// - Can be overridden by the `MAX_FEE_PER_GAS` environment variable.
+    pub max_fee_per_gas: u128,
+
+    /// Upper bound (in wei) for the `maxPriorityFeePerGas` of `respondToTask` transactions.
+    /// - Defaults to 10 gwei.
+    /// - Can be overridden by the `MAX_PRIORITY_FEE_PER_GAS` environment variable.
+    pub max_priority_fee_per_gas: u128,
+
+    /// Path of the JSON file used to persist in-flight operator responses.
+    /// - Defaults to `aggregator_store.json` in the working directory.
+    /// - Can be overridden by the `TASK_STORE_PATH` environment variable.
+    pub task_store_path: PathBuf,
+
+    /// Number of tasks whose status is kept in memory.
+    /// - Once exceeded, the oldest completed or failed tasks are evicted and their status is
+    ///   served from the archive of the task store instead. Pending tasks are never evicted.
+    /// - Defaults to 10,000.
+    /// - Can be overridden by the `MAX_TASKS_IN_MEMORY` environment variable.
+    pub max_tasks_in_memory: usize,
+
+    /// Interval (in seconds) between two refreshes of the registered operator list.
+    /// - Tasks already receiving responses keep the operator count they started with.
+    /// - Defaults to 60 seconds, `0` disables the refresh.
+    /// - Can be overridden by the `OPERATOR_REFRESH_INTERVAL_SECS` environment variable.
+    pub operator_refresh_interval_secs: u64,
+
+    /// Time (in seconds) responses are still collected once every expected response arrived,
+    /// before the task is aggregated. Responses from operators that registered after the task
+    /// started receiving responses are recorded and take part in the consensus, but operators
+    /// that already responded can no longer change their result.
+    /// - Defaults to 0 (the task is aggregated right away).
+    /// - Can be overridden by the `RESPONSE_GRACE_PERIOD_SECS` environment variable.
+    pub response_grace_period_secs: u64,
+
+    /// Time (in seconds) a pending task waits for its operators to report. Once it elapses, the
+    /// task is aggregated with the responses received so far, and fails if they don't reach
+    /// consensus, so an unresponsive operator can't leave it pending forever.
+    /// - Defaults to 0 (tasks wait for every operator).
+    /// - Can be overridden by the `TASK_TIMEOUT_SECS` environment variable.
+    pub task_timeout_secs: u64,
+
+    /// Maximum number of task results submitted in a single `respondToTasks` transaction,
+    /// which costs less gas than one `respondToTask` transaction per task when many tasks
+    /// finalize together.
+    /// - Defaults to 1 (every result is submitted on its own), values above 1 enable batching.
+    /// - Can be overridden by the `RESULT_BATCH_SIZE` environment variable.
+    pub result_batch_size: usize,
+
+    /// Time (in milliseconds) the results finalized after the first one of a batch are
+    /// gathered before the batch is submitted, unless it fills up first.
+    /// - Defaults to 2,000 milliseconds. Only used when batching is enabled.
+    /// - Can be overridden by the `RESULT_BATCH_WINDOW_MS` environment variable.
+    pub result_batch_window_ms: u64,
+
+    /// How the responses to a task reach consensus: `unanimous` requires every response to
+    /// agree, `majority` requires more than half of the registered operators to agree,
+    /// `threshold` requires a fraction of them (e.g. `{ threshold = { numerator = 2,
+    /// denominator = 3 } }`), and `stake_weighted` requires operators holding two thirds of
+    /// their stake, as recorded by GizaAVS `operatorStake`.
+    /// - Defaults to `unanimous`.
+    /// - Can be overridden by the `CONSENSUS_STRATEGY` environment variable, with
+    ///   `threshold:<numerator>/<denominator>` selecting a threshold.
+    pub consensus: ConsensusKind,
+
+    /// Maximum size (in bytes) of a request body accepted by the server.
+    /// - Larger requests are rejected with `413 Payload Too Large` before being buffered.
+    /// - Defaults to 64 KiB.
+    /// - Can be overridden by the `MAX_REQUEST_BODY_SIZE` environment variable.
+    pub max_request_body_size: usize,
+
+    /// Maximum length (in bytes) of the `result` of an operator response.
+    /// - Longer results are rejected with `413 Payload Too Large`.
+    /// - Defaults to 16 KiB.
+    /// - Can be overridden by the `MAX_RESULT_SIZE` environment variable.
+    pub max_result_size: usize,
+
+    /// Maximum number of task ids a single `POST /task_status` request may query.
+    /// - Larger batches are rejected with `400 Bad Request`.
+    /// - Defaults to 100.
+    /// - Can be overridden by the `MAX_TASK_STATUS_BATCH_SIZE` environment variable.
+    pub max_task_status_batch_size: usize,
+
+    /// Difference (in seconds) between the timestamp of an operator response and the
+    /// aggregator's clock above which a warning is logged, as registration expiries and task
+    /// timeouts rely on wall-clock time. The difference includes the latency of the request.
+    /// - Defaults to 30, `0` disables the check.
+    /// - Can be overridden by the `MAX_CLOCK_SKEW_SECS` environment variable.
+    pub max_clock_skew_secs: u64,
+
+    /// Oldest signature scheme accepted from operators. Responses of version 0 only sign the
+    /// result, so their task id can be swapped; version 1 responses also sign the task id, and
+    /// requiring them rejects operators that haven't upgraded.
+    /// - Defaults to 1, set it to 0 to accept operators still signing version 0 responses.
+    /// - Can be overridden by the `MIN_RESPONSE_VERSION` environment variable.
+    pub min_response_version: u8,
+
+    /// Number of operators recovered from response signatures kept in an LRU cache, so that
+    /// resubmitted responses skip the ECDSA recovery.
+    /// - Defaults to 1,024, `0` disables the cache.
+    /// - Can be overridden by the `RECOVERY_CACHE_SIZE` environment variable.
+    pub recovery_cache_size: usize,
+
+    /// Number of blocks a `TaskRequested` event must be buried under before the task is
+    /// treated as `PENDING`. Events reorged out before reaching that depth are discarded.
+    /// - Defaults to the chain's `Chain::default_task_confirmations` (0 on Anvil).
+    /// - Can be overridden by the `TASK_CONFIRMATIONS` environment variable.
+    pub task_confirmations: Option<u64>,
+
+    /// Number of blocks covered by a single query when fetching historical logs (registered
+    /// operators, task history). Histories are paginated through windows of this size, as
+    /// many providers reject queries spanning too many blocks or returning too many logs.
+    /// - Defaults to 10,000 blocks.
+    /// - Can be overridden by the `LOG_QUERY_BLOCK_RANGE` environment variable.
+    pub log_query_block_range: u64,
+
+    /// Block the state the aggregator acts on is read at: the AVSDirectory status of the
+    /// registered operators and the status of past tasks on startup. `safe` or `finalized`
+    /// keep reorg-prone state out, at the cost of seeing changes later. The status checked
+    /// after submitting a result is always read at the latest block, where the submission is.
+    /// - Defaults to `latest`.
+    /// - Can be overridden by the `READ_BLOCK_TAG` environment variable.
+    pub read_block_tag: ReadBlockTag,
+
+    /// Port of the gRPC interface, served alongside the HTTP one.
+    /// - The gRPC interface is disabled when unset, and is only available when the aggregator
+    ///   is built with the `grpc` feature.
+    /// - Can be overridden by the `GRPC_PORT` environment variable.
+    pub grpc_port: Option<u16>,
+
+    /// PEM certificate chain served over TLS by the HTTP server, along with `tls_key_path`.
+    /// - The server uses plain HTTP when unset, which is only suitable for local development.
+    /// - Can be overridden by the `TLS_CERT_PATH` environment variable.
+    pub tls_cert_path: Option<PathBuf>,
+
+    /// PEM private key of `tls_cert_path`.
+    /// - Can be overridden by the `TLS_KEY_PATH` environment variable.
+    pub tls_key_path: Option<PathBuf>,
+
+    /// File every finalized task (status, consensus result, operator responses and timings) is
+    /// appended to as a JSON line, for offline analysis.
+    /// - Disabled when unset.
+    /// - Can be overridden by the `NDJSON_LOG_PATH` environment variable.
+    pub ndjson_log_path: Option<PathBuf>,
+
+    /// Bearer token required by the debugging endpoints, which expose operator behavior.
+    /// - Those endpoints are disabled when unset.
+    /// - Can be overridden by the `DEBUG_TOKEN` environment variable.
+    pub debug_token: Option<String>,
+
+    /// Bearer token required by the admin endpoints, which alter the state of tasks.
+    /// - Those endpoints are disabled when unset.
+    /// - Can be overridden by the `ADMIN_TOKEN` environment variable.
+    pub admin_token: Option<String>,
+
+    /// The `ResultType` produced by each client app, used to validate operator results.
+    /// - Apps not listed default to `uint256`.
+    /// - Read from the `CLIENT_APP_RESULT_TYPES` environment variable as a comma separated
+    ///   list of `<app_id>:<uint256|bytes|json|text>` pairs, or from a `[result_types]` table
+    ///   in the configuration file.
+    pub result_types: ResultTypes,
+
+    /// The AVS deployments served by the aggregator, each with its own tasks, operator
+    /// responses and task store.
+    /// - Read from the `[[deployments]]` entries of the configuration file.
+    /// - When empty, only the GizaAVS deployment on the chain given on the command line is
+    ///   served.
+    pub deployments: Vec<AvsDeployment>,
 }
 
 impl AggregatorConfig {
-    pub(super) fn from_env() -> Self {
+    /// Builds the configuration from environment variables (and `.env`), using the built-in
+    /// defaults for anything unset.
+    ///
+    /// # Errors
+    /// Returns `AggregatorError::ConfigError` if an environment variable has an invalid value,
+    /// or if the keystore cannot be decrypted.
+    pub fn from_env() -> Result<Self, AggregatorError> {
+        Self::load(FileConfig::default())
+    }
+
+    /// Builds the configuration from the TOML file at `path`. Environment variables still
+    /// take precedence over the values in the file.
+    ///
+    /// # Errors
+    /// Returns `AggregatorError::ConfigError` if the file cannot be read or parsed, if an
+    /// environment variable has an invalid value, or if the keystore cannot be decrypted.
+    pub fn from_file(path: &Path) -> Result<Self, AggregatorError> {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            AggregatorError::ConfigError(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        let file_config = toml::from_str(&contents).map_err(|e| {
+            AggregatorError::ConfigError(format!("Failed to parse {}: {}", path.display(), e))
+        })?;
+
+        Self::load(file_config)
+    }
+
+    // Layer environment variables over the file values over the built-in defaults
+    fn load(file_config: FileConfig) -> Result<Self, AggregatorError> {
         // Load environment variables from .env file if present
         dotenv().ok();
 
-        let ecdsa_signer: PrivateKeySigner =
-            "6e7912cf57b1cd9df1b05712e92a082c8c06511f62432abdaad503060822bc72"
+        let keystore_path = env::var("AGGREGATOR_KEYSTORE_PATH")
+            .ok()
+            .map(PathBuf::from)
+            .or(file_config.keystore_path);
+        let ecdsa_signer = match &keystore_path {
+            Some(keystore_path) => Self::decrypt_keystore(keystore_path)?,
+            None => DEV_PRIVATE_KEY
                 .parse()
-                .expect("Failed to parse ECDSA private key");
+                .expect("Failed to parse ECDSA private key"),
+        };
+
+        let max_fee_per_gas = Self::env_or(
+            "MAX_FEE_PER_GAS",
+            file_config
+                .max_fee_per_gas
+                .unwrap_or(DEFAULT_MAX_FEE_PER_GAS),
+        )?;
+        let max_priority_fee_per_gas = Self::env_or(
+            "MAX_PRIORITY_FEE_PER_GAS",
+            file_config
+                .max_priority_fee_per_gas
+                .unwrap_or(DEFAULT_MAX_PRIORITY_FEE_PER_GAS),
+        )?;
+        let task_store_path = Self::env_or(
+            "TASK_STORE_PATH",
+            file_config
+                .task_store_path
+                .unwrap_or_else(|| DEFAULT_TASK_STORE_PATH.into()),
+        )?;
+        let max_tasks_in_memory = Self::env_or(
+            "MAX_TASKS_IN_MEMORY",
+            file_config
+                .max_tasks_in_memory
+                .unwrap_or(DEFAULT_MAX_TASKS_IN_MEMORY),
+        )?;
+        let operator_refresh_interval_secs = Self::env_or(
+            "OPERATOR_REFRESH_INTERVAL_SECS",
+            file_config
+                .operator_refresh_interval_secs
+                .unwrap_or(DEFAULT_OPERATOR_REFRESH_INTERVAL_SECS),
+        )?;
+        let response_grace_period_secs = Self::env_or(
+            "RESPONSE_GRACE_PERIOD_SECS",
+            file_config
+                .response_grace_period_secs
+                .unwrap_or(DEFAULT_RESPONSE_GRACE_PERIOD_SECS),
+        )?;
+        let task_timeout_secs = Self::env_or(
+            "TASK_TIMEOUT_SECS",
+            file_config
+                .task_timeout_secs
+                .unwrap_or(DEFAULT_TASK_TIMEOUT_SECS),
+        )?;
+        let result_batch_size = Self::env_or(
+            "RESULT_BATCH_SIZE",
+            file_config
+                .result_batch_size
+                .unwrap_or(DEFAULT_RESULT_BATCH_SIZE),
+        )?;
+        let result_batch_window_ms = Self::env_or(
+            "RESULT_BATCH_WINDOW_MS",
+            file_config
+                .result_batch_window_ms
+                .unwrap_or(DEFAULT_RESULT_BATCH_WINDOW_MS),
+        )?;
+        let consensus = Self::env_or(
+            "CONSENSUS_STRATEGY",
+            file_config.consensus.unwrap_or_default(),
+        )?;
+        let max_request_body_size = Self::env_or(
+            "MAX_REQUEST_BODY_SIZE",
+            file_config
+                .max_request_body_size
+                .unwrap_or(DEFAULT_MAX_REQUEST_BODY_SIZE),
+        )?;
+        let max_result_size = Self::env_or(
+            "MAX_RESULT_SIZE",
+            file_config
+                .max_result_size
+                .unwrap_or(DEFAULT_MAX_RESULT_SIZE),
+        )?;
+        let max_task_status_batch_size = Self::env_or(
+            "MAX_TASK_STATUS_BATCH_SIZE",
+            file_config
+                .max_task_status_batch_size
+                .unwrap_or(DEFAULT_MAX_TASK_STATUS_BATCH_SIZE),
+        )?;
+        let max_clock_skew_secs = Self::env_or(
+            "MAX_CLOCK_SKEW_SECS",
+            file_config
+                .max_clock_skew_secs
+                .unwrap_or(DEFAULT_MAX_CLOCK_SKEW_SECS),
+        )?;
+        let min_response_version = Self::env_or(
+            "MIN_RESPONSE_VERSION",
+            file_config
+                .min_response_version
+                .unwrap_or(DEFAULT_MIN_RESPONSE_VERSION),
+        )?;
+        let recovery_cache_size = Self::env_or(
+            "RECOVERY_CACHE_SIZE",
+            file_config
+                .recovery_cache_size
+                .unwrap_or(DEFAULT_RECOVERY_CACHE_SIZE),
+        )?;
+        let task_confirmations = env::var("TASK_CONFIRMATIONS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .or(file_config.task_confirmations);
+        let log_query_block_range = Self::env_or(
+            "LOG_QUERY_BLOCK_RANGE",
+            file_config
+                .log_query_block_range
+                .unwrap_or(DEFAULT_LOG_QUERY_BLOCK_RANGE),
+        )?;
+        let read_block_tag = Self::env_or(
+            "READ_BLOCK_TAG",
+            file_config.read_block_tag.unwrap_or_default(),
+        )?;
+        let grpc_port = env::var("GRPC_PORT")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .or(file_config.grpc_port);
+        let tls_cert_path = env::var("TLS_CERT_PATH")
+            .ok()
+            .map(PathBuf::from)
+            .or(file_config.tls_cert_path);
+        let tls_key_path = env::var("TLS_KEY_PATH")
+            .ok()
+            .map(PathBuf::from)
+            .or(file_config.tls_key_path);
+        let ndjson_log_path = env::var("NDJSON_LOG_PATH")
+            .ok()
+            .map(PathBuf::from)
+            .or(file_config.ndjson_log_path);
+        let debug_token = env::var("DEBUG_TOKEN").ok().or(file_config.debug_token);
+        let admin_token = env::var("ADMIN_TOKEN").ok().or(file_config.admin_token);
+        let result_types = Self::env_or(
+            "CLIENT_APP_RESULT_TYPES",
+            file_config.result_types.unwrap_or_default(),
+        )?;
+
+        Ok(Self {
+            ecdsa_signer,
+            keystore_path,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            task_store_path,
+            max_tasks_in_memory,
+            operator_refresh_interval_secs,
+            response_grace_period_secs,
+            task_timeout_secs,
+            result_batch_size,
+            result_batch_window_ms,
+            consensus,
+            max_request_body_size,
+            max_result_size,
+            max_task_status_batch_size,
+            max_clock_skew_secs,
+            min_response_version,
+            recovery_cache_size,
+            task_confirmations,
+            log_query_block_range,
+            read_block_tag,
+            grpc_port,
+            tls_cert_path,
+            tls_key_path,
+            ndjson_log_path,
+            debug_token,
+            admin_token,
+            result_types,
+            deployments: file_config.deployments,
+        })
+    }
+
+    /// Checks the invariants of the configuration before anything is started, so that every
+    /// misconfiguration is reported at once rather than as a failure midway through startup.
+    ///
+    /// # Errors
+    /// Returns `AggregatorError::ConfigError` listing every problem found.
+    pub fn validate(&self) -> Result<(), AggregatorError> {
+        let mut problems = Vec::new();
+
+        if self.max_priority_fee_per_gas > self.max_fee_per_gas {
+            problems.push(format!(
+                "max_priority_fee_per_gas ({}) exceeds max_fee_per_gas ({})",
+                self.max_priority_fee_per_gas, self.max_fee_per_gas
+            ));
+        }
+        for (name, value) in [
+            ("max_tasks_in_memory", self.max_tasks_in_memory),
+            ("result_batch_size", self.result_batch_size),
+            ("max_request_body_size", self.max_request_body_size),
+            ("max_result_size", self.max_result_size),
+            (
+                "max_task_status_batch_size",
+                self.max_task_status_batch_size,
+            ),
+        ] {
+            if value == 0 {
+                problems.push(format!("{} must be positive", name));
+            }
+        }
+        if let ConsensusKind::Threshold {
+            numerator,
+            denominator,
+        } = self.consensus
+        {
+            if numerator == 0 || numerator > denominator {
+                problems.push(format!(
+                    "Consensus threshold {}/{} must be within (0, 1]",
+                    numerator, denominator
+                ));
+            }
+        }
+        if self.log_query_block_range == 0 {
+            problems.push("log_query_block_range must be positive".to_string());
+        }
+        if response_signing_bytes(self.min_response_version, TaskId::default(), "").is_none() {
+            problems.push(format!(
+                "Unsupported min_response_version: {}",
+                self.min_response_version
+            ));
+        }
+
+        match (&self.tls_cert_path, &self.tls_key_path) {
+            (Some(_), None) | (None, Some(_)) => {
+                problems.push("tls_cert_path and tls_key_path must be set together".to_string())
+            }
+            _ => {}
+        }
+        for path in [&self.tls_cert_path, &self.tls_key_path]
+            .into_iter()
+            .flatten()
+        {
+            if !path.is_file() {
+                problems.push(format!("{} does not exist", path.display()));
+            }
+        }
+        let written_paths = [Some(&self.task_store_path), self.ndjson_log_path.as_ref()]
+            .into_iter()
+            .flatten()
+            .chain(
+                self.deployments
+                    .iter()
+                    .filter_map(|deployment| deployment.task_store_path.as_ref()),
+            );
+        for path in written_paths {
+            if let Some(parent) = path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+            {
+                if !parent.is_dir() {
+                    problems.push(format!("Directory of {} does not exist", path.display()));
+                }
+            }
+        }
+
+        for (name, token) in [
+            ("debug_token", &self.debug_token),
+            ("admin_token", &self.admin_token),
+        ] {
+            if token
+                .as_deref()
+                .is_some_and(|token| token.trim().is_empty())
+            {
+                problems.push(format!("{} is set but empty", name));
+            }
+        }
+
+        for (index, deployment) in self.deployments.iter().enumerate() {
+            if deployment.id.is_empty() {
+                problems.push("Deployment ids must not be empty".to_string());
+            } else if self.deployments[..index]
+                .iter()
+                .any(|other| other.id == deployment.id)
+            {
+                problems.push(format!("Duplicate deployment id: {:?}", deployment.id));
+            }
+        }
+
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(AggregatorError::ConfigError(format!(
+                "{} problem(s) found:\n  - {}",
+                problems.len(),
+                problems.join("\n  - ")
+            ))),
+        }
+    }
+
+    // Decrypt the keystore at `path` with the passphrase in `AGGREGATOR_KEYSTORE_PASSWORD`
+    fn decrypt_keystore(path: &Path) -> Result<PrivateKeySigner, AggregatorError> {
+        let password = env::var("AGGREGATOR_KEYSTORE_PASSWORD").map_err(|_| {
+            AggregatorError::ConfigError(
+                "AGGREGATOR_KEYSTORE_PASSWORD must be set to unlock the keystore".to_string(),
+            )
+        })?;
+        PrivateKeySigner::decrypt_keystore(path, password).map_err(|e| {
+            AggregatorError::ConfigError(format!(
+                "Failed to decrypt keystore {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Reads and parses the environment variable `key`, falling back to `default` when it is
+    /// unset.
+    ///
+    /// # Errors
+    /// Returns `AggregatorError::ConfigError` if the variable is set but cannot be parsed.
+    fn env_or<T: FromStr>(key: &str, default: T) -> Result<T, AggregatorError> {
+        match env::var(key) {
+            Ok(value) => value.parse().map_err(|_| {
+                AggregatorError::ConfigError(format!("Invalid value for {}: {:?}", key, value))
+            }),
+            Err(_) => Ok(default),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::Address;
+
+    #[test]
+    fn test_min_response_version() -> Result<(), AggregatorError> {
+        // Responses not covering their task id are refused unless explicitly allowed
+        assert_eq!(AggregatorConfig::from_env()?.min_response_version, 1);
+
+        let path = env::temp_dir().join(format!("aggregator_config_{}.toml", std::process::id()));
+        fs::write(&path, "min_response_version = 0\n").unwrap();
+        let config = AggregatorConfig::from_file(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(config?.min_response_version, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_deployment_addresses() {
+        let deployment = |addresses: &str| {
+            toml::from_str::<AvsDeployment>(&format!(
+                "id = \"custom\"\nchain = \"anvil\"\n{}",
+                addresses
+            ))
+        };
+
+        // Without addresses the GizaAVS deployment is used
+        assert_eq!(
+            deployment("").unwrap().addresses,
+            ContractAddresses::default()
+        );
+
+        // A custom deployment gives every address, a partial one is refused
+        let addresses = "[addresses]\n\
+            task_registry = \"0x0000000000000000000000000000000000000001\"\n\
+            client_app_registry = \"0x0000000000000000000000000000000000000002\"\n\
+            avs_directory = \"0x0000000000000000000000000000000000000003\"\n";
+        assert!(deployment(addresses)
+            .unwrap_err()
+            .to_string()
+            .contains("giza_avs"));
+        let addresses = format!(
+            "{}giza_avs = \"0x0000000000000000000000000000000000000004\"\n",
+            addresses
+        );
+        assert_eq!(
+            deployment(&addresses).unwrap().addresses,
+            ContractAddresses {
+                task_registry: Address::with_last_byte(1),
+                client_app_registry: Address::with_last_byte(2),
+                avs_directory: Address::with_last_byte(3),
+                giza_avs: Address::with_last_byte(4),
+            }
+        );
+    }
+
+    #[test]
+    fn test_env_or() -> Result<(), AggregatorError> {
+        assert_eq!(AggregatorConfig::env_or("AGGREGATOR_TEST_ENV_OR", 7u64)?, 7);
+        env::set_var("AGGREGATOR_TEST_ENV_OR", "42");
+        assert_eq!(
+            AggregatorConfig::env_or("AGGREGATOR_TEST_ENV_OR", 7u64)?,
+            42
+        );
+
+        // A value that doesn't parse fails the configuration rather than using the default
+        env::set_var("AGGREGATOR_TEST_ENV_OR", "forty-two");
+        let error = AggregatorConfig::env_or("AGGREGATOR_TEST_ENV_OR", 7u64).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Invalid value for AGGREGATOR_TEST_ENV_OR: \"forty-two\""),
+            "{}",
+            error
+        );
+        env::remove_var("AGGREGATOR_TEST_ENV_OR");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decrypt_keystore() {
+        let signer = PrivateKeySigner::random();
+        let dir = env::temp_dir().join(format!("aggregator_keystore_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (_, name) = PrivateKeySigner::encrypt_keystore(
+            &dir,
+            &mut rand::thread_rng(),
+            signer.credential().to_bytes(),
+            "passphrase",
+            None,
+        )
+        .unwrap();
+        let path = dir.join(name);
+
+        env::set_var("AGGREGATOR_KEYSTORE_PASSWORD", "passphrase");
+        let keystore = AggregatorConfig::decrypt_keystore(&path).unwrap();
+        assert_eq!(keystore.address(), signer.address());
+
+        // A wrong passphrase is a configuration error, not a panic
+        env::set_var("AGGREGATOR_KEYSTORE_PASSWORD", "wrong");
+        assert!(matches!(
+            AggregatorConfig::decrypt_keystore(&path),
+            Err(AggregatorError::ConfigError(_))
+        ));
+        env::remove_var("AGGREGATOR_KEYSTORE_PASSWORD");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_validate() -> Result<(), AggregatorError> {
+        AggregatorConfig::from_env()?.validate()?;
+
+        // Each problem, along with a change to a valid configuration that triggers it
+        type Invalidate = fn(&mut AggregatorConfig);
+        let cases: Vec<(&str, Invalidate)> = vec![
+            ("exceeds max_fee_per_gas", |config| {
+                config.max_priority_fee_per_gas = config.max_fee_per_gas + 1
+            }),
+            ("max_tasks_in_memory must be positive", |config| {
+                config.max_tasks_in_memory = 0
+            }),
+            ("result_batch_size must be positive", |config| {
+                config.result_batch_size = 0
+            }),
+            ("max_request_body_size must be positive", |config| {
+                config.max_request_body_size = 0
+            }),
+            ("max_result_size must be positive", |config| {
+                config.max_result_size = 0
+            }),
+            ("max_task_status_batch_size must be positive", |config| {
+                config.max_task_status_batch_size = 0
+            }),
+            ("must be within (0, 1]", |config| {
+                config.consensus = ConsensusKind::Threshold {
+                    numerator: 3,
+                    denominator: 2,
+                }
+            }),
+            ("log_query_block_range must be positive", |config| {
+                config.log_query_block_range = 0
+            }),
+            ("Unsupported min_response_version", |config| {
+                config.min_response_version = u8::MAX
+            }),
+            ("must be set together", |config| {
+                config.tls_cert_path = Some(env::temp_dir())
+            }),
+            ("does not exist", |config| {
+                config.tls_cert_path = Some(PathBuf::from("/nonexistent/cert.pem"));
+                config.tls_key_path = Some(PathBuf::from("/nonexistent/key.pem"));
+            }),
+            ("Directory of", |config| {
+                config.ndjson_log_path = Some(PathBuf::from("/nonexistent/tasks.ndjson"))
+            }),
+            ("debug_token is set but empty", |config| {
+                config.debug_token = Some(" ".to_string())
+            }),
+            ("admin_token is set but empty", |config| {
+                config.admin_token = Some(String::new())
+            }),
+            ("Deployment ids must not be empty", |config| {
+                config.deployments = vec![AvsDeployment {
+                    id: String::new(),
+                    ..AvsDeployment::default_for(Chain::Anvil)
+                }]
+            }),
+            ("Duplicate deployment id", |config| {
+                config.deployments = vec![
+                    AvsDeployment::default_for(Chain::Anvil),
+                    AvsDeployment::default_for(Chain::Holesky),
+                ]
+            }),
+        ];
+        for (problem, invalidate) in cases {
+            let mut config = AggregatorConfig::from_env()?;
+            invalidate(&mut config);
+            let error = config.validate().unwrap_err().to_string();
+            assert!(
+                error.contains(problem),
+                "expected {:?} in {:?}",
+                problem,
+                error
+            );
+        }
 
-        Self { ecdsa_signer }
+        Ok(())
     }
 }
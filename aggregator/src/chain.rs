@@ -0,0 +1,341 @@
+use crate::{AggregatorError, HttpProviderWithSigner};
+use alloy::{
+    contract::Event,
+    eips::BlockId,
+    providers::{utils::Eip1559Estimation, Provider},
+    rpc::types::{Log, TransactionRequest},
+    sol_types::SolEvent,
+    transports::{RpcError, Transport, TransportErrorKind},
+};
+use alloy_primitives::{Address, TxHash, U256};
+use async_trait::async_trait;
+use contract_bindings::{
+    AVSDirectory::AVSDirectoryInstance, AppId, ContractAddresses, GizaAVS::GizaAVSInstance,
+    OperatorAvsStatus, TaskId, TaskRegistry::TaskRegistryInstance, TaskStatus,
+};
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{error, info};
+
+// Block the contracts were deployed at, where historical log queries start
+const HISTORY_START_BLOCK: u64 = 2577255;
+
+// JSON-RPC error code of a transaction rejected by the node (EIP-1474), which nodes answer a
+// sender that can't pay for its transaction with
+const TRANSACTION_REJECTED_CODE: i64 = -32003;
+
+// Map the failure of a transaction request, telling a sender that can't pay for it apart by the
+// error code of the node's answer
+fn tx_error(error: RpcError<TransportErrorKind>) -> AggregatorError {
+    match error.as_error_resp() {
+        Some(payload) if payload.code == TRANSACTION_REJECTED_CODE => {
+            AggregatorError::InsufficientFunds(payload.message.to_string())
+        }
+        _ => AggregatorError::TxError(error.to_string()),
+    }
+}
+
+/// `AvsChain` is the aggregator's view of the contracts of an AVS deployment: the state it
+/// reconciles its own with, and the transactions it submits task results with.
+///
+/// It is implemented over JSON-RPC by `RpcChain`, and by scripted fakes in tests. The
+/// `TaskRequested` subscription is not part of it, as it needs a live pubsub connection.
+#[async_trait]
+pub(crate) trait AvsChain: Send + Sync {
+    /// Returns every operator that registered in GizaAVS, some of which may have left since.
+    async fn registered_operators(&self) -> Result<Vec<Address>, AggregatorError>;
+
+    /// Returns the AVSDirectory status of `operator` at `block`.
+    async fn operator_status(
+        &self,
+        operator: Address,
+        block: BlockId,
+    ) -> Result<OperatorAvsStatus, AggregatorError>;
+
+    /// Returns the stake GizaAVS recorded for `operator` at `block`, which weighs its responses
+    /// in stake-weighted consensus.
+    async fn operator_stake(
+        &self,
+        operator: Address,
+        block: BlockId,
+    ) -> Result<U256, AggregatorError>;
+
+    /// Returns every task requested from the TaskRegistry, oldest first, along with the client
+    /// app it was requested for.
+    async fn requested_tasks(&self) -> Result<Vec<(TaskId, AppId)>, AggregatorError>;
+
+    /// Returns the status the TaskRegistry recorded for `task_id` at `block`.
+    async fn task_status(
+        &self,
+        task_id: TaskId,
+        block: BlockId,
+    ) -> Result<TaskStatus, AggregatorError>;
+
+    /// Estimates the EIP-1559 fees of a transaction submitted now.
+    async fn estimate_fees(&self) -> Result<Eip1559Estimation, AggregatorError>;
+
+    /// Returns the next nonce of `address`, counting its transactions still in the mempool.
+    async fn pending_nonce(&self, address: Address) -> Result<u64, AggregatorError>;
+
+    /// Returns the next nonce of `address` as of the latest block, ignoring its transactions
+    /// still in the mempool.
+    async fn mined_nonce(&self, address: Address) -> Result<u64, AggregatorError>;
+
+    /// Fills, signs and broadcasts `tx_request`, returning its hash.
+    async fn broadcast(&self, tx_request: TransactionRequest) -> Result<TxHash, AggregatorError>;
+
+    /// Returns whether the transaction `tx_hash` succeeded, `None` until it is mined.
+    async fn transaction_status(&self, tx_hash: TxHash) -> Result<Option<bool>, AggregatorError>;
+}
+
+/// `RpcChain` reaches the contracts of a deployment through the HTTP endpoint of a node,
+/// signing transactions with the aggregator's wallet.
+pub(crate) struct RpcChain {
+    /// Addresses of the deployment's contracts.
+    addresses: ContractAddresses,
+    /// Provider signing with the aggregator's key.
+    http_provider: HttpProviderWithSigner,
+    /// Number of blocks covered by a single historical log query.
+    log_query_block_range: u64,
+}
+
+impl RpcChain {
+    /// Creates a view of the contracts at `addresses` through `http_provider`.
+    pub fn new(
+        addresses: ContractAddresses,
+        http_provider: HttpProviderWithSigner,
+        log_query_block_range: u64,
+    ) -> Self {
+        Self {
+            addresses,
+            http_provider,
+            log_query_block_range,
+        }
+    }
+
+    // Query the logs of `event` from the deployment block to the chain head, in windows of
+    // `log_query_block_range` blocks so that providers limiting the block range or the size of a
+    // query don't reject it. Failures are reported with `error`.
+    async fn query_history<T, P, E>(
+        &self,
+        mut event: Event<T, P, E>,
+        error: fn(String) -> AggregatorError,
+    ) -> Result<Vec<(E, Log)>, AggregatorError>
+    where
+        T: Transport + Clone,
+        P: Provider<T>,
+        E: SolEvent,
+    {
+        let event_name = E::SIGNATURE.split('(').next().unwrap_or(E::SIGNATURE);
+        let head = self
+            .http_provider
+            .get_block_number()
+            .await
+            .map_err(|e| error(e.to_string()))?;
+        let block_range = self.log_query_block_range.max(1);
+
+        let mut logs = Vec::new();
+        let mut from_block = HISTORY_START_BLOCK;
+        while from_block <= head {
+            let to_block = (from_block + block_range - 1).min(head);
+            event.filter = event
+                .filter
+                .clone()
+                .from_block(from_block)
+                .to_block(to_block);
+            logs.extend(event.query().await.map_err(|e| error(e.to_string()))?);
+            info!(
+                "Fetched {} {} logs up to block {}/{}",
+                logs.len(),
+                event_name,
+                to_block,
+                head
+            );
+            from_block = to_block + 1;
+        }
+
+        Ok(logs)
+    }
+}
+
+#[async_trait]
+impl AvsChain for RpcChain {
+    async fn registered_operators(&self) -> Result<Vec<Address>, AggregatorError> {
+        let giza_avs = GizaAVSInstance::new(self.addresses.giza_avs, self.http_provider.clone());
+
+        Ok(self
+            .query_history(
+                giza_avs.OperatorRegistered_filter(),
+                AggregatorError::OperatorListFetchError,
+            )
+            .await?
+            .into_iter()
+            .map(|(operator_registered, _)| operator_registered.operator)
+            .collect())
+    }
+
+    async fn operator_status(
+        &self,
+        operator: Address,
+        block: BlockId,
+    ) -> Result<OperatorAvsStatus, AggregatorError> {
+        let avs_directory =
+            AVSDirectoryInstance::new(self.addresses.avs_directory, self.http_provider.clone());
+
+        avs_directory
+            .avsOperatorStatus(self.addresses.giza_avs, operator)
+            .block(block)
+            .call()
+            .await
+            .map(|status| OperatorAvsStatus::from(status._0))
+            .map_err(|e| AggregatorError::OperatorListFetchError(e.to_string()))
+    }
+
+    async fn operator_stake(
+        &self,
+        operator: Address,
+        block: BlockId,
+    ) -> Result<U256, AggregatorError> {
+        let giza_avs = GizaAVSInstance::new(self.addresses.giza_avs, self.http_provider.clone());
+
+        giza_avs
+            .operatorStake(operator)
+            .block(block)
+            .call()
+            .await
+            .map(|stake| stake.stake)
+            .map_err(|e| AggregatorError::OperatorListFetchError(e.to_string()))
+    }
+
+    async fn requested_tasks(&self) -> Result<Vec<(TaskId, AppId)>, AggregatorError> {
+        let task_registry =
+            TaskRegistryInstance::new(self.addresses.task_registry, self.http_provider.clone());
+
+        Ok(self
+            .query_history(
+                task_registry.TaskRequested_filter(),
+                AggregatorError::TaskHistoryFetchError,
+            )
+            .await?
+            .into_iter()
+            .map(|(task_requested, _)| {
+                (
+                    TaskId::from(task_requested.taskId),
+                    task_requested.taskRequest.appId.into(),
+                )
+            })
+            .collect())
+    }
+
+    async fn task_status(
+        &self,
+        task_id: TaskId,
+        block: BlockId,
+    ) -> Result<TaskStatus, AggregatorError> {
+        let task_registry =
+            TaskRegistryInstance::new(self.addresses.task_registry, self.http_provider.clone());
+
+        task_registry
+            .tasks(task_id.into())
+            .block(block)
+            .call()
+            .await
+            .map(|status| TaskStatus::from(status._0))
+            .map_err(|e| AggregatorError::TaskHistoryFetchError(e.to_string()))
+    }
+
+    async fn estimate_fees(&self) -> Result<Eip1559Estimation, AggregatorError> {
+        self.http_provider
+            .estimate_eip1559_fees(None)
+            .await
+            .map_err(|e| AggregatorError::TxError(e.to_string()))
+    }
+
+    async fn pending_nonce(&self, address: Address) -> Result<u64, AggregatorError> {
+        self.http_provider
+            .get_transaction_count(address)
+            .pending()
+            .await
+            .map_err(|e| AggregatorError::TxError(e.to_string()))
+    }
+
+    async fn mined_nonce(&self, address: Address) -> Result<u64, AggregatorError> {
+        self.http_provider
+            .get_transaction_count(address)
+            .latest()
+            .await
+            .map_err(|e| AggregatorError::TxError(e.to_string()))
+    }
+
+    async fn broadcast(&self, tx_request: TransactionRequest) -> Result<TxHash, AggregatorError> {
+        let mut attempts = 0;
+        const MAX_ATTEMPTS: u32 = 6;
+        const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+        let filled_tx = loop {
+            match self.http_provider.fill(tx_request.clone()).await {
+                Ok(tx) => break tx,
+                Err(e) => {
+                    // Retrying won't help until the account is funded
+                    let e = tx_error(e);
+                    attempts += 1;
+                    if attempts >= MAX_ATTEMPTS
+                        || matches!(e, AggregatorError::InsufficientFunds(_))
+                    {
+                        error!("Failed to fill tx after {} attempts: {:?}", attempts, e);
+                        return Err(e);
+                    }
+                    sleep(RETRY_DELAY).await;
+                }
+            }
+        };
+
+        let tx_to_submit = filled_tx
+            .as_envelope()
+            .ok_or_else(|| AggregatorError::TxError("Transaction was not signed".to_string()))?;
+        let pending_tx = self
+            .http_provider
+            .send_tx_envelope(tx_to_submit.clone())
+            .await
+            .map_err(tx_error)?;
+
+        Ok(*pending_tx.tx_hash())
+    }
+
+    async fn transaction_status(&self, tx_hash: TxHash) -> Result<Option<bool>, AggregatorError> {
+        self.http_provider
+            .get_transaction_receipt(tx_hash)
+            .await
+            .map(|receipt| receipt.map(|receipt| receipt.status()))
+            .map_err(|e| AggregatorError::TxError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tx_error() {
+        let rejected = |code: i64| {
+            let payload = serde_json::json!({
+                "code": code,
+                "message": "insufficient funds for gas * price + value",
+            });
+            tx_error(RpcError::ErrorResp(
+                serde_json::from_value(payload).unwrap(),
+            ))
+        };
+
+        // Only the error code tells an unfunded sender apart, not the message
+        assert!(matches!(
+            rejected(TRANSACTION_REJECTED_CODE),
+            AggregatorError::InsufficientFunds(_)
+        ));
+        assert!(matches!(rejected(-32000), AggregatorError::TxError(_)));
+        assert!(matches!(
+            tx_error(TransportErrorKind::backend_gone()),
+            AggregatorError::TxError(_)
+        ));
+    }
+}
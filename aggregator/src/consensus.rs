@@ -0,0 +1,399 @@
+use alloy_primitives::{Address, U256};
+use serde::Deserialize;
+use std::{collections::HashMap, fmt::Debug, str::FromStr, sync::Arc};
+
+/// A response reduced to its canonical result, `None` when the raw result isn't a valid value
+/// of the client app's result type.
+pub type CanonicalResponse = (Address, Option<Vec<u8>>);
+
+/// The operators expected to respond to a task, along with their stake when it is known.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OperatorSet {
+    operators: Vec<Address>,
+    stakes: HashMap<Address, U256>,
+}
+
+impl OperatorSet {
+    pub fn new(operators: Vec<Address>) -> Self {
+        Self {
+            operators,
+            stakes: HashMap::new(),
+        }
+    }
+
+    /// The same set, with the stake of each operator. Operators without a stake weigh nothing.
+    pub fn with_stakes(self, stakes: HashMap<Address, U256>) -> Self {
+        Self { stakes, ..self }
+    }
+
+    /// Stake of `operator`, zero if it is outside the set or has none.
+    pub fn stake(&self, operator: &Address) -> U256 {
+        match self.contains(operator) {
+            true => self.stakes.get(operator).copied().unwrap_or_default(),
+            false => U256::ZERO,
+        }
+    }
+
+    /// Total stake of the operators in the set.
+    pub fn total_stake(&self) -> U256 {
+        self.operators
+            .iter()
+            .map(|operator| self.stake(operator))
+            .fold(U256::ZERO, |total, stake| total.saturating_add(stake))
+    }
+
+    /// Number of operators in the set.
+    pub fn len(&self) -> usize {
+        self.operators.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operators.is_empty()
+    }
+
+    pub fn contains(&self, operator: &Address) -> bool {
+        self.operators.contains(operator)
+    }
+}
+
+/// Decides which result, if any, the responses to a task agree on.
+///
+/// Strategies only see canonical results, so equal values formatted differently by operators
+/// are already equal bytes.
+pub trait ConsensusStrategy: Debug + Send + Sync {
+    /// Returns the canonical result agreed on by `responses`, or `None` if they don't reach
+    /// consensus and the task fails.
+    fn decide(
+        &self,
+        responses: &[CanonicalResponse],
+        operator_set: &OperatorSet,
+    ) -> Option<Vec<u8>>;
+}
+
+/// Every response must be valid and carry the same result.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Unanimous;
+
+impl ConsensusStrategy for Unanimous {
+    fn decide(
+        &self,
+        responses: &[CanonicalResponse],
+        _operator_set: &OperatorSet,
+    ) -> Option<Vec<u8>> {
+        let (_, first) = responses.first()?;
+        let first = first.as_ref()?;
+        responses
+            .iter()
+            .all(|(_, result)| result.as_ref() == Some(first))
+            .then(|| first.clone())
+    }
+}
+
+/// A result must be carried by more than half of the operator set. Invalid responses, and
+/// responses from operators outside the set, don't count towards any result.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Majority;
+
+impl ConsensusStrategy for Majority {
+    fn decide(
+        &self,
+        responses: &[CanonicalResponse],
+        operator_set: &OperatorSet,
+    ) -> Option<Vec<u8>> {
+        let votes = responses
+            .iter()
+            .filter(|(operator, _)| operator_set.contains(operator))
+            .filter_map(|(_, result)| result.as_ref())
+            .collect::<Vec<_>>();
+
+        votes
+            .iter()
+            .find(|candidate| {
+                let count = votes.iter().filter(|vote| vote == candidate).count();
+                count * 2 > operator_set.len()
+            })
+            .map(|result| result.to_vec())
+    }
+}
+
+/// A result must be carried by at least `numerator / denominator` of the operator set, e.g.
+/// two thirds. Invalid responses, and responses from operators outside the set, don't count
+/// towards any result.
+#[derive(Debug, Clone, Copy)]
+pub struct Threshold {
+    pub numerator: u64,
+    pub denominator: u64,
+}
+
+impl ConsensusStrategy for Threshold {
+    fn decide(
+        &self,
+        responses: &[CanonicalResponse],
+        operator_set: &OperatorSet,
+    ) -> Option<Vec<u8>> {
+        let votes = responses
+            .iter()
+            .filter(|(operator, _)| operator_set.contains(operator))
+            .filter_map(|(_, result)| result.as_ref())
+            .collect::<Vec<_>>();
+
+        votes
+            .iter()
+            .find(|candidate| {
+                let count = votes.iter().filter(|vote| vote == candidate).count() as u128;
+                count * self.denominator as u128
+                    >= operator_set.len() as u128 * self.numerator as u128
+            })
+            .map(|result| result.to_vec())
+    }
+}
+
+/// A result must be carried by operators holding at least two thirds of the stake of the
+/// operator set. Invalid responses, and responses from operators outside the set or without
+/// stake, don't count towards any result, and a set without stake never reaches consensus.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StakeWeighted;
+
+impl ConsensusStrategy for StakeWeighted {
+    fn decide(
+        &self,
+        responses: &[CanonicalResponse],
+        operator_set: &OperatorSet,
+    ) -> Option<Vec<u8>> {
+        let total_stake = operator_set.total_stake();
+        if total_stake.is_zero() {
+            return None;
+        }
+
+        let mut stakes: Vec<(&Vec<u8>, U256)> = Vec::new();
+        for (operator, result) in responses {
+            let Some(result) = result else {
+                continue;
+            };
+            let stake = operator_set.stake(operator);
+            match stakes
+                .iter_mut()
+                .find(|(candidate, _)| *candidate == result)
+            {
+                Some((_, agreeing)) => *agreeing = agreeing.saturating_add(stake),
+                None => stakes.push((result, stake)),
+            }
+        }
+
+        stakes
+            .into_iter()
+            .find(|(_, agreeing)| {
+                agreeing.saturating_mul(U256::from(3)) >= total_stake.saturating_mul(U256::from(2))
+            })
+            .map(|(result, _)| result.clone())
+    }
+}
+
+/// The consensus strategies selectable from the configuration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsensusKind {
+    #[default]
+    Unanimous,
+    Majority,
+    /// A fraction of the operator set, e.g. `{ numerator = 2, denominator = 3 }`.
+    Threshold {
+        numerator: u64,
+        denominator: u64,
+    },
+    /// Two thirds of the stake of the operator set, as recorded in GizaAVS.
+    StakeWeighted,
+}
+
+impl ConsensusKind {
+    /// Builds the strategy.
+    pub fn strategy(&self) -> Arc<dyn ConsensusStrategy> {
+        match *self {
+            ConsensusKind::Unanimous => Arc::new(Unanimous),
+            ConsensusKind::Majority => Arc::new(Majority),
+            ConsensusKind::Threshold {
+                numerator,
+                denominator,
+            } => Arc::new(Threshold {
+                numerator,
+                denominator,
+            }),
+            ConsensusKind::StakeWeighted => Arc::new(StakeWeighted),
+        }
+    }
+
+    /// Whether the strategy weighs responses by the stake of their operator, which then has to
+    /// be fetched from the chain.
+    pub fn uses_stakes(&self) -> bool {
+        matches!(self, ConsensusKind::StakeWeighted)
+    }
+}
+
+impl FromStr for ConsensusKind {
+    type Err = String;
+
+    // `threshold:<numerator>/<denominator>` selects a threshold, e.g. `threshold:2/3`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim().to_lowercase();
+        if let Some(fraction) = value.strip_prefix("threshold:") {
+            let parse = |part: Option<&str>| part.and_then(|part| part.trim().parse().ok());
+            let mut parts = fraction.split('/');
+            return match (parse(parts.next()), parse(parts.next()), parts.next()) {
+                (Some(numerator), Some(denominator), None) => Ok(ConsensusKind::Threshold {
+                    numerator,
+                    denominator,
+                }),
+                _ => Err(format!("Invalid consensus threshold: {:?}", fraction)),
+            };
+        }
+
+        match value.as_str() {
+            "unanimous" => Ok(ConsensusKind::Unanimous),
+            "majority" => Ok(ConsensusKind::Majority),
+            "stake_weighted" => Ok(ConsensusKind::StakeWeighted),
+            other => Err(format!("Unknown consensus strategy: {:?}", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operator_set(count: u8) -> OperatorSet {
+        OperatorSet::new((1..=count).map(Address::repeat_byte).collect())
+    }
+
+    fn response(operator: u8, result: Option<&[u8]>) -> CanonicalResponse {
+        (Address::repeat_byte(operator), result.map(<[u8]>::to_vec))
+    }
+
+    #[test]
+    fn test_unanimous() {
+        let operators = operator_set(3);
+        let agreeing = [
+            response(1, Some(b"42")),
+            response(2, Some(b"42")),
+            response(3, Some(b"42")),
+        ];
+        assert_eq!(
+            Unanimous.decide(&agreeing, &operators),
+            Some(b"42".to_vec())
+        );
+
+        let disagreeing = [
+            response(1, Some(b"42")),
+            response(2, Some(b"42")),
+            response(3, Some(b"43")),
+        ];
+        assert_eq!(Unanimous.decide(&disagreeing, &operators), None);
+
+        let invalid = [response(1, Some(b"42")), response(2, None)];
+        assert_eq!(Unanimous.decide(&invalid, &operators), None);
+
+        assert_eq!(Unanimous.decide(&[], &operators), None);
+    }
+
+    #[test]
+    fn test_majority() {
+        let operators = operator_set(3);
+        let majority = [
+            response(1, Some(b"42")),
+            response(2, None),
+            response(3, Some(b"42")),
+        ];
+        assert_eq!(Majority.decide(&majority, &operators), Some(b"42".to_vec()));
+
+        // Half of the set isn't a majority, even if every response agrees
+        let operators = operator_set(4);
+        let half = [response(1, Some(b"42")), response(2, Some(b"42"))];
+        assert_eq!(Majority.decide(&half, &operators), None);
+
+        // Operators outside the set don't vote
+        let outsiders = [
+            response(1, Some(b"42")),
+            response(2, Some(b"42")),
+            response(9, Some(b"42")),
+        ];
+        assert_eq!(Majority.decide(&outsiders, &operators), None);
+    }
+
+    #[test]
+    fn test_threshold() {
+        let two_thirds = Threshold {
+            numerator: 2,
+            denominator: 3,
+        };
+        let operators = operator_set(3);
+        let reached = [
+            response(1, Some(b"42")),
+            response(2, Some(b"43")),
+            response(3, Some(b"42")),
+        ];
+        assert_eq!(
+            two_thirds.decide(&reached, &operators),
+            Some(b"42".to_vec())
+        );
+
+        // 2 out of 4 is below two thirds
+        let operators = operator_set(4);
+        let below = [response(1, Some(b"42")), response(2, Some(b"42"))];
+        assert_eq!(two_thirds.decide(&below, &operators), None);
+    }
+
+    #[test]
+    fn test_stake_weighted() {
+        let stakes = |stakes: &[(u8, u64)]| -> HashMap<Address, U256> {
+            stakes
+                .iter()
+                .map(|(operator, stake)| (Address::repeat_byte(*operator), U256::from(*stake)))
+                .collect()
+        };
+        let operators = operator_set(3).with_stakes(stakes(&[(1, 60), (2, 30), (3, 10)]));
+
+        // A single operator holding two thirds of the stake outweighs the others
+        let heavy = [
+            response(1, Some(b"42")),
+            response(2, Some(b"43")),
+            response(3, Some(b"43")),
+        ];
+        assert_eq!(StakeWeighted.decide(&heavy, &operators), None);
+        let operators = operator_set(3).with_stakes(stakes(&[(1, 70), (2, 20), (3, 10)]));
+        assert_eq!(
+            StakeWeighted.decide(&heavy, &operators),
+            Some(b"42".to_vec())
+        );
+
+        // Outsiders and invalid responses weigh nothing
+        let outsiders = [
+            response(1, None),
+            response(2, Some(b"42")),
+            response(9, Some(b"42")),
+        ];
+        assert_eq!(StakeWeighted.decide(&outsiders, &operators), None);
+
+        // Without stake, nothing is ever agreed on
+        assert_eq!(StakeWeighted.decide(&heavy, &operator_set(3)), None);
+    }
+
+    #[test]
+    fn test_consensus_kind_from_str() {
+        assert_eq!(
+            "Majority".parse::<ConsensusKind>(),
+            Ok(ConsensusKind::Majority)
+        );
+        assert_eq!(
+            "stake_weighted".parse::<ConsensusKind>(),
+            Ok(ConsensusKind::StakeWeighted)
+        );
+        assert_eq!(
+            "threshold:2/3".parse::<ConsensusKind>(),
+            Ok(ConsensusKind::Threshold {
+                numerator: 2,
+                denominator: 3
+            })
+        );
+        assert!("threshold:2".parse::<ConsensusKind>().is_err());
+        assert!("quorum".parse::<ConsensusKind>().is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use std::{future::Future, net::SocketAddr, sync::Arc};
+
+use alloy_primitives::{FixedBytes, Signature};
+use contract_bindings::TaskId;
+use tonic::{transport::Server, Code, Request, Response, Status};
+use tracing::info;
+
+use crate::server::{self, AppState, AvsState, OperatorResponse, ServerError};
+
+mod proto {
+    tonic::include_proto!("aggregator");
+}
+
+use proto::{
+    aggregator_server::{Aggregator, AggregatorServer},
+    SubmitTaskRequest, SubmitTaskResponse, TaskStatusRequest, TaskStatusResponse,
+};
+
+// Report server errors with the closest gRPC status code
+impl From<ServerError> for Status {
+    fn from(error: ServerError) -> Self {
+        let code = match error {
+            ServerError::InvalidRequest(_)
+            | ServerError::InvalidSignature
+            | ServerError::UnsupportedVersion(_) => Code::InvalidArgument,
+            ServerError::PayloadTooLarge(_) => Code::ResourceExhausted,
+            ServerError::InvalidOperator => Code::PermissionDenied,
+            ServerError::TaskDoesNotExist
+            | ServerError::TaskNotPending
+            | ServerError::UnknownAvs(_)
+            | ServerError::NoResponses
+            | ServerError::NoOffchainResult => Code::NotFound,
+            ServerError::TaskAlreadyCompleted
+            | ServerError::TaskNotFailed(_)
+            | ServerError::ResultAlreadyAggregated
+            | ServerError::TaskFailed => Code::FailedPrecondition,
+            ServerError::Unauthorized => Code::Unauthenticated,
+            ServerError::NotReady(_) | ServerError::ChainSubmissionFailed(_) => Code::Unavailable,
+            ServerError::InsufficientFunds(_) => Code::FailedPrecondition,
+            ServerError::InternalError(_) => Code::Internal,
+        };
+        Status::new(code, error.to_string())
+    }
+}
+
+// gRPC counterpart of the `submit_task` and `task_status` handlers, sharing their state and
+// validation
+struct AggregatorService {
+    state: Arc<AppState>,
+}
+
+impl AggregatorService {
+    // Look up the deployment of a request, the default one when unset
+    fn avs(&self, avs_id: &str) -> Result<&AvsState, ServerError> {
+        match avs_id {
+            "" => self.state.avs(&self.state.default_avs),
+            avs_id => self.state.avs(avs_id),
+        }
+    }
+}
+
+fn parse_task_id(task_id: &[u8]) -> Result<TaskId, ServerError> {
+    FixedBytes::<32>::try_from(task_id)
+        .map(TaskId::from)
+        .map_err(|_| ServerError::InvalidRequest("Task id must be 32 bytes".to_string()))
+}
+
+impl TryFrom<SubmitTaskRequest> for OperatorResponse {
+    type Error = ServerError;
+
+    fn try_from(request: SubmitTaskRequest) -> Result<Self, Self::Error> {
+        Ok(OperatorResponse {
+            task_id: parse_task_id(&request.task_id)?,
+            result: request.result,
+            signature: Signature::try_from(request.signature.as_slice())
+                .map_err(|_| ServerError::InvalidSignature)?,
+            version: u8::try_from(request.version).map_err(|_| {
+                ServerError::InvalidRequest(format!("Invalid version: {}", request.version))
+            })?,
+            exit_code: request.exit_code,
+            duration_ms: request.duration_ms,
+            timestamp: request.timestamp,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Aggregator for AggregatorService {
+    async fn submit_task(
+        &self,
+        request: Request<SubmitTaskRequest>,
+    ) -> Result<Response<SubmitTaskResponse>, Status> {
+        let request = request.into_inner();
+        let avs = self.avs(&request.avs_id)?;
+        server::submit_task(avs, &self.state.config, request.try_into()?).await?;
+
+        Ok(Response::new(SubmitTaskResponse {}))
+    }
+
+    async fn task_status(
+        &self,
+        request: Request<TaskStatusRequest>,
+    ) -> Result<Response<TaskStatusResponse>, Status> {
+        let request = request.into_inner();
+        let avs = self.avs(&request.avs_id)?;
+        let task_status = server::task_status(avs, parse_task_id(&request.task_id)?)?.0;
+
+        // Both enums follow the TaskRegistry numbering
+        Ok(Response::new(TaskStatusResponse {
+            status: u8::from(task_status).into(),
+        }))
+    }
+}
+
+// Serve the gRPC interface on the configured port until `shutdown` resolves, returning right
+// away when no port is configured
+pub async fn run_grpc_server(
+    app_state: Arc<AppState>,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<(), ServerError> {
+    let Some(port) = app_state.config.grpc_port else {
+        return Ok(());
+    };
+    let address = SocketAddr::from(([0, 0, 0, 0], port));
+    let max_message_size = app_state.config.max_request_body_size;
+    let service = AggregatorServer::new(AggregatorService { state: app_state })
+        .max_decoding_message_size(max_message_size);
+
+    info!("gRPC server listening on {}", address);
+    Server::builder()
+        .add_service(service)
+        .serve_with_shutdown(address, shutdown)
+        .await
+        .map_err(|e| ServerError::InternalError(format!("gRPC server error: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{Parity, U256};
+
+    #[test]
+    fn test_submit_task_request() {
+        let signature = Signature::new(U256::from(1), U256::from(2), Parity::Parity(false));
+        let request = SubmitTaskRequest {
+            avs_id: String::new(),
+            task_id: vec![7; 32],
+            result: "42".to_string(),
+            signature: signature.as_bytes().to_vec(),
+            version: 1,
+            exit_code: Some(0),
+            duration_ms: Some(12),
+            timestamp: None,
+        };
+        let response = OperatorResponse::try_from(request.clone()).unwrap();
+        assert_eq!(response.task_id, TaskId(FixedBytes::repeat_byte(7)));
+        assert_eq!(response.result, "42");
+        assert_eq!(response.signature.as_bytes(), signature.as_bytes());
+        assert_eq!(
+            (response.version, response.exit_code, response.duration_ms),
+            (1, Some(0), Some(12))
+        );
+
+        // Malformed fields are rejected as invalid arguments before any validation
+        for invalid in [
+            SubmitTaskRequest {
+                task_id: vec![7; 31],
+                ..request.clone()
+            },
+            SubmitTaskRequest {
+                signature: vec![0; 64],
+                ..request.clone()
+            },
+            SubmitTaskRequest {
+                version: 256,
+                ..request.clone()
+            },
+        ] {
+            let status = Status::from(OperatorResponse::try_from(invalid).unwrap_err());
+            assert_eq!(status.code(), Code::InvalidArgument);
+        }
+    }
+
+    #[test]
+    fn test_status_codes() {
+        for (error, code) in [
+            (ServerError::InvalidOperator, Code::PermissionDenied),
+            (ServerError::TaskDoesNotExist, Code::NotFound),
+            (ServerError::TaskAlreadyCompleted, Code::FailedPrecondition),
+            (ServerError::Unauthorized, Code::Unauthenticated),
+            (
+                ServerError::NotReady("syncing".to_string()),
+                Code::Unavailable,
+            ),
+        ] {
+            assert_eq!(Status::from(error).code(), code);
+        }
+    }
+}
@@ -1,35 +1,55 @@
-use aggregator_config::AggregatorConfig;
+use aggregator_config::{AggregatorConfig, AvsDeployment};
 use alloy::{
+    eips::{BlockId, BlockNumberOrTag},
     network::{Ethereum, EthereumWallet},
     providers::{
         fillers::{
             BlobGasFiller, ChainIdFiller, FillProvider, GasFiller, JoinFill, NonceFiller,
             WalletFiller,
         },
-        Identity, IpcConnect, Provider, ProviderBuilder, RootProvider,
+        Identity, Provider, ProviderBuilder, RootProvider,
     },
     pubsub::PubSubFrontend,
+    rpc::types::TransactionRequest,
+    sol_types::SolCall,
     transports::http::{Client, Http},
 };
-use alloy_primitives::{Address, FixedBytes, U256};
+use alloy_primitives::{keccak256, Address, TxHash, B256, U256};
+use chain::{AvsChain, RpcChain};
+use consensus::{ConsensusStrategy, OperatorSet};
 use contract_bindings::{
-    AVSDirectory::AVSDirectoryInstance, Chain, GizaAVS::GizaAVSInstance,
-    TaskRegistry::TaskRegistryInstance, TaskStatus, AVS_DIRECTORY_ADDRESS, GIZA_AVS_ADDRESS,
-    TASK_REGISTRY_ADDRESS,
+    AppId, Chain, ConfirmationBuffer, ContractAddresses, OperatorAvsStatus, ResultType, TaskId,
+    TaskRegistry::{respondToTaskCall, respondToTasksCall, TaskRegistryInstance, TaskRequested},
+    TaskStatus, TaskTimings,
 };
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
 use eyre::Result;
 use futures::StreamExt;
-use server::{AppState, OperatorResponse};
-use std::sync::Arc;
+use server::{
+    AppState, AvsState, ConsensusStats, OperatorFailure, OperatorMessage, OperatorResponse,
+    RecoveryCache, TaskEvent,
+};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use task_log::{TaskLog, TaskRecord};
+use task_store::{OffchainResult, TaskStore};
 use thiserror::Error;
-use tokio::sync::mpsc;
-use tokio::time::sleep;
-use tracing::{error, info};
+use tokio::sync::{broadcast, mpsc, Mutex as AsyncMutex};
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time::{sleep, timeout_at, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, info_span, warn, Instrument};
 
 pub mod aggregator_config;
+mod chain;
+pub mod consensus;
+#[cfg(feature = "grpc")]
+mod grpc;
 pub mod server;
+pub mod task_log;
+pub mod task_store;
 
 // Define custom error types for better error handling and reporting
 #[derive(Error, Debug)]
@@ -44,12 +64,20 @@ pub enum AggregatorError {
     TaskListenerError(String),
     #[error("Server error: {0}")]
     ServerError(String),
-    #[error("Signature error: {0}")]
-    SignatureError(String),
     #[error("Tx error: {0}")]
     TxError(String),
+    #[error("Insufficient funds: {0}")]
+    InsufficientFunds(String),
+    #[error("Task store error: {0}")]
+    TaskStoreError(String),
+    #[error("Config error: {0}")]
+    ConfigError(String),
 }
 
+// Crate version and the git commit it was built from
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const GIT_SHA: &str = env!("GIT_SHA");
+
 // Type alias for the complex provider type to improve readability
 pub type HttpProviderWithSigner = Arc<
     FillProvider<
@@ -66,40 +94,410 @@ pub type HttpProviderWithSigner = Arc<
     >,
 >;
 
-type OperatorResponsesByTaskId = DashMap<FixedBytes<32>, DashMap<Address, OperatorResponse>>;
+// Task events buffered for the slowest `/tasks/:task_id/events` client before it starts missing
+// some
+const TASK_EVENTS_CAPACITY: usize = 256;
+
+type OperatorResponsesByTaskId = DashMap<TaskId, DashMap<Address, OperatorResponse>>;
+
+type OperatorFailuresByTaskId = DashMap<TaskId, DashMap<Address, String>>;
+
+type TimingsByTaskId = DashMap<TaskId, TaskTimings>;
+
+type PipelineHandle = JoinHandle<Result<(), AggregatorError>>;
+
+// Handles of the background tasks of a deployment, each ending once the tasks feeding it hang up
+struct Pipeline {
+    // Ends once the server drops its sender and the queued responses are persisted
+    response_queue: PipelineHandle,
+    // Ends once the response queue, the task listener and the timeouts stop, after processing
+    // the responses already aggregated
+    task_processor: JoinHandle<()>,
+    // Ends once the task processor stops, after submitting its results
+    result_sender: PipelineHandle,
+}
 
 #[derive(Debug, Clone)]
 struct AggregatedResponse {
-    task_id: FixedBytes<32>,
+    task_id: TaskId,
     responses: DashMap<Address, OperatorResponse>,
 }
 
 #[derive(Debug, Clone)]
 struct TaskResult {
-    task_id: FixedBytes<32>,
+    task_id: TaskId,
     status: TaskStatus,
     result: U256,
+    // keccak256 of the canonical consensus result, unset for failed tasks
+    result_hash: Option<B256>,
+    // Number of times the result was resubmitted after failing to land on-chain
+    resubmissions: u32,
+}
+
+// A broadcast `respondToTask` (or `respondToTasks`) transaction that has not been confirmed yet
+#[derive(Debug, Clone)]
+struct PendingSubmission {
+    // The results submitted by the transaction, more than one when batched
+    task_results: Vec<TaskResult>,
+    // The request with its nonce and fees pinned, so it can be replaced at the same nonce
+    tx_request: TransactionRequest,
+    // Hashes of every version broadcast for this nonce, any of which may end up mined
+    tx_hashes: Vec<TxHash>,
+}
+
+// How a submission left the mempool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SubmissionOutcome {
+    // One of its versions was mined and succeeded
+    Succeeded(TxHash),
+    // One of its versions was mined and reverted
+    Reverted(TxHash),
+    // Its nonce was used by another transaction, none of its versions can be mined anymore
+    NonceConsumed,
+}
+
+impl PendingSubmission {
+    fn task_ids(&self) -> Vec<TaskId> {
+        self.task_results
+            .iter()
+            .map(|task_result| task_result.task_id)
+            .collect()
+    }
+}
+
+// Canonical bytes of a raw `result_type` result along with their keccak256 hash. Every result
+// agreeing with the consensus yields the same hash, so verifiers can recompute it from any of the
+// operator responses.
+pub(crate) fn canonical_result(
+    result_type: ResultType,
+    raw: &str,
+) -> eyre::Result<(Vec<u8>, B256)> {
+    let canonical = result_type.canonicalize(raw)?;
+    let hash = keccak256(&canonical);
+    Ok((canonical, hash))
+}
+
+// Results of the responses to a task, ordered by operator address. Maps iterate in an arbitrary
+// order, so consensus and the off-chain result are only reproducible over sorted results.
+pub(crate) fn sorted_results(
+    responses: &DashMap<Address, OperatorResponse>,
+) -> Vec<(Address, String)> {
+    let mut results = responses
+        .iter()
+        .map(|entry| (*entry.key(), entry.value().result.clone()))
+        .collect::<Vec<_>>();
+    results.sort_unstable_by_key(|(operator, _)| *operator);
+    results
+}
+
+// Full consensus result of a task whose `result_type` is only submitted on-chain as its hash,
+// recovered from the first operator result matching `result_hash`. `uint256` results are
+// submitted as is and have nothing to keep off-chain.
+pub(crate) fn offchain_result(
+    result_type: ResultType,
+    result_hash: B256,
+    results: &[(Address, String)],
+) -> Option<OffchainResult> {
+    if result_type == ResultType::Uint256 {
+        return None;
+    }
+    results.iter().find_map(|(_, raw)| {
+        let (canonical, hash) = canonical_result(result_type, raw).ok()?;
+        (hash == result_hash).then(|| OffchainResult {
+            result_type,
+            result: canonical.into(),
+        })
+    })
+}
+
+// Reach consensus over the results submitted for a task: it completes with the result
+// `strategy` picks among the canonical results, and fails if there is none. Completed tasks also
+// get the hash of their canonical result.
+pub(crate) fn compute_consensus(
+    task_id: TaskId,
+    result_type: ResultType,
+    strategy: &dyn ConsensusStrategy,
+    operator_set: &OperatorSet,
+    results: &[(Address, String)],
+) -> (TaskStatus, U256, Option<B256>) {
+    // Results that don't match the app's result type are left for the strategy to weigh
+    let canonical_results = results
+        .iter()
+        .map(|(operator, result)| {
+            let canonical = result_type
+                .canonicalize(result)
+                .inspect_err(|e| {
+                    warn!(
+                        "Invalid {:?} result from operator {:?}: {:?}",
+                        result_type, operator, e
+                    )
+                })
+                .ok();
+            (*operator, canonical)
+        })
+        .collect::<Vec<_>>();
+
+    match strategy.decide(&canonical_results, operator_set) {
+        Some(result) => {
+            let hash = keccak256(&result);
+            info!(
+                "Consensus reached for task: \x1b[1;33m{:?}\x1b[0m (result hash {:?})",
+                task_id, hash
+            );
+            (
+                TaskStatus::COMPLETED,
+                result_type.onchain_value(&result),
+                Some(hash),
+            )
+        }
+        _ => {
+            info!(
+                "Consensus not reached for task: \x1b[1;33m{:?}\x1b[0m",
+                task_id
+            );
+            (TaskStatus::FAILED, U256::ZERO, None)
+        }
+    }
+}
+
+// Build the transaction answering `task_results` on the TaskRegistry at `task_registry`. A single
+// result goes through `respondToTask`, which costs less than a batch of one.
+fn respond_request(task_registry: Address, task_results: &[TaskResult]) -> TransactionRequest {
+    let input = match task_results {
+        [task_result] => respondToTaskCall {
+            taskId: task_result.task_id.into(),
+            status: task_result.status.clone().into(),
+            taskResult: task_result.result,
+        }
+        .abi_encode(),
+        _ => respondToTasksCall {
+            taskIds: task_results
+                .iter()
+                .map(|task_result| task_result.task_id.into())
+                .collect(),
+            statuses: task_results
+                .iter()
+                .map(|task_result| task_result.status.clone().into())
+                .collect(),
+            taskResults: task_results
+                .iter()
+                .map(|task_result| task_result.result)
+                .collect(),
+        }
+        .abi_encode(),
+    };
+
+    TransactionRequest::default()
+        .to(task_registry)
+        .input(input.into())
 }
 
-// Main Aggregator struct representing the core functionality
+// Main Aggregator struct, running one pipeline per served AVS deployment behind a single server
 pub struct Aggregator {
+    avs: Vec<AvsAggregator>,
+    config: Arc<AggregatorConfig>,
+}
+
+// The listen/aggregate/submit pipeline of a single AVS deployment, with its own tasks, operator
+// responses and task store
+#[derive(Clone)]
+struct AvsAggregator {
+    avs_id: String,
+    chain: Chain,
+    addresses: ContractAddresses,
     operator_list: Arc<DashMap<Address, ()>>,
-    tasks: Arc<DashMap<FixedBytes<32>, TaskStatus>>,
+    // Stake of the registered operators, only fetched when the consensus strategy weighs it
+    operator_stakes: Arc<DashMap<Address, U256>>,
+    tasks: Arc<DashMap<TaskId, TaskStatus>>,
+    task_app_ids: Arc<DashMap<TaskId, AppId>>,
     operator_responses: Arc<OperatorResponsesByTaskId>,
-    http_provider: HttpProviderWithSigner,
-    pubsub_provider: Arc<RootProvider<PubSubFrontend>>,
+    // Reasons reported by the operators that failed each in-flight task, which aren't waited for
+    operator_failures: Arc<OperatorFailuresByTaskId>,
+    // Operators expected to respond to each in-flight task, with their stake, snapshotted when
+    // the task is created so that operator list refreshes don't move the target mid-task
+    task_operator_sets: Arc<DashMap<TaskId, OperatorSet>>,
+    // Tasks handed over to aggregation, whose later responses don't trigger another one
+    aggregated_tasks: Arc<DashSet<TaskId>>,
+    // Timers aggregating each pending task once `task_timeout_secs` elapse, removed by whichever
+    // of the timer and the last response aggregates the task first
+    task_timeouts: Arc<DashMap<TaskId, JoinHandle<()>>>,
+    timings: Arc<TimingsByTaskId>,
+    // Finalized tasks still held in `tasks`, oldest first, used to cap its size
+    finalized_tasks: Arc<Mutex<VecDeque<TaskId>>>,
+    // Reads and writes to the deployment's contracts
+    avs_chain: Arc<dyn AvsChain>,
+    task_store: Arc<TaskStore>,
+    // Audit trail of finalized tasks, shared by every deployment
+    task_log: Arc<TaskLog>,
+    // Operators recovered from response signatures, shared with the server
+    recovery_cache: Arc<RecoveryCache>,
+    // Outcome of the consensus rounds, served by `/stats`
+    consensus_stats: Arc<ConsensusStats>,
+    // Status transitions of tasks, followed by the `/tasks/:task_id/events` clients
+    task_events: broadcast::Sender<TaskEvent>,
+    // Next nonce of the aggregator account, shared by the deployments on the same chain so their
+    // submissions don't collide. `None` until fetched from the chain.
+    next_nonce: Arc<AsyncMutex<Option<u64>>>,
+    // Blocks a task event must be buried under before the task is treated as pending
+    task_confirmations: u64,
+    consensus: Arc<dyn ConsensusStrategy>,
+    // Stops the task listener, the operator list refresher and the task timeouts once the
+    // server stopped and the response queue is flushed
+    shutdown: CancellationToken,
+    config: Arc<AggregatorConfig>,
 }
 
 impl Aggregator {
-    // Initialize a new Aggregator instance
-    pub async fn new(chain: Chain) -> Result<Self, AggregatorError> {
-        let config = AggregatorConfig::from_env();
+    // Initialize a new Aggregator instance, serving the configured deployments or the GizaAVS
+    // deployment on `chain` if none is configured
+    pub async fn new(chain: Chain, config: AggregatorConfig) -> Result<Self, AggregatorError> {
+        let config = Arc::new(config);
+
+        let deployments = if config.deployments.is_empty() {
+            vec![AvsDeployment::default_for(chain)]
+        } else {
+            config.deployments.clone()
+        };
+
+        let mut avs: Vec<AvsAggregator> = Vec::with_capacity(deployments.len());
+        let mut nonces: HashMap<Chain, Arc<AsyncMutex<Option<u64>>>> = HashMap::new();
+        let task_log = Arc::new(TaskLog::open(config.ndjson_log_path.as_deref()));
+        for deployment in &deployments {
+            if config.keystore_path.is_none() && deployment.chain != Chain::Anvil {
+                return Err(AggregatorError::ConfigError(format!(
+                    "AGGREGATOR_KEYSTORE_PATH must be set to serve {:?} on {:?}, the built-in key is only accepted on Anvil",
+                    deployment.id, deployment.chain
+                )));
+            }
+
+            if avs.iter().any(|other| other.avs_id == deployment.id) {
+                return Err(AggregatorError::ConfigError(format!(
+                    "Duplicate deployment id: {:?}",
+                    deployment.id
+                )));
+            }
+
+            // Deployments must not share a store, so a default one is derived from the id
+            let task_store_path = match &deployment.task_store_path {
+                Some(path) => path.clone(),
+                None if deployments.len() == 1 => config.task_store_path.clone(),
+                None => {
+                    let file_name = config
+                        .task_store_path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    config
+                        .task_store_path
+                        .with_file_name(format!("{}_{}", deployment.id, file_name))
+                }
+            };
+            let next_nonce = nonces.entry(deployment.chain.clone()).or_default().clone();
+
+            avs.push(AvsAggregator::new(
+                deployment,
+                task_store_path,
+                task_log.clone(),
+                next_nonce,
+                config.clone(),
+            )?);
+        }
+
+        Ok(Self { avs, config })
+    }
+
+    // Main run function to start the Aggregator, until `shutdown` is cancelled. On shutdown the
+    // server stops accepting responses and the ones already acknowledged are drained from the
+    // queue and persisted. The background tasks of every deployment then stop, and the results
+    // already aggregated are broadcast and awaited before returning, so the process never exits
+    // in the middle of a `respondToTask`. The task stores are synced last.
+    pub async fn run(&self, shutdown: CancellationToken) -> Result<(), AggregatorError> {
+        let mut avs_states = HashMap::with_capacity(self.avs.len());
+        let mut pipelines = Vec::with_capacity(self.avs.len());
+        for avs in &self.avs {
+            let span = info_span!("avs", id = %avs.avs_id);
+            let (avs_state, pipeline) = avs.start().instrument(span).await?;
+            avs_states.insert(avs.avs_id.clone(), Arc::new(avs_state));
+            pipelines.push(pipeline);
+        }
+
+        // Start the server
+        info!("Initialization complete. Starting server...");
+        let app_state = AppState {
+            avs: avs_states,
+            default_avs: self.avs[0].avs_id.clone(),
+            config: self.config.clone(),
+        };
+
+        server::run_server(app_state, shutdown.cancelled_owned())
+            .await
+            .map_err(|e| AggregatorError::ServerError(e.to_string()))?;
+
+        self.drain(pipelines).await
+    }
+
+    // Wait for `pipelines` to finish the work queued before the server stopped: the queued
+    // responses are persisted, the aggregated results submitted, and the task stores synced
+    async fn drain(&self, pipelines: Vec<Pipeline>) -> Result<(), AggregatorError> {
+        // The server dropped its senders, so the queues end once every buffered response is
+        // persisted
+        info!("Server stopped. Flushing queued operator responses...");
+        let mut drained = Vec::with_capacity(pipelines.len());
+        for pipeline in pipelines {
+            match pipeline.response_queue.await {
+                Ok(result) => result?,
+                Err(e) => error!("Operator response queue panicked: {:?}", e),
+            }
+            drained.push((pipeline.task_processor, pipeline.result_sender));
+        }
+
+        // Nothing feeds the pipelines anymore once their listeners and timeouts stop, so the
+        // task processors end after processing the responses already aggregated, and the result
+        // senders after submitting their results
+        info!("Flushing task results...");
+        for avs in &self.avs {
+            avs.shutdown.cancel();
+        }
+        for (task_processor, result_sender) in drained {
+            if let Err(e) = task_processor.await {
+                error!("Task processor panicked: {:?}", e);
+            }
+            match result_sender.await {
+                Ok(result) => result?,
+                Err(e) => error!("Task result sender panicked: {:?}", e),
+            }
+        }
+
+        // The task stores write in the background, wait for their last changes
+        for avs in &self.avs {
+            let task_store = avs.task_store.clone();
+            if let Err(e) = tokio::task::spawn_blocking(move || task_store.sync()).await {
+                error!("Task store sync panicked: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl AvsAggregator {
+    // Block the state the aggregator acts on is read at
+    fn read_block(&self) -> BlockId {
+        BlockNumberOrTag::from(self.config.read_block_tag).into()
+    }
 
-        let ecdsa_signer = config.ecdsa_signer;
-        let wallet = EthereumWallet::from(ecdsa_signer.clone());
+    // Initialize the pipeline of `deployment`
+    fn new(
+        deployment: &AvsDeployment,
+        task_store_path: PathBuf,
+        task_log: Arc<TaskLog>,
+        next_nonce: Arc<AsyncMutex<Option<u64>>>,
+        config: Arc<AggregatorConfig>,
+    ) -> Result<Self, AggregatorError> {
+        let wallet = EthereumWallet::from(config.ecdsa_signer.clone());
 
         // Create HttpProvider
-        let rpc_url = chain.http_url();
+        let rpc_url = deployment.chain.http_url();
 
         let http_provider = Arc::new(
             ProviderBuilder::new()
@@ -107,171 +505,417 @@ impl Aggregator {
                 .wallet(wallet)
                 .on_http(rpc_url),
         );
+        let avs_chain = RpcChain::new(
+            deployment.addresses,
+            http_provider,
+            config.log_query_block_range,
+        );
 
-        let pubsub_provider: Arc<RootProvider<PubSubFrontend>> = match chain {
-            Chain::Anvil => {
-                let ipc = IpcConnect::new("/tmp/anvil.ipc".to_string());
-                ProviderBuilder::new()
-                    .on_ipc(ipc)
-                    .await
-                    .map_err(|e| AggregatorError::ProviderInitError(e.to_string()))?
-            }
-            Chain::Holesky => ProviderBuilder::new()
-                .on_ws(alloy::providers::WsConnect {
-                    url: chain.ws_url().to_string(),
-                    auth: None,
-                })
-                .await
-                .map_err(|e| AggregatorError::ProviderInitError(e.to_string()))?,
-        }
-        .into();
+        let task_store = Arc::new(TaskStore::open(&task_store_path)?);
+
+        Ok(Self::with_chain(
+            deployment,
+            Arc::new(avs_chain),
+            task_store,
+            task_log,
+            next_nonce,
+            config,
+        ))
+    }
 
-        Ok(Self {
+    // Initialize the pipeline of `deployment` on top of `avs_chain`
+    fn with_chain(
+        deployment: &AvsDeployment,
+        avs_chain: Arc<dyn AvsChain>,
+        task_store: Arc<TaskStore>,
+        task_log: Arc<TaskLog>,
+        next_nonce: Arc<AsyncMutex<Option<u64>>>,
+        config: Arc<AggregatorConfig>,
+    ) -> Self {
+        let task_confirmations = config
+            .task_confirmations
+            .unwrap_or_else(|| deployment.chain.default_task_confirmations());
+
+        Self {
+            avs_id: deployment.id.clone(),
+            chain: deployment.chain.clone(),
+            addresses: deployment.addresses,
             operator_list: Arc::new(DashMap::new()),
+            operator_stakes: Arc::new(DashMap::new()),
             tasks: Arc::new(DashMap::new()),
+            task_app_ids: Arc::new(DashMap::new()),
             operator_responses: Arc::new(DashMap::new()),
-            http_provider,
-            pubsub_provider,
-        })
+            operator_failures: Arc::new(DashMap::new()),
+            task_operator_sets: Arc::new(DashMap::new()),
+            aggregated_tasks: Arc::new(DashSet::new()),
+            task_timeouts: Arc::new(DashMap::new()),
+            timings: Arc::new(DashMap::new()),
+            finalized_tasks: Arc::new(Mutex::new(VecDeque::new())),
+            avs_chain,
+            task_store,
+            task_log,
+            recovery_cache: Arc::new(RecoveryCache::new(config.recovery_cache_size)),
+            consensus_stats: Arc::new(ConsensusStats::default()),
+            task_events: broadcast::channel(TASK_EVENTS_CAPACITY).0,
+            next_nonce,
+            task_confirmations,
+            consensus: config.consensus.strategy(),
+            shutdown: CancellationToken::new(),
+            config,
+        }
+    }
+
+    // Start the deployment, returning its server state and the handles of its pipeline
+    async fn start(&self) -> Result<(AvsState, Pipeline), AggregatorError> {
+        // New tasks are only received over pubsub, the node must be reachable before going on
+        let pubsub_provider = Arc::new(
+            self.chain
+                .connect_pubsub()
+                .await
+                .map_err(|e| AggregatorError::ProviderInitError(format!("{:#}", e)))?,
+        );
+
+        let (avs_state, tx_new_task, pipeline) = self.start_pipeline().await?;
+
+        // Spawn the task listener
+        let task_listener = self.clone();
+        tokio::spawn(
+            async move {
+                if let Err(e) = task_listener
+                    .listen_for_task(pubsub_provider, tx_new_task)
+                    .await
+                {
+                    error!("Task listener error: {:?}", e);
+                }
+            }
+            .in_current_span(),
+        );
+
+        // Spawn the operator list refresher, to pick up operators registering after startup
+        tokio::spawn(self.clone().refresh_operator_list().in_current_span());
+
+        Ok((avs_state, pipeline))
     }
 
-    // Main run function to start the Aggregator
-    pub async fn run(&mut self) -> Result<(), AggregatorError> {
+    // Restore the state of the deployment and spawn its pipeline, returning the server state,
+    // the sender of new tasks for the task listener and the handles of the pipeline
+    async fn start_pipeline(
+        &self,
+    ) -> Result<(AvsState, mpsc::Sender<AggregatedResponse>, Pipeline), AggregatorError> {
         // Fetch and update operator list
         let fetched_operators = self.fetch_operator_list().await?;
-        self.operator_list.clear();
-        for operator in fetched_operators {
-            self.operator_list.insert(operator, ());
+        self.update_operator_list(fetched_operators);
+        self.update_operator_stakes().await;
+        if self.operator_list.is_empty() {
+            warn!("\x1b[1;31mNo registered operators found, tasks won't be aggregated\x1b[0m");
         }
 
         // Fetch and update task history
-        self.tasks = Arc::new(self.fetch_task_history().await?);
+        self.fetch_task_history().await?;
 
-        // Spawn the task listener
-        let tasks = self.tasks.clone();
-        let pubsub_provider = self.pubsub_provider.clone();
-        tokio::spawn(async move {
-            if let Err(e) = Self::listen_for_task(tasks, pubsub_provider).await {
-                error!("Task listener error: {:?}", e);
-            }
-        });
+        // Restore responses received before the last shutdown
+        self.restore_operator_responses()?;
 
         // Create channels for operator responses and task processing
-        let (tx_response, rx_response) = mpsc::channel::<OperatorResponse>(100);
+        let (tx_response, rx_response) = mpsc::channel::<OperatorMessage>(100);
         let (tx_aggregated_response, rx_aggregated_response) =
             mpsc::channel::<AggregatedResponse>(100);
         let (tx_task_process, rx_task_process) = mpsc::channel::<TaskResult>(100);
-        let operator_responses = self.operator_responses.clone();
-        let operator_list = self.operator_list.clone();
-        let tasks = self.tasks.clone();
-
-        // Spawn the operator response queue processor
-        tokio::spawn(Self::queue_operator_response(
-            rx_response,
-            operator_responses.clone(),
-            tx_aggregated_response,
-            operator_list.clone(),
-        ));
+        let (tx_task_retry, rx_task_retry) = mpsc::channel::<TaskResult>(100);
 
-        // Spawn the task processor
-        tokio::spawn(Self::process_completed_tasks(
-            rx_aggregated_response,
-            tx_task_process,
-            tasks,
-        ));
+        // Spawn the pipeline before restoring tasks, as the restored aggregations can outnumber
+        // the slots of its channels
+        let response_queue = tokio::spawn(
+            self.clone()
+                .queue_operator_response(rx_response, tx_aggregated_response.clone())
+                .in_current_span(),
+        );
+        let task_processor = tokio::spawn(
+            self.clone()
+                .process_completed_tasks(rx_aggregated_response, tx_task_process)
+                .in_current_span(),
+        );
+        let result_sender = tokio::spawn(
+            self.clone()
+                .send_task_result(rx_task_process, rx_task_retry, tx_task_retry)
+                .in_current_span(),
+        );
 
-        // Spawn the task result sender
-        tokio::spawn(Self::send_task_result(
-            rx_task_process,
-            self.http_provider.clone(),
-        ));
+        // Tasks whose responses were all received before the last shutdown are aggregated again
+        let restored_aggregations = self.restored_aggregations();
+        let pending_tasks = self
+            .tasks
+            .iter()
+            .filter(|entry| *entry.value() == TaskStatus::PENDING)
+            .map(|entry| *entry.key())
+            .filter(|task_id| {
+                !restored_aggregations
+                    .iter()
+                    .any(|aggregated_response| aggregated_response.task_id == *task_id)
+            })
+            .collect::<Vec<_>>();
+        for aggregated_response in restored_aggregations {
+            if let Err(e) = tx_aggregated_response.send(aggregated_response).await {
+                error!("Error sending restored aggregated response: {:?}", e);
+            }
+        }
+        // The other pending tasks get a full timeout from now, as their requests aren't timed
+        for task_id in pending_tasks {
+            self.start_task_timeout(task_id, &tx_aggregated_response);
+        }
 
-        // Start the server
-        info!("Initialization complete. Starting server...");
-        let app_state = AppState {
+        let pipeline = Pipeline {
+            response_queue,
+            task_processor,
+            result_sender,
+        };
+        Ok((
+            self.avs_state(tx_response),
+            tx_aggregated_response,
+            pipeline,
+        ))
+    }
+
+    // Server state of the deployment, sharing its maps with the pipeline so both sides see the
+    // same tasks and responses, and queueing the received responses on `sender`
+    fn avs_state(&self, sender: mpsc::Sender<OperatorMessage>) -> AvsState {
+        AvsState {
             operator_list: self.operator_list.clone(),
             tasks: self.tasks.clone(),
-            sender: tx_response,
-        };
+            operator_responses: self.operator_responses.clone(),
+            operator_failures: self.operator_failures.clone(),
+            task_operator_sets: self.task_operator_sets.clone(),
+            aggregated_tasks: self.aggregated_tasks.clone(),
+            finalized_tasks: self.finalized_tasks.clone(),
+            task_store: self.task_store.clone(),
+            recovery_cache: self.recovery_cache.clone(),
+            consensus_stats: self.consensus_stats.clone(),
+            task_events: self.task_events.clone(),
+            sender,
+            avs_chain: self.avs_chain.clone(),
+        }
+    }
 
-        server::run_server(app_state)
-            .await
-            .map_err(|e| AggregatorError::ServerError(e.to_string()))
+    // Aggregated responses for restored tasks that already have a response from every operator
+    fn restored_aggregations(&self) -> Vec<AggregatedResponse> {
+        let operator_length = self.operator_list.len();
+        if operator_length == 0 {
+            return Vec::new();
+        }
+
+        self.operator_responses
+            .iter()
+            .filter(|entry| entry.value().len() >= operator_length)
+            .map(|entry| AggregatedResponse {
+                task_id: *entry.key(),
+                responses: entry.value().clone(),
+            })
+            .collect()
     }
 
     // Fetch the list of registered operators
     async fn fetch_operator_list(&self) -> Result<Vec<Address>, AggregatorError> {
         info!("Fetching operator list");
-        let giza_avs = GizaAVSInstance::new(GIZA_AVS_ADDRESS, self.http_provider.clone());
-        let avs_directory =
-            AVSDirectoryInstance::new(AVS_DIRECTORY_ADDRESS, self.http_provider.clone());
 
         // Fetch operators list from GizaAVS
-        let operator_list = giza_avs
-            .OperatorRegistered_filter()
-            .from_block(2577255)
-            .query()
-            .await
-            .map_err(|e| AggregatorError::OperatorListFetchError(e.to_string()))?
-            .into_iter()
-            .map(|(operator_address, _)| operator_address.operator)
-            .collect::<Vec<_>>();
+        let operator_list = self.avs_chain.registered_operators().await?;
 
         // Filter out operators not registered in AVS Directory
         let mut registered_operators = Vec::new();
         for &operator in &operator_list {
-            let is_registered = avs_directory
-                .avsOperatorStatus(GIZA_AVS_ADDRESS, operator)
-                .call()
+            let status = self
+                .avs_chain
+                .operator_status(operator, self.read_block())
                 .await
-                .map(|status| status._0 != U256::ZERO)
-                .unwrap_or(false);
-            if is_registered {
-                registered_operators.push(operator);
+                .unwrap_or(OperatorAvsStatus::UNREGISTERED);
+            match status {
+                OperatorAvsStatus::REGISTERED => registered_operators.push(operator),
+                OperatorAvsStatus::UNREGISTERED => {}
+                OperatorAvsStatus::UNKNOWN(value) => warn!(
+                    "Ignoring operator \x1b[1;34m{:?}\x1b[0m with unknown AVSDirectory status {}",
+                    operator, value
+                ),
             }
         }
 
         Ok(registered_operators)
     }
 
+    // Replace the operator list with `operators`, leaving unchanged entries in place
+    fn update_operator_list(&self, operators: Vec<Address>) {
+        self.operator_list
+            .retain(|operator, _| operators.contains(operator));
+        for operator in operators {
+            if self.operator_list.insert(operator, ()).is_none() {
+                info!("Operator \x1b[1;34m{:?}\x1b[0m registered", operator);
+            }
+        }
+    }
+
+    // Fetch the stake of every registered operator when the consensus strategy weighs it. An
+    // operator whose stake can't be fetched keeps its previous stake, or none.
+    async fn update_operator_stakes(&self) {
+        if !self.config.consensus.uses_stakes() {
+            return;
+        }
+
+        let operators: Vec<Address> = self
+            .operator_list
+            .iter()
+            .map(|entry| *entry.key())
+            .collect();
+        self.operator_stakes
+            .retain(|operator, _| operators.contains(operator));
+        for operator in operators {
+            match self
+                .avs_chain
+                .operator_stake(operator, self.read_block())
+                .await
+            {
+                Ok(stake) => {
+                    if self.operator_stakes.insert(operator, stake) != Some(stake) {
+                        info!(
+                            "Operator \x1b[1;34m{:?}\x1b[0m has a stake of {}",
+                            operator, stake
+                        );
+                    }
+                }
+                Err(e) => warn!(
+                    "Failed to fetch the stake of operator \x1b[1;34m{:?}\x1b[0m: {:?}",
+                    operator, e
+                ),
+            }
+        }
+    }
+
+    // Periodically re-fetch the list of registered operators
+    async fn refresh_operator_list(self) {
+        let interval = Duration::from_secs(self.config.operator_refresh_interval_secs);
+        if interval.is_zero() {
+            return;
+        }
+
+        loop {
+            tokio::select! {
+                _ = sleep(interval) => (),
+                _ = self.shutdown.cancelled() => break,
+            }
+            match self.fetch_operator_list().await {
+                Ok(operators) => {
+                    self.update_operator_list(operators);
+                    self.update_operator_stakes().await;
+                }
+                Err(e) => error!("Failed to refresh operator list: {:?}", e),
+            }
+        }
+    }
+
     // Fetch the history of tasks
-    async fn fetch_task_history(
-        &self,
-    ) -> Result<DashMap<FixedBytes<32>, TaskStatus>, AggregatorError> {
+    async fn fetch_task_history(&self) -> Result<(), AggregatorError> {
         info!("Fetching task history");
-        let task_registry =
-            TaskRegistryInstance::new(TASK_REGISTRY_ADDRESS, self.http_provider.clone());
 
-        let task_list = task_registry
-            .TaskRequested_filter()
-            .from_block(2577255)
-            .query()
-            .await
-            .map_err(|e| AggregatorError::TaskHistoryFetchError(e.to_string()))?
+        let task_list = self
+            .avs_chain
+            .requested_tasks()
+            .await?
             .into_iter()
-            .map(|(task_id, _)| task_id.taskId)
+            .map(|(task_id, app_id)| {
+                self.task_app_ids.insert(task_id, app_id);
+                task_id
+            })
             .collect::<Vec<_>>();
 
-        let tasks = DashMap::new();
+        let mut task_statuses = Vec::with_capacity(task_list.len());
         for task in task_list {
-            let task_status = task_registry
-                .tasks(task)
-                .call()
-                .await
-                .map_err(|e| AggregatorError::TaskHistoryFetchError(e.to_string()))?
-                ._0;
-            tasks.insert(task, TaskStatus::from(task_status));
+            let task_status = self.avs_chain.task_status(task, self.read_block()).await?;
+            task_statuses.push((task, task_status));
+        }
+
+        self.task_store
+            .insert_task_statuses(task_statuses.iter().cloned());
+
+        // Finalized tasks are kept in memory oldest first, up to the configured window
+        for (task, task_status) in task_statuses {
+            self.tasks.insert(task, task_status.clone());
+            if task_status == TaskStatus::COMPLETED || task_status == TaskStatus::FAILED {
+                self.finalized_tasks.lock().unwrap().push_back(task);
+            }
+        }
+        self.evict_finalized_tasks();
+
+        Ok(())
+    }
+
+    // Reload persisted operator responses, keeping only those for tasks still pending on-chain
+    fn restore_operator_responses(&self) -> Result<(), AggregatorError> {
+        for (task_id, responses) in self.task_store.operator_responses() {
+            let task_status = self.tasks.get(&task_id).as_deref().cloned();
+
+            if task_status != Some(TaskStatus::PENDING) {
+                info!(
+                    "Discarding stale responses for task \x1b[1;33m{:?}\x1b[0m ({:?})",
+                    task_id, task_status
+                );
+                self.task_store.remove_operator_responses(&task_id);
+                continue;
+            }
+
+            info!(
+                "Restored {} responses for task \x1b[1;33m{:?}\x1b[0m",
+                responses.len(),
+                task_id
+            );
+            self.operator_responses
+                .insert(task_id, responses.into_iter().collect());
+        }
+
+        Ok(())
+    }
+
+    // Record the final status of a task, persisting it so it can still be served once evicted
+    fn finalize_task(&self, task_id: TaskId, task_status: TaskStatus) {
+        self.task_store
+            .insert_task_status(task_id, task_status.clone());
+        self.tasks.insert(task_id, task_status);
+        self.finalized_tasks.lock().unwrap().push_back(task_id);
+        self.evict_finalized_tasks();
+    }
+
+    // Drop the oldest finalized tasks from memory while above the configured window, moving them
+    // to the archive of the task store. Pending tasks are never evicted.
+    fn evict_finalized_tasks(&self) {
+        let mut finalized_tasks = self.finalized_tasks.lock().unwrap();
+        let mut evicted = Vec::new();
+        while self.tasks.len() > self.config.max_tasks_in_memory {
+            let Some(task_id) = finalized_tasks.pop_front() else {
+                break;
+            };
+            self.tasks.remove(&task_id);
+            self.task_app_ids.remove(&task_id);
+            self.timings.remove(&task_id);
+            evicted.push(task_id);
         }
+        drop(finalized_tasks);
+        self.task_store.archive(&evicted);
+    }
 
-        Ok(tasks)
+    // Drop the responses of a task whose result has been submitted on-chain
+    fn evict_operator_responses(&self, task_id: &TaskId) {
+        self.operator_responses.remove(task_id);
+        self.operator_failures.remove(task_id);
+        self.task_operator_sets.remove(task_id);
+        self.aggregated_tasks.remove(task_id);
+        self.task_store.remove_operator_responses(task_id);
     }
 
-    // Listen for new tasks and update the task list
+    // Listen for new tasks on `pubsub_provider` and update the task list
     async fn listen_for_task(
-        tasks: Arc<DashMap<FixedBytes<32>, TaskStatus>>,
+        self,
         pubsub_provider: Arc<RootProvider<PubSubFrontend>>,
+        tx_aggregated_response: mpsc::Sender<AggregatedResponse>,
     ) -> Result<(), AggregatorError> {
-        let task_registry = TaskRegistryInstance::new(TASK_REGISTRY_ADDRESS, pubsub_provider);
+        let task_registry =
+            TaskRegistryInstance::new(self.addresses.task_registry, pubsub_provider.clone());
 
         let mut stream = task_registry
             .TaskRequested_filter()
@@ -280,96 +924,510 @@ impl Aggregator {
             .map_err(|e| AggregatorError::TaskListenerError(e.to_string()))?
             .into_stream();
 
-        info!("Subscribed to TaskRegistry events. Waiting for events...");
+        // New blocks tell how deep the buffered events are
+        let mut blocks = pubsub_provider
+            .subscribe_blocks()
+            .await
+            .map_err(|e| AggregatorError::TaskListenerError(e.to_string()))?
+            .into_stream();
+        let mut unconfirmed = ConfirmationBuffer::new(self.task_confirmations);
+
+        info!(
+            "Subscribed to TaskRegistry events, acting on them after {} confirmations. Waiting for events...",
+            self.task_confirmations
+        );
 
-        while let Some(log) = stream.next().await {
-            match log {
-                Ok(event) => {
-                    tasks.insert(event.0.taskId, TaskStatus::PENDING);
-                    info!("New task detected: \x1b[1;33m{:?}\x1b[0m", event.0.taskId);
+        loop {
+            tokio::select! {
+                log = stream.next() => match log {
+                    Some(Ok((event, _))) if self.task_confirmations == 0 => {
+                        self.track_task(event, &tx_aggregated_response)
+                    }
+                    Some(Ok((event, log))) => unconfirmed.push(event, &log),
+                    Some(Err(e)) => error!("Error receiving event: {:?}", e),
+                    None => {
+                        return Err(AggregatorError::TaskListenerError(
+                            "TaskRegistry event subscription ended".to_string(),
+                        ))
+                    }
+                },
+                block = blocks.next() => {
+                    let Some(block) = block else {
+                        return Err(AggregatorError::TaskListenerError(
+                            "Block subscription ended".to_string(),
+                        ));
+                    };
+                    let (confirmed, reorged) = unconfirmed
+                        .take_confirmed(&*pubsub_provider, block.header.number)
+                        .await;
+                    for event in reorged {
+                        warn!(
+                            "Discarding task reorged out before confirmation: \x1b[1;33m{:?}\x1b[0m",
+                            event.taskId
+                        );
+                    }
+                    for event in confirmed {
+                        self.track_task(event, &tx_aggregated_response);
+                    }
                 }
-                Err(e) => error!("Error receiving event: {:?}", e),
+                _ = self.shutdown.cancelled() => break,
             }
         }
 
         Ok(())
     }
 
+    // Start tracking a confirmed task as pending
+    fn track_task(
+        &self,
+        event: TaskRequested,
+        tx_aggregated_response: &mpsc::Sender<AggregatedResponse>,
+    ) {
+        let task_id = TaskId::from(event.taskId);
+        self.task_operator_sets.insert(task_id, self.operator_set());
+        self.timings.insert(task_id, TaskTimings::default());
+        self.task_app_ids
+            .insert(task_id, event.taskRequest.appId.into());
+        self.tasks.insert(task_id, TaskStatus::PENDING);
+        self.task_store
+            .insert_task_status(task_id, TaskStatus::PENDING);
+        info!("New task detected: \x1b[1;33m{:?}\x1b[0m", task_id);
+        self.publish_task_event(TaskEvent::status(task_id, TaskStatus::PENDING));
+        self.start_task_timeout(task_id, tx_aggregated_response);
+    }
+
+    // Aggregate `task_id` with the responses received so far if its operators haven't all
+    // reported within `task_timeout_secs`
+    fn start_task_timeout(
+        &self,
+        task_id: TaskId,
+        tx_aggregated_response: &mpsc::Sender<AggregatedResponse>,
+    ) {
+        let task_timeout = Duration::from_secs(self.config.task_timeout_secs);
+        if task_timeout.is_zero() {
+            return;
+        }
+
+        let aggregator = self.clone();
+        let tx_aggregated_response = tx_aggregated_response.clone();
+        let timer = tokio::spawn(
+            async move {
+                // Tasks still pending on shutdown get a new timeout on the next start
+                tokio::select! {
+                    _ = sleep(task_timeout) => aggregator
+                        .expire_task(task_id, task_timeout, &tx_aggregated_response)
+                        .await,
+                    _ = aggregator.shutdown.cancelled() => (),
+                }
+            }
+            .in_current_span(),
+        );
+        if let Some(previous) = self.task_timeouts.insert(task_id, timer) {
+            previous.abort();
+        }
+    }
+
+    // Aggregate a task whose timeout elapsed before all of its operators reported, unless it was
+    // aggregated in the meantime
+    async fn expire_task(
+        &self,
+        task_id: TaskId,
+        task_timeout: Duration,
+        tx_aggregated_response: &mpsc::Sender<AggregatedResponse>,
+    ) {
+        if self.task_timeouts.remove(&task_id).is_none()
+            || self.tasks.get(&task_id).as_deref() != Some(&TaskStatus::PENDING)
+        {
+            return;
+        }
+
+        // Later responses are still accepted while the task is pending, but no longer counted.
+        // Marked as aggregated, so that operators who already responded can't change their result
+        self.aggregated_tasks.insert(task_id);
+        let responses = self
+            .operator_responses
+            .get(&task_id)
+            .map(|responses| responses.clone())
+            .unwrap_or_default();
+        warn!(
+            "Task \x1b[1;33m{:?}\x1b[0m timed out after {:?} with {} of {} responses, aggregating",
+            task_id,
+            task_timeout,
+            responses.len(),
+            self.task_operator_set(&task_id).len()
+        );
+        self.timings
+            .entry(task_id)
+            .or_default()
+            .record("responses_collected");
+
+        let aggregated_response = AggregatedResponse { task_id, responses };
+        if let Err(e) = tx_aggregated_response.send(aggregated_response).await {
+            error!("Error sending aggregated response: {:?}", e);
+        }
+    }
+
+    // Push a status transition to the clients following the task, if any
+    fn publish_task_event(&self, event: TaskEvent) {
+        // Sending only fails when nobody is listening
+        let _ = self.task_events.send(event);
+    }
+
     // Process operator responses
     async fn queue_operator_response(
-        mut rx: mpsc::Receiver<OperatorResponse>,
-        operator_responses: Arc<OperatorResponsesByTaskId>,
+        self,
+        mut rx: mpsc::Receiver<OperatorMessage>,
         tx_aggregated_response: mpsc::Sender<AggregatedResponse>,
-        operator_list: Arc<DashMap<Address, ()>>,
     ) -> Result<(), AggregatorError> {
-        while let Some(response) = rx.recv().await {
-            let operator_address = response
-                .signature
-                .recover_address_from_msg(response.result.as_bytes())
-                .map_err(|e| AggregatorError::SignatureError(e.to_string()))?;
+        while let Some(message) = rx.recv().await {
+            let response = match message {
+                OperatorMessage::Response(response) => response,
+                OperatorMessage::Failure(operator_address, failure) => {
+                    self.record_failure(operator_address, failure, &tx_aggregated_response)
+                        .await;
+                    continue;
+                }
+                // Without a timer of its own, the task would be taken for one that timed out
+                // and never aggregated
+                OperatorMessage::Requeued(task_id) => {
+                    self.task_operator_sets.insert(task_id, self.operator_set());
+                    self.aggregated_tasks.remove(&task_id);
+                    self.timings.insert(task_id, TaskTimings::default());
+                    self.start_task_timeout(task_id, &tx_aggregated_response);
+                    continue;
+                }
+            };
+
+            // Without registered operators there is no operator set to reach consensus against
+            if self.operator_list.is_empty() {
+                warn!(
+                    "Ignoring response for task \x1b[1;33m{:?}\x1b[0m: no registered operators",
+                    response.task_id
+                );
+                continue;
+            }
+
+            // A bad response is dropped on its own, it must not stop the queue
+            let operator_address = match self.recovery_cache.recover(&response) {
+                Ok(operator_address) => operator_address,
+                Err(e) => {
+                    warn!(
+                        "Dropping response for task \x1b[1;33m{:?}\x1b[0m: signature recovery failed: {}",
+                        response.task_id, e
+                    );
+                    continue;
+                }
+            };
+            // The operator may have been removed by a refresh since the server accepted it
+            if !self.operator_list.contains_key(&operator_address) {
+                warn!(
+                    "Dropping response for task \x1b[1;33m{:?}\x1b[0m: unknown operator \x1b[1;34m{:?}\x1b[0m",
+                    response.task_id, operator_address
+                );
+                continue;
+            }
+
+            // Retries queued before the first submission was recorded must not trigger a second
+            // aggregation
+            if server::is_duplicate(&self.operator_responses, &operator_address, &response) {
+                info!(
+                    "Ignoring duplicate response from operator: \x1b[1;34m{:?}\x1b[0m for task: \x1b[1;33m{:?}\x1b[0m",
+                    operator_address, response.task_id
+                );
+                continue;
+            }
+            // Checked again here, the task may have been aggregated since the server accepted
+            // the response
+            if server::changes_aggregated_result(
+                &self.operator_responses,
+                &self.aggregated_tasks,
+                &operator_address,
+                &response,
+            ) {
+                warn!(
+                    "Dropping changed result from operator: \x1b[1;34m{:?}\x1b[0m for task: \x1b[1;33m{:?}\x1b[0m, the task was already aggregated",
+                    operator_address, response.task_id
+                );
+                continue;
+            }
 
             info!(
                 "Aggregating response from operator: \x1b[1;34m{:?}\x1b[0m for task: \x1b[1;33m{:?}\x1b[0m",
                 operator_address, response.task_id
             );
+            // The exit code isn't signed, so it only flags the response rather than rejecting it
+            if let Some(exit_code) = response.exit_code.filter(|exit_code| *exit_code != 0) {
+                warn!(
+                    "Operator \x1b[1;34m{:?}\x1b[0m reported a container exiting with code {} for task \x1b[1;33m{:?}\x1b[0m (ran for {:?} ms)",
+                    operator_address, exit_code, response.task_id, response.duration_ms
+                );
+            }
 
-            operator_responses
+            self.operator_responses
                 .entry(response.clone().task_id)
-                .or_insert_with(DashMap::new)
+                .or_default()
                 .insert(operator_address, response.clone());
+            // A result sent after a failure report replaces it, without changing the number of
+            // operators that reported
+            let replaced_failure = self
+                .operator_failures
+                .get(&response.task_id)
+                .is_some_and(|failures| failures.remove(&operator_address).is_some());
 
-            // For AVSthon we wait for full operator responses
-            // Once hashmap is full we process the task
-            let operator_length = operator_list.len();
-            if operator_responses.get(&response.task_id).unwrap().len() == operator_length {
-                let aggregated_response = AggregatedResponse {
-                    task_id: response.task_id,
-                    responses: operator_responses.get(&response.task_id).unwrap().clone(),
-                };
-                match tx_aggregated_response.send(aggregated_response).await {
-                    Ok(_) => (),
-                    Err(e) => error!("Error sending aggregated response: {:?}", e),
-                }
+            // Persist the response so it survives a restart before the task is aggregated
+            self.task_store
+                .insert_operator_response(operator_address, &response);
+
+            // Tasks are aggregated once every operator reported, or earlier once the responses
+            // settle the consensus
+            let all_reported = !replaced_failure && self.all_operators_reported(&response.task_id);
+            if all_reported || self.quorum_reached(&response.task_id) {
+                self.aggregate_task(response.task_id, &tx_aggregated_response)
+                    .await;
             }
         }
 
         Ok(())
     }
 
-    async fn process_completed_tasks(
-        mut rx: mpsc::Receiver<AggregatedResponse>,
-        tx_task_process: mpsc::Sender<TaskResult>,
-        tasks: Arc<DashMap<FixedBytes<32>, TaskStatus>>,
+    // Record that an operator couldn't produce a result for a task, so the task is aggregated
+    // from the other operators' responses without waiting for it
+    async fn record_failure(
+        &self,
+        operator_address: Address,
+        failure: OperatorFailure,
+        tx_aggregated_response: &mpsc::Sender<AggregatedResponse>,
     ) {
-        while let Some(aggregated_response) = rx.recv().await {
-            let task_id = aggregated_response.task_id;
-            let extracted_result = aggregated_response
-                .responses
-                .iter()
-                .map(|entry| entry.value().result.trim().to_string())
-                .collect::<Vec<String>>()
-                .iter()
-                .map(|result| result.parse().unwrap())
-                .collect::<Vec<U256>>();
-
-            // Check if all values in the array are equal
-            let (task_status, consensus_result) =
-                if extracted_result.iter().all(|&x| x == extracted_result[0]) {
-                    info!("Consensus reached for task: \x1b[1;33m{:?}\x1b[0m", task_id);
-                    (TaskStatus::COMPLETED, extracted_result[0])
-                } else {
-                    info!(
-                        "Consensus not reached for task: \x1b[1;33m{:?}\x1b[0m",
-                        task_id
-                    );
-                    (TaskStatus::FAILED, U256::ZERO)
-                };
-
+        let task_id = failure.task_id;
+        // The operator may have been removed by a refresh since the server accepted it
+        if !self.operator_list.contains_key(&operator_address) {
+            warn!(
+                "Dropping failure for task \x1b[1;33m{:?}\x1b[0m: unknown operator \x1b[1;34m{:?}\x1b[0m",
+                task_id, operator_address
+            );
+            return;
+        }
+        // Retries and failures sent after a result must not change the count
+        let responded = self
+            .operator_responses
+            .get(&task_id)
+            .is_some_and(|responses| responses.contains_key(&operator_address));
+        let failures = self.operator_failures.entry(task_id).or_default();
+        if responded || failures.contains_key(&operator_address) {
+            info!(
+                "Ignoring failure from operator: \x1b[1;34m{:?}\x1b[0m for task: \x1b[1;33m{:?}\x1b[0m",
+                operator_address, task_id
+            );
+            return;
+        }
+
+        warn!(
+            "Operator \x1b[1;34m{:?}\x1b[0m failed task \x1b[1;33m{:?}\x1b[0m: {}",
+            operator_address, task_id, failure.reason
+        );
+        failures.insert(operator_address, failure.reason);
+        drop(failures);
+
+        if self.all_operators_reported(&task_id) {
+            self.aggregate_task(task_id, tx_aggregated_response).await;
+        }
+    }
+
+    // Whether every operator expected to respond to `task_id` sent a result or reported a
+    // failure
+    fn all_operators_reported(&self, task_id: &TaskId) -> bool {
+        let operator_length = self.task_operator_set(task_id).len();
+        let responses = self
+            .operator_responses
+            .get(task_id)
+            .map_or(0, |responses| responses.len());
+        let failures = self
+            .operator_failures
+            .get(task_id)
+            .map_or(0, |failures| failures.len());
+        responses + failures == operator_length
+    }
+
+    // Whether the responses received so far for `task_id` already settle its consensus, so it
+    // can be aggregated without waiting for the other operators. Only stake-weighted consensus
+    // decides early: once two thirds of the stake agree, no other result can reach the quorum.
+    fn quorum_reached(&self, task_id: &TaskId) -> bool {
+        if !self.config.consensus.uses_stakes() {
+            return false;
+        }
+        let Some(responses) = self.operator_responses.get(task_id) else {
+            return false;
+        };
+
+        let result_type = self.result_type(task_id);
+        let canonical_results = sorted_results(&responses)
+            .into_iter()
+            .map(|(operator, result)| (operator, result_type.canonicalize(&result).ok()))
+            .collect::<Vec<_>>();
+        self.consensus
+            .decide(&canonical_results, &self.task_operator_set(task_id))
+            .is_some()
+    }
+
+    // Result type declared for the client app of `task_id`
+    fn result_type(&self, task_id: &TaskId) -> ResultType {
+        self.task_app_ids
+            .get(task_id)
+            .map(|app_id| self.config.result_types.get(app_id.value()))
+            .unwrap_or_default()
+    }
+
+    // The operators expected to respond to `task_id`, as snapshotted when it was created. Tasks
+    // restored from the store get the current operators on first use.
+    fn task_operator_set(&self, task_id: &TaskId) -> OperatorSet {
+        self.task_operator_sets
+            .entry(*task_id)
+            .or_insert_with(|| self.operator_set())
+            .clone()
+    }
+
+    // The registered operators, sorted by address, with their stake
+    fn operator_set(&self) -> OperatorSet {
+        let mut operators = self
+            .operator_list
+            .iter()
+            .map(|operator| *operator.key())
+            .collect::<Vec<_>>();
+        operators.sort_unstable();
+        let stakes = self
+            .operator_stakes
+            .iter()
+            .map(|entry| (*entry.key(), *entry.value()))
+            .collect();
+        OperatorSet::new(operators).with_stakes(stakes)
+    }
+
+    // Aggregate a task whose operators all reported or that reached its quorum, after the
+    // response grace period if any. A task is only aggregated once.
+    async fn aggregate_task(
+        &self,
+        task_id: TaskId,
+        tx_aggregated_response: &mpsc::Sender<AggregatedResponse>,
+    ) {
+        if !self.aggregated_tasks.insert(task_id) {
+            return;
+        }
+        // The task timed out and was aggregated without waiting for the last operators
+        if self.config.task_timeout_secs > 0 {
+            match self.task_timeouts.remove(&task_id) {
+                Some((_, timer)) => timer.abort(),
+                None => return,
+            }
+        }
+
+        self.timings
+            .entry(task_id)
+            .or_default()
+            .record("responses_collected");
+
+        let grace_period = Duration::from_secs(self.config.response_grace_period_secs);
+        if grace_period.is_zero() {
+            self.send_aggregated_response(task_id, tx_aggregated_response)
+                .await;
+        } else {
+            // Keep recording late responses for a while, the task stays pending so the server
+            // still accepts them
+            info!(
+                "All expected responses received for task \x1b[1;33m{:?}\x1b[0m, aggregating in {:?}",
+                task_id, grace_period
+            );
+            let aggregator = self.clone();
+            let tx_aggregated_response = tx_aggregated_response.clone();
+            tokio::spawn(
+                async move {
+                    sleep(grace_period).await;
+                    aggregator
+                        .send_aggregated_response(task_id, &tx_aggregated_response)
+                        .await;
+                }
+                .in_current_span(),
+            );
+        }
+    }
+
+    // Hand every response recorded for `task_id` over to aggregation
+    async fn send_aggregated_response(
+        &self,
+        task_id: TaskId,
+        tx_aggregated_response: &mpsc::Sender<AggregatedResponse>,
+    ) {
+        // A task every operator failed is aggregated without any response, and fails
+        let Some(responses) = self
+            .operator_responses
+            .get(&task_id)
+            .map(|responses| responses.clone())
+            .or_else(|| {
+                self.operator_failures
+                    .contains_key(&task_id)
+                    .then(DashMap::new)
+            })
+        else {
+            return;
+        };
+
+        let aggregated_response = AggregatedResponse { task_id, responses };
+        if let Err(e) = tx_aggregated_response.send(aggregated_response).await {
+            error!("Error sending aggregated response: {:?}", e);
+        }
+    }
+
+    async fn process_completed_tasks(
+        self,
+        mut rx: mpsc::Receiver<AggregatedResponse>,
+        tx_task_process: mpsc::Sender<TaskResult>,
+    ) {
+        while let Some(aggregated_response) = rx.recv().await {
+            let task_id = aggregated_response.task_id;
+            let result_type = self.result_type(&task_id);
+
+            let results = sorted_results(&aggregated_response.responses);
+            let operator_set = self.task_operator_set(&task_id);
+            let (task_status, consensus_result, result_hash) = compute_consensus(
+                task_id,
+                result_type,
+                self.consensus.as_ref(),
+                &operator_set,
+                &results,
+            );
+            self.timings.entry(task_id).or_default().record("consensus");
+            self.consensus_stats.record(&task_status, results.len());
+            let offchain_result = result_hash
+                .and_then(|result_hash| offchain_result(result_type, result_hash, &results));
+
+            self.task_log.append(&TaskRecord {
+                avs_id: self.avs_id.clone(),
+                task_id,
+                status: task_status.clone(),
+                result: consensus_result,
+                result_hash,
+                responses: results.into_iter().collect(),
+                timings_ms: self
+                    .timings
+                    .get(&task_id)
+                    .map(|timings| TaskRecord::timings_ms(&timings))
+                    .unwrap_or_default(),
+                finalized_at: TaskRecord::now(),
+            });
+
+            // Keep the hash around for audits, as the on-chain result can't be traced back to
+            // the operator outputs, and the full result of hashed types for clients to fetch
+            if let Some(result_hash) = result_hash {
+                self.task_store
+                    .insert_result_hash(task_id, result_hash, offchain_result);
+            }
+
             match tx_task_process
                 .send(TaskResult {
                     task_id,
                     status: task_status.clone(),
                     result: consensus_result,
+                    result_hash,
+                    resubmissions: 0,
                 })
                 .await
             {
@@ -377,59 +1435,1852 @@ impl Aggregator {
                 Err(e) => error!("Failed to send consensus result: {:?}", e),
             }
 
-            tasks.insert(task_id, task_status);
+            self.finalize_task(task_id, task_status.clone());
+            self.publish_task_event(TaskEvent {
+                task_id,
+                status: task_status,
+                result: Some(consensus_result),
+                result_hash,
+            });
         }
     }
 
     async fn send_task_result(
+        self,
         mut rx: mpsc::Receiver<TaskResult>,
-        http_provider: HttpProviderWithSigner,
+        mut rx_retry: mpsc::Receiver<TaskResult>,
+        tx_retry: mpsc::Sender<TaskResult>,
     ) -> Result<(), AggregatorError> {
-        let task_registry = TaskRegistryInstance::new(TASK_REGISTRY_ADDRESS, http_provider.clone());
+        let signer_address = self.config.ecdsa_signer.address();
 
-        while let Some(task_result) = rx.recv().await {
-            info!(
-                "Sending task result for: \x1b[1;33m{:?}\x1b[0m",
-                task_result.task_id
-            );
-            let tx_request = task_registry
-                .respondToTask(
-                    task_result.task_id,
-                    task_result.status.into(),
-                    task_result.result,
-                )
-                .into_transaction_request();
+        // Confirmations are tracked separately so one slow transaction doesn't stall the queue
+        let (tx_pending, rx_pending) = mpsc::channel::<PendingSubmission>(100);
+        let confirmations = tokio::spawn(
+            self.clone()
+                .track_confirmations(rx_pending, tx_retry)
+                .in_current_span(),
+        );
+
+        loop {
+            // Resubmissions come first. The pipeline is drained once the task processor, which
+            // only stops on shutdown, hangs up.
+            let task_result = tokio::select! {
+                biased;
+                Some(task_result) = rx_retry.recv() => task_result,
+                task_result = rx.recv() => match task_result {
+                    Some(task_result) => task_result,
+                    None => break,
+                },
+            };
+            let task_results = self.next_batch(task_result, &mut rx).await;
+            let task_ids = task_results
+                .iter()
+                .map(|task_result| task_result.task_id)
+                .collect::<Vec<_>>();
+            info!("Sending task result for: \x1b[1;33m{:?}\x1b[0m", task_ids);
 
             let mut attempts = 0;
             const MAX_ATTEMPTS: u32 = 6;
             const RETRY_DELAY: Duration = Duration::from_secs(5);
 
-            let filled_tx = loop {
-                match http_provider.fill(tx_request.clone()).await {
-                    Ok(tx) => break tx,
-                    Err(e) => {
-                        attempts += 1;
-                        if attempts >= MAX_ATTEMPTS {
-                            error!("Failed to fill tx after {} attempts: {:?}", MAX_ATTEMPTS, e);
-                            return Err(AggregatorError::TxError(e.to_string()));
-                        }
-                        sleep(RETRY_DELAY).await;
+            // Wait for the network fees to come back under the configured caps rather than
+            // submitting at an arbitrarily high price during a gas spike
+            let fees = loop {
+                match self.avs_chain.estimate_fees().await {
+                    Ok(fees) if fees.max_fee_per_gas <= self.config.max_fee_per_gas => {
+                        break Some(fees)
                     }
+                    Ok(fees) => warn!(
+                        "Estimated max fee per gas {} wei is above the cap of {} wei",
+                        fees.max_fee_per_gas, self.config.max_fee_per_gas
+                    ),
+                    Err(e) => warn!("Failed to estimate fees: {:?}", e),
+                }
+                attempts += 1;
+                if attempts >= MAX_ATTEMPTS {
+                    break None;
                 }
+                sleep(RETRY_DELAY).await;
             };
 
-            let tx_to_submit = filled_tx.as_envelope().unwrap();
-            let pending_tx = http_provider
-                .send_tx_envelope(tx_to_submit.clone())
-                .await
-                .map_err(|e| AggregatorError::TxError(e.to_string()))?;
-            info!(
-                "Tx hash \x1b[1;32m{:?}\x1b[0m for task \x1b[1;33m{:?}\x1b[0m",
-                pending_tx.tx_hash(),
+            let Some(fees) = fees else {
+                error!(
+                    "Fees above cap after {} attempts, dropping tasks \x1b[1;33m{:?}\x1b[0m",
+                    MAX_ATTEMPTS, task_ids
+                );
+                continue;
+            };
+
+            // Nonces are assigned here rather than by the nonce filler, so results can be broadcast
+            // back to back and a stuck transaction can be replaced at the same nonce. The lock is
+            // held until the broadcast so deployments on the same chain take turns.
+            let mut next_nonce = self.next_nonce.lock().await;
+            let nonce = match *next_nonce {
+                Some(nonce) => nonce,
+                None => self.avs_chain.pending_nonce(signer_address).await?,
+            };
+
+            let tx_request = respond_request(self.addresses.task_registry, &task_results)
+                .from(signer_address)
+                .nonce(nonce)
+                .max_fee_per_gas(fees.max_fee_per_gas)
+                .max_priority_fee_per_gas(
+                    fees.max_priority_fee_per_gas
+                        .min(self.config.max_priority_fee_per_gas),
+                );
+
+            match self.avs_chain.broadcast(tx_request.clone()).await {
+                Ok(tx_hash) => {
+                    *next_nonce = Some(nonce + 1);
+                    drop(next_nonce);
+                    for task_result in &task_results {
+                        info!(
+                            "Tx hash \x1b[1;32m{:?}\x1b[0m (nonce {}) for task \x1b[1;33m{:?}\x1b[0m, result hash {:?}",
+                            tx_hash, nonce, task_result.task_id, task_result.result_hash
+                        );
+                        self.timings
+                            .entry(task_result.task_id)
+                            .or_default()
+                            .record("submitted");
+
+                        // The result is on its way on-chain, its responses are no longer needed
+                        self.evict_operator_responses(&task_result.task_id);
+                    }
+
+                    let submission = PendingSubmission {
+                        task_results,
+                        tx_request,
+                        tx_hashes: vec![tx_hash],
+                    };
+                    if let Err(e) = tx_pending.send(submission).await {
+                        error!("Failed to track pending transaction: {:?}", e);
+                    }
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to send result for tasks \x1b[1;33m{:?}\x1b[0m: {:?}",
+                        task_ids, e
+                    );
+                    // The nonce may or may not have been consumed, resync it from the chain
+                    *next_nonce = None;
+                }
+            }
+        }
+
+        // Their responses are already evicted, the broadcast transactions are followed until
+        // mined so the tasks are not left half-submitted
+        drop(tx_pending);
+        if let Err(e) = confirmations.await {
+            error!("Confirmation tracker panicked: {:?}", e);
+        }
+        while let Ok(task_result) = rx_retry.try_recv() {
+            error!(
+                "Shutting down, task \x1b[1;33m{:?}\x1b[0m is not resubmitted",
                 task_result.task_id
             );
         }
 
         Ok(())
     }
+
+    // Gather the results finalized within the batch window following `first`, up to the batch
+    // size, so they are submitted in a single transaction. Without batching the result is sent on
+    // its own right away.
+    async fn next_batch(
+        &self,
+        first: TaskResult,
+        rx: &mut mpsc::Receiver<TaskResult>,
+    ) -> Vec<TaskResult> {
+        let mut batch = vec![first];
+        if self.config.result_batch_size <= 1 {
+            return batch;
+        }
+
+        let deadline = Instant::now() + Duration::from_millis(self.config.result_batch_window_ms);
+        while batch.len() < self.config.result_batch_size {
+            match timeout_at(deadline, rx.recv()).await {
+                // A task answered twice would revert the whole batch, a resubmitted result
+                // replaces the one already batched
+                Ok(Some(task_result)) => {
+                    match batch
+                        .iter_mut()
+                        .find(|batched| batched.task_id == task_result.task_id)
+                    {
+                        Some(batched) => *batched = task_result,
+                        None => batch.push(task_result),
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        batch
+    }
+
+    // Wait for each broadcast transaction to be mined, concurrently
+    async fn track_confirmations(
+        self,
+        mut rx: mpsc::Receiver<PendingSubmission>,
+        tx_retry: mpsc::Sender<TaskResult>,
+    ) {
+        let mut confirmations = JoinSet::new();
+        while let Some(submission) = rx.recv().await {
+            confirmations.spawn(
+                self.clone()
+                    .await_confirmation(submission, tx_retry.clone())
+                    .in_current_span(),
+            );
+            // Reap the confirmed submissions so the set doesn't grow with every transaction
+            while confirmations.try_join_next().is_some() {}
+        }
+
+        // The result sender stopped, wait for the transactions still in flight
+        if !confirmations.is_empty() {
+            info!(
+                "Waiting for {} transactions to be confirmed",
+                confirmations.len()
+            );
+        }
+        while confirmations.join_next().await.is_some() {}
+    }
+
+    // Poll for the receipt of a submission, replacing it at the same nonce with bumped fees
+    // whenever it stays unconfirmed for too long. A submission is only ever replaced at its own
+    // nonce: once out of replacements it keeps waiting for one of its versions to be mined, as a
+    // new transaction could land alongside it. Once mined (or abandoned on shutdown) the task
+    // status is reconciled with the registry.
+    async fn await_confirmation(
+        self,
+        mut submission: PendingSubmission,
+        tx_retry: mpsc::Sender<TaskResult>,
+    ) {
+        const POLL_INTERVAL: Duration = Duration::from_secs(2);
+        const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(60);
+        const MAX_REPLACEMENTS: u32 = 3;
+
+        let mut replacements = 0;
+        let mut started = tokio::time::Instant::now();
+
+        let outcome = loop {
+            if let Some(outcome) = self.submission_outcome(&submission).await {
+                break Some(outcome);
+            }
+            if replacements >= MAX_REPLACEMENTS && self.shutdown.is_cancelled() {
+                break None;
+            }
+            if replacements < MAX_REPLACEMENTS && started.elapsed() >= CONFIRMATION_TIMEOUT {
+                replacements += 1;
+                started = tokio::time::Instant::now();
+                self.replace_submission(&mut submission, replacements, MAX_REPLACEMENTS)
+                    .await;
+                if replacements == MAX_REPLACEMENTS {
+                    warn!(
+                        "Tx for task \x1b[1;33m{:?}\x1b[0m out of replacements, waiting for it to be mined",
+                        submission.task_ids()
+                    );
+                }
+            }
+            sleep(POLL_INTERVAL).await;
+        };
+
+        let resubmit = match outcome {
+            Some(SubmissionOutcome::Succeeded(tx_hash)) => {
+                info!(
+                    "Tx \x1b[1;32m{:?}\x1b[0m confirmed for task \x1b[1;33m{:?}\x1b[0m",
+                    tx_hash,
+                    submission.task_ids()
+                );
+                for task_result in &submission.task_results {
+                    if let Some((task_id, mut task_timings)) =
+                        self.timings.remove(&task_result.task_id)
+                    {
+                        task_timings.record("confirmed");
+                        info!(
+                            "Task \x1b[1;33m{:?}\x1b[0m timings: {}",
+                            task_id,
+                            task_timings.summary()
+                        );
+                    }
+                }
+                true
+            }
+            // The registry refused the result, resubmitting it would revert again or duplicate
+            // a result recorded since
+            Some(SubmissionOutcome::Reverted(tx_hash)) => {
+                error!(
+                    "Tx {:?} reverted for task \x1b[1;33m{:?}\x1b[0m",
+                    tx_hash,
+                    submission.task_ids()
+                );
+                false
+            }
+            // None of the versions of the submission can be mined anymore, so a resubmission
+            // can't duplicate it
+            Some(SubmissionOutcome::NonceConsumed) => {
+                warn!(
+                    "Nonce of the tx for task \x1b[1;33m{:?}\x1b[0m was used by another transaction",
+                    submission.task_ids()
+                );
+                true
+            }
+            None => {
+                error!(
+                    "Tx for task \x1b[1;33m{:?}\x1b[0m still unconfirmed on shutdown",
+                    submission.task_ids()
+                );
+                false
+            }
+        };
+        for task_result in submission.task_results {
+            self.reconcile_task_status(task_result, resubmit, &tx_retry)
+                .await;
+        }
+    }
+
+    // Whether one of the versions of `submission` was mined, or its nonce was used by another
+    // transaction
+    async fn submission_outcome(
+        &self,
+        submission: &PendingSubmission,
+    ) -> Option<SubmissionOutcome> {
+        let nonce = submission.tx_request.nonce.unwrap_or_default();
+        let from = submission.tx_request.from.unwrap_or_default();
+
+        // Read before the receipts, so a nonce used by one of the versions shows its receipt
+        let mined_nonce = self.avs_chain.mined_nonce(from).await.ok();
+        for tx_hash in &submission.tx_hashes {
+            if let Ok(Some(succeeded)) = self.avs_chain.transaction_status(*tx_hash).await {
+                return Some(match succeeded {
+                    true => SubmissionOutcome::Succeeded(*tx_hash),
+                    false => SubmissionOutcome::Reverted(*tx_hash),
+                });
+            }
+        }
+        mined_nonce
+            .filter(|mined_nonce| *mined_nonce > nonce)
+            .map(|_| SubmissionOutcome::NonceConsumed)
+    }
+
+    // Broadcast `submission` again at its nonce with bumped fees
+    async fn replace_submission(
+        &self,
+        submission: &mut PendingSubmission,
+        replacement: u32,
+        max_replacements: u32,
+    ) {
+        // Replacements must bump both fees (by at least 10%) to be accepted by the mempool
+        let max_fee_per_gas = submission.tx_request.max_fee_per_gas.unwrap_or_default();
+        let max_priority_fee_per_gas = submission
+            .tx_request
+            .max_priority_fee_per_gas
+            .unwrap_or_default();
+        let bumped_max_fee = (max_fee_per_gas * 5 / 4).min(self.config.max_fee_per_gas);
+        let bumped_priority_fee =
+            (max_priority_fee_per_gas * 5 / 4).min(self.config.max_priority_fee_per_gas);
+
+        if bumped_max_fee <= max_fee_per_gas || bumped_priority_fee <= max_priority_fee_per_gas {
+            warn!(
+                "Tx for task \x1b[1;33m{:?}\x1b[0m is stuck but fees are already at the cap",
+                submission.task_ids()
+            );
+            return;
+        }
+
+        submission.tx_request.max_fee_per_gas = Some(bumped_max_fee);
+        submission.tx_request.max_priority_fee_per_gas = Some(bumped_priority_fee);
+
+        warn!(
+            "Replacing stuck tx for task \x1b[1;33m{:?}\x1b[0m (attempt {}/{})",
+            submission.task_ids(),
+            replacement,
+            max_replacements
+        );
+
+        match self
+            .avs_chain
+            .broadcast(submission.tx_request.clone())
+            .await
+        {
+            Ok(tx_hash) => submission.tx_hashes.push(tx_hash),
+            Err(e) => error!("Failed to replace tx: {:?}", e),
+        }
+    }
+
+    // Compare the status recorded by the registry with the one submitted for a task. The local
+    // status follows the registry, and results still pending on-chain are resubmitted when
+    // `resubmit` allows it.
+    async fn reconcile_task_status(
+        &self,
+        mut task_result: TaskResult,
+        resubmit: bool,
+        tx_retry: &mpsc::Sender<TaskResult>,
+    ) {
+        const MAX_RESUBMISSIONS: u32 = 3;
+
+        let task_id = task_result.task_id;
+        let onchain_status = match self
+            .avs_chain
+            .task_status(task_id, BlockNumberOrTag::Latest.into())
+            .await
+        {
+            Ok(status) => status,
+            Err(e) => {
+                error!(
+                    "Failed to read on-chain status of task \x1b[1;33m{:?}\x1b[0m: {:?}",
+                    task_id, e
+                );
+                return;
+            }
+        };
+
+        match onchain_status {
+            TaskStatus::PENDING if !resubmit => error!(
+                "Task \x1b[1;33m{:?}\x1b[0m still pending on-chain, its result is not resubmitted",
+                task_id
+            ),
+            TaskStatus::PENDING if task_result.resubmissions < MAX_RESUBMISSIONS => {
+                task_result.resubmissions += 1;
+                warn!(
+                    "Task \x1b[1;33m{:?}\x1b[0m still pending on-chain, resubmitting ({}/{})",
+                    task_id, task_result.resubmissions, MAX_RESUBMISSIONS
+                );
+                if let Err(e) = tx_retry.send(task_result).await {
+                    error!("Failed to resubmit task result: {:?}", e);
+                }
+            }
+            TaskStatus::PENDING => error!(
+                "Task \x1b[1;33m{:?}\x1b[0m still pending on-chain after {} resubmissions",
+                task_id, MAX_RESUBMISSIONS
+            ),
+            status if status == task_result.status => {
+                info!(
+                    "Task \x1b[1;33m{:?}\x1b[0m finalized on-chain as {:?}",
+                    task_id, status
+                );
+            }
+            status => {
+                warn!(
+                    "Task \x1b[1;33m{:?}\x1b[0m is {:?} on-chain but {:?} was submitted",
+                    task_id, status, task_result.status
+                );
+                if let Some(mut task_status) = self.tasks.get_mut(&task_id) {
+                    *task_status = status.clone();
+                }
+                self.task_store.insert_task_status(task_id, status.clone());
+                self.publish_task_event(TaskEvent::status(task_id, status));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{
+        providers::utils::Eip1559Estimation,
+        signers::{local::PrivateKeySigner, SignerSync},
+    };
+    use async_trait::async_trait;
+    use consensus::Unanimous;
+    use contract_bindings::{
+        failure_signing_bytes, response_signing_bytes, TaskRegistry,
+        RESPONSE_VERSION_ECDSA_TASK_BOUND,
+    };
+    use eyre::Result;
+
+    // Chain whose state is scripted by the test, recording the transactions broadcast to it
+    #[derive(Default)]
+    struct FakeChain {
+        operators: Vec<(Address, OperatorAvsStatus)>,
+        stakes: Vec<(Address, U256)>,
+        tasks: Vec<(TaskId, AppId, TaskStatus)>,
+        broadcasts: Mutex<Vec<TransactionRequest>>,
+        // Status of every transaction, succeeded unless set
+        receipt: Option<Option<bool>>,
+        // Nonce of every account at the latest block
+        mined_nonce: u64,
+    }
+
+    #[async_trait]
+    impl AvsChain for FakeChain {
+        async fn registered_operators(&self) -> Result<Vec<Address>, AggregatorError> {
+            Ok(self
+                .operators
+                .iter()
+                .map(|(operator, _)| *operator)
+                .collect())
+        }
+
+        async fn operator_status(
+            &self,
+            operator: Address,
+            _block: BlockId,
+        ) -> Result<OperatorAvsStatus, AggregatorError> {
+            self.operators
+                .iter()
+                .find(|(registered, _)| *registered == operator)
+                .map(|(_, status)| *status)
+                .ok_or_else(|| AggregatorError::OperatorListFetchError("Unknown".to_string()))
+        }
+
+        async fn operator_stake(
+            &self,
+            operator: Address,
+            _block: BlockId,
+        ) -> Result<U256, AggregatorError> {
+            Ok(self
+                .stakes
+                .iter()
+                .find(|(staked, _)| *staked == operator)
+                .map(|(_, stake)| *stake)
+                .unwrap_or_default())
+        }
+
+        async fn requested_tasks(&self) -> Result<Vec<(TaskId, AppId)>, AggregatorError> {
+            Ok(self
+                .tasks
+                .iter()
+                .map(|(task_id, app_id, _)| (*task_id, *app_id))
+                .collect())
+        }
+
+        async fn task_status(
+            &self,
+            task_id: TaskId,
+            _block: BlockId,
+        ) -> Result<TaskStatus, AggregatorError> {
+            Ok(self
+                .tasks
+                .iter()
+                .find(|(requested, _, _)| *requested == task_id)
+                .map(|(_, _, status)| status.clone())
+                .unwrap_or(TaskStatus::EMPTY))
+        }
+
+        async fn estimate_fees(&self) -> Result<Eip1559Estimation, AggregatorError> {
+            Ok(Eip1559Estimation {
+                max_fee_per_gas: 1,
+                max_priority_fee_per_gas: 1,
+            })
+        }
+
+        async fn pending_nonce(&self, _address: Address) -> Result<u64, AggregatorError> {
+            Ok(0)
+        }
+
+        async fn mined_nonce(&self, _address: Address) -> Result<u64, AggregatorError> {
+            Ok(self.mined_nonce)
+        }
+
+        async fn broadcast(
+            &self,
+            tx_request: TransactionRequest,
+        ) -> Result<TxHash, AggregatorError> {
+            let mut broadcasts = self.broadcasts.lock().unwrap();
+            broadcasts.push(tx_request);
+            Ok(TxHash::with_last_byte(broadcasts.len() as u8))
+        }
+
+        async fn transaction_status(
+            &self,
+            _tx_hash: TxHash,
+        ) -> Result<Option<bool>, AggregatorError> {
+            Ok(self.receipt.unwrap_or(Some(true)))
+        }
+    }
+
+    // Pipeline of the default deployment on top of `avs_chain`, persisting to a fresh store
+    fn test_aggregator(avs_chain: FakeChain, name: &str) -> Result<AvsAggregator> {
+        let task_store_path =
+            std::env::temp_dir().join(format!("aggregator_{}_{}.json", name, std::process::id()));
+        let _ = std::fs::remove_file(&task_store_path);
+        let _ = std::fs::remove_file(task_store_path.with_extension("archive.jsonl"));
+        let _ = std::fs::remove_file(task_store_path.with_extension("responses.jsonl"));
+
+        let mut config = AggregatorConfig::from_env()?;
+        config.consensus = Default::default();
+        Ok(AvsAggregator::with_chain(
+            &AvsDeployment::default_for(Chain::Anvil),
+            Arc::new(avs_chain),
+            Arc::new(TaskStore::open(&task_store_path)?),
+            Arc::new(TaskLog::open(None)),
+            Arc::new(AsyncMutex::new(None)),
+            Arc::new(config),
+        ))
+    }
+
+    fn task_id(byte: u8) -> TaskId {
+        TaskId(B256::repeat_byte(byte))
+    }
+
+    fn app_id() -> AppId {
+        AppId(B256::repeat_byte(0xaa))
+    }
+
+    fn response(signer: &PrivateKeySigner, task_id: TaskId, result: &str) -> OperatorResponse {
+        OperatorResponse {
+            task_id,
+            result: result.to_string(),
+            signature: signer
+                .sign_message_sync(
+                    &response_signing_bytes(RESPONSE_VERSION_ECDSA_TASK_BOUND, task_id, result)
+                        .unwrap(),
+                )
+                .unwrap(),
+            version: RESPONSE_VERSION_ECDSA_TASK_BOUND,
+            exit_code: None,
+            duration_ms: None,
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn test_canonical_result_hash() -> Result<()> {
+        let (canonical, hash) = canonical_result(ResultType::Uint256, "42")?;
+        assert_eq!(canonical, U256::from(42).to_be_bytes_vec());
+        assert_eq!(hash, keccak256(U256::from(42).to_be_bytes::<32>()));
+
+        // Equivalent results hash the same
+        assert_eq!(canonical_result(ResultType::Uint256, " 0x2A\n")?.1, hash);
+        let (_, json_hash) = canonical_result(ResultType::Json, r#"{"a": 1, "b": 2}"#)?;
+        assert_eq!(
+            canonical_result(ResultType::Json, r#"{"b":2,"a":1}"#)?.1,
+            json_hash
+        );
+
+        assert!(canonical_result(ResultType::Uint256, "not a number").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_consensus_result_hash() -> Result<()> {
+        let task_id = TaskId::default();
+        let (_, hash) = canonical_result(ResultType::Uint256, "42")?;
+
+        let operator_set = OperatorSet::new(vec![Address::repeat_byte(1), Address::repeat_byte(2)]);
+
+        let agreeing = [
+            (Address::repeat_byte(1), "42".to_string()),
+            (Address::repeat_byte(2), "0x2a".to_string()),
+        ];
+        assert_eq!(
+            compute_consensus(
+                task_id,
+                ResultType::Uint256,
+                &Unanimous,
+                &operator_set,
+                &agreeing
+            ),
+            (TaskStatus::COMPLETED, U256::from(42), Some(hash))
+        );
+
+        let disagreeing = [
+            (Address::repeat_byte(1), "42".to_string()),
+            (Address::repeat_byte(2), "43".to_string()),
+        ];
+        assert_eq!(
+            compute_consensus(
+                task_id,
+                ResultType::Uint256,
+                &Unanimous,
+                &operator_set,
+                &disagreeing
+            ),
+            (TaskStatus::FAILED, U256::ZERO, None)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sorted_results() -> Result<()> {
+        let signers = [
+            PrivateKeySigner::random(),
+            PrivateKeySigner::random(),
+            PrivateKeySigner::random(),
+        ];
+        let task = task_id(1);
+        // Equivalent JSON results formatted differently, the raw one kept off-chain depends on
+        // the order the results are visited in
+        let raw_results = [
+            r#"{"a":1,"b":2}"#,
+            r#"{"b": 2, "a": 1}"#,
+            r#"{"a": 1, "b": 2}"#,
+        ];
+        let responses = |order: [usize; 3]| {
+            let responses = DashMap::new();
+            for index in order {
+                let signer = &signers[index];
+                responses.insert(signer.address(), response(signer, task, raw_results[index]));
+            }
+            responses
+        };
+
+        let forward = sorted_results(&responses([0, 1, 2]));
+        let backward = sorted_results(&responses([2, 1, 0]));
+        assert_eq!(forward, backward);
+        assert!(forward.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+        let operator_set =
+            OperatorSet::new(signers.iter().map(|signer| signer.address()).collect());
+        let aggregate = |results: &[(Address, String)]| {
+            let (status, result, result_hash) =
+                compute_consensus(task, ResultType::Json, &Unanimous, &operator_set, results);
+            let offchain = result_hash
+                .and_then(|result_hash| offchain_result(ResultType::Json, result_hash, results));
+            (status, result, result_hash, offchain)
+        };
+        let (status, result, result_hash, offchain) = aggregate(&forward);
+        assert_eq!(status, TaskStatus::COMPLETED);
+        assert!(result_hash.is_some());
+        assert_eq!(
+            (status, result, result_hash, offchain),
+            aggregate(&backward)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_offchain_result() -> Result<()> {
+        let results = [
+            (Address::repeat_byte(1), "[1, 2]".to_string()),
+            (Address::repeat_byte(2), r#"{"b": 2, "a": 1}"#.to_string()),
+        ];
+        let (canonical, hash) = canonical_result(ResultType::Json, r#"{"a":1,"b":2}"#)?;
+
+        // The stored result hashes to the value submitted on-chain
+        let offchain = offchain_result(ResultType::Json, hash, &results).unwrap();
+        assert_eq!(offchain.result.to_vec(), canonical);
+        assert_eq!(
+            ResultType::Json.onchain_value(&offchain.result),
+            U256::from_be_bytes(keccak256(&offchain.result).0)
+        );
+
+        // Integers are submitted as is
+        let (_, hash) = canonical_result(ResultType::Uint256, "42")?;
+        let results = [(Address::repeat_byte(1), "42".to_string())];
+        assert_eq!(offchain_result(ResultType::Uint256, hash, &results), None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_operator_list() -> Result<()> {
+        let registered = Address::repeat_byte(1);
+        let aggregator = test_aggregator(
+            FakeChain {
+                operators: vec![
+                    (registered, OperatorAvsStatus::REGISTERED),
+                    (Address::repeat_byte(2), OperatorAvsStatus::UNREGISTERED),
+                    (
+                        Address::repeat_byte(3),
+                        OperatorAvsStatus::UNKNOWN(U256::from(7)),
+                    ),
+                ],
+                ..Default::default()
+            },
+            "operator_list",
+        )?;
+
+        // Operators that left the AVS, or whose status can't be decoded, are not counted
+        assert_eq!(aggregator.fetch_operator_list().await?, vec![registered]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_task_history() -> Result<()> {
+        let aggregator = test_aggregator(
+            FakeChain {
+                tasks: vec![
+                    (task_id(1), app_id(), TaskStatus::PENDING),
+                    (task_id(2), app_id(), TaskStatus::COMPLETED),
+                    (task_id(3), app_id(), TaskStatus::FAILED),
+                ],
+                ..Default::default()
+            },
+            "task_history",
+        )?;
+        let signer = PrivateKeySigner::random();
+        for task_id in [task_id(1), task_id(2)] {
+            aggregator
+                .task_store
+                .insert_operator_response(signer.address(), &response(&signer, task_id, "42"));
+        }
+
+        aggregator.fetch_task_history().await?;
+        aggregator.restore_operator_responses()?;
+
+        assert_eq!(
+            aggregator.tasks.get(&task_id(1)).as_deref(),
+            Some(&TaskStatus::PENDING)
+        );
+        assert_eq!(
+            aggregator.task_store.task_status(&task_id(2)),
+            Some(TaskStatus::COMPLETED)
+        );
+        assert_eq!(
+            aggregator.task_app_ids.get(&task_id(3)).as_deref(),
+            Some(&app_id())
+        );
+        assert_eq!(
+            *aggregator.finalized_tasks.lock().unwrap(),
+            VecDeque::from([task_id(2), task_id(3)])
+        );
+
+        // Only the responses of tasks still pending on-chain are restored
+        assert!(aggregator.operator_responses.contains_key(&task_id(1)));
+        assert!(!aggregator.operator_responses.contains_key(&task_id(2)));
+        assert_eq!(
+            aggregator
+                .task_store
+                .operator_responses()
+                .keys()
+                .collect::<Vec<_>>(),
+            vec![&task_id(1)]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restored_aggregations() -> Result<()> {
+        let aggregator = test_aggregator(
+            FakeChain {
+                tasks: vec![
+                    (task_id(1), app_id(), TaskStatus::PENDING),
+                    (task_id(2), app_id(), TaskStatus::PENDING),
+                ],
+                ..Default::default()
+            },
+            "restored_aggregations",
+        )?;
+        let signers = [PrivateKeySigner::random(), PrivateKeySigner::random()];
+        aggregator.update_operator_list(signers.iter().map(|signer| signer.address()).collect());
+        for signer in &signers {
+            aggregator
+                .task_store
+                .insert_operator_response(signer.address(), &response(signer, task_id(1), "42"));
+        }
+        aggregator.task_store.insert_operator_response(
+            signers[0].address(),
+            &response(&signers[0], task_id(2), "42"),
+        );
+
+        aggregator.fetch_task_history().await?;
+        aggregator.restore_operator_responses()?;
+
+        // Only the task every operator already responded to is aggregated again
+        let restored = aggregator.restored_aggregations();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].task_id, task_id(1));
+        assert_eq!(restored[0].responses.len(), signers.len());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_start_pipeline_with_restored_tasks() -> Result<()> {
+        let signer = PrivateKeySigner::random();
+        let task_ids = (0..150u8).map(task_id).collect::<Vec<_>>();
+        let aggregator = test_aggregator(
+            FakeChain {
+                operators: vec![(signer.address(), OperatorAvsStatus::REGISTERED)],
+                tasks: task_ids
+                    .iter()
+                    .map(|task_id| (*task_id, app_id(), TaskStatus::PENDING))
+                    .collect(),
+                ..Default::default()
+            },
+            "start_pipeline",
+        )?;
+        for task_id in &task_ids {
+            aggregator
+                .task_store
+                .insert_operator_response(signer.address(), &response(&signer, *task_id, "42"));
+        }
+
+        // More restored aggregations than slots in the pipeline's channels don't block startup
+        let (avs_state, tx_new_task, pipeline) =
+            tokio::time::timeout(Duration::from_secs(10), aggregator.start_pipeline()).await??;
+
+        // Once everything feeding it stops, the pipeline drains every restored task
+        drop((avs_state, tx_new_task));
+        tokio::time::timeout(Duration::from_secs(10), pipeline.response_queue).await???;
+        aggregator.shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(10), pipeline.task_processor).await??;
+        tokio::time::timeout(Duration::from_secs(30), pipeline.result_sender).await???;
+
+        for task_id in &task_ids {
+            assert_eq!(
+                aggregator.task_store.task_status(task_id),
+                Some(TaskStatus::COMPLETED)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_persists_queued_responses() -> Result<()> {
+        let signers = [PrivateKeySigner::random(), PrivateKeySigner::random()];
+        let avs = test_aggregator(
+            FakeChain {
+                operators: signers
+                    .iter()
+                    .map(|signer| (signer.address(), OperatorAvsStatus::REGISTERED))
+                    .collect(),
+                tasks: vec![
+                    (task_id(1), app_id(), TaskStatus::PENDING),
+                    (task_id(2), app_id(), TaskStatus::PENDING),
+                ],
+                ..Default::default()
+            },
+            "shutdown",
+        )?;
+        let config = avs.config.clone();
+        let (avs_state, tx_new_task, pipeline) = avs.start_pipeline().await?;
+
+        // Every operator responds to the second task, which is aggregated, and only one to the
+        // first, then the server stops right away with the responses still queued
+        for signer in &signers {
+            server::submit_task(&avs_state, &config, response(signer, task_id(2), "42")).await?;
+        }
+        server::submit_task(&avs_state, &config, response(&signers[0], task_id(1), "42")).await?;
+        drop((avs_state, tx_new_task));
+
+        let aggregator = Aggregator {
+            avs: vec![avs.clone()],
+            config,
+        };
+        tokio::time::timeout(Duration::from_secs(30), aggregator.drain(vec![pipeline])).await??;
+
+        // The aggregated result was processed and submitted before the drain returned
+        assert_eq!(
+            avs.task_store.task_status(&task_id(2)),
+            Some(TaskStatus::COMPLETED)
+        );
+
+        // The response that wasn't aggregated was flushed to the store, while those of the
+        // submitted task were evicted
+        let task_store_path =
+            std::env::temp_dir().join(format!("aggregator_shutdown_{}.json", std::process::id()));
+        let responses = TaskStore::open(&task_store_path)?.operator_responses();
+        assert!(!responses.contains_key(&task_id(2)));
+        assert!(responses
+            .get(&task_id(1))
+            .is_some_and(|responses| responses.contains_key(&signers[0].address())));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_process_completed_tasks() -> Result<()> {
+        let signers = [PrivateKeySigner::random(), PrivateKeySigner::random()];
+        let aggregator = test_aggregator(FakeChain::default(), "completed_tasks")?;
+        aggregator.update_operator_list(signers.iter().map(|signer| signer.address()).collect());
+
+        let (tx_aggregated, rx_aggregated) = mpsc::channel(2);
+        let (tx_result, mut rx_result) = mpsc::channel(2);
+        let processor = tokio::spawn(
+            aggregator
+                .clone()
+                .process_completed_tasks(rx_aggregated, tx_result),
+        );
+
+        for (task, results) in [(task_id(1), ["42", "0x2a"]), (task_id(2), ["42", "43"])] {
+            aggregator.tasks.insert(task, TaskStatus::PENDING);
+            let responses = signers
+                .iter()
+                .zip(results)
+                .map(|(signer, result)| (signer.address(), response(signer, task, result)))
+                .collect();
+            tx_aggregated
+                .send(AggregatedResponse {
+                    task_id: task,
+                    responses,
+                })
+                .await?;
+        }
+        drop(tx_aggregated);
+        processor.await?;
+
+        // Equivalent results agree, different ones fail the task
+        let agreeing = rx_result.recv().await.unwrap();
+        assert_eq!(agreeing.task_id, task_id(1));
+        assert_eq!(agreeing.status, TaskStatus::COMPLETED);
+        assert_eq!(agreeing.result, U256::from(42));
+        let disagreeing = rx_result.recv().await.unwrap();
+        assert_eq!(disagreeing.status, TaskStatus::FAILED);
+        assert_eq!(disagreeing.result_hash, None);
+
+        assert_eq!(
+            aggregator.tasks.get(&task_id(1)).as_deref(),
+            Some(&TaskStatus::COMPLETED)
+        );
+        assert_eq!(
+            aggregator.task_store.task_status(&task_id(2)),
+            Some(TaskStatus::FAILED)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_operator_failure() -> Result<()> {
+        let signers = [
+            PrivateKeySigner::random(),
+            PrivateKeySigner::random(),
+            PrivateKeySigner::random(),
+        ];
+        let aggregator = test_aggregator(FakeChain::default(), "operator_failure")?;
+        aggregator.update_operator_list(signers.iter().map(|signer| signer.address()).collect());
+
+        let (tx_message, rx_message) = mpsc::channel(8);
+        let (tx_aggregated, mut rx_aggregated) = mpsc::channel(2);
+        let queue = tokio::spawn(
+            aggregator
+                .clone()
+                .queue_operator_response(rx_message, tx_aggregated),
+        );
+        let failure = |signer: &PrivateKeySigner, task_id: TaskId| {
+            OperatorMessage::Failure(
+                signer.address(),
+                OperatorFailure {
+                    task_id,
+                    reason: "image pull failed".to_string(),
+                    signature: signer
+                        .sign_message_sync(&failure_signing_bytes(task_id, "image pull failed"))
+                        .unwrap(),
+                },
+            )
+        };
+
+        // The failed operator isn't waited for, even when its retry comes in
+        let task = task_id(1);
+        aggregator.tasks.insert(task, TaskStatus::PENDING);
+        for message in [
+            OperatorMessage::Response(response(&signers[0], task, "42")),
+            failure(&signers[1], task),
+            failure(&signers[1], task),
+            OperatorMessage::Response(response(&signers[2], task, "42")),
+        ] {
+            tx_message.send(message).await?;
+        }
+
+        // A task every operator failed is aggregated without any response
+        let failed = task_id(2);
+        aggregator.tasks.insert(failed, TaskStatus::PENDING);
+        for signer in &signers {
+            tx_message.send(failure(signer, failed)).await?;
+        }
+        drop(tx_message);
+        queue.await??;
+
+        let aggregated = rx_aggregated.recv().await.unwrap();
+        assert_eq!(aggregated.task_id, task);
+        assert_eq!(aggregated.responses.len(), 2);
+        let aggregated = rx_aggregated.recv().await.unwrap();
+        assert_eq!(aggregated.task_id, failed);
+        assert!(aggregated.responses.is_empty());
+        assert!(rx_aggregated.recv().await.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_operator_set_snapshot() -> Result<()> {
+        let signers = [
+            PrivateKeySigner::random(),
+            PrivateKeySigner::random(),
+            PrivateKeySigner::random(),
+        ];
+        let aggregator = test_aggregator(FakeChain::default(), "operator_set_snapshot")?;
+        aggregator.update_operator_list(vec![signers[0].address(), signers[1].address()]);
+
+        let (tx_message, rx_message) = mpsc::channel(8);
+        let (tx_aggregated, mut rx_aggregated) = mpsc::channel(2);
+        let requested = |task: TaskId| TaskRequested {
+            taskId: task.into(),
+            taskRequest: TaskRegistry::TaskRequest {
+                appId: app_id().into(),
+            },
+        };
+
+        // An operator registering after a task was created isn't waited for, while tasks
+        // created since expect it to respond
+        let task = task_id(1);
+        aggregator.track_task(requested(task), &tx_aggregated);
+        aggregator.update_operator_list(signers.iter().map(|signer| signer.address()).collect());
+        let later = task_id(2);
+        aggregator.track_task(requested(later), &tx_aggregated);
+        let queue = tokio::spawn(
+            aggregator
+                .clone()
+                .queue_operator_response(rx_message, tx_aggregated),
+        );
+        for signer in &signers[..2] {
+            for task in [task, later] {
+                tx_message
+                    .send(OperatorMessage::Response(response(signer, task, "42")))
+                    .await?;
+            }
+        }
+        let aggregated = rx_aggregated.recv().await.unwrap();
+        assert_eq!(aggregated.task_id, task);
+        assert_eq!(aggregated.responses.len(), 2);
+
+        tx_message
+            .send(OperatorMessage::Response(response(
+                &signers[2],
+                later,
+                "42",
+            )))
+            .await?;
+        let aggregated = rx_aggregated.recv().await.unwrap();
+        assert_eq!(aggregated.task_id, later);
+        assert_eq!(aggregated.responses.len(), 3);
+        drop(tx_message);
+        queue.await??;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stake_weighted_quorum() -> Result<()> {
+        let signers = [
+            PrivateKeySigner::random(),
+            PrivateKeySigner::random(),
+            PrivateKeySigner::random(),
+        ];
+        let mut aggregator = test_aggregator(FakeChain::default(), "stake_weighted_quorum")?;
+        let mut config = AggregatorConfig::from_env()?;
+        config.consensus = consensus::ConsensusKind::StakeWeighted;
+        aggregator.consensus = config.consensus.strategy();
+        aggregator.config = Arc::new(config);
+        aggregator.update_operator_list(signers.iter().map(|signer| signer.address()).collect());
+        for (signer, stake) in signers.iter().zip([1, 1, 4]) {
+            aggregator
+                .operator_stakes
+                .insert(signer.address(), U256::from(stake));
+        }
+
+        let (tx_message, rx_message) = mpsc::channel(8);
+        let (tx_aggregated, mut rx_aggregated) = mpsc::channel(2);
+        let queue = tokio::spawn(
+            aggregator
+                .clone()
+                .queue_operator_response(rx_message, tx_aggregated),
+        );
+
+        // A third of the stake doesn't settle the task, two thirds do without waiting for the
+        // last operator, whose response doesn't aggregate the task again
+        let task = task_id(1);
+        aggregator.tasks.insert(task, TaskStatus::PENDING);
+        tx_message
+            .send(OperatorMessage::Response(response(&signers[0], task, "42")))
+            .await?;
+        tx_message
+            .send(OperatorMessage::Response(response(&signers[2], task, "42")))
+            .await?;
+        let aggregated = rx_aggregated.recv().await.unwrap();
+        assert_eq!(aggregated.task_id, task);
+        assert_eq!(aggregated.responses.len(), 2);
+
+        tx_message
+            .send(OperatorMessage::Response(response(&signers[1], task, "43")))
+            .await?;
+        drop(tx_message);
+        queue.await??;
+        assert!(rx_aggregated.recv().await.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_changed_result_after_aggregation() -> Result<()> {
+        let signers = [PrivateKeySigner::random(), PrivateKeySigner::random()];
+        let aggregator = test_aggregator(FakeChain::default(), "changed_result")?;
+        aggregator.update_operator_list(signers.iter().map(|signer| signer.address()).collect());
+
+        let (tx_message, rx_message) = mpsc::channel(8);
+        let (tx_aggregated, mut rx_aggregated) = mpsc::channel(2);
+        let queue = tokio::spawn(
+            aggregator
+                .clone()
+                .queue_operator_response(rx_message, tx_aggregated),
+        );
+
+        // A result changed before the task is aggregated replaces the first one, a result
+        // changed afterwards is dropped rather than aggregating the task again
+        let task = task_id(1);
+        aggregator.tasks.insert(task, TaskStatus::PENDING);
+        for message in [
+            response(&signers[0], task, "41"),
+            response(&signers[0], task, "42"),
+            response(&signers[1], task, "42"),
+            response(&signers[1], task, "43"),
+        ] {
+            tx_message.send(OperatorMessage::Response(message)).await?;
+        }
+        drop(tx_message);
+        queue.await??;
+
+        let aggregated = rx_aggregated.recv().await.unwrap();
+        assert_eq!(aggregated.task_id, task);
+        assert!(aggregated
+            .responses
+            .iter()
+            .all(|response| response.result == "42"));
+        assert!(rx_aggregated.recv().await.is_none());
+        assert_eq!(
+            aggregator
+                .operator_responses
+                .get(&task)
+                .unwrap()
+                .get(&signers[1].address())
+                .unwrap()
+                .result,
+            "42"
+        );
+
+        // The server refuses the change up front
+        let (tx_message, _rx_message) = mpsc::channel(1);
+        let avs_state = aggregator.avs_state(tx_message);
+        let config = AggregatorConfig::from_env()?;
+        assert!(matches!(
+            server::submit_task(&avs_state, &config, response(&signers[0], task, "43")).await,
+            Err(server::ServerError::ResultAlreadyAggregated)
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_task_result_errors() -> Result<()> {
+        let aggregator = test_aggregator(FakeChain::default(), "task_result_errors")?;
+        let (tx_message, _rx_message) = mpsc::channel(1);
+        let avs_state = aggregator.avs_state(tx_message);
+
+        // Tasks without an off-chain result tell why: not there, not done, failed, or their
+        // result is on-chain as is
+        for (status, expected) in [
+            (None, "Task does not exist"),
+            (Some(TaskStatus::PENDING), "Not ready"),
+            (Some(TaskStatus::FAILED), "Task failed"),
+            (Some(TaskStatus::COMPLETED), "Task has no off-chain result"),
+        ] {
+            if let Some(status) = &status {
+                aggregator.tasks.insert(task_id(1), status.clone());
+            }
+            let error = server::task_result(&avs_state, task_id(1)).unwrap_err();
+            assert!(
+                error.to_string().starts_with(expected),
+                "{:?} gave {}",
+                status,
+                error
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_routing() -> Result<()> {
+        let first = test_aggregator(FakeChain::default(), "routing_first")?;
+        let second = test_aggregator(FakeChain::default(), "routing_second")?;
+        first.tasks.insert(task_id(1), TaskStatus::PENDING);
+        second.tasks.insert(task_id(2), TaskStatus::COMPLETED);
+        let (tx_message, _rx_message) = mpsc::channel(1);
+        let app_state = AppState {
+            avs: HashMap::from([
+                (
+                    "first".to_string(),
+                    Arc::new(first.avs_state(tx_message.clone())),
+                ),
+                ("second".to_string(), Arc::new(second.avs_state(tx_message))),
+            ]),
+            default_avs: "first".to_string(),
+            config: first.config.clone(),
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let app = server::router(Arc::new(app_state));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        // Paths with an AVS id reach that deployment only, the others the default one
+        let client = reqwest::Client::new();
+        for (path, expected) in [
+            (format!("/task_status/{}", task_id(1)), TaskStatus::PENDING),
+            (format!("/task_status/{}", task_id(2)), TaskStatus::EMPTY),
+            (
+                format!("/avs/first/task_status/{}", task_id(1)),
+                TaskStatus::PENDING,
+            ),
+            (
+                format!("/avs/second/task_status/{}", task_id(2)),
+                TaskStatus::COMPLETED,
+            ),
+            (
+                format!("/avs/second/task_status/{}", task_id(1)),
+                TaskStatus::EMPTY,
+            ),
+        ] {
+            let response = client.get(format!("{}{}", url, path)).send().await?;
+            assert_eq!(response.json::<TaskStatus>().await?, expected, "{}", path);
+        }
+
+        // Unknown deployments are reported as such
+        let response = client
+            .get(format!("{}/avs/third/task_status/{}", url, task_id(1)))
+            .send()
+            .await?;
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+        assert!(response.text().await?.contains("Unknown AVS: third"));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "simulate")]
+    #[tokio::test]
+    async fn test_simulate_consensus() -> Result<()> {
+        let aggregator = test_aggregator(FakeChain::default(), "simulate_consensus")?;
+        let mut config = AggregatorConfig::from_env()?;
+        config.consensus = consensus::ConsensusKind::Majority;
+        let (tx_message, _rx_message) = mpsc::channel(1);
+        let app_state = AppState {
+            avs: HashMap::from([(
+                "default".to_string(),
+                Arc::new(aggregator.avs_state(tx_message)),
+            )]),
+            default_avs: "default".to_string(),
+            config: Arc::new(config),
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/simulate_consensus", listener.local_addr()?);
+        let app = server::router(Arc::new(app_state));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        // The configured strategy decides, over an operator set made of the responders
+        let client = reqwest::Client::new();
+        let operators = [
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+        ];
+        for (results, status, result) in [
+            (["42", "42", "43"], TaskStatus::COMPLETED, U256::from(42)),
+            (["42", "43", "44"], TaskStatus::FAILED, U256::ZERO),
+        ] {
+            let responses = operators.iter().zip(results).collect::<Vec<_>>();
+            let simulation = client
+                .post(&url)
+                .json(&serde_json::json!({ "task_id": task_id(1), "responses": responses }))
+                .send()
+                .await?
+                .json::<serde_json::Value>()
+                .await?;
+            assert_eq!(simulation["status"], serde_json::to_value(status)?);
+            assert_eq!(simulation["result"], serde_json::to_value(result)?);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tls_server() -> Result<()> {
+        let testdata = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata");
+        let aggregator = test_aggregator(FakeChain::default(), "tls_server")?;
+        let mut config = AggregatorConfig::from_env()?;
+        config.tls_cert_path = Some(testdata.join("localhost.pem"));
+        config.tls_key_path = Some(testdata.join("localhost-key.pem"));
+        let (tx_message, _rx_message) = mpsc::channel(1);
+        let app_state = AppState {
+            avs: HashMap::from([(
+                "default".to_string(),
+                Arc::new(aggregator.avs_state(tx_message)),
+            )]),
+            default_avs: "default".to_string(),
+            config: Arc::new(config),
+        };
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        let shutdown = CancellationToken::new();
+        let server = tokio::spawn(server::run_http_server(
+            Arc::new(app_state),
+            listener,
+            shutdown.clone().cancelled_owned(),
+        ));
+
+        // Clients trusting the certificate's CA get answers over HTTPS only
+        let ca = reqwest::Certificate::from_pem(&std::fs::read(testdata.join("ca.pem"))?)?;
+        let client = reqwest::Client::builder()
+            .add_root_certificate(ca)
+            .build()?;
+        let response = client
+            .get(format!("https://localhost:{}/version", port))
+            .send()
+            .await?;
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert!(client
+            .get(format!("http://localhost:{}/version", port))
+            .send()
+            .await
+            .is_err());
+
+        shutdown.cancel();
+        server.await??;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_task_events() -> Result<()> {
+        let aggregator = test_aggregator(FakeChain::default(), "task_events")?;
+        let task = task_id(1);
+        aggregator.tasks.insert(task, TaskStatus::PENDING);
+        let (tx_message, _rx_message) = mpsc::channel(1);
+        let app_state = AppState {
+            avs: HashMap::from([(
+                "default".to_string(),
+                Arc::new(aggregator.avs_state(tx_message)),
+            )]),
+            default_avs: "default".to_string(),
+            config: aggregator.config.clone(),
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let app = server::router(Arc::new(app_state));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        // Unknown tasks have no events to follow
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("{}/tasks/{}/events", url, task_id(2)))
+            .send()
+            .await?;
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+        // The current status comes first
+        let mut response = client
+            .get(format!("{}/tasks/{}/events", url, task))
+            .send()
+            .await?;
+        let mut received = String::new();
+        while !received.contains("PENDING") {
+            let chunk = response.chunk().await?.unwrap();
+            received.push_str(std::str::from_utf8(&chunk)?);
+        }
+
+        // Then the transitions of the task only, until the final one closes the stream
+        aggregator.publish_task_event(TaskEvent::status(task_id(2), TaskStatus::FAILED));
+        aggregator.publish_task_event(TaskEvent {
+            task_id: task,
+            status: TaskStatus::COMPLETED,
+            result: Some(U256::from(42)),
+            result_hash: None,
+        });
+        let received = response.text().await?;
+        assert!(!received.contains("FAILED"), "{}", received);
+        assert!(received.contains("event: status"), "{}", received);
+        assert!(
+            received.contains(r#""status":"COMPLETED","result":"0x2a""#),
+            "{}",
+            received
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_evict_finalized_tasks() -> Result<()> {
+        let mut aggregator = test_aggregator(FakeChain::default(), "evict_finalized_tasks")?;
+        Arc::get_mut(&mut aggregator.config)
+            .unwrap()
+            .max_tasks_in_memory = 1;
+        for task in [task_id(1), task_id(2)] {
+            aggregator.task_app_ids.insert(task, app_id());
+            aggregator.timings.insert(task, TaskTimings::default());
+        }
+
+        // The oldest finalized task is dropped from memory along with all of its state
+        aggregator.finalize_task(task_id(1), TaskStatus::FAILED);
+        aggregator.finalize_task(task_id(2), TaskStatus::FAILED);
+        assert!(!aggregator.tasks.contains_key(&task_id(1)));
+        assert!(!aggregator.task_app_ids.contains_key(&task_id(1)));
+        assert!(!aggregator.timings.contains_key(&task_id(1)));
+        assert!(aggregator.timings.contains_key(&task_id(2)));
+        assert_eq!(
+            aggregator.task_store.task_status(&task_id(1)),
+            Some(TaskStatus::FAILED)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_task_statuses() -> Result<()> {
+        let aggregator = test_aggregator(FakeChain::default(), "task_statuses")?;
+        let mut config = AggregatorConfig::from_env()?;
+        config.max_task_status_batch_size = 2;
+        aggregator.tasks.insert(task_id(1), TaskStatus::PENDING);
+        let (tx_message, _rx_message) = mpsc::channel(1);
+        let avs_state = aggregator.avs_state(tx_message);
+        let batch = |task_ids: Vec<TaskId>| server::TaskStatusBatch { task_ids };
+
+        // Unknown tasks are reported as such rather than failing the batch
+        let statuses =
+            server::task_statuses(&avs_state, &config, batch(vec![task_id(1), task_id(2)]))?.0;
+        assert_eq!(
+            statuses,
+            std::collections::BTreeMap::from([
+                (task_id(1), TaskStatus::PENDING),
+                (task_id(2), TaskStatus::EMPTY),
+            ])
+        );
+
+        // Batches over the cap are rejected as a whole
+        let error = server::task_statuses(
+            &avs_state,
+            &config,
+            batch(vec![task_id(1), task_id(2), task_id(3)]),
+        )
+        .unwrap_err();
+        assert!(matches!(error, server::ServerError::InvalidRequest(_)));
+        assert_eq!(
+            axum::response::IntoResponse::into_response(error).status(),
+            axum::http::StatusCode::BAD_REQUEST
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_avs_state() -> Result<()> {
+        let signer = PrivateKeySigner::random();
+        let aggregator = test_aggregator(FakeChain::default(), "avs_state")?;
+        let (tx_message, mut rx_message) = mpsc::channel(1);
+        let app_state = AppState {
+            avs: HashMap::from([(
+                aggregator.avs_id.clone(),
+                Arc::new(aggregator.avs_state(tx_message)),
+            )]),
+            default_avs: aggregator.avs_id.clone(),
+            config: aggregator.config.clone(),
+        };
+        let avs_state = app_state.avs(&app_state.default_avs).unwrap();
+
+        // The server reads the maps the pipeline writes to, and the other way around
+        let task = task_id(1);
+        aggregator.update_operator_list(vec![signer.address()]);
+        aggregator.tasks.insert(task, TaskStatus::PENDING);
+        assert!(avs_state.operator_list.contains_key(&signer.address()));
+        assert_eq!(
+            avs_state.tasks.get(&task).as_deref(),
+            Some(&TaskStatus::PENDING)
+        );
+        avs_state
+            .operator_responses
+            .entry(task)
+            .or_default()
+            .insert(signer.address(), response(&signer, task, "42"));
+        assert!(aggregator.operator_responses.contains_key(&task));
+
+        avs_state
+            .sender
+            .send(OperatorMessage::Response(response(&signer, task, "42")))
+            .await?;
+        assert!(rx_message.recv().await.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resubmitted_response() -> Result<()> {
+        let signer = PrivateKeySigner::random();
+        let aggregator = test_aggregator(FakeChain::default(), "resubmitted_response")?;
+        aggregator.update_operator_list(vec![signer.address()]);
+        let task = task_id(1);
+        aggregator.tasks.insert(task, TaskStatus::PENDING);
+        let (tx_message, rx_message) = mpsc::channel(8);
+        let (tx_aggregated, mut rx_aggregated) = mpsc::channel(2);
+        let avs_state = aggregator.avs_state(tx_message);
+        let queue = tokio::spawn(
+            aggregator
+                .clone()
+                .queue_operator_response(rx_message, tx_aggregated),
+        );
+        let config = aggregator.config.clone();
+
+        server::submit_task(&avs_state, &config, response(&signer, task, "42")).await?;
+        let aggregated = rx_aggregated.recv().await.unwrap();
+        assert_eq!(aggregated.responses.len(), 1);
+
+        // A retry of the recorded response is acknowledged without being queued again, even
+        // once the task is no longer pending
+        aggregator.tasks.insert(task, TaskStatus::COMPLETED);
+        let status =
+            server::submit_task(&avs_state, &config, response(&signer, task, "42")).await?;
+        assert_eq!(status, axum::http::StatusCode::OK);
+        assert_eq!(aggregator.operator_responses.get(&task).unwrap().len(), 1);
+
+        // A changed result isn't a retry
+        aggregator.tasks.insert(task, TaskStatus::PENDING);
+        let error = server::submit_task(&avs_state, &config, response(&signer, task, "43"))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            server::ServerError::ResultAlreadyAggregated
+        ));
+
+        drop(avs_state);
+        queue.await??;
+        assert!(rx_aggregated.recv().await.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_task_timeout() -> Result<()> {
+        let signers = [
+            PrivateKeySigner::random(),
+            PrivateKeySigner::random(),
+            PrivateKeySigner::random(),
+        ];
+        let mut aggregator = test_aggregator(FakeChain::default(), "task_timeout")?;
+        Arc::get_mut(&mut aggregator.config)
+            .unwrap()
+            .task_timeout_secs = 1;
+        aggregator.update_operator_list(signers.iter().map(|signer| signer.address()).collect());
+
+        let (tx_message, rx_message) = mpsc::channel(8);
+        let (tx_aggregated, mut rx_aggregated) = mpsc::channel(2);
+        let task = task_id(1);
+        aggregator.tasks.insert(task, TaskStatus::PENDING);
+        aggregator.start_task_timeout(task, &tx_aggregated);
+        let queue = tokio::spawn(
+            aggregator
+                .clone()
+                .queue_operator_response(rx_message, tx_aggregated),
+        );
+
+        // The task is aggregated with the only response received before the timeout
+        tx_message
+            .send(OperatorMessage::Response(response(&signers[0], task, "42")))
+            .await?;
+        let aggregated = rx_aggregated.recv().await.unwrap();
+        assert_eq!(aggregated.task_id, task);
+        assert_eq!(aggregated.responses.len(), 1);
+
+        // The operator that responded can no longer change its result
+        assert!(server::changes_aggregated_result(
+            &aggregator.operator_responses,
+            &aggregator.aggregated_tasks,
+            &signers[0].address(),
+            &response(&signers[0], task, "43"),
+        ));
+
+        // Late responses completing the set don't aggregate it a second time
+        for signer in &signers[1..] {
+            tx_message
+                .send(OperatorMessage::Response(response(signer, task, "42")))
+                .await?;
+        }
+        drop(tx_message);
+        queue.await??;
+        assert!(rx_aggregated.recv().await.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_requeued_task_is_aggregated() -> Result<()> {
+        let signers = [PrivateKeySigner::random(), PrivateKeySigner::random()];
+        let mut aggregator = test_aggregator(
+            FakeChain {
+                tasks: vec![
+                    (task_id(1), app_id(), TaskStatus::PENDING),
+                    (task_id(2), app_id(), TaskStatus::FAILED),
+                ],
+                ..Default::default()
+            },
+            "requeued_task",
+        )?;
+        Arc::get_mut(&mut aggregator.config)
+            .unwrap()
+            .task_timeout_secs = 60;
+        aggregator.update_operator_list(signers.iter().map(|signer| signer.address()).collect());
+
+        let (tx_message, rx_message) = mpsc::channel(8);
+        let (tx_aggregated, mut rx_aggregated) = mpsc::channel(2);
+        let avs_state = aggregator.avs_state(tx_message.clone());
+        let queue = tokio::spawn(
+            aggregator
+                .clone()
+                .queue_operator_response(rx_message, tx_aggregated),
+        );
+
+        // A task whose failure already landed on-chain can't be answered again
+        aggregator.finalize_task(task_id(2), TaskStatus::FAILED);
+        assert!(matches!(
+            server::requeue_task(&avs_state, task_id(2)).await,
+            Err(server::ServerError::TaskFinalizedOnChain(
+                TaskStatus::FAILED
+            ))
+        ));
+
+        // A failed task is requeued, its timer expired long ago
+        let task = task_id(1);
+        aggregator.finalize_task(task, TaskStatus::FAILED);
+        server::requeue_task(&avs_state, task).await?;
+        assert_eq!(
+            aggregator.tasks.get(&task).as_deref(),
+            Some(&TaskStatus::PENDING)
+        );
+
+        // It is aggregated once every operator responded again
+        for signer in &signers {
+            tx_message
+                .send(OperatorMessage::Response(response(signer, task, "42")))
+                .await?;
+        }
+        drop((tx_message, avs_state));
+        queue.await??;
+        let aggregated = rx_aggregated.recv().await.unwrap();
+        assert_eq!(aggregated.task_id, task);
+        assert_eq!(aggregated.responses.len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_task_status() -> Result<()> {
+        let aggregator = test_aggregator(
+            FakeChain {
+                tasks: vec![
+                    (task_id(1), app_id(), TaskStatus::PENDING),
+                    (task_id(2), app_id(), TaskStatus::FAILED),
+                ],
+                ..Default::default()
+            },
+            "reconcile",
+        )?;
+        let (tx_retry, mut rx_retry) = mpsc::channel(2);
+        let task_result = |task_id| TaskResult {
+            task_id,
+            status: TaskStatus::COMPLETED,
+            result: U256::from(42),
+            result_hash: None,
+            resubmissions: 0,
+        };
+
+        // A result still pending on-chain is resubmitted, unless told otherwise
+        aggregator
+            .reconcile_task_status(task_result(task_id(1)), false, &tx_retry)
+            .await;
+        assert!(rx_retry.try_recv().is_err());
+        aggregator
+            .reconcile_task_status(task_result(task_id(1)), true, &tx_retry)
+            .await;
+        let resubmitted = rx_retry.try_recv()?;
+        assert_eq!(resubmitted.task_id, task_id(1));
+        assert_eq!(resubmitted.resubmissions, 1);
+
+        // The local status follows the one recorded on-chain
+        aggregator.tasks.insert(task_id(2), TaskStatus::COMPLETED);
+        aggregator
+            .reconcile_task_status(task_result(task_id(2)), true, &tx_retry)
+            .await;
+        assert!(rx_retry.try_recv().is_err());
+        assert_eq!(
+            aggregator.tasks.get(&task_id(2)).as_deref(),
+            Some(&TaskStatus::FAILED)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_await_confirmation() -> Result<()> {
+        // Receipt of the submission, and nonce of the aggregator account at the latest block
+        for (receipt, mined_nonce, resubmitted) in [
+            (Some(true), 1, true),
+            (Some(false), 1, false),
+            (None, 1, true),
+        ] {
+            let aggregator = test_aggregator(
+                FakeChain {
+                    tasks: vec![(task_id(1), app_id(), TaskStatus::PENDING)],
+                    receipt: Some(receipt),
+                    mined_nonce,
+                    ..Default::default()
+                },
+                "await_confirmation",
+            )?;
+            let submission = PendingSubmission {
+                task_results: vec![TaskResult {
+                    task_id: task_id(1),
+                    status: TaskStatus::COMPLETED,
+                    result: U256::from(42),
+                    result_hash: None,
+                    resubmissions: 0,
+                }],
+                tx_request: TransactionRequest::default()
+                    .from(Address::repeat_byte(1))
+                    .nonce(0),
+                tx_hashes: vec![TxHash::repeat_byte(1)],
+            };
+
+            // A task left pending by a mined transaction, or one whose nonce was used by
+            // another, is resubmitted. A reverted one is not.
+            let (tx_retry, mut rx_retry) = mpsc::channel(1);
+            aggregator
+                .clone()
+                .await_confirmation(submission, tx_retry)
+                .await;
+            assert_eq!(
+                rx_retry.try_recv().is_ok(),
+                resubmitted,
+                "receipt {:?}",
+                receipt
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_respond_request() {
+        let task_registry = Address::repeat_byte(1);
+        let task_results = [1, 2].map(|byte| TaskResult {
+            task_id: task_id(byte),
+            status: TaskStatus::COMPLETED,
+            result: U256::from(byte),
+            result_hash: None,
+            resubmissions: 0,
+        });
+        let selector = |task_results: &[TaskResult]| {
+            respond_request(task_registry, task_results)
+                .input
+                .input()
+                .map(|input| input[..4].to_vec())
+        };
+
+        // A batch of one costs less through `respondToTask`
+        assert_eq!(
+            selector(&task_results[..1]),
+            Some(respondToTaskCall::SELECTOR.to_vec())
+        );
+        assert_eq!(
+            selector(&task_results),
+            Some(respondToTasksCall::SELECTOR.to_vec())
+        );
+    }
 }
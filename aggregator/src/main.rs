@@ -1,9 +1,12 @@
-use std::env;
+use std::{env, path::PathBuf};
 
-use aggregator::Aggregator;
+use aggregator::{aggregator_config::AggregatorConfig, Aggregator, GIT_SHA, VERSION};
+use contract_bindings::Chain;
 use eyre::Result;
 use time::macros::format_description;
-use tracing::error;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
 use tracing_subscriber::fmt;
 
 fn init_tracing() {
@@ -24,6 +27,46 @@ fn init_tracing() {
         .event_format(format)
         .with_max_level(tracing::Level::INFO)
         .init();
+
+    info!("Aggregator {} ({})", VERSION, GIT_SHA);
+}
+
+// Remove `<flag> <path>` (e.g. `--config <path>`) from the arguments, returning the path if
+// present
+fn take_path_flag(args: &mut Vec<String>, flag: &str) -> Option<PathBuf> {
+    let index = args.iter().position(|arg| arg == flag)?;
+    if index + 1 >= args.len() {
+        return None;
+    }
+    let path = args.remove(index + 1);
+    args.remove(index);
+    Some(path.into())
+}
+
+// Resolve on Ctrl-C or SIGTERM
+async fn shutdown_signal() {
+    let terminate = async {
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {:?}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl-C: {:?}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    tokio::select! {
+        _ = terminate => {}
+        _ = interrupt => {}
+    }
 }
 
 #[tokio::main]
@@ -31,24 +74,44 @@ async fn main() -> Result<()> {
     // Initialize tracing
     init_tracing();
 
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    if let Some(chain_config_path) = take_path_flag(&mut args, "--chain-config") {
+        Chain::from_config(&chain_config_path)?.install()?;
+    }
+    let config = match take_path_flag(&mut args, "--config") {
+        Some(path) => AggregatorConfig::from_file(&path)?,
+        None => AggregatorConfig::from_env()?,
+    };
+    // Report every misconfiguration up front, before connecting to anything
+    config.validate()?;
+
     match args.len() {
         2 => {
             // Correct number of arguments, continue with the private key
             let chain = args[1].clone().into();
-            let mut aggregator = Aggregator::new(chain).await?;
-            aggregator.run().await?
+            let aggregator = Aggregator::new(chain, config).await?;
+            let shutdown = CancellationToken::new();
+            tokio::spawn({
+                let shutdown = shutdown.clone();
+                async move {
+                    shutdown_signal().await;
+                    info!("Shutdown requested, finishing in-flight tasks...");
+                    shutdown.cancel();
+                }
+            });
+            aggregator.run(shutdown).await?;
         }
         _ => {
-            error!("Usage: {} <chain> ", args[0]);
+            error!(
+                "Usage: {} [--config <path>] [--chain-config <path>] <chain> ",
+                args[0]
+            );
             error!("Only the chain is expected as argument");
             std::process::exit(1);
         }
     }
 
-    // Keep the main thread running
-    tokio::signal::ctrl_c().await?;
-    println!("Shutting down");
+    info!("Shutting down");
 
     Ok(())
 }
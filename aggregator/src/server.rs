@@ -1,31 +1,90 @@
-use alloy_primitives::{Address, FixedBytes, Signature};
+use alloy::eips::BlockNumberOrTag;
+use alloy_primitives::{keccak256, Address, Signature, B256, U256};
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::{IntoResponse, Response},
+    extract::{rejection::JsonRejection, Path, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
     routing::{get, post},
     Json, Router,
 };
-use contract_bindings::TaskStatus;
-use dashmap::DashMap;
+use axum_macros::FromRequest;
+use axum_server::{tls_rustls::RustlsConfig, Handle};
+use contract_bindings::{
+    failure_signing_bytes, response_signing_bytes, TaskId, TaskStatus, RESPONSE_VERSION_ECDSA,
+    RESPONSE_VERSION_ECDSA_TASK_BOUND,
+};
+use dashmap::{DashMap, DashSet};
+use futures::{FutureExt, Stream, StreamExt};
+use lru::LruCache;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    future::Future,
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
 use thiserror::Error;
-use tokio::net::TcpListener;
-use tracing::{error, info};
+use tokio::{
+    net::TcpListener,
+    sync::broadcast::{self, error::RecvError},
+};
+use tracing::{info, warn};
+
+use tower_http::limit::RequestBodyLimitLayer;
+
+use crate::{
+    aggregator_config::AggregatorConfig, chain::AvsChain, consensus::OperatorSet,
+    task_log::TaskRecord, task_store::TaskStore, AggregatorError, GIT_SHA, VERSION,
+};
 
 // Custom error type for server-related errors
 #[derive(Error, Debug)]
 pub enum ServerError {
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
     #[error("Invalid signature")]
     InvalidSignature,
+    #[error("Unsupported response version: {0}")]
+    UnsupportedVersion(u8),
     #[error("Invalid operator")]
     InvalidOperator,
     #[error("Task does not exist")]
     TaskDoesNotExist,
     #[error("Task already completed")]
     TaskAlreadyCompleted,
+    #[error("Task is not failed: {0:?}")]
+    TaskNotFailed(TaskStatus),
+    #[error("Task is not pending")]
+    TaskNotPending,
+    #[error("Result already aggregated")]
+    ResultAlreadyAggregated,
+    #[error("Task is already {0:?} on-chain")]
+    TaskFinalizedOnChain(TaskStatus),
+    #[error("Unknown AVS: {0}")]
+    UnknownAvs(String),
+    #[error("Unauthorized")]
+    Unauthorized,
+    #[error("No responses for task")]
+    NoResponses,
+    #[error("Task has no off-chain result")]
+    NoOffchainResult,
+    #[error("Task failed")]
+    TaskFailed,
+    #[error("Not ready: {0}")]
+    NotReady(String),
+    #[error("Chain submission failed: {0}")]
+    ChainSubmissionFailed(String),
+    #[error("Insufficient funds: {0}")]
+    InsufficientFunds(String),
     #[error("Internal server error: {0}")]
     InternalError(String),
 }
@@ -34,9 +93,15 @@ pub enum ServerError {
 impl IntoResponse for ServerError {
     fn into_response(self) -> Response {
         let (status, error_message) = match self {
+            ServerError::InvalidRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            ServerError::PayloadTooLarge(msg) => (StatusCode::PAYLOAD_TOO_LARGE, msg),
             ServerError::InvalidSignature => {
                 (StatusCode::BAD_REQUEST, "Invalid signature".to_string())
             }
+            ServerError::UnsupportedVersion(version) => (
+                StatusCode::BAD_REQUEST,
+                format!("Unsupported response version: {}", version),
+            ),
             ServerError::InvalidOperator => (StatusCode::FORBIDDEN, "Invalid operator".to_string()),
             ServerError::TaskDoesNotExist => {
                 (StatusCode::NOT_FOUND, "Task does not exist".to_string())
@@ -44,6 +109,55 @@ impl IntoResponse for ServerError {
             ServerError::TaskAlreadyCompleted => {
                 (StatusCode::CONFLICT, "Task already completed".to_string())
             }
+            ServerError::TaskNotFailed(status) => (
+                StatusCode::CONFLICT,
+                format!("Only failed tasks can be requeued, task is {:?}", status),
+            ),
+            ServerError::TaskFinalizedOnChain(status) => (
+                StatusCode::CONFLICT,
+                format!(
+                    "Only tasks still pending on-chain can be requeued, task is {:?} on-chain",
+                    status
+                ),
+            ),
+            ServerError::TaskNotPending => {
+                (StatusCode::NOT_FOUND, "Task is not pending".to_string())
+            }
+            ServerError::ResultAlreadyAggregated => (
+                StatusCode::CONFLICT,
+                "The task was already aggregated, its result can't be changed".to_string(),
+            ),
+            ServerError::UnknownAvs(avs_id) => {
+                (StatusCode::NOT_FOUND, format!("Unknown AVS: {}", avs_id))
+            }
+            ServerError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized".to_string()),
+            ServerError::NoResponses => (
+                StatusCode::NOT_FOUND,
+                "No responses received for task".to_string(),
+            ),
+            ServerError::NoOffchainResult => (
+                StatusCode::NOT_FOUND,
+                "Task has no off-chain result, its result was submitted on-chain as is"
+                    .to_string(),
+            ),
+            ServerError::TaskFailed => (
+                StatusCode::CONFLICT,
+                "Task failed, it has no result".to_string(),
+            ),
+            ServerError::NotReady(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg),
+            // The chain or the RPC node failed, the same request may succeed later
+            ServerError::ChainSubmissionFailed(msg) => (
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to submit the transaction on-chain, retry later: {}", msg),
+            ),
+            // Retrying won't help until the submitting account is funded
+            ServerError::InsufficientFunds(msg) => (
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Insufficient funds to submit the transaction, fund the account before retrying: {}",
+                    msg
+                ),
+            ),
             ServerError::InternalError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
         };
 
@@ -55,92 +169,1558 @@ impl IntoResponse for ServerError {
     }
 }
 
+// Map body extraction failures (malformed JSON, missing or mistyped fields, wrong content type)
+// into the same error envelope as every other endpoint
+impl From<JsonRejection> for ServerError {
+    fn from(rejection: JsonRejection) -> Self {
+        match rejection.status() {
+            StatusCode::PAYLOAD_TOO_LARGE => ServerError::PayloadTooLarge(rejection.body_text()),
+            _ => ServerError::InvalidRequest(rejection.body_text()),
+        }
+    }
+}
+
+// Map transaction failures into the errors clients can act upon: an unfunded account is a
+// permanent failure, anything else the chain or the RPC node rejected (reverts, nonce races,
+// unreachable node) may go through on a retry
+impl From<AggregatorError> for ServerError {
+    fn from(error: AggregatorError) -> Self {
+        match error {
+            AggregatorError::InsufficientFunds(msg) => ServerError::InsufficientFunds(msg),
+            AggregatorError::TxError(msg) => ServerError::ChainSubmissionFailed(msg),
+            error => ServerError::InternalError(error.to_string()),
+        }
+    }
+}
+
+// JSON body extractor whose rejections are reported as `ServerError`
+#[derive(FromRequest)]
+#[from_request(via(Json), rejection(ServerError))]
+struct JsonBody<T>(T);
+
 // Struct to represent an operator's response to a task
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct OperatorResponse {
-    pub task_id: FixedBytes<32>,
+    pub task_id: TaskId,
     pub result: String,
     pub signature: Signature,
+    // Signature scheme of the response, responses from operators predating it use ECDSA over
+    // the result alone
+    #[serde(default)]
+    pub version: u8,
+    // Exit code and run time (in milliseconds) of the operator's container. They aren't signed,
+    // so they can only flag a response, and are missing from operators predating them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    // Unix time (in seconds) the operator signed the response at, only used to detect clock
+    // skew. Not signed either, and missing from operators predating it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+}
+
+impl OperatorResponse {
+    // Bytes signed by the operator, independent of the JSON the response was sent as
+    pub fn signing_bytes(&self) -> Result<Vec<u8>, ServerError> {
+        response_signing_bytes(self.version, self.task_id, &self.result)
+            .ok_or(ServerError::UnsupportedVersion(self.version))
+    }
+
+    // Recover the operator that signed the response, according to its signature scheme
+    pub fn recover_operator(&self) -> Result<Address, ServerError> {
+        let signing_bytes = self.signing_bytes()?;
+        match self.version {
+            RESPONSE_VERSION_ECDSA | RESPONSE_VERSION_ECDSA_TASK_BOUND => self
+                .signature
+                .recover_address_from_msg(signing_bytes)
+                .map_err(|_| ServerError::InvalidSignature),
+            version => Err(ServerError::UnsupportedVersion(version)),
+        }
+    }
+}
+
+// Struct to represent an operator reporting that it couldn't produce a result for a task, e.g.
+// because the image of its client app failed to pull or run
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OperatorFailure {
+    pub task_id: TaskId,
+    pub reason: String,
+    // ECDSA signature of the task id and the reason
+    pub signature: Signature,
+}
+
+impl OperatorFailure {
+    // Recover the operator that signed the failure report
+    pub fn recover_operator(&self) -> Result<Address, ServerError> {
+        self.signature
+            .recover_address_from_msg(failure_signing_bytes(self.task_id, &self.reason))
+            .map_err(|_| ServerError::InvalidSignature)
+    }
+}
+
+// What the server hands over to the response queue of a deployment
+#[derive(Debug)]
+pub enum OperatorMessage {
+    Response(OperatorResponse),
+    // A verified failure report, along with the operator that signed it
+    Failure(Address, OperatorFailure),
+    // A failed task reset to pending by an admin, whose responses are collected again
+    Requeued(TaskId),
+}
+
+// Operators a response may come from, such as the registered operator list of a deployment or
+// the set an auditor collected from the chain
+pub trait KnownOperators {
+    fn contains_operator(&self, operator: &Address) -> bool;
+}
+
+impl KnownOperators for DashMap<Address, ()> {
+    fn contains_operator(&self, operator: &Address) -> bool {
+        self.contains_key(operator)
+    }
+}
+
+impl KnownOperators for HashSet<Address> {
+    fn contains_operator(&self, operator: &Address) -> bool {
+        self.contains(operator)
+    }
+}
+
+impl KnownOperators for [Address] {
+    fn contains_operator(&self, operator: &Address) -> bool {
+        self.contains(operator)
+    }
+}
+
+// Verify a response without any aggregator state, e.g. a stored one being audited: recover the
+// operator that signed it and check that it is one of `known_operators`. Task-bound signatures
+// also cover the `task_id`, so a response moved to another task recovers some other address;
+// legacy ones only cover the result.
+pub fn verify_operator_response<K: KnownOperators + ?Sized>(
+    response: &OperatorResponse,
+    known_operators: &K,
+) -> Result<Address, ServerError> {
+    check_known_operator(response.recover_operator()?, known_operators)
+}
+
+fn check_known_operator<K: KnownOperators + ?Sized>(
+    operator: Address,
+    known_operators: &K,
+) -> Result<Address, ServerError> {
+    match known_operators.contains_operator(&operator) {
+        true => Ok(operator),
+        false => Err(ServerError::InvalidOperator),
+    }
+}
+
+// Bounded LRU cache of the operators recovered from response signatures, so that resubmitted
+// responses don't pay for an ECDSA recovery on every submission. Only successful recoveries are
+// cached.
+pub struct RecoveryCache {
+    // `None` when caching is disabled
+    cache: Option<Mutex<LruCache<B256, Address>>>,
+}
+
+impl RecoveryCache {
+    // A cache holding up to `capacity` recovered operators, disabled when `capacity` is 0
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            cache: NonZeroUsize::new(capacity).map(|capacity| Mutex::new(LruCache::new(capacity))),
+        }
+    }
+
+    // Recover the operator that signed `response`, from the cache when it was already seen
+    pub fn recover(&self, response: &OperatorResponse) -> Result<Address, ServerError> {
+        let Some(cache) = &self.cache else {
+            return response.recover_operator();
+        };
+
+        // The recovered address only depends on the scheme, the signature and the signed bytes,
+        // which include the task id of task-bound responses
+        let key = keccak256(
+            [
+                &[response.version][..],
+                &response.signature.as_bytes()[..],
+                &response.signing_bytes()?,
+            ]
+            .concat(),
+        );
+        if let Some(operator) = cache.lock().unwrap().get(&key) {
+            return Ok(*operator);
+        }
+
+        let operator = response.recover_operator()?;
+        cache.lock().unwrap().put(key, operator);
+        Ok(operator)
+    }
+
+    // Same as `verify_operator_response`, recovering the operator from the cache when the
+    // response was already seen
+    pub fn verify<K: KnownOperators + ?Sized>(
+        &self,
+        response: &OperatorResponse,
+        known_operators: &K,
+    ) -> Result<Address, ServerError> {
+        check_known_operator(self.recover(response)?, known_operators)
+    }
+}
+
+// Outcome of the consensus rounds run since the aggregator started. Unlike the task map, it
+// isn't affected by finalized tasks being evicted from memory.
+#[derive(Default)]
+pub struct ConsensusStats {
+    completed: AtomicU64,
+    failed: AtomicU64,
+    // Responses the completed tasks were aggregated from
+    completed_responses: AtomicU64,
+}
+
+impl ConsensusStats {
+    // Record a consensus round over `responses` responses
+    pub fn record(&self, status: &TaskStatus, responses: usize) {
+        match status {
+            TaskStatus::COMPLETED => {
+                self.completed.fetch_add(1, Ordering::Relaxed);
+                self.completed_responses
+                    .fetch_add(responses as u64, Ordering::Relaxed);
+            }
+            _ => {
+                self.failed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    // Average number of responses a completed task was aggregated from, 0 before any completes
+    fn average_responses(&self) -> f64 {
+        match self.completed.load(Ordering::Relaxed) {
+            0 => 0.0,
+            completed => self.completed_responses.load(Ordering::Relaxed) as f64 / completed as f64,
+        }
+    }
+
+    // Share of the consensus rounds that failed, 0 before any round
+    fn failure_rate(&self) -> f64 {
+        let failed = self.failed.load(Ordering::Relaxed);
+        match self.completed.load(Ordering::Relaxed) + failed {
+            0 => 0.0,
+            rounds => failed as f64 / rounds as f64,
+        }
+    }
+}
+
+// A status transition of a task, pushed to the clients following it. Completed and failed
+// tasks carry the consensus result.
+#[derive(Serialize, Debug, Clone)]
+pub struct TaskEvent {
+    pub task_id: TaskId,
+    pub status: TaskStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result_hash: Option<B256>,
+}
+
+impl TaskEvent {
+    // An event without a consensus result
+    pub fn status(task_id: TaskId, status: TaskStatus) -> Self {
+        Self {
+            task_id,
+            status,
+            result: None,
+            result_hash: None,
+        }
+    }
+
+    // Whether no transition follows this one
+    fn is_final(&self) -> bool {
+        matches!(self.status, TaskStatus::COMPLETED | TaskStatus::FAILED)
+    }
+}
+
+// State of a single AVS deployment served by the aggregator
+pub struct AvsState {
+    pub operator_list: Arc<DashMap<Address, ()>>,
+    pub tasks: Arc<DashMap<TaskId, TaskStatus>>,
+    pub operator_responses: Arc<DashMap<TaskId, DashMap<Address, OperatorResponse>>>,
+    pub operator_failures: Arc<DashMap<TaskId, DashMap<Address, String>>>,
+    pub task_operator_sets: Arc<DashMap<TaskId, OperatorSet>>,
+    pub aggregated_tasks: Arc<DashSet<TaskId>>,
+    pub finalized_tasks: Arc<Mutex<VecDeque<TaskId>>>,
+    pub task_store: Arc<TaskStore>,
+    pub recovery_cache: Arc<RecoveryCache>,
+    pub consensus_stats: Arc<ConsensusStats>,
+    pub task_events: broadcast::Sender<TaskEvent>,
+    pub sender: tokio::sync::mpsc::Sender<OperatorMessage>,
+    // Contracts of the deployment, read before acting on a task's on-chain state
+    pub(crate) avs_chain: Arc<dyn AvsChain>,
 }
 
 // Application state shared across request handlers
 #[derive(Clone)]
 pub struct AppState {
-    pub operator_list: Arc<DashMap<Address, ()>>,
-    pub tasks: Arc<DashMap<FixedBytes<32>, TaskStatus>>,
-    pub sender: tokio::sync::mpsc::Sender<OperatorResponse>,
+    // Served deployments by id
+    pub avs: HashMap<String, Arc<AvsState>>,
+    // Deployment served by the routes without an AVS id in their path
+    pub default_avs: String,
+    pub config: Arc<AggregatorConfig>,
 }
 
-// Main function to run the server
-pub async fn run_server(app_state: AppState) -> Result<(), ServerError> {
-    let app = Router::new()
+impl AppState {
+    // Look up a served deployment
+    pub(crate) fn avs(&self, avs_id: &str) -> Result<&AvsState, ServerError> {
+        self.avs
+            .get(avs_id)
+            .map(Arc::as_ref)
+            .ok_or_else(|| ServerError::UnknownAvs(avs_id.to_string()))
+    }
+}
+
+// Whether `operator` already submitted the result of `response` for its task
+pub(crate) fn is_duplicate(
+    operator_responses: &DashMap<TaskId, DashMap<Address, OperatorResponse>>,
+    operator: &Address,
+    response: &OperatorResponse,
+) -> bool {
+    operator_responses
+        .get(&response.task_id)
+        .is_some_and(|responses| {
+            responses
+                .get(operator)
+                .is_some_and(|recorded| recorded.result == response.result)
+        })
+}
+
+// Whether `operator` already responded to the task of `response` with another result, after
+// the task was handed over to aggregation. The aggregated result can no longer change, a
+// second aggregation would send a `respondToTask` that reverts.
+pub(crate) fn changes_aggregated_result(
+    operator_responses: &DashMap<TaskId, DashMap<Address, OperatorResponse>>,
+    aggregated_tasks: &DashSet<TaskId>,
+    operator: &Address,
+    response: &OperatorResponse,
+) -> bool {
+    aggregated_tasks.contains(&response.task_id)
+        && operator_responses
+            .get(&response.task_id)
+            .is_some_and(|responses| responses.contains_key(operator))
+}
+
+impl AvsState {
+    // Look up a task status, falling back to the store for tasks evicted from memory
+    fn task_status(&self, task_id: &TaskId) -> Option<TaskStatus> {
+        self.tasks
+            .get(task_id)
+            .as_deref()
+            .cloned()
+            .or_else(|| self.task_store.task_status(task_id))
+    }
+}
+
+// Main function to run the server, along with the gRPC one when configured, until `shutdown`
+// resolves
+pub async fn run_server(
+    app_state: AppState,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<(), ServerError> {
+    let app_state = Arc::new(app_state);
+    let shutdown = shutdown.shared();
+
+    #[cfg(feature = "grpc")]
+    let grpc_server = crate::grpc::run_grpc_server(app_state.clone(), shutdown.clone());
+    #[cfg(not(feature = "grpc"))]
+    let grpc_server = async {
+        if app_state.config.grpc_port.is_some() {
+            tracing::warn!(
+                "GRPC_PORT is set but the aggregator was built without the `grpc` feature"
+            );
+        }
+        Ok::<_, ServerError>(())
+    };
+
+    let listener = std::net::TcpListener::bind("0.0.0.0:8080")
+        .map_err(|e| ServerError::InternalError(format!("Failed to bind to address: {}", e)))?;
+    tokio::try_join!(
+        run_http_server(app_state.clone(), listener, shutdown),
+        grpc_server
+    )?;
+    Ok(())
+}
+
+// Routes of the HTTP server, routes without an AVS id serving the default deployment
+pub(crate) fn router(app_state: Arc<AppState>) -> Router {
+    // Reject oversized bodies before axum buffers them
+    let body_limit = RequestBodyLimitLayer::new(app_state.config.max_request_body_size);
+
+    let mut app = Router::new()
+        .route("/task_status", post(handle_task_statuses))
         .route("/task_status/:task_id", get(handle_task_status))
+        .route("/task_result/:task_id", get(handle_task_result))
         .route("/submit_task", post(handle_submit_task))
-        .with_state(Arc::new(app_state));
+        .route("/submit_failure", post(handle_submit_failure))
+        .route("/avs/:avs_id/task_status", post(handle_avs_task_statuses))
+        .route(
+            "/avs/:avs_id/task_status/:task_id",
+            get(handle_avs_task_status),
+        )
+        .route(
+            "/avs/:avs_id/task_result/:task_id",
+            get(handle_avs_task_result),
+        )
+        .route("/avs/:avs_id/submit_task", post(handle_avs_submit_task))
+        .route(
+            "/avs/:avs_id/submit_failure",
+            post(handle_avs_submit_failure),
+        )
+        .route("/avs/:avs_id/ready", get(handle_avs_ready))
+        .route("/tasks/:task_id/events", get(handle_task_events))
+        .route(
+            "/avs/:avs_id/tasks/:task_id/events",
+            get(handle_avs_task_events),
+        )
+        .route(
+            "/tasks/:task_id/participation",
+            get(handle_task_participation),
+        )
+        .route(
+            "/avs/:avs_id/tasks/:task_id/participation",
+            get(handle_avs_task_participation),
+        )
+        .route("/avs/:avs_id/stats", get(handle_avs_stats))
+        .route("/stats", get(handle_stats))
+        .route("/version", get(handle_version))
+        .route("/ready", get(handle_ready));
 
-    let listener = TcpListener::bind("0.0.0.0:8080")
-        .await
+    // Debugging endpoints are only served when a token protects them
+    if app_state.config.debug_token.is_some() {
+        app = app
+            .route("/tasks/:task_id/responses", get(handle_task_responses))
+            .route(
+                "/avs/:avs_id/tasks/:task_id/responses",
+                get(handle_avs_task_responses),
+            );
+    }
+
+    // Admin endpoints are only served when a token protects them
+    if app_state.config.admin_token.is_some() {
+        app = app
+            .route("/admin/requeue/:task_id", post(handle_requeue_task))
+            .route(
+                "/avs/:avs_id/admin/requeue/:task_id",
+                post(handle_avs_requeue_task),
+            );
+    }
+
+    #[cfg(feature = "simulate")]
+    {
+        app = app.route("/simulate_consensus", post(handle_simulate_consensus));
+    }
+
+    app.layer(body_limit).with_state(app_state)
+}
+
+// Serve the HTTP interface on `listener` until `shutdown` resolves, over TLS when a certificate
+// is configured
+pub(crate) async fn run_http_server(
+    app_state: Arc<AppState>,
+    listener: std::net::TcpListener,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<(), ServerError> {
+    let config = app_state.config.clone();
+    let app = router(app_state);
+    let address = listener
+        .local_addr()
+        .and_then(|address| listener.set_nonblocking(true).map(|()| address))
         .map_err(|e| ServerError::InternalError(format!("Failed to bind to address: {}", e)))?;
 
-    info!("Server listening on 0.0.0.0:8080");
-    axum::serve(listener, app.into_make_service())
-        .await
-        .map_err(|e| ServerError::InternalError(format!("Server error: {}", e)))
+    match (&config.tls_cert_path, &config.tls_key_path) {
+        (None, None) => {
+            let listener = TcpListener::from_std(listener).map_err(|e| {
+                ServerError::InternalError(format!("Failed to bind to address: {}", e))
+            })?;
+
+            info!("Server listening on {}", address);
+            axum::serve(listener, app.into_make_service())
+                .with_graceful_shutdown(shutdown)
+                .await
+                .map_err(|e| ServerError::InternalError(format!("Server error: {}", e)))
+        }
+        (Some(cert_path), Some(key_path)) => {
+            // Dependencies enable both the ring and aws-lc-rs providers, so rustls can't pick
+            // one by itself. This fails when a provider is already installed, which is fine.
+            let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+            let tls_config = RustlsConfig::from_pem_file(cert_path, key_path)
+                .await
+                .map_err(|e| {
+                    ServerError::InternalError(format!("Failed to load TLS certificate: {}", e))
+                })?;
+
+            let handle = Handle::new();
+            let shutdown_handle = handle.clone();
+            tokio::spawn(async move {
+                shutdown.await;
+                shutdown_handle.graceful_shutdown(None);
+            });
+
+            info!("Server listening on {} (TLS)", address);
+            axum_server::from_tcp_rustls(listener, tls_config)
+                .handle(handle)
+                .serve(app.into_make_service())
+                .await
+                .map_err(|e| ServerError::InternalError(format!("Server error: {}", e)))
+        }
+        _ => Err(ServerError::InternalError(
+            "TLS_CERT_PATH and TLS_KEY_PATH must be set together".to_string(),
+        )),
+    }
+}
+
+// Handler for GET /version endpoint
+async fn handle_version() -> Json<serde_json::Value> {
+    Json(json!({
+        "version": VERSION,
+        "git_sha": GIT_SHA,
+    }))
+}
+
+// Handler for GET /ready endpoint, the aggregator can only reach consensus once operators are
+// registered in every served deployment
+async fn handle_ready(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<serde_json::Value>, ServerError> {
+    let operators = state
+        .avs
+        .iter()
+        .map(|(avs_id, avs)| (avs_id.clone(), avs.operator_list.len()))
+        .collect::<HashMap<_, _>>();
+    if let Some((avs_id, _)) = operators.iter().find(|(_, &count)| count == 0) {
+        return Err(ServerError::NotReady(format!(
+            "No operators are registered in the AVS {:?}",
+            avs_id
+        )));
+    }
+
+    Ok(Json(json!({
+        "ready": true,
+        "operators": operators,
+    })))
+}
+
+// Handler for GET /avs/:avs_id/ready endpoint
+async fn handle_avs_ready(
+    State(state): State<Arc<AppState>>,
+    Path(avs_id): Path<String>,
+) -> Result<Json<serde_json::Value>, ServerError> {
+    let operators = state.avs(&avs_id)?.operator_list.len();
+    if operators == 0 {
+        return Err(ServerError::NotReady(
+            "No operators are registered in the AVS".to_string(),
+        ));
+    }
+
+    Ok(Json(json!({
+        "ready": true,
+        "operators": operators,
+    })))
+}
+
+// Handler for GET /stats endpoint
+async fn handle_stats(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<serde_json::Value>, ServerError> {
+    Ok(stats(state.avs(&state.default_avs)?))
+}
+
+// Handler for GET /avs/:avs_id/stats endpoint
+async fn handle_avs_stats(
+    State(state): State<Arc<AppState>>,
+    Path(avs_id): Path<String>,
+) -> Result<Json<serde_json::Value>, ServerError> {
+    Ok(stats(state.avs(&avs_id)?))
+}
+
+// Aggregate numbers of a deployment for dashboards and health checks. Task counts cover the
+// tasks held in memory, consensus numbers every round since the aggregator started.
+fn stats(avs: &AvsState) -> Json<serde_json::Value> {
+    let mut tasks_by_status = HashMap::<String, usize>::new();
+    for task in avs.tasks.iter() {
+        *tasks_by_status
+            .entry(format!("{:?}", task.value()))
+            .or_default() += 1;
+    }
+
+    let consensus = &avs.consensus_stats;
+    Json(json!({
+        "tasks": avs.tasks.len(),
+        "tasks_by_status": tasks_by_status,
+        "operators": avs.operator_list.len(),
+        "completed_tasks": consensus.completed.load(Ordering::Relaxed),
+        "failed_tasks": consensus.failed.load(Ordering::Relaxed),
+        "average_responses_per_completed_task": consensus.average_responses(),
+        "consensus_failure_rate": consensus.failure_rate(),
+    }))
+}
+
+// Parse a task id path parameter, so that malformed ids get the same error envelope as every
+// other invalid request rather than axum's plain text rejection
+fn parse_task_id(task_id: &str) -> Result<TaskId, ServerError> {
+    task_id.parse().map_err(|_| {
+        ServerError::InvalidRequest(format!(
+            "Invalid task id {:?}: expected 32-byte hex",
+            task_id
+        ))
+    })
 }
 
 // Handler for GET /task_status/:task_id endpoint
 async fn handle_task_status(
     State(state): State<Arc<AppState>>,
-    Path(task_id): Path<FixedBytes<32>>,
+    Path(task_id): Path<String>,
 ) -> Result<Json<TaskStatus>, ServerError> {
-    let task_status = state
-        .tasks
-        .get(&task_id)
-        .as_deref()
-        .cloned()
-        .unwrap_or(TaskStatus::EMPTY);
+    task_status(state.avs(&state.default_avs)?, parse_task_id(&task_id)?)
+}
+
+// Handler for GET /avs/:avs_id/task_status/:task_id endpoint
+async fn handle_avs_task_status(
+    State(state): State<Arc<AppState>>,
+    Path((avs_id, task_id)): Path<(String, String)>,
+) -> Result<Json<TaskStatus>, ServerError> {
+    task_status(state.avs(&avs_id)?, parse_task_id(&task_id)?)
+}
+
+// Body of a batch task status request
+#[derive(Deserialize, Debug)]
+pub struct TaskStatusBatch {
+    pub task_ids: Vec<TaskId>,
+}
+
+// Handler for POST /task_status endpoint
+async fn handle_task_statuses(
+    State(state): State<Arc<AppState>>,
+    JsonBody(batch): JsonBody<TaskStatusBatch>,
+) -> Result<Json<BTreeMap<TaskId, TaskStatus>>, ServerError> {
+    task_statuses(state.avs(&state.default_avs)?, &state.config, batch)
+}
+
+// Handler for POST /avs/:avs_id/task_status endpoint
+async fn handle_avs_task_statuses(
+    State(state): State<Arc<AppState>>,
+    Path(avs_id): Path<String>,
+    JsonBody(batch): JsonBody<TaskStatusBatch>,
+) -> Result<Json<BTreeMap<TaskId, TaskStatus>>, ServerError> {
+    task_statuses(state.avs(&avs_id)?, &state.config, batch)
+}
+
+// Look up the status of every task of `batch` at once, unknown tasks being `EMPTY` as with
+// `task_status`
+pub(crate) fn task_statuses(
+    avs: &AvsState,
+    config: &AggregatorConfig,
+    batch: TaskStatusBatch,
+) -> Result<Json<BTreeMap<TaskId, TaskStatus>>, ServerError> {
+    if batch.task_ids.len() > config.max_task_status_batch_size {
+        return Err(ServerError::InvalidRequest(format!(
+            "Too many task ids: {} requested, at most {} per request",
+            batch.task_ids.len(),
+            config.max_task_status_batch_size
+        )));
+    }
+
+    let task_statuses = batch
+        .task_ids
+        .into_iter()
+        .map(|task_id| {
+            let task_status = avs.task_status(&task_id).unwrap_or(TaskStatus::EMPTY);
+            (task_id, task_status)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    info!("Served {} task statuses", task_statuses.len());
+    Ok(Json(task_statuses))
+}
+
+// Handler for GET /task_result/:task_id endpoint
+async fn handle_task_result(
+    State(state): State<Arc<AppState>>,
+    Path(task_id): Path<String>,
+) -> Result<Json<serde_json::Value>, ServerError> {
+    task_result(state.avs(&state.default_avs)?, parse_task_id(&task_id)?)
+}
+
+// Handler for GET /avs/:avs_id/task_result/:task_id endpoint
+async fn handle_avs_task_result(
+    State(state): State<Arc<AppState>>,
+    Path((avs_id, task_id)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, ServerError> {
+    task_result(state.avs(&avs_id)?, parse_task_id(&task_id)?)
+}
+
+// Serve the full consensus result of a task submitted on-chain as its keccak256 hash, clients
+// verify it by hashing `result` and comparing it with the on-chain value
+pub(crate) fn task_result(
+    avs: &AvsState,
+    task_id: TaskId,
+) -> Result<Json<serde_json::Value>, ServerError> {
+    let Some(offchain_result) = avs.task_store.result(&task_id) else {
+        return Err(match avs.task_status(&task_id) {
+            None | Some(TaskStatus::EMPTY) => ServerError::TaskDoesNotExist,
+            Some(TaskStatus::PENDING) => {
+                ServerError::NotReady("Task has not completed yet".to_string())
+            }
+            Some(TaskStatus::FAILED) => ServerError::TaskFailed,
+            Some(_) => ServerError::NoOffchainResult,
+        });
+    };
+
+    info!("Served task result for {:?}", task_id);
+    Ok(Json(json!({
+        "task_id": task_id,
+        "result_type": offchain_result.result_type,
+        "result": offchain_result.result,
+        "result_hash": keccak256(&offchain_result.result),
+    })))
+}
+
+// Handler for GET /tasks/:task_id/events endpoint
+async fn handle_task_events(
+    State(state): State<Arc<AppState>>,
+    Path(task_id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, ServerError> {
+    task_events(state.avs(&state.default_avs)?, parse_task_id(&task_id)?)
+}
+
+// Handler for GET /avs/:avs_id/tasks/:task_id/events endpoint
+async fn handle_avs_task_events(
+    State(state): State<Arc<AppState>>,
+    Path((avs_id, task_id)): Path<(String, String)>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, ServerError> {
+    task_events(state.avs(&avs_id)?, parse_task_id(&task_id)?)
+}
+
+// Stream the status transitions of a task as server-sent events, starting with its current
+// status and ending once it is completed or failed. The stream, and its subscription, are
+// dropped when the client disconnects.
+fn task_events(
+    avs: &AvsState,
+    task_id: TaskId,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, ServerError> {
+    // Subscribe before reading the status, so no transition is missed in between
+    let receiver = avs.task_events.subscribe();
+    let current = match avs.task_status(&task_id) {
+        None | Some(TaskStatus::EMPTY) => return Err(ServerError::TaskDoesNotExist),
+        Some(status) => TaskEvent::status(task_id, status),
+    };
+    let finished = current.is_final();
+    let tasks = avs.tasks.clone();
+
+    let transitions = futures::stream::unfold(
+        (receiver, finished),
+        move |(mut receiver, finished)| {
+            let tasks = tasks.clone();
+            async move {
+                if finished {
+                    return None;
+                }
+                loop {
+                    match receiver.recv().await {
+                        Ok(event) if event.task_id == task_id => {
+                            let finished = event.is_final();
+                            return Some((event, (receiver, finished)));
+                        }
+                        Ok(_) => {}
+                        // The buffer overflowed and the event of this task may be lost, fall back
+                        // to its current status so the client isn't left waiting
+                        Err(RecvError::Lagged(skipped)) => {
+                            warn!(
+                                "Task events subscriber for \x1b[1;33m{:?}\x1b[0m skipped {} events",
+                                task_id, skipped
+                            );
+                            let status = tasks.get(&task_id).as_deref().cloned();
+                            if let Some(status) = status.filter(|status| {
+                                matches!(status, TaskStatus::COMPLETED | TaskStatus::FAILED)
+                            }) {
+                                return Some((
+                                    TaskEvent::status(task_id, status),
+                                    (receiver, true),
+                                ));
+                            }
+                        }
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            }
+        },
+    );
+
+    let events = futures::stream::once(async move { current })
+        .chain(transitions)
+        .map(|event| Event::default().event("status").json_data(event));
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+pub(crate) fn task_status(
+    avs: &AvsState,
+    task_id: TaskId,
+) -> Result<Json<TaskStatus>, ServerError> {
+    let task_status = avs.task_status(&task_id).unwrap_or(TaskStatus::EMPTY);
 
     info!("Served task status for {:?}", task_id);
     Ok(Json(task_status))
 }
 
+// Check the bearer token of a request to an endpoint protected by `expected`
+fn authorize(headers: &HeaderMap, expected: &Option<String>) -> Result<(), ServerError> {
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match (token, expected) {
+        (Some(token), Some(expected)) if tokens_match(token, expected) => Ok(()),
+        _ => Err(ServerError::Unauthorized),
+    }
+}
+
+// Compare a token with the expected one in a time independent of where they differ, so the
+// expected token can't be guessed a byte at a time from response times. Hashing both first
+// also hides its length.
+fn tokens_match(token: &str, expected: &str) -> bool {
+    let (token, expected) = (keccak256(token), keccak256(expected));
+    token
+        .iter()
+        .zip(expected.iter())
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+// Handler for POST /admin/requeue/:task_id endpoint
+async fn handle_requeue_task(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(task_id): Path<String>,
+) -> Result<StatusCode, ServerError> {
+    authorize(&headers, &state.config.admin_token)?;
+    requeue_task(state.avs(&state.default_avs)?, parse_task_id(&task_id)?).await
+}
+
+// Handler for POST /avs/:avs_id/admin/requeue/:task_id endpoint
+async fn handle_avs_requeue_task(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((avs_id, task_id)): Path<(String, String)>,
+) -> Result<StatusCode, ServerError> {
+    authorize(&headers, &state.config.admin_token)?;
+    requeue_task(state.avs(&avs_id)?, parse_task_id(&task_id)?).await
+}
+
+// Reset a failed task to pending, dropping the responses it was aggregated from so that
+// operators can respond to it again. Only tasks still pending on-chain can be requeued: once the
+// registry recorded a result, a new one would only revert. Operators pick tasks up from on-chain
+// events, so they are not notified and have to resubmit their results themselves. The task gets
+// a new timeout, so it is aggregated like a newly detected one.
+pub(crate) async fn requeue_task(
+    avs: &AvsState,
+    task_id: TaskId,
+) -> Result<StatusCode, ServerError> {
+    match avs.task_status(&task_id) {
+        Some(TaskStatus::FAILED) => {}
+        None | Some(TaskStatus::EMPTY) => return Err(ServerError::TaskDoesNotExist),
+        Some(status) => return Err(ServerError::TaskNotFailed(status)),
+    }
+    match avs
+        .avs_chain
+        .task_status(task_id, BlockNumberOrTag::Latest.into())
+        .await?
+    {
+        TaskStatus::PENDING => {}
+        status => return Err(ServerError::TaskFinalizedOnChain(status)),
+    }
+
+    avs.task_store
+        .insert_task_status(task_id, TaskStatus::PENDING);
+    avs.task_store.remove_operator_responses(&task_id);
+
+    // Pending tasks must not be evicted from memory
+    avs.finalized_tasks
+        .lock()
+        .unwrap()
+        .retain(|finalized| *finalized != task_id);
+    avs.operator_responses.remove(&task_id);
+    avs.operator_failures.remove(&task_id);
+    avs.task_operator_sets.remove(&task_id);
+    avs.tasks.insert(task_id, TaskStatus::PENDING);
+    avs.sender
+        .send(OperatorMessage::Requeued(task_id))
+        .await
+        .map_err(|e| ServerError::InternalError(format!("Failed to requeue task: {}", e)))?;
+
+    info!("Requeued task \x1b[1;33m{:?}\x1b[0m", task_id);
+    Ok(StatusCode::OK)
+}
+
+// Handler for GET /tasks/:task_id/participation endpoint
+async fn handle_task_participation(
+    State(state): State<Arc<AppState>>,
+    Path(task_id): Path<String>,
+) -> Result<Json<serde_json::Value>, ServerError> {
+    task_participation(state.avs(&state.default_avs)?, parse_task_id(&task_id)?)
+}
+
+// Handler for GET /avs/:avs_id/tasks/:task_id/participation endpoint
+async fn handle_avs_task_participation(
+    State(state): State<Arc<AppState>>,
+    Path((avs_id, task_id)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, ServerError> {
+    task_participation(state.avs(&avs_id)?, parse_task_id(&task_id)?)
+}
+
+// The operators that responded to a pending task, reported its failure, or have yet to do
+// either. Only addresses are served, the results stay behind the debug endpoint.
+fn task_participation(
+    avs: &AvsState,
+    task_id: TaskId,
+) -> Result<Json<serde_json::Value>, ServerError> {
+    if avs.tasks.get(&task_id).as_deref() != Some(&TaskStatus::PENDING) {
+        return Err(ServerError::TaskNotPending);
+    }
+
+    let responded = avs
+        .operator_responses
+        .get(&task_id)
+        .map(|responses| responses.iter().map(|entry| *entry.key()).collect())
+        .unwrap_or_default();
+    let failed = avs
+        .operator_failures
+        .get(&task_id)
+        .map(|failures| failures.iter().map(|entry| *entry.key()).collect())
+        .unwrap_or_default();
+    let operators = avs.operator_list.iter().map(|entry| *entry.key()).collect();
+    let (responded, failed, outstanding) = participation(operators, responded, failed);
+
+    Ok(Json(json!({
+        "task_id": task_id,
+        "responded": responded,
+        "failed": failed,
+        "outstanding": outstanding,
+    })))
+}
+
+// Split the operators of a task into those that responded, those that reported a failure and
+// those still outstanding, each sorted by address. Operators that answered but have since left
+// the operator list are still reported.
+pub(crate) fn participation(
+    operators: Vec<Address>,
+    mut responded: Vec<Address>,
+    mut failed: Vec<Address>,
+) -> (Vec<Address>, Vec<Address>, Vec<Address>) {
+    responded.sort_unstable();
+    failed.sort_unstable();
+    let mut outstanding: Vec<Address> = operators
+        .into_iter()
+        .filter(|operator| {
+            responded.binary_search(operator).is_err() && failed.binary_search(operator).is_err()
+        })
+        .collect();
+    outstanding.sort_unstable();
+    (responded, failed, outstanding)
+}
+
+// Handler for GET /tasks/:task_id/responses endpoint
+async fn handle_task_responses(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(task_id): Path<String>,
+) -> Result<Json<HashMap<Address, String>>, ServerError> {
+    authorize(&headers, &state.config.debug_token)?;
+    task_responses(state.avs(&state.default_avs)?, parse_task_id(&task_id)?)
+}
+
+// Handler for GET /avs/:avs_id/tasks/:task_id/responses endpoint
+async fn handle_avs_task_responses(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((avs_id, task_id)): Path<(String, String)>,
+) -> Result<Json<HashMap<Address, String>>, ServerError> {
+    authorize(&headers, &state.config.debug_token)?;
+    task_responses(state.avs(&avs_id)?, parse_task_id(&task_id)?)
+}
+
+// The result submitted by each operator for a task, kept until the task result is submitted
+// on-chain
+fn task_responses(
+    avs: &AvsState,
+    task_id: TaskId,
+) -> Result<Json<HashMap<Address, String>>, ServerError> {
+    let responses = avs
+        .operator_responses
+        .get(&task_id)
+        .ok_or(ServerError::NoResponses)?
+        .iter()
+        .map(|entry| (*entry.key(), entry.value().result.clone()))
+        .collect();
+
+    info!("Served operator responses for {:?}", task_id);
+    Ok(Json(responses))
+}
+
+// Synthetic responses to run through the consensus logic
+#[cfg(feature = "simulate")]
+#[derive(Deserialize)]
+struct SimulationRequest {
+    task_id: TaskId,
+    // Client app whose result type applies, `uint256` when unset
+    app_id: Option<contract_bindings::AppId>,
+    responses: Vec<(Address, String)>,
+}
+
+// Handler for POST /simulate_consensus endpoint, only served with the `simulate` feature
+#[cfg(feature = "simulate")]
+async fn handle_simulate_consensus(
+    State(state): State<Arc<AppState>>,
+    JsonBody(simulation): JsonBody<SimulationRequest>,
+) -> Result<Json<serde_json::Value>, ServerError> {
+    let result_type = simulation
+        .app_id
+        .map(|app_id| state.config.result_types.get(&app_id))
+        .unwrap_or_default();
+    // Every simulated responder is part of the operator set
+    let operator_set = OperatorSet::new(
+        simulation
+            .responses
+            .iter()
+            .map(|(operator, _)| *operator)
+            .collect(),
+    );
+    let (task_status, result, result_hash) = crate::compute_consensus(
+        simulation.task_id,
+        result_type,
+        state.config.consensus.strategy().as_ref(),
+        &operator_set,
+        &simulation.responses,
+    );
+
+    Ok(Json(json!({
+        "task_id": simulation.task_id,
+        "status": task_status,
+        "result": result,
+        "result_hash": result_hash,
+    })))
+}
+
+// Signed difference (in seconds) between an operator's `timestamp` and the aggregator's `now`,
+// when it exceeds `max_skew_secs`. A `max_skew_secs` of 0 disables the check.
+pub(crate) fn clock_skew(timestamp: u64, now: u64, max_skew_secs: u64) -> Option<i128> {
+    (max_skew_secs > 0 && timestamp.abs_diff(now) > max_skew_secs)
+        .then(|| i128::from(timestamp) - i128::from(now))
+}
+
 // Handler for POST /submit_task endpoint
 async fn handle_submit_task(
     State(state): State<Arc<AppState>>,
-    Json(operator_response): Json<OperatorResponse>,
+    JsonBody(operator_response): JsonBody<OperatorResponse>,
 ) -> Result<StatusCode, ServerError> {
-    // Verify the signature and check if it came from a valid operator
-    let recover_address = operator_response
-        .signature
-        .recover_address_from_msg(&operator_response.result)
-        .map_err(|_| ServerError::InvalidSignature)?;
+    submit_task(
+        state.avs(&state.default_avs)?,
+        &state.config,
+        operator_response,
+    )
+    .await
+}
+
+// Handler for POST /avs/:avs_id/submit_task endpoint
+async fn handle_avs_submit_task(
+    State(state): State<Arc<AppState>>,
+    Path(avs_id): Path<String>,
+    JsonBody(operator_response): JsonBody<OperatorResponse>,
+) -> Result<StatusCode, ServerError> {
+    submit_task(state.avs(&avs_id)?, &state.config, operator_response).await
+}
+
+pub(crate) async fn submit_task(
+    avs: &AvsState,
+    config: &AggregatorConfig,
+    operator_response: OperatorResponse,
+) -> Result<StatusCode, ServerError> {
+    if operator_response.result.len() > config.max_result_size {
+        return Err(ServerError::PayloadTooLarge(format!(
+            "Result exceeds the maximum size of {} bytes",
+            config.max_result_size
+        )));
+    }
 
-    if !state.operator_list.contains_key(&recover_address) {
-        return Err(ServerError::InvalidOperator);
+    // Legacy signatures don't cover the task id, reject them once operators are expected to
+    // have upgraded
+    if operator_response.version < config.min_response_version {
+        return Err(ServerError::UnsupportedVersion(operator_response.version));
     }
 
-    let task_status = state
-        .tasks
-        .get(&operator_response.task_id)
-        .as_deref()
-        .cloned();
+    // Verify the signature and check if it came from a valid operator. Task-bound signatures
+    // recover some other address when the task id was changed after signing.
+    let recover_address = avs
+        .recovery_cache
+        .verify(&operator_response, &*avs.operator_list)?;
 
-    match task_status {
-        Some(status) if status == TaskStatus::EMPTY => {
-            return Err(ServerError::TaskDoesNotExist);
+    if let Some(skew) = operator_response
+        .timestamp
+        .and_then(|timestamp| clock_skew(timestamp, TaskRecord::now(), config.max_clock_skew_secs))
+    {
+        warn!(
+            "Clock of operator \x1b[1;34m{:?}\x1b[0m is {}s {} the aggregator's (more than {}s), check that both hosts sync their clocks",
+            recover_address,
+            skew.unsigned_abs(),
+            if skew > 0 { "ahead of" } else { "behind" },
+            config.max_clock_skew_secs
+        );
+    }
+
+    // A retry of a recorded response is acknowledged again without being queued, so operators
+    // can safely resubmit when they didn't get the first answer
+    if is_duplicate(
+        &avs.operator_responses,
+        &recover_address,
+        &operator_response,
+    ) {
+        info!(
+            "Duplicate response from operator {:?} for task {:?}",
+            recover_address, operator_response.task_id
+        );
+        return Ok(StatusCode::OK);
+    }
+
+    check_task_pending(avs, &operator_response.task_id)?;
+
+    // A changed result is only accepted until the task is aggregated
+    if changes_aggregated_result(
+        &avs.operator_responses,
+        &avs.aggregated_tasks,
+        &recover_address,
+        &operator_response,
+    ) {
+        return Err(ServerError::ResultAlreadyAggregated);
+    }
+
+    avs.sender
+        .send(OperatorMessage::Response(operator_response))
+        .await
+        .map_err(|e| {
+            ServerError::InternalError(format!("Failed to send operator response: {}", e))
+        })?;
+
+    Ok(StatusCode::OK)
+}
+
+// Check that a task still accepts responses
+fn check_task_pending(avs: &AvsState, task_id: &TaskId) -> Result<(), ServerError> {
+    match avs.task_status(task_id) {
+        Some(TaskStatus::EMPTY) | None => Err(ServerError::TaskDoesNotExist),
+        Some(TaskStatus::COMPLETED | TaskStatus::FAILED) => Err(ServerError::TaskAlreadyCompleted),
+        _ => Ok(()),
+    }
+}
+
+// Handler for POST /submit_failure endpoint
+async fn handle_submit_failure(
+    State(state): State<Arc<AppState>>,
+    JsonBody(operator_failure): JsonBody<OperatorFailure>,
+) -> Result<StatusCode, ServerError> {
+    submit_failure(
+        state.avs(&state.default_avs)?,
+        &state.config,
+        operator_failure,
+    )
+    .await
+}
+
+// Handler for POST /avs/:avs_id/submit_failure endpoint
+async fn handle_avs_submit_failure(
+    State(state): State<Arc<AppState>>,
+    Path(avs_id): Path<String>,
+    JsonBody(operator_failure): JsonBody<OperatorFailure>,
+) -> Result<StatusCode, ServerError> {
+    submit_failure(state.avs(&avs_id)?, &state.config, operator_failure).await
+}
+
+// Record that an operator couldn't produce a result for a task, so the task is aggregated
+// without waiting for it. The operator is verified like the sender of a response.
+pub(crate) async fn submit_failure(
+    avs: &AvsState,
+    config: &AggregatorConfig,
+    operator_failure: OperatorFailure,
+) -> Result<StatusCode, ServerError> {
+    if operator_failure.reason.len() > config.max_result_size {
+        return Err(ServerError::PayloadTooLarge(format!(
+            "Reason exceeds the maximum size of {} bytes",
+            config.max_result_size
+        )));
+    }
+
+    let operator = check_known_operator(operator_failure.recover_operator()?, &*avs.operator_list)?;
+
+    // A failure can't retract a result, and a retried report is acknowledged again
+    let task_id = operator_failure.task_id;
+    if avs
+        .operator_responses
+        .get(&task_id)
+        .is_some_and(|responses| responses.contains_key(&operator))
+    {
+        return Err(ServerError::InvalidRequest(
+            "The operator already submitted a result for this task".to_string(),
+        ));
+    }
+    if avs
+        .operator_failures
+        .get(&task_id)
+        .is_some_and(|failures| failures.contains_key(&operator))
+    {
+        info!(
+            "Duplicate failure from operator {:?} for task {:?}",
+            operator, task_id
+        );
+        return Ok(StatusCode::OK);
+    }
+
+    check_task_pending(avs, &task_id)?;
+
+    avs.sender
+        .send(OperatorMessage::Failure(operator, operator_failure))
+        .await
+        .map_err(|e| {
+            ServerError::InternalError(format!("Failed to send operator failure: {}", e))
+        })?;
+
+    Ok(StatusCode::OK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::{local::PrivateKeySigner, SignerSync};
+    use eyre::Result;
+    use std::time::Instant;
+
+    fn signed_response(signer: &PrivateKeySigner, result: &str) -> Result<OperatorResponse> {
+        versioned_response(signer, RESPONSE_VERSION_ECDSA_TASK_BOUND, result)
+    }
+
+    fn versioned_response(
+        signer: &PrivateKeySigner,
+        version: u8,
+        result: &str,
+    ) -> Result<OperatorResponse> {
+        let task_id = TaskId::default();
+        Ok(OperatorResponse {
+            task_id,
+            result: result.to_string(),
+            signature: signer
+                .sign_message_sync(&response_signing_bytes(version, task_id, result).unwrap())?,
+            version,
+            exit_code: None,
+            duration_ms: None,
+            timestamp: None,
+        })
+    }
+
+    #[test]
+    fn test_clock_skew() {
+        let now = 1_700_000_000;
+        assert_eq!(clock_skew(now + 10, now, 30), None);
+        assert_eq!(clock_skew(now - 30, now, 30), None);
+        assert_eq!(clock_skew(now + 31, now, 30), Some(31));
+        assert_eq!(clock_skew(now - 120, now, 30), Some(-120));
+
+        // Timestamps beyond `i64::MAX` don't overflow
+        assert_eq!(
+            clock_skew(u64::MAX, now, 30),
+            Some(i128::from(u64::MAX) - i128::from(now))
+        );
+        assert_eq!(
+            clock_skew(1 << 63, now, 30),
+            Some((1i128 << 63) - i128::from(now))
+        );
+        assert_eq!(
+            clock_skew(now, u64::MAX, 30),
+            Some(i128::from(now) - i128::from(u64::MAX))
+        );
+
+        // A threshold of 0 disables the check
+        assert_eq!(clock_skew(now - 120, now, 0), None);
+    }
+
+    #[test]
+    fn test_signing_bytes_round_trip() -> Result<()> {
+        let signer = PrivateKeySigner::random();
+        let response = signed_response(&signer, r#"{"b":2,"a":1}"#)?;
+
+        // The signature survives the transport encoding, whatever the order of its fields
+        let json = serde_json::to_value(&response)?;
+        let reordered = format!(
+            r#"{{"version":{},"signature":{},"result":{},"task_id":{}}}"#,
+            json["version"], json["signature"], json["result"], json["task_id"]
+        );
+        for encoded in [serde_json::to_string(&response)?, reordered] {
+            let decoded: OperatorResponse = serde_json::from_str(&encoded)?;
+            assert_eq!(decoded.signing_bytes()?, response.signing_bytes()?);
+            assert_eq!(decoded.recover_operator()?, signer.address());
         }
-        Some(status) if status == TaskStatus::COMPLETED || status == TaskStatus::FAILED => {
-            return Err(ServerError::TaskAlreadyCompleted);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_task_id() {
+        let task_id = format!("0x{}", "ab".repeat(32));
+        assert_eq!(
+            parse_task_id(&task_id).ok(),
+            Some(TaskId::from(B256::repeat_byte(0xab)))
+        );
+
+        let too_short = "0xabcd";
+        assert!(matches!(
+            parse_task_id(too_short),
+            Err(ServerError::InvalidRequest(_))
+        ));
+
+        let not_hex = format!("0x{}", "zz".repeat(32));
+        assert!(matches!(
+            parse_task_id(&not_hex),
+            Err(ServerError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_authorize() {
+        let expected = Some("secret".to_string());
+        let headers = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(AUTHORIZATION, value.parse().unwrap());
+            headers
+        };
+
+        assert!(authorize(&headers("Bearer secret"), &expected).is_ok());
+        for value in ["Bearer secreT", "Bearer secret ", "Bearer ", "secret"] {
+            assert!(matches!(
+                authorize(&headers(value), &expected),
+                Err(ServerError::Unauthorized)
+            ));
         }
-        None => {
-            return Err(ServerError::TaskDoesNotExist);
+        assert!(authorize(&HeaderMap::new(), &expected).is_err());
+
+        // Endpoints without a token are never authorized
+        assert!(authorize(&headers("Bearer secret"), &None).is_err());
+    }
+
+    #[test]
+    fn test_participation() {
+        let [a, b, c, d] = [1, 2, 3, 4].map(Address::repeat_byte);
+
+        // Operators that left are still reported, the others are sorted by address
+        let (responded, failed, outstanding) = participation(vec![d, c, b], vec![b, a], vec![c]);
+        assert_eq!(responded, vec![a, b]);
+        assert_eq!(failed, vec![c]);
+        assert_eq!(outstanding, vec![d]);
+
+        let (responded, failed, outstanding) = participation(vec![b, a], vec![], vec![]);
+        assert!(responded.is_empty() && failed.is_empty());
+        assert_eq!(outstanding, vec![a, b]);
+    }
+
+    #[test]
+    fn test_consensus_stats() {
+        let stats = ConsensusStats::default();
+        assert_eq!(stats.average_responses(), 0.0);
+        assert_eq!(stats.failure_rate(), 0.0);
+
+        stats.record(&TaskStatus::COMPLETED, 3);
+        stats.record(&TaskStatus::COMPLETED, 2);
+        stats.record(&TaskStatus::FAILED, 3);
+        stats.record(&TaskStatus::FAILED, 1);
+        assert_eq!(stats.average_responses(), 2.5);
+        assert_eq!(stats.failure_rate(), 0.5);
+    }
+
+    #[test]
+    fn test_verify_operator_response() -> Result<()> {
+        let signer = PrivateKeySigner::random();
+        let known_operators = HashSet::from([signer.address()]);
+        let response = signed_response(&signer, "42")?;
+
+        assert_eq!(
+            verify_operator_response(&response, &known_operators)?,
+            signer.address()
+        );
+        assert_eq!(
+            RecoveryCache::new(1).verify(&response, &known_operators)?,
+            signer.address()
+        );
+
+        // A valid signature from an operator that isn't registered
+        let other = signed_response(&PrivateKeySigner::random(), "42")?;
+        assert!(matches!(
+            verify_operator_response(&other, &known_operators),
+            Err(ServerError::InvalidOperator)
+        ));
+
+        // A result altered after signing recovers some other address
+        let mut tampered = response.clone();
+        tampered.result = "43".to_string();
+        assert!(verify_operator_response(&tampered, &known_operators).is_err());
+
+        // Responses of unknown signature schemes are rejected before any recovery
+        let mut unversioned = response.clone();
+        unversioned.version = RESPONSE_VERSION_ECDSA_TASK_BOUND + 1;
+        assert!(matches!(
+            verify_operator_response(&unversioned, [signer.address()].as_slice()),
+            Err(ServerError::UnsupportedVersion(version)) if version == unversioned.version
+        ));
+
+        // Legacy responses are still accepted
+        let legacy = versioned_response(&signer, RESPONSE_VERSION_ECDSA, "42")?;
+        assert_eq!(
+            verify_operator_response(&legacy, &known_operators)?,
+            signer.address()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_task_id_tampering() -> Result<()> {
+        let signer = PrivateKeySigner::random();
+        let known_operators = HashSet::from([signer.address()]);
+        let cache = RecoveryCache::new(16);
+        let response = signed_response(&signer, "42")?;
+        assert_eq!(cache.verify(&response, &known_operators)?, signer.address());
+
+        // A signed result attached to another task recovers some other address, even once the
+        // original response is cached
+        let mut tampered = response.clone();
+        tampered.task_id = TaskId::from(B256::repeat_byte(0xab));
+        assert!(matches!(
+            verify_operator_response(&tampered, &known_operators),
+            Err(ServerError::InvalidOperator | ServerError::InvalidSignature)
+        ));
+        assert!(matches!(
+            cache.verify(&tampered, &known_operators),
+            Err(ServerError::InvalidOperator | ServerError::InvalidSignature)
+        ));
+
+        // Legacy signatures don't cover the task id, which is why they can be refused
+        let mut legacy = versioned_response(&signer, RESPONSE_VERSION_ECDSA, "42")?;
+        legacy.task_id = tampered.task_id;
+        assert_eq!(
+            verify_operator_response(&legacy, &known_operators)?,
+            signer.address()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_operator_failure() -> Result<()> {
+        let signer = PrivateKeySigner::random();
+        let task_id = TaskId::from(B256::repeat_byte(0xab));
+        let reason = "image pull failed";
+        let failure = OperatorFailure {
+            task_id,
+            reason: reason.to_string(),
+            signature: signer.sign_message_sync(&failure_signing_bytes(task_id, reason))?,
+        };
+        assert_eq!(failure.recover_operator()?, signer.address());
+
+        // Neither the task nor the reason can be changed after signing
+        let mut other_task = failure.clone();
+        other_task.task_id = TaskId::default();
+        assert_ne!(other_task.recover_operator().ok(), Some(signer.address()));
+        let mut other_reason = failure.clone();
+        other_reason.reason = "container failed".to_string();
+        assert_ne!(other_reason.recover_operator().ok(), Some(signer.address()));
+
+        // A response signature doesn't make a valid failure report
+        let response = signed_response(&signer, reason)?;
+        let forged = OperatorFailure {
+            task_id: response.task_id,
+            reason: response.result,
+            signature: response.signature,
+        };
+        assert_ne!(forged.recover_operator().ok(), Some(signer.address()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recovery_cache() -> Result<()> {
+        let signer = PrivateKeySigner::random();
+        let cache = RecoveryCache::new(1);
+
+        let first = signed_response(&signer, "42")?;
+        let second = signed_response(&signer, "43")?;
+        for response in [&first, &first, &second, &first] {
+            assert_eq!(cache.recover(response)?, signer.address());
         }
-        _ => {}
+
+        // A result that doesn't match its signature must not hit the entry of the original
+        let mut tampered = first.clone();
+        tampered.result = "43".to_string();
+        assert!(!matches!(cache.recover(&tampered), Ok(operator) if operator == signer.address()));
+
+        Ok(())
     }
 
-    state.sender.send(operator_response).await.map_err(|e| {
-        ServerError::InternalError(format!("Failed to send operator response: {}", e))
-    })?;
+    #[test]
+    fn test_tx_error_status() {
+        let status = |error: AggregatorError| ServerError::from(error).into_response().status();
 
-    Ok(StatusCode::OK)
+        assert_eq!(
+            status(AggregatorError::InsufficientFunds(
+                "insufficient funds for gas * price + value".to_string()
+            )),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status(AggregatorError::TxError(
+                "insufficient funds for gas * price + value".to_string()
+            )),
+            StatusCode::BAD_GATEWAY
+        );
+        assert_eq!(
+            status(AggregatorError::TxError("nonce too low".to_string())),
+            StatusCode::BAD_GATEWAY
+        );
+        assert_eq!(
+            status(AggregatorError::TxError("execution reverted".to_string())),
+            StatusCode::BAD_GATEWAY
+        );
+        assert_eq!(
+            status(AggregatorError::TaskStoreError("disk full".to_string())),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    // Run with `cargo test --release -- --ignored --nocapture test_recovery_cache_burst`
+    #[test]
+    #[ignore = "measurement, not a check"]
+    fn test_recovery_cache_burst() -> Result<()> {
+        const RESUBMISSIONS: usize = 10_000;
+        let signer = PrivateKeySigner::random();
+        let response = signed_response(&signer, "42")?;
+        // Report through the test harness, which captures the output unless `--nocapture`
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        for capacity in [0, 1024] {
+            let cache = RecoveryCache::new(capacity);
+            let started = Instant::now();
+            for _ in 0..RESUBMISSIONS {
+                cache.recover(&response)?;
+            }
+            info!(
+                "{} recoveries with a cache of {}: {:?}",
+                RESUBMISSIONS,
+                capacity,
+                started.elapsed()
+            );
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,110 @@
+use alloy_primitives::{Address, B256, U256};
+use contract_bindings::{TaskId, TaskStatus, TaskTimings};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{error, info};
+
+/// A finalized task, as written to the task log.
+#[derive(Debug, Serialize)]
+pub struct TaskRecord {
+    /// Deployment the task belongs to.
+    pub avs_id: String,
+    pub task_id: TaskId,
+    pub status: TaskStatus,
+    /// The result submitted on-chain.
+    pub result: U256,
+    /// keccak256 of the canonical consensus result, unset for failed tasks.
+    pub result_hash: Option<B256>,
+    /// The raw result submitted by each operator, ordered by operator address.
+    pub responses: BTreeMap<Address, String>,
+    /// Duration (in milliseconds) of each recorded stage of the task, and of the whole task.
+    pub timings_ms: BTreeMap<&'static str, u128>,
+    /// Unix timestamp (in seconds) at which the task was finalized.
+    pub finalized_at: u64,
+}
+
+impl TaskRecord {
+    /// Converts the stages recorded in `timings` into `timings_ms` entries.
+    pub fn timings_ms(timings: &TaskTimings) -> BTreeMap<&'static str, u128> {
+        timings
+            .stages()
+            .iter()
+            .map(|(stage, duration)| (*stage, duration.as_millis()))
+            .chain(std::iter::once(("total", timings.total().as_millis())))
+            .collect()
+    }
+
+    /// The current Unix timestamp, for `finalized_at`.
+    pub fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default()
+    }
+}
+
+/// `TaskLog` appends every finalized task to a newline-delimited JSON file, as an audit trail
+/// for offline analysis.
+///
+/// It is independent of the `TaskStore`: the file is never read back nor rewritten, and failing
+/// to open or write it is logged without affecting the aggregation.
+#[derive(Debug, Default)]
+pub struct TaskLog {
+    /// The open log file, `None` when the log is disabled or could not be opened.
+    file: Option<Mutex<File>>,
+}
+
+impl TaskLog {
+    /// Opens the log at `path` for appending, creating it if needed. The log is disabled when
+    /// `path` is `None` or the file cannot be opened.
+    pub fn open(path: Option<&Path>) -> Self {
+        let Some(path) = path else {
+            return Self::default();
+        };
+
+        match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => {
+                info!("Logging finalized tasks to {}", path.display());
+                Self {
+                    file: Some(Mutex::new(file)),
+                }
+            }
+            Err(e) => {
+                error!(
+                    "Failed to open task log {}, finalized tasks won't be logged: {:?}",
+                    path.display(),
+                    e
+                );
+                Self::default()
+            }
+        }
+    }
+
+    /// Appends `record` as a single JSON line.
+    pub fn append(&self, record: &TaskRecord) {
+        let Some(file) = &self.file else {
+            return;
+        };
+
+        let mut line = match serde_json::to_vec(record) {
+            Ok(line) => line,
+            Err(e) => {
+                error!("Failed to serialize task record: {:?}", e);
+                return;
+            }
+        };
+        line.push(b'\n');
+
+        // A single write per line, so lines from concurrent deployments don't interleave
+        if let Err(e) = file.lock().unwrap().write_all(&line) {
+            error!("Failed to write to task log: {:?}", e);
+        }
+    }
+}
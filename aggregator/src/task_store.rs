@@ -0,0 +1,683 @@
+use crate::{server::OperatorResponse, AggregatorError};
+use alloy_primitives::{Address, Bytes, B256};
+use contract_bindings::{ResultType, TaskId, TaskStatus};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread::{self, JoinHandle},
+};
+use tracing::{error, warn};
+
+type StoredResponses = HashMap<TaskId, HashMap<Address, OperatorResponse>>;
+
+// On-disk layout of the store
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoreState {
+    operator_responses: StoredResponses,
+    #[serde(default)]
+    tasks: HashMap<TaskId, TaskStatus>,
+    #[serde(default)]
+    result_hashes: HashMap<TaskId, B256>,
+    #[serde(default)]
+    results: HashMap<TaskId, OffchainResult>,
+}
+
+/// `OffchainResult` is the full consensus result of a task whose result type is submitted
+/// on-chain as the keccak256 hash of its canonical bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OffchainResult {
+    /// The result type declared for the task's client app.
+    pub result_type: ResultType,
+    /// The canonical bytes of the result, whose keccak256 hash is the on-chain result.
+    pub result: Bytes,
+}
+
+// A change to the operator responses, as a line of the journal. Responses change far more often
+// than the rest of the state, so they are appended to the journal rather than rewriting the
+// snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+enum ResponseChange {
+    Insert {
+        operator: Address,
+        response: OperatorResponse,
+    },
+    Remove {
+        task_id: TaskId,
+    },
+}
+
+impl ResponseChange {
+    fn apply(self, responses: &mut StoredResponses) {
+        match self {
+            Self::Insert { operator, response } => {
+                responses
+                    .entry(response.task_id)
+                    .or_default()
+                    .insert(operator, response);
+            }
+            Self::Remove { task_id } => {
+                responses.remove(&task_id);
+            }
+        }
+    }
+}
+
+/// A finalized task moved out of the snapshot once evicted from memory, as a line of the archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ArchivedTask {
+    task_id: TaskId,
+    status: TaskStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result_hash: Option<B256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<OffchainResult>,
+}
+
+// Files written by the writer thread, along with the length of the append-only ones
+#[derive(Debug)]
+struct StoreFiles {
+    snapshot: PathBuf,
+    journal: PathBuf,
+    journal_len: u64,
+    archive: PathBuf,
+    archive_len: u64,
+}
+
+// State shared between the store and its writer thread
+#[derive(Debug, Default)]
+struct Inner {
+    // Live state, rewritten as a whole to the snapshot file
+    state: StoreState,
+    // Whether `state` changed, other than its operator responses, since it was last handed to
+    // the writer
+    dirty: bool,
+    // Changes to the operator responses not appended to the journal yet
+    journaling: Vec<ResponseChange>,
+    // Archived tasks not appended to the archive yet, still served from memory until they are
+    archiving: Vec<ArchivedTask>,
+    // Offset of the line of every task in the archive, the latest one winning
+    archived: HashMap<TaskId, u64>,
+    // Number of changes made to the store, and number of them already written
+    requested: u64,
+    written: u64,
+    // Set when the store is dropped, so the writer exits once everything is written
+    closed: bool,
+}
+
+/// `TaskStore` persists the aggregator's in-flight state, so that operator responses received
+/// before a restart are not lost, along with the status of every known task so that tasks
+/// evicted from memory can still be served.
+///
+/// The live state is kept in a JSON snapshot, and the tasks evicted from memory are moved to an
+/// append-only archive next to it (`<path>.archive.jsonl`) that is read back on lookup, so
+/// neither the memory used by the store nor the size of the snapshot grows with the task history.
+/// Operator responses are appended to a journal (`<path>.responses.jsonl`) as they arrive, and
+/// only folded into the snapshot when the rest of the state changes, so a response never
+/// rewrites the whole store.
+///
+/// Writes happen on a dedicated thread, so callers never block on the disk: consecutive changes
+/// are coalesced into one snapshot, which replaces the previous one through a temporary file and
+/// a rename so a crash mid-write never leaves a truncated store behind. `sync` waits for the
+/// pending writes, and dropping the store flushes them.
+#[derive(Debug)]
+pub struct TaskStore {
+    /// Location of the archive of evicted tasks.
+    archive_path: PathBuf,
+    /// State shared with the writer thread, and the condition it waits on.
+    shared: Arc<(Mutex<Inner>, Condvar)>,
+    /// The writer thread, joined on drop.
+    writer: Option<JoinHandle<()>>,
+}
+
+impl TaskStore {
+    /// Opens the store at `path`, loading its contents, replaying its journal and indexing its
+    /// archive if they already exist, and starts its writer thread.
+    ///
+    /// # Errors
+    /// Returns `AggregatorError::TaskStoreError` if a file exists but cannot be read or parsed.
+    pub fn open(path: &Path) -> Result<Self, AggregatorError> {
+        let mut state: StoreState = if path.exists() {
+            let contents =
+                fs::read(path).map_err(|e| AggregatorError::TaskStoreError(e.to_string()))?;
+            serde_json::from_slice(&contents)
+                .map_err(|e| AggregatorError::TaskStoreError(e.to_string()))?
+        } else {
+            StoreState::default()
+        };
+
+        let journal_path = path.with_extension("responses.jsonl");
+        let journal_len = Self::replay_journal(&journal_path, &mut state.operator_responses)
+            .map_err(|e| AggregatorError::TaskStoreError(e.to_string()))?;
+
+        let archive_path = path.with_extension("archive.jsonl");
+        let (archived, archive_len) = Self::index_archive(&archive_path)
+            .map_err(|e| AggregatorError::TaskStoreError(e.to_string()))?;
+
+        let shared = Arc::new((
+            Mutex::new(Inner {
+                state,
+                archived,
+                ..Default::default()
+            }),
+            Condvar::new(),
+        ));
+        let writer = {
+            let shared = shared.clone();
+            let files = StoreFiles {
+                snapshot: path.to_path_buf(),
+                journal: journal_path,
+                journal_len,
+                archive: archive_path.clone(),
+                archive_len,
+            };
+            thread::Builder::new()
+                .name("task-store-writer".to_string())
+                .spawn(move || Self::write_loop(&shared, files))
+                .map_err(|e| AggregatorError::TaskStoreError(e.to_string()))?
+        };
+
+        Ok(Self {
+            archive_path,
+            shared,
+            writer: Some(writer),
+        })
+    }
+
+    /// Returns every persisted operator response, grouped by task id.
+    pub fn operator_responses(&self) -> StoredResponses {
+        self.lock().state.operator_responses.clone()
+    }
+
+    /// Persists the response of `operator` for the response's task.
+    pub fn insert_operator_response(&self, operator: Address, response: &OperatorResponse) {
+        self.journal(ResponseChange::Insert {
+            operator,
+            response: response.clone(),
+        });
+    }
+
+    /// Drops every persisted response for `task_id`.
+    pub fn remove_operator_responses(&self, task_id: &TaskId) {
+        if self.lock().state.operator_responses.contains_key(task_id) {
+            self.journal(ResponseChange::Remove { task_id: *task_id });
+        }
+    }
+
+    /// Returns the persisted status of `task_id`, if any.
+    pub fn task_status(&self, task_id: &TaskId) -> Option<TaskStatus> {
+        if let Some(task_status) = self.lock().state.tasks.get(task_id) {
+            return Some(task_status.clone());
+        }
+        self.archived_task(task_id).map(|task| task.status)
+    }
+
+    /// Persists the status of `task_id`.
+    pub fn insert_task_status(&self, task_id: TaskId, task_status: TaskStatus) {
+        self.insert_task_statuses([(task_id, task_status)]);
+    }
+
+    /// Persists a batch of task statuses with a single write.
+    pub fn insert_task_statuses(
+        &self,
+        task_statuses: impl IntoIterator<Item = (TaskId, TaskStatus)>,
+    ) {
+        self.update(|state| state.tasks.extend(task_statuses));
+    }
+
+    /// Returns the hash of the canonical consensus result of `task_id`, if it completed.
+    pub fn result_hash(&self, task_id: &TaskId) -> Option<B256> {
+        if let Some(result_hash) = self.lock().state.result_hashes.get(task_id) {
+            return Some(*result_hash);
+        }
+        self.archived_task(task_id)
+            .and_then(|task| task.result_hash)
+    }
+
+    /// Returns the full consensus result of `task_id`, if it was only submitted on-chain as its
+    /// hash.
+    pub fn result(&self, task_id: &TaskId) -> Option<OffchainResult> {
+        if let Some(result) = self.lock().state.results.get(task_id) {
+            return Some(result.clone());
+        }
+        self.archived_task(task_id).and_then(|task| task.result)
+    }
+
+    /// Persists the hash of the canonical consensus result of `task_id`, along with the result
+    /// itself when only its hash is submitted on-chain, with a single write.
+    pub fn insert_result_hash(
+        &self,
+        task_id: TaskId,
+        result_hash: B256,
+        result: Option<OffchainResult>,
+    ) {
+        self.update(|state| {
+            state.result_hashes.insert(task_id, result_hash);
+            if let Some(result) = result {
+                state.results.insert(task_id, result);
+            }
+        });
+    }
+
+    /// Moves the finalized tasks `task_ids`, evicted from the aggregator's memory, from the
+    /// snapshot to the archive, along with their result. Tasks without a persisted status are
+    /// only dropped.
+    pub fn archive(&self, task_ids: &[TaskId]) {
+        if task_ids.is_empty() {
+            return;
+        }
+        let mut inner = self.lock();
+        for task_id in task_ids {
+            if inner.state.operator_responses.remove(task_id).is_some() {
+                // Journaled too, so replaying the journal over the new snapshot doesn't bring
+                // the responses back
+                inner
+                    .journaling
+                    .push(ResponseChange::Remove { task_id: *task_id });
+            }
+            let result_hash = inner.state.result_hashes.remove(task_id);
+            let result = inner.state.results.remove(task_id);
+            if let Some(status) = inner.state.tasks.remove(task_id) {
+                inner.archiving.push(ArchivedTask {
+                    task_id: *task_id,
+                    status,
+                    result_hash,
+                    result,
+                });
+            }
+        }
+        Self::mark_dirty(&mut inner, &self.shared.1);
+    }
+
+    /// Blocks until every change made so far is written to disk.
+    pub fn sync(&self) {
+        let (lock, written) = &*self.shared;
+        let mut inner = lock.lock().unwrap();
+        let requested = inner.requested;
+        while inner.written < requested {
+            inner = written.wait(inner).unwrap();
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.shared.0.lock().unwrap()
+    }
+
+    // Apply `change` to the live state and hand it to the writer
+    fn update(&self, change: impl FnOnce(&mut StoreState)) {
+        let mut inner = self.lock();
+        change(&mut inner.state);
+        Self::mark_dirty(&mut inner, &self.shared.1);
+    }
+
+    fn mark_dirty(inner: &mut Inner, wake: &Condvar) {
+        inner.dirty = true;
+        inner.requested += 1;
+        wake.notify_all();
+    }
+
+    // Apply `change` to the live responses and hand it to the writer, without dirtying the
+    // snapshot
+    fn journal(&self, change: ResponseChange) {
+        let mut inner = self.lock();
+        change.clone().apply(&mut inner.state.operator_responses);
+        inner.journaling.push(change);
+        inner.requested += 1;
+        self.shared.1.notify_all();
+    }
+
+    // Apply the changes of the journal at `path` to `responses`, returning the length of the
+    // journal. A last line left incomplete by a crash is truncated, as in the archive.
+    fn replay_journal(path: &Path, responses: &mut StoredResponses) -> std::io::Result<u64> {
+        let Ok(file) = File::open(path) else {
+            return Ok(0);
+        };
+
+        let mut journal = BufReader::new(file);
+        let mut offset = 0;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = journal.read_line(&mut line)? as u64;
+            if read == 0 || !line.ends_with('\n') {
+                break;
+            }
+            match serde_json::from_str::<ResponseChange>(&line) {
+                Ok(change) => change.apply(responses),
+                Err(e) => warn!(
+                    "Skipping invalid response change at offset {}: {}",
+                    offset, e
+                ),
+            }
+            offset += read;
+        }
+
+        if offset < journal.get_ref().metadata()?.len() {
+            warn!("Truncating incomplete response change at offset {}", offset);
+            OpenOptions::new().write(true).open(path)?.set_len(offset)?;
+        }
+        Ok(offset)
+    }
+
+    // Look `task_id` up among the archived tasks, reading its line back from the archive
+    fn archived_task(&self, task_id: &TaskId) -> Option<ArchivedTask> {
+        let offset = {
+            let inner = self.lock();
+            if let Some(task) = inner
+                .archiving
+                .iter()
+                .rev()
+                .find(|task| task.task_id == *task_id)
+            {
+                return Some(task.clone());
+            }
+            *inner.archived.get(task_id)?
+        };
+
+        let read = || -> std::io::Result<ArchivedTask> {
+            let mut archive = BufReader::new(File::open(&self.archive_path)?);
+            archive.seek(SeekFrom::Start(offset))?;
+            let mut line = String::new();
+            archive.read_line(&mut line)?;
+            Ok(serde_json::from_str(&line)?)
+        };
+        read()
+            .inspect_err(|e| error!("Failed to read archived task {:?}: {:?}", task_id, e))
+            .ok()
+    }
+
+    // Index the lines of the archive at `path` by task id, returning the index and the length
+    // of the archive. A last line left incomplete by a crash is truncated, so the next append
+    // starts on a line of its own.
+    fn index_archive(path: &Path) -> std::io::Result<(HashMap<TaskId, u64>, u64)> {
+        let mut archived = HashMap::new();
+        let Ok(file) = File::open(path) else {
+            return Ok((archived, 0));
+        };
+
+        let mut archive = BufReader::new(file);
+        let mut offset = 0;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = archive.read_line(&mut line)? as u64;
+            if read == 0 || !line.ends_with('\n') {
+                break;
+            }
+            match serde_json::from_str::<ArchivedTask>(&line) {
+                Ok(task) => {
+                    archived.insert(task.task_id, offset);
+                }
+                Err(e) => warn!("Skipping invalid archived task at offset {}: {}", offset, e),
+            }
+            offset += read;
+        }
+
+        if offset < archive.get_ref().metadata()?.len() {
+            warn!("Truncating incomplete archived task at offset {}", offset);
+            OpenOptions::new().write(true).open(path)?.set_len(offset)?;
+        }
+        Ok((archived, offset))
+    }
+
+    // Write the changes handed by the store until it is dropped: response changes are appended
+    // to the journal, evicted tasks are archived before the snapshot that no longer holds them,
+    // and the journal is emptied once a snapshot holding its changes is written
+    fn write_loop(shared: &(Mutex<Inner>, Condvar), mut files: StoreFiles) {
+        let (lock, wake) = shared;
+        let mut inner = lock.lock().unwrap();
+        loop {
+            while !inner.dirty && inner.journaling.is_empty() && !inner.closed {
+                inner = wake.wait(inner).unwrap();
+            }
+            if !inner.dirty && inner.journaling.is_empty() {
+                return;
+            }
+
+            let dirty = std::mem::take(&mut inner.dirty);
+            let requested = inner.requested;
+            let journaling = std::mem::take(&mut inner.journaling);
+            let archiving = inner.archiving.clone();
+            let snapshot = dirty.then(|| serde_json::to_vec(&inner.state));
+            drop(inner);
+
+            // Journaled first, so the journal left behind by a crash before it is emptied only
+            // replays changes the new snapshot already holds
+            match Self::append_lines(&files.journal, files.journal_len, &journaling) {
+                Ok(offsets) => {
+                    if let Some(end) = offsets.last().map(|(_, end)| *end) {
+                        files.journal_len = end;
+                    }
+                }
+                Err(e) => error!("Failed to journal operator responses: {:?}", e),
+            }
+
+            let offsets = match Self::append_lines(&files.archive, files.archive_len, &archiving) {
+                Ok(offsets) => offsets,
+                Err(e) => {
+                    error!("Failed to archive tasks: {:?}", e);
+                    Vec::new()
+                }
+            };
+            if let Some(end) = offsets.last().map(|(_, end)| *end) {
+                files.archive_len = end;
+            }
+            // The snapshot no longer holds the tasks being archived, writing it before they are
+            // archived would lose them on a crash
+            if let Some(snapshot) = snapshot.filter(|_| offsets.len() == archiving.len()) {
+                match snapshot
+                    .map_err(std::io::Error::from)
+                    .and_then(|snapshot| Self::write_snapshot(&files.snapshot, &snapshot))
+                    .and_then(|_| File::create(&files.journal))
+                {
+                    Ok(_) => files.journal_len = 0,
+                    Err(e) => error!("Failed to persist the task store: {:?}", e),
+                }
+            }
+
+            inner = lock.lock().unwrap();
+            inner.archiving.drain(..offsets.len());
+            for (task, (offset, _)) in archiving.iter().zip(offsets) {
+                inner.archived.insert(task.task_id, offset);
+            }
+            inner.written = requested;
+            wake.notify_all();
+        }
+    }
+
+    // Append `entries` as lines to the file of length `len` at `path`, returning the start and
+    // end offsets of their lines. A failed append is rolled back.
+    fn append_lines(
+        path: &Path,
+        len: u64,
+        entries: &[impl Serialize],
+    ) -> std::io::Result<Vec<(u64, u64)>> {
+        if entries.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut lines = Vec::new();
+        let mut offsets = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let start = len + lines.len() as u64;
+            serde_json::to_writer(&mut lines, entry)?;
+            lines.push(b'\n');
+            offsets.push((start, len + lines.len() as u64));
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if let Err(e) = file.write_all(&lines).and_then(|_| file.sync_data()) {
+            let _ = file.set_len(len);
+            return Err(e);
+        }
+        Ok(offsets)
+    }
+
+    // Atomically replace the snapshot at `path` with `snapshot`
+    fn write_snapshot(path: &Path, snapshot: &[u8]) -> std::io::Result<()> {
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, snapshot)?;
+        fs::rename(&tmp_path, path)
+    }
+}
+
+impl Drop for TaskStore {
+    fn drop(&mut self) {
+        self.lock().closed = true;
+        self.shared.1.notify_all();
+        if let Some(writer) = self.writer.take() {
+            if writer.join().is_err() {
+                error!("Task store writer panicked");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{Parity, U256};
+
+    fn task_id(byte: u8) -> TaskId {
+        TaskId(B256::repeat_byte(byte))
+    }
+
+    // A fresh store in the temporary directory, along with its path
+    fn test_store(name: &str) -> Result<(TaskStore, PathBuf), AggregatorError> {
+        let path =
+            std::env::temp_dir().join(format!("task_store_{}_{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(path.with_extension("archive.jsonl"));
+        let _ = fs::remove_file(path.with_extension("responses.jsonl"));
+        Ok((TaskStore::open(&path)?, path))
+    }
+
+    #[test]
+    fn test_archive() -> Result<(), AggregatorError> {
+        let (store, path) = test_store("archive")?;
+        store.insert_task_statuses([
+            (task_id(1), TaskStatus::COMPLETED),
+            (task_id(2), TaskStatus::FAILED),
+            (task_id(3), TaskStatus::PENDING),
+        ]);
+
+        // Archived tasks leave the snapshot but are still served, before and after being written
+        store.archive(&[task_id(1), task_id(2)]);
+        assert_eq!(store.task_status(&task_id(1)), Some(TaskStatus::COMPLETED));
+        store.sync();
+        assert!(store.lock().state.tasks.keys().eq([&task_id(3)]));
+        assert_eq!(store.task_status(&task_id(2)), Some(TaskStatus::FAILED));
+        assert_eq!(store.task_status(&task_id(4)), None);
+
+        // So are their result hash and off-chain result
+        store.insert_task_status(task_id(5), TaskStatus::COMPLETED);
+        let result = OffchainResult {
+            result_type: ResultType::Text,
+            result: Bytes::from_static(b"hello"),
+        };
+        store.insert_result_hash(task_id(5), B256::repeat_byte(9), Some(result.clone()));
+        store.archive(&[task_id(5)]);
+        store.sync();
+        assert!(store.lock().state.result_hashes.is_empty());
+        assert!(store.lock().state.results.is_empty());
+        assert_eq!(store.result_hash(&task_id(5)), Some(B256::repeat_byte(9)));
+        assert_eq!(store.result(&task_id(5)), Some(result));
+
+        // A requeued task is served from the snapshot again
+        store.insert_task_status(task_id(2), TaskStatus::PENDING);
+        assert_eq!(store.task_status(&task_id(2)), Some(TaskStatus::PENDING));
+        drop(store);
+
+        // Both the snapshot and the archive are reloaded
+        let store = TaskStore::open(&path)?;
+        assert_eq!(store.task_status(&task_id(1)), Some(TaskStatus::COMPLETED));
+        assert_eq!(store.task_status(&task_id(2)), Some(TaskStatus::PENDING));
+        assert_eq!(store.task_status(&task_id(3)), Some(TaskStatus::PENDING));
+
+        Ok(())
+    }
+
+    fn response(task_id: TaskId, result: &str) -> OperatorResponse {
+        OperatorResponse {
+            task_id,
+            result: result.to_string(),
+            signature: alloy_primitives::Signature::new(
+                U256::from(1),
+                U256::from(1),
+                Parity::Parity(false),
+            ),
+            version: 0,
+            exit_code: None,
+            duration_ms: None,
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn test_response_journal() -> Result<(), AggregatorError> {
+        let (store, path) = test_store("response_journal")?;
+        let (operator_1, operator_2) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        store.insert_operator_response(operator_1, &response(task_id(1), "42"));
+        store.insert_operator_response(operator_2, &response(task_id(1), "43"));
+        store.insert_operator_response(operator_1, &response(task_id(2), "42"));
+        store.remove_operator_responses(&task_id(2));
+        store.sync();
+
+        // Responses are only journaled, the snapshot isn't written for them
+        assert!(!path.exists());
+        drop(store);
+
+        // A crash mid-append leaves a partial line behind, which is dropped on replay
+        let journal_path = path.with_extension("responses.jsonl");
+        let mut journal = OpenOptions::new().append(true).open(&journal_path).unwrap();
+        journal.write_all(b"{\"change\":\"insert\",").unwrap();
+        drop(journal);
+
+        let store = TaskStore::open(&path)?;
+        let responses = store.operator_responses();
+        assert!(responses.keys().eq([&task_id(1)]));
+        assert_eq!(responses[&task_id(1)][&operator_2].result, "43");
+
+        // A snapshot holds the journaled responses and empties the journal
+        store.insert_task_status(task_id(1), TaskStatus::PENDING);
+        store.sync();
+        assert_eq!(fs::metadata(&journal_path).unwrap().len(), 0);
+        store.insert_operator_response(operator_1, &response(task_id(3), "42"));
+        store.archive(&[task_id(1)]);
+        drop(store);
+
+        let store = TaskStore::open(&path)?;
+        assert!(store.operator_responses().keys().eq([&task_id(3)]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_incomplete_archive() -> Result<(), AggregatorError> {
+        let (store, path) = test_store("incomplete_archive")?;
+        store.insert_task_status(task_id(1), TaskStatus::COMPLETED);
+        store.archive(&[task_id(1)]);
+        drop(store);
+
+        // A crash mid-append leaves a partial line behind, which is dropped on the next open
+        let archive_path = path.with_extension("archive.jsonl");
+        let mut archive = OpenOptions::new().append(true).open(&archive_path).unwrap();
+        archive.write_all(b"{\"task_id\":").unwrap();
+        drop(archive);
+
+        let store = TaskStore::open(&path)?;
+        store.insert_task_status(task_id(2), TaskStatus::FAILED);
+        store.archive(&[task_id(2)]);
+        drop(store);
+
+        let store = TaskStore::open(&path)?;
+        assert_eq!(store.task_status(&task_id(1)), Some(TaskStatus::COMPLETED));
+        assert_eq!(store.task_status(&task_id(2)), Some(TaskStatus::FAILED));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,381 @@
+//! End-to-end test of the operator ↔ aggregator ↔ chain loop.
+//!
+//! The test starts its own Anvil instance over IPC, forking Holesky at the block the contracts
+//! are deployed against, and deploys the contracts with `forge`. Both must be in `PATH`.
+//!
+//! The operators are mocked: the test registers them in GizaAVS itself, runs the task through a
+//! mocked container runtime and submits signed results straight to the aggregator, so neither
+//! Docker nor a client app image is needed.
+//!
+//! The test only runs with the `e2e` feature, with `make test-integration-anvil` or
+//! `cargo test --features e2e --test task_flow`. It serves the aggregator on port 8080.
+
+use aggregator::{aggregator_config::AggregatorConfig, Aggregator};
+use alloy::{
+    network::EthereumWallet,
+    providers::{Provider, ProviderBuilder},
+    signers::{local::PrivateKeySigner, Signer, SignerSync},
+};
+use alloy_primitives::{Address, FixedBytes, U256};
+use contract_bindings::{
+    response_signing_bytes,
+    AVSDirectory::AVSDirectoryInstance,
+    Chain, ChainConfig, ChainEndpoints,
+    GizaAVS::GizaAVSInstance,
+    ISignatureUtils::SignatureWithSaltAndExpiry,
+    TaskId,
+    TaskRegistry::{TaskRegistryInstance, TaskRequested},
+    TaskStatus, AVS_DIRECTORY_ADDRESS, CLIENT_APP_REGISTRY_ADDRESS, GIZA_AVS_ADDRESS,
+    RESPONSE_VERSION_ECDSA_TASK_BOUND, TASK_REGISTRY_ADDRESS,
+};
+use eyre::{eyre, Result};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Stdio,
+    str::FromStr,
+    time::Duration,
+};
+use tokio::{
+    process::{Child, Command},
+    time::{sleep, Instant},
+};
+use tokio_util::sync::CancellationToken;
+
+const DEPLOYER_PK: &str = "71e769d81989880a9237a3404793b463cae6a44d99c93ff1218de8ba159ed90f";
+const OPERATOR_PKS: [&str; 2] = [
+    "2a7f875389f0ce57b6d3200fb88e9a95e864a2ff589e8b1b11e56faff32a1fc5",
+    "277268d9094a360c4dfa3cc538cde5a8759d59759fd0b6d6a80b709718208cd8",
+];
+const CLIENT_APP_ID: &str = "0xc86aab04e8ef18a63006f43fa41a2a0150bae3dbe276d581fa8b5cde0ccbc966";
+const AGGREGATOR_URL: &str = "http://localhost:8080";
+const REGISTRATION_SALT: &str =
+    "0x2ef06b8bbad022ca2dd29795902ceb588d06d1cfd10cb6e687db0dbb837865e9";
+const TASK_RESULT: &str = "42";
+const TIMEOUT: Duration = Duration::from_secs(120);
+// Holesky block the contracts are deployed against, as in `make anvil`
+const FORK_BLOCK_NUMBER: &str = "2630129";
+
+// Anvil instance forking Holesky, stopped when dropped
+struct Anvil {
+    _process: Child,
+    ipc_path: PathBuf,
+}
+
+impl Drop for Anvil {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.ipc_path);
+    }
+}
+
+// Start Anvil with an IPC socket and a free HTTP port, installed as the endpoints of
+// `Chain::Anvil`, and wait until it answers
+async fn start_anvil() -> Result<Anvil> {
+    let ipc_path = std::env::temp_dir().join(format!("anvil_task_flow_{}.ipc", std::process::id()));
+    let port = std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port();
+
+    let mut process = Command::new("anvil")
+        .arg("--ipc")
+        .arg(&ipc_path)
+        .args(["--port", &port.to_string()])
+        .args(["--fork-url", Chain::Holesky.http_url().as_str()])
+        .args(["--fork-block-number", FORK_BLOCK_NUMBER])
+        .stdout(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| eyre!("Failed to start anvil, is it in PATH? {}", e))?;
+
+    ChainConfig(HashMap::from([(
+        Chain::Anvil,
+        ChainEndpoints {
+            http_url: Some(format!("http://127.0.0.1:{}", port).parse()?),
+            ipc_path: Some(ipc_path.clone()),
+            ..Default::default()
+        },
+    )]))
+    .install()?;
+
+    let provider = ProviderBuilder::new().on_http(Chain::Anvil.http_url());
+    let started = Instant::now();
+    while !ipc_path.exists() || provider.get_block_number().await.is_err() {
+        if let Some(status) = process.try_wait()? {
+            return Err(eyre!("Anvil exited with {}", status));
+        }
+        if started.elapsed() > TIMEOUT {
+            return Err(eyre!("Anvil didn't start within {:?}", TIMEOUT));
+        }
+        sleep(Duration::from_millis(200)).await;
+    }
+
+    Ok(Anvil {
+        _process: process,
+        ipc_path,
+    })
+}
+
+// Give every account in `accounts` 100 ether to send its transactions, whatever its balance on
+// the fork
+async fn fund(accounts: &[Address]) -> Result<()> {
+    let provider = ProviderBuilder::new().on_http(Chain::Anvil.http_url());
+    let balance = U256::from(100) * U256::from(10).pow(U256::from(18));
+    for account in accounts {
+        provider
+            .raw_request::<_, ()>("anvil_setBalance".into(), (account, balance))
+            .await?;
+    }
+    Ok(())
+}
+
+// Deploy the contracts with their deployment script, which puts them at the addresses the
+// crates are built with when run by the deployer on the fork
+async fn deploy_contracts() -> Result<()> {
+    let status = Command::new("forge")
+        .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("../contracts"))
+        .args(["script", "script/DeployContracts.s.sol", "--broadcast"])
+        .args(["--rpc-url", Chain::Anvil.http_url().as_str()])
+        .args(["--private-key", &format!("0x{}", DEPLOYER_PK)])
+        .stdout(Stdio::null())
+        .status()
+        .await
+        .map_err(|e| eyre!("Failed to run forge, is it in PATH? {}", e))?;
+    if !status.success() {
+        return Err(eyre!("Contract deployment failed with {}", status));
+    }
+
+    let provider = ProviderBuilder::new().on_http(Chain::Anvil.http_url());
+    for address in [
+        CLIENT_APP_REGISTRY_ADDRESS,
+        TASK_REGISTRY_ADDRESS,
+        GIZA_AVS_ADDRESS,
+    ] {
+        if provider.get_code_at(address).await?.is_empty() {
+            return Err(eyre!("No contract deployed at {}", address));
+        }
+    }
+    Ok(())
+}
+
+// Output of the client app for `task_id`, standing in for the operator's container runtime.
+// The app is deterministic, so every operator gets the same result.
+fn mock_run(_task_id: TaskId) -> &'static str {
+    TASK_RESULT
+}
+
+// Register `signer` in GizaAVS and opt it in for the client app, as the operator does on startup
+async fn register_mock_operator(signer: &PrivateKeySigner) -> Result<()> {
+    fund(&[signer.address()]).await?;
+    let provider = ProviderBuilder::new()
+        .with_recommended_fillers()
+        .wallet(EthereumWallet::from(signer.clone()))
+        .on_http(Chain::Anvil.http_url());
+    let giza_avs = GizaAVSInstance::new(GIZA_AVS_ADDRESS, &provider);
+    let avs_directory = AVSDirectoryInstance::new(AVS_DIRECTORY_ADDRESS, &provider);
+    let operator = signer.address();
+
+    if !giza_avs
+        .isOperatorRegistered(operator)
+        .call()
+        .await?
+        .isRegistered
+    {
+        let salt = FixedBytes::<32>::from_str(REGISTRATION_SALT)?;
+        let expiry = U256::from(u32::MAX);
+        let digest_hash = avs_directory
+            .calculateOperatorAVSRegistrationDigestHash(operator, GIZA_AVS_ADDRESS, salt, expiry)
+            .call()
+            .await?
+            ._0;
+        let signature = signer.sign_hash(&digest_hash).await?;
+
+        giza_avs
+            .registerOperatorToAVS(
+                operator,
+                SignatureWithSaltAndExpiry {
+                    signature: signature.as_bytes().into(),
+                    salt,
+                    expiry,
+                },
+            )
+            .send()
+            .await?
+            .watch()
+            .await?;
+    }
+
+    let client_app_id = FixedBytes::<32>::from_str(CLIENT_APP_ID)?;
+    if !giza_avs
+        .operatorClientAppIdRegistrationStatus(operator, client_app_id)
+        .call()
+        .await?
+        .isRegistered
+    {
+        giza_avs
+            .optInClientAppId(client_app_id)
+            .send()
+            .await?
+            .watch()
+            .await?;
+    }
+
+    Ok(())
+}
+
+// Register every mock operator and return their signers
+async fn mock_operators() -> Result<Vec<PrivateKeySigner>> {
+    let operators = OPERATOR_PKS
+        .iter()
+        .map(|pk| PrivateKeySigner::from_str(pk))
+        .collect::<Result<Vec<_>, _>>()?;
+    for operator in &operators {
+        register_mock_operator(operator).await?;
+    }
+    Ok(operators)
+}
+
+// Request a task for the client app, returning its id and the block it was requested in
+async fn create_task() -> Result<(TaskId, u64)> {
+    let deployer = PrivateKeySigner::from_str(DEPLOYER_PK)?;
+    let provider = ProviderBuilder::new()
+        .with_recommended_fillers()
+        .wallet(EthereumWallet::from(deployer))
+        .on_http(Chain::Anvil.http_url());
+    let task_registry = TaskRegistryInstance::new(TASK_REGISTRY_ADDRESS, &provider);
+
+    let receipt = task_registry
+        .createTask(FixedBytes::<32>::from_str(CLIENT_APP_ID)?)
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+    let task_id = receipt
+        .inner
+        .logs()
+        .iter()
+        .find_map(|log| log.log_decode::<TaskRequested>().ok())
+        .ok_or_else(|| eyre!("TaskRequested event not found"))?
+        .inner
+        .data
+        .taskId
+        .into();
+
+    Ok((task_id, receipt.block_number.unwrap_or_default()))
+}
+
+// Run `task_id` and submit its result to the aggregator, signed by `operator`
+async fn submit_result(
+    http_client: &reqwest::Client,
+    operator: &PrivateKeySigner,
+    task_id: TaskId,
+) -> Result<()> {
+    let result = mock_run(task_id);
+    let signing_bytes = response_signing_bytes(RESPONSE_VERSION_ECDSA_TASK_BOUND, task_id, result)
+        .ok_or_else(|| eyre!("Unsupported response version"))?;
+    let signature = operator.sign_message_sync(&signing_bytes)?;
+    let response = http_client
+        .post(format!("{}/submit_task", AGGREGATOR_URL))
+        .json(&json!({
+            "task_id": task_id,
+            "result": result,
+            "signature": signature,
+            "version": RESPONSE_VERSION_ECDSA_TASK_BOUND,
+        }))
+        .send()
+        .await?;
+    assert!(
+        response.status().is_success(),
+        "{:?}",
+        response.text().await?
+    );
+    Ok(())
+}
+
+// Poll the aggregator until it reports `expected` for `task_id`
+async fn wait_for_aggregator_status(
+    http_client: &reqwest::Client,
+    task_id: TaskId,
+    expected: TaskStatus,
+) -> Result<()> {
+    let started = Instant::now();
+    while started.elapsed() < TIMEOUT {
+        if let Ok(response) = http_client
+            .get(format!("{}/task_status/{}", AGGREGATOR_URL, task_id))
+            .send()
+            .await
+        {
+            if response.json::<TaskStatus>().await.ok() == Some(expected.clone()) {
+                return Ok(());
+            }
+        }
+        sleep(Duration::from_secs(1)).await;
+    }
+    Err(eyre!("Aggregator never reported {:?}", expected))
+}
+
+#[tokio::test]
+#[cfg_attr(
+    not(feature = "e2e"),
+    ignore = "requires anvil and forge, run with --features e2e"
+)]
+async fn test_task_flow() -> Result<()> {
+    let _anvil = start_anvil().await?;
+
+    let mut config = AggregatorConfig::from_env()?;
+    config.task_store_path =
+        std::env::temp_dir().join(format!("aggregator_task_flow_{}.json", std::process::id()));
+    let _ = fs::remove_file(&config.task_store_path);
+
+    let deployer = PrivateKeySigner::from_str(DEPLOYER_PK)?;
+    fund(&[deployer.address(), config.ecdsa_signer.address()]).await?;
+    deploy_contracts().await?;
+    let operators = mock_operators().await?;
+
+    // Start the aggregator once every mock operator is registered, so it waits on all of them
+    let aggregator = Aggregator::new(Chain::Anvil, config).await?;
+    tokio::spawn(async move { aggregator.run(CancellationToken::new()).await });
+
+    // Request a task
+    let (task_id, requested_block) = create_task().await?;
+
+    // Every mock operator runs the task and submits its result
+    let http_client = reqwest::Client::new();
+    wait_for_aggregator_status(&http_client, task_id, TaskStatus::PENDING).await?;
+
+    for operator in &operators {
+        submit_result(&http_client, operator, task_id).await?;
+    }
+
+    // The aggregator should reach consensus and submit the result on-chain
+    let provider = ProviderBuilder::new().on_http(Chain::Anvil.http_url());
+    let task_registry = TaskRegistryInstance::new(TASK_REGISTRY_ADDRESS, &provider);
+    let started = Instant::now();
+    loop {
+        let status = TaskStatus::from(task_registry.tasks(task_id.into()).call().await?._0);
+        if status == TaskStatus::COMPLETED {
+            break;
+        }
+        assert!(
+            started.elapsed() < TIMEOUT,
+            "Task still {:?} on-chain after {:?}",
+            status,
+            TIMEOUT
+        );
+        sleep(Duration::from_secs(1)).await;
+    }
+
+    let responded = task_registry
+        .TaskResponded_filter()
+        .topic1(task_id.0)
+        .from_block(requested_block)
+        .query()
+        .await?;
+    let (event, _) = responded
+        .first()
+        .ok_or_else(|| eyre!("TaskResponded event not found"))?;
+    assert_eq!(event.taskResult, U256::from_str(TASK_RESULT)?);
+
+    Ok(())
+}
@@ -1,19 +1,34 @@
 //! This file provides Rust bindings for interacting with smart contracts.
-//! Currently, it connects to the contracts through Anvil using an IPC connection.
+//! The operator and the aggregator reach the contracts over HTTP, and subscribe to their
+//! events over the transport `Chain` selects: IPC on Anvil, WebSocket on Holesky. The node
+//! endpoints of each chain can be configured with `Chain::from_config`.
 //!
 //! ## Prerequisites
 //! - Start an Anvil instance using `anvil --ipc` in one terminal.
 //! - Deploy the contract using `make contracts-deploy` in a different terminal.
-//!
-//! ## TODO(chalex-eth):
-//! - Currently, only IPC connections are supported as we are working locally.
-//! - Future improvements include adding support for WebSocket (Ws) and HTTP connections
-//!   over a generic type of Provider and Transport. This requires further exploration
-//!   with the new Alloy crate.
 
-use alloy::{sol, transports::http::reqwest::Url};
-use alloy_primitives::{address, Address};
-use serde::Serialize;
+use alloy::{
+    eips::BlockNumberOrTag,
+    providers::{IpcConnect, Provider, ProviderBuilder, RootProvider, WsConnect},
+    pubsub::PubSubFrontend,
+    rpc::types::Log,
+    sol,
+    sol_types::SolValue,
+    transports::{http::reqwest::Url, Transport},
+};
+use alloy_primitives::{address, hex, keccak256, Address, BlockHash, FixedBytes, U256};
+use eyre::WrapErr;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+use tokio::time::sleep;
+use tracing::warn;
 
 pub const TASK_REGISTRY_ADDRESS: Address = address!("56421D6AEb393C5361a3f262e5b94626B7E88aD7");
 pub const CLIENT_APP_REGISTRY_ADDRESS: Address =
@@ -22,6 +37,82 @@ pub const AVS_DIRECTORY_ADDRESS: Address = address!("055733000064333CaDDbC92763c
 pub const GIZA_AVS_ADDRESS: Address = address!("9f06d855F70a99fcDcA4c9f26A6066499A93923d");
 pub const OPERATOR_UJI_ADDRESS: Address = address!("37893031A8484066232AcBE6bFe7E2a7A4411a7d");
 
+// Attempts made to connect to the node before giving up, so a node still booting (e.g. started
+// alongside the operator or aggregator by docker-compose) is waited for
+const PROVIDER_CONNECT_MAX_ATTEMPTS: u32 = 8;
+
+// Delay before the second connection attempt, doubled after every failed attempt
+const PROVIDER_CONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+
+// Upper bound on the delay between two connection attempts
+const PROVIDER_CONNECT_MAX_DELAY: Duration = Duration::from_secs(16);
+
+/// Version of operator responses signed with ECDSA over the raw result bytes (EIP-191), the
+/// scheme of responses that carry no version.
+///
+/// The signature doesn't cover the task id, so a response can be replayed for another task.
+pub const RESPONSE_VERSION_ECDSA: u8 = 0;
+
+/// Version of operator responses signed with ECDSA over the ABI encoding of the version, the
+/// task id and the result (EIP-191), binding the response to its task.
+pub const RESPONSE_VERSION_ECDSA_TASK_BOUND: u8 = 1;
+
+/// Returns the bytes an operator signs for the `result` of task `task_id`, for responses of
+/// `version`.
+///
+/// Operators sign and the aggregator verifies these bytes rather than the JSON a response is
+/// sent as, so signatures don't depend on how the response happens to be serialized. Each
+/// version has a fixed layout, prefixed as an EIP-191 message by the signer:
+/// - `RESPONSE_VERSION_ECDSA` responses sign the raw UTF-8 bytes of the result, leaving the
+///   task id unauthenticated.
+/// - `RESPONSE_VERSION_ECDSA_TASK_BOUND` responses sign `abi.encode(uint8 version, bytes32
+///   taskId, string result)`.
+///
+/// Returns `None` for an unknown version.
+pub fn response_signing_bytes(version: u8, task_id: TaskId, result: &str) -> Option<Vec<u8>> {
+    match version {
+        RESPONSE_VERSION_ECDSA => Some(result.as_bytes().to_vec()),
+        RESPONSE_VERSION_ECDSA_TASK_BOUND => {
+            Some((U256::from(version), task_id.0, result.to_string()).abi_encode_params())
+        }
+        _ => None,
+    }
+}
+
+/// Returns the bytes an operator signs to report that it failed task `task_id`, e.g. because
+/// the image of its client app couldn't be pulled or run.
+///
+/// The layout is `abi.encode(bytes32 domain, bytes32 taskId, string reason)` with the domain
+/// `keccak256("OperatorFailure")`, prefixed as an EIP-191 message by the signer. The domain
+/// can't be mistaken for the version a response starts with, so a failure report can't be
+/// passed off as a response, nor a response as a failure report.
+pub fn failure_signing_bytes(task_id: TaskId, reason: &str) -> Vec<u8> {
+    (keccak256("OperatorFailure"), task_id.0, reason.to_string()).abi_encode_params()
+}
+
+/// Addresses of the contracts making up one AVS deployment.
+///
+/// Defaults to the GizaAVS deployment. Every address is required when deserialized, so a
+/// partial custom deployment can't silently point at some of the GizaAVS contracts.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ContractAddresses {
+    pub task_registry: Address,
+    pub client_app_registry: Address,
+    pub avs_directory: Address,
+    pub giza_avs: Address,
+}
+
+impl Default for ContractAddresses {
+    fn default() -> Self {
+        Self {
+            task_registry: TASK_REGISTRY_ADDRESS,
+            client_app_registry: CLIENT_APP_REGISTRY_ADDRESS,
+            avs_directory: AVS_DIRECTORY_ADDRESS,
+            giza_avs: GIZA_AVS_ADDRESS,
+        }
+    }
+}
+
 sol!(
     #[sol(rpc)]
     TaskRegistry,
@@ -54,7 +145,60 @@ impl std::fmt::Debug for ClientAppRegistry::ClientAppMetadata {
     }
 }
 
-#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+/// Defines a newtype over `FixedBytes<32>` for one kind of identifier, so that identifiers of
+/// different kinds can't be mixed up. It (de)serializes and formats like the wrapped bytes.
+macro_rules! bytes32_id {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $name(pub FixedBytes<32>);
+
+        impl From<FixedBytes<32>> for $name {
+            fn from(value: FixedBytes<32>) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<$name> for FixedBytes<32> {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = hex::FromHexError;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                FixedBytes::<32>::from_str(value).map(Self)
+            }
+        }
+
+        impl std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                std::fmt::Debug::fmt(&self.0, f)
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                std::fmt::Display::fmt(&self.0, f)
+            }
+        }
+    };
+}
+
+bytes32_id!(
+    /// Identifier of a task in the `TaskRegistry`.
+    TaskId
+);
+
+bytes32_id!(
+    /// Identifier of a client app in the `ClientAppRegistry`.
+    AppId
+);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum TaskStatus {
     EMPTY,
     PENDING,
@@ -85,6 +229,268 @@ impl From<TaskStatus> for u8 {
     }
 }
 
+/// Registration status of an operator in an AVS, as returned by
+/// `AVSDirectory.avsOperatorStatus`.
+///
+/// The status is an enum on-chain, so values added by later EigenLayer releases decode to
+/// `UNKNOWN` rather than being mistaken for a registration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperatorAvsStatus {
+    UNREGISTERED,
+    REGISTERED,
+    UNKNOWN(U256),
+}
+
+impl From<U256> for OperatorAvsStatus {
+    fn from(value: U256) -> Self {
+        match u8::try_from(value) {
+            Ok(0) => OperatorAvsStatus::UNREGISTERED,
+            Ok(1) => OperatorAvsStatus::REGISTERED,
+            _ => OperatorAvsStatus::UNKNOWN(value),
+        }
+    }
+}
+
+/// The kind of output a client app produces, used to validate and canonicalize raw results.
+///
+/// Operators may format the same value differently, so results are compared in canonical form:
+/// surrounding whitespace is ignored, integers may be decimal or `0x`-prefixed hex with any
+/// number of leading zeros, hex is case-insensitive, JSON is compared regardless of
+/// formatting and key order, and text is compared regardless of line endings.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultType {
+    /// An unsigned 256-bit integer, in decimal (`42`, `0042`) or `0x`-prefixed hex (`0x2a`,
+    /// `0X2A`).
+    #[default]
+    Uint256,
+    /// Arbitrary bytes, hex encoded with or without a `0x` prefix.
+    Bytes,
+    /// A JSON document.
+    Json,
+    /// Free-form UTF-8 text, such as a classification label.
+    Text,
+}
+
+impl FromStr for ResultType {
+    type Err = eyre::Error;
+
+    fn from_str(value: &str) -> eyre::Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "uint256" => Ok(ResultType::Uint256),
+            "bytes" => Ok(ResultType::Bytes),
+            "json" => Ok(ResultType::Json),
+            "text" => Ok(ResultType::Text),
+            other => Err(eyre::eyre!("Unknown result type: {:?}", other)),
+        }
+    }
+}
+
+impl ResultType {
+    /// Parses a raw result into its canonical byte representation, failing if it does not
+    /// match the expected type. Equal values always yield equal bytes.
+    pub fn canonicalize(&self, raw: &str) -> eyre::Result<Vec<u8>> {
+        let raw = raw.trim();
+        match self {
+            ResultType::Uint256 => {
+                // Lowercase so that a `0X` prefix parses like `0x`
+                let value = U256::from_str(&raw.to_ascii_lowercase())?;
+                Ok(value.to_be_bytes_vec())
+            }
+            ResultType::Bytes => Ok(hex::decode(raw.to_ascii_lowercase())?),
+            ResultType::Json => {
+                // serde_json objects are key-sorted, so re-serializing yields a canonical form
+                let value: serde_json::Value = serde_json::from_str(raw)?;
+                Ok(serde_json::to_vec(&value)?)
+            }
+            // Operators on different platforms may print different line endings
+            ResultType::Text => Ok(raw.replace("\r\n", "\n").into_bytes()),
+        }
+    }
+
+    /// Converts canonical result bytes into the `uint256` submitted on-chain: integers are
+    /// submitted as is, other types are submitted as the keccak256 hash of their bytes.
+    pub fn onchain_value(&self, canonical: &[u8]) -> U256 {
+        match self {
+            ResultType::Uint256 => U256::from_be_slice(canonical),
+            ResultType::Bytes | ResultType::Json | ResultType::Text => {
+                U256::from_be_bytes(keccak256(canonical).0)
+            }
+        }
+    }
+}
+
+/// Off-chain registry of the `ResultType` produced by each client app.
+///
+/// Apps that are not listed default to `ResultType::Uint256`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ResultTypes(HashMap<AppId, ResultType>);
+
+impl ResultTypes {
+    /// Returns the result type declared for `app_id`.
+    pub fn get(&self, app_id: &AppId) -> ResultType {
+        self.0.get(app_id).copied().unwrap_or_default()
+    }
+}
+
+impl FromStr for ResultTypes {
+    type Err = eyre::Error;
+
+    /// Parses a comma separated list of `<app_id>:<result_type>` pairs.
+    fn from_str(value: &str) -> eyre::Result<Self> {
+        value
+            .split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(|entry| {
+                let (app_id, result_type) = entry
+                    .split_once(':')
+                    .ok_or_else(|| eyre::eyre!("Invalid result type entry: {:?}", entry))?;
+                Ok((AppId::from_str(app_id.trim())?, result_type.parse()?))
+            })
+            .collect::<eyre::Result<_>>()
+            .map(ResultTypes)
+    }
+}
+
+/// Per-stage durations of a task's lifecycle, used to log a single timing summary once the
+/// task is done.
+#[derive(Debug, Clone)]
+pub struct TaskTimings {
+    started: Instant,
+    last: Instant,
+    stages: Vec<(&'static str, Duration)>,
+}
+
+impl Default for TaskTimings {
+    fn default() -> Self {
+        Self::start_at(Instant::now())
+    }
+}
+
+impl TaskTimings {
+    /// Starts timing a task whose first stage began at `started`.
+    pub fn start_at(started: Instant) -> Self {
+        Self {
+            started,
+            last: started,
+            stages: Vec::new(),
+        }
+    }
+
+    /// Marks the end of `stage`, recording the time elapsed since the previous stage ended.
+    pub fn record(&mut self, stage: &'static str) {
+        let now = Instant::now();
+        self.stages.push((stage, now.duration_since(self.last)));
+        self.last = now;
+    }
+
+    /// The recorded stages, in order, with the time each one took.
+    pub fn stages(&self) -> &[(&'static str, Duration)] {
+        &self.stages
+    }
+
+    /// Time elapsed between the start of the task and the last recorded stage.
+    pub fn total(&self) -> Duration {
+        self.last.duration_since(self.started)
+    }
+
+    /// Formats the recorded stages as `stage=<ms>ms` pairs followed by the total.
+    pub fn summary(&self) -> String {
+        self.stages
+            .iter()
+            .map(|(stage, duration)| format!("{}={}ms", stage, duration.as_millis()))
+            .chain(std::iter::once(format!(
+                "total={}ms",
+                self.total().as_millis()
+            )))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+// An event seen on-chain that is not deep enough yet to be acted upon
+#[derive(Debug)]
+struct UnconfirmedEvent<E> {
+    event: E,
+    block_number: u64,
+    block_hash: BlockHash,
+    log_index: u64,
+}
+
+/// Holds events until they are buried under enough blocks to be considered final, dropping
+/// those reorged out in the meantime.
+#[derive(Debug)]
+pub struct ConfirmationBuffer<E> {
+    confirmations: u64,
+    unconfirmed: Vec<UnconfirmedEvent<E>>,
+}
+
+impl<E> ConfirmationBuffer<E> {
+    /// Creates a buffer releasing events once `confirmations` blocks were built on top of them.
+    pub fn new(confirmations: u64) -> Self {
+        Self {
+            confirmations,
+            unconfirmed: Vec::new(),
+        }
+    }
+
+    /// Buffers `event`, emitted by `log`. A log flagged as removed by a reorg drops the event it
+    /// was emitted with instead, and logs not mined yet are ignored.
+    pub fn push(&mut self, event: E, log: &Log) {
+        let (Some(block_number), Some(block_hash)) = (log.block_number, log.block_hash) else {
+            return;
+        };
+        let log_index = log.log_index.unwrap_or_default();
+
+        if log.removed {
+            self.unconfirmed.retain(|unconfirmed| {
+                unconfirmed.block_hash != block_hash || unconfirmed.log_index != log_index
+            });
+            return;
+        }
+
+        self.unconfirmed.push(UnconfirmedEvent {
+            event,
+            block_number,
+            block_hash,
+            log_index,
+        });
+    }
+
+    /// Takes the events that are at least `confirmations` deep at block `head`, returned as
+    /// `(confirmed, reorged)`: an event is reorged if its block is no longer part of the
+    /// canonical chain. Events whose block could not be fetched stay buffered.
+    pub async fn take_confirmed<T, P>(&mut self, provider: &P, head: u64) -> (Vec<E>, Vec<E>)
+    where
+        T: Transport + Clone,
+        P: Provider<T>,
+    {
+        let (deep, shallow) = std::mem::take(&mut self.unconfirmed)
+            .into_iter()
+            .partition::<Vec<_>, _>(|unconfirmed| {
+                unconfirmed.block_number + self.confirmations <= head
+            });
+        self.unconfirmed = shallow;
+
+        let mut confirmed = Vec::new();
+        let mut reorged = Vec::new();
+        for unconfirmed in deep {
+            match provider
+                .get_block_by_number(BlockNumberOrTag::Number(unconfirmed.block_number), false)
+                .await
+            {
+                Ok(Some(block)) if block.header.hash == unconfirmed.block_hash => {
+                    confirmed.push(unconfirmed.event)
+                }
+                Ok(_) => reorged.push(unconfirmed.event),
+                Err(_) => self.unconfirmed.push(unconfirmed),
+            }
+        }
+
+        (confirmed, reorged)
+    }
+}
+
 sol!(
     #[sol(rpc)]
     ClientAppRegistry,
@@ -102,9 +508,11 @@ sol! {
     interface AVSDirectory {
     function calculateOperatorAVSRegistrationDigestHash(address operator, address avs, bytes32 salt, uint256 expiry) external view returns (bytes32);
     function avsOperatorStatus(address avs,address operator) external view returns (uint256);
+    function operatorSaltIsSpent(address operator, bytes32 salt) external view returns (bool);
 }}
 
-#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
 pub enum Chain {
     Anvil,
     Holesky,
@@ -120,8 +528,105 @@ impl From<String> for Chain {
     }
 }
 
+/// Endpoints of the node a chain is reached through. Unset fields keep the built-in
+/// endpoints of the chain.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChainEndpoints {
+    /// HTTP JSON-RPC endpoint, used for calls and transactions.
+    #[serde(deserialize_with = "deserialize_url")]
+    pub http_url: Option<Url>,
+    /// WebSocket endpoint, used for subscriptions on chains other than Anvil.
+    #[serde(deserialize_with = "deserialize_url")]
+    pub ws_url: Option<Url>,
+    /// IPC socket, used for subscriptions on Anvil.
+    pub ipc_path: Option<PathBuf>,
+}
+
+/// Parses an endpoint URL, rejecting invalid ones when the file is read rather than on use.
+fn deserialize_url<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Url>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|url| Url::parse(&url).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Node endpoints of each chain, read from a TOML file with a table per chain name:
+///
+/// ```toml
+/// [holesky]
+/// http_url = "https://holesky.example.com"
+/// ws_url = "wss://holesky.example.com"
+///
+/// [anvil]
+/// ipc_path = "/tmp/anvil.ipc"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct ChainConfig(pub HashMap<Chain, ChainEndpoints>);
+
+/// Chain configuration installed by `ChainConfig::install`, read by `Chain::http_url`,
+/// `Chain::ws_url` and `Chain::ipc_path`.
+static CHAIN_CONFIG: OnceLock<ChainConfig> = OnceLock::new();
+
+impl ChainConfig {
+    /// Makes these endpoints the ones returned by `Chain::http_url`, `Chain::ws_url` and
+    /// `Chain::ipc_path` for the rest of the process.
+    ///
+    /// # Errors
+    /// Returns an error if a chain configuration was already installed.
+    pub fn install(self) -> eyre::Result<()> {
+        CHAIN_CONFIG
+            .set(self)
+            .map_err(|_| eyre::eyre!("A chain configuration is already installed"))
+    }
+
+    /// Returns the endpoints configured for `chain`, if any.
+    fn endpoints(chain: &Chain) -> Option<&'static ChainEndpoints> {
+        CHAIN_CONFIG.get()?.0.get(chain)
+    }
+}
+
 impl Chain {
+    /// Parses the node endpoints of each chain from the TOML file at `path`. Install the
+    /// result with `ChainConfig::install` for `http_url`, `ws_url` and `ipc_path` to use it;
+    /// chains and fields missing from the file keep their built-in endpoints.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed, e.g. when it names an unknown
+    /// chain or holds an invalid URL.
+    pub fn from_config(path: &Path) -> eyre::Result<ChainConfig> {
+        let contents = fs::read_to_string(path)
+            .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&contents).wrap_err_with(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Returns the chain with the numeric id `id`, if it is supported.
+    pub fn from_chain_id(id: u64) -> Option<Chain> {
+        match id {
+            31337 => Some(Chain::Anvil),
+            17000 => Some(Chain::Holesky),
+            _ => None,
+        }
+    }
+
+    /// Numeric id of the chain, as reported by wallets and `eth_chainId`.
+    pub fn chain_id(&self) -> u64 {
+        match self {
+            Chain::Anvil => 31337,
+            Chain::Holesky => 17000,
+        }
+    }
+
+    /// HTTP JSON-RPC endpoint of the chain's node, from the installed `ChainConfig` if it sets
+    /// one.
     pub fn http_url(&self) -> Url {
+        if let Some(http_url) =
+            ChainConfig::endpoints(self).and_then(|endpoints| endpoints.http_url.clone())
+        {
+            return http_url;
+        }
         match self {
             Chain::Anvil => Url::parse("http://localhost:8545").unwrap(),
             Chain::Holesky => {
@@ -131,7 +636,13 @@ impl Chain {
         }
     }
 
+    /// WebSocket endpoint of the chain's node, from the installed `ChainConfig` if it sets one.
     pub fn ws_url(&self) -> Url {
+        if let Some(ws_url) =
+            ChainConfig::endpoints(self).and_then(|endpoints| endpoints.ws_url.clone())
+        {
+            return ws_url;
+        }
         match self {
             Chain::Anvil => Url::parse("/tmp/anvil.ipc").unwrap(),
             Chain::Holesky => {
@@ -140,6 +651,74 @@ impl Chain {
             }
         }
     }
+
+    /// IPC socket of the chain's node, from the installed `ChainConfig` if it sets one, or the
+    /// socket `anvil --ipc` creates.
+    pub fn ipc_path(&self) -> PathBuf {
+        ChainConfig::endpoints(self)
+            .and_then(|endpoints| endpoints.ipc_path.clone())
+            .unwrap_or_else(|| PathBuf::from("/tmp/anvil.ipc"))
+    }
+
+    /// Connects to the chain's node over IPC (Anvil) or WebSocket, to subscribe to its events.
+    /// Connection attempts are retried with exponential backoff while the node is unreachable,
+    /// e.g. while it is still starting up.
+    ///
+    /// # Errors
+    /// Returns an error if the node is still unreachable after every attempt.
+    pub async fn connect_pubsub(&self) -> eyre::Result<RootProvider<PubSubFrontend>> {
+        let started = Instant::now();
+        let mut attempt = 1;
+        let mut delay = PROVIDER_CONNECT_BASE_DELAY;
+
+        loop {
+            let provider = match self {
+                Chain::Anvil => {
+                    let ipc = IpcConnect::new(self.ipc_path().to_string_lossy().into_owned());
+                    ProviderBuilder::new().on_ipc(ipc).await
+                }
+                Chain::Holesky => {
+                    let ws = WsConnect {
+                        url: self.ws_url().to_string(),
+                        auth: None,
+                    };
+                    ProviderBuilder::new().on_ws(ws).await
+                }
+            };
+
+            match provider {
+                Ok(provider) => return Ok(provider),
+                Err(e) if attempt >= PROVIDER_CONNECT_MAX_ATTEMPTS => {
+                    return Err(e).wrap_err_with(|| {
+                        format!(
+                            "Failed to connect to the {:?} node after {} attempts over {:?}",
+                            self,
+                            attempt,
+                            started.elapsed()
+                        )
+                    })
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to connect to the {:?} node (attempt {}/{}), retrying in {:?}: {}",
+                        self, attempt, PROVIDER_CONNECT_MAX_ATTEMPTS, delay, e
+                    );
+                    sleep(delay).await;
+                    attempt += 1;
+                    delay = (delay * 2).min(PROVIDER_CONNECT_MAX_DELAY);
+                }
+            }
+        }
+    }
+
+    /// Number of blocks a task event must be buried under before it is treated as final.
+    /// Anvil never reorgs, so its events are final right away.
+    pub fn default_task_confirmations(&self) -> u64 {
+        match self {
+            Chain::Anvil => 0,
+            Chain::Holesky => 3,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -147,7 +726,322 @@ mod tests {
     use super::*;
     use alloy::providers::{IpcConnect, ProviderBuilder};
     use eyre::Result;
-    use tokio;
+
+    #[test]
+    fn test_response_signing_bytes() {
+        // The layout is part of the protocol: changing it invalidates every signature
+        let task_id = TaskId::from(FixedBytes::repeat_byte(0xab));
+        assert_eq!(
+            response_signing_bytes(RESPONSE_VERSION_ECDSA, task_id, "42"),
+            Some(vec![b'4', b'2'])
+        );
+        assert_eq!(
+            response_signing_bytes(RESPONSE_VERSION_ECDSA, task_id, r#"{"b":2,"a":1}"#),
+            Some(br#"{"b":2,"a":1}"#.to_vec())
+        );
+
+        // Version, task id, offset of the result, then its length and padded bytes
+        let mut expected = Vec::new();
+        expected.extend(U256::from(RESPONSE_VERSION_ECDSA_TASK_BOUND).to_be_bytes::<32>());
+        expected.extend([0xab; 32]);
+        expected.extend(U256::from(96).to_be_bytes::<32>());
+        expected.extend(U256::from(2).to_be_bytes::<32>());
+        expected.extend(b"42");
+        expected.extend([0; 30]);
+        assert_eq!(
+            response_signing_bytes(RESPONSE_VERSION_ECDSA_TASK_BOUND, task_id, "42"),
+            Some(expected)
+        );
+        assert_ne!(
+            response_signing_bytes(RESPONSE_VERSION_ECDSA_TASK_BOUND, TaskId::default(), "42"),
+            response_signing_bytes(RESPONSE_VERSION_ECDSA_TASK_BOUND, task_id, "42")
+        );
+
+        assert_eq!(
+            response_signing_bytes(RESPONSE_VERSION_ECDSA_TASK_BOUND + 1, task_id, "42"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_failure_signing_bytes() {
+        let task_id = TaskId::from(FixedBytes::repeat_byte(0xab));
+        let signing_bytes = failure_signing_bytes(task_id, "42");
+
+        assert_eq!(
+            &signing_bytes[..32],
+            keccak256("OperatorFailure").as_slice()
+        );
+        assert_eq!(&signing_bytes[32..64], [0xab; 32].as_slice());
+        assert_ne!(
+            Some(signing_bytes),
+            response_signing_bytes(RESPONSE_VERSION_ECDSA_TASK_BOUND, task_id, "42")
+        );
+    }
+
+    #[test]
+    fn test_bytes32_id() -> Result<()> {
+        let bytes = FixedBytes::<32>::repeat_byte(0xab);
+        let task_id = TaskId::from(bytes);
+        assert_eq!(FixedBytes::<32>::from(task_id), bytes);
+
+        // Ids read and print like the wrapped bytes, so existing payloads keep their format
+        let json = serde_json::to_string(&task_id)?;
+        assert_eq!(json, serde_json::to_string(&bytes)?);
+        assert_eq!(serde_json::from_str::<TaskId>(&json)?, task_id);
+        assert_eq!(task_id.to_string(), bytes.to_string());
+        assert_eq!(format!("{:?}", task_id), format!("{:?}", bytes));
+        assert_eq!(TaskId::from_str(&bytes.to_string())?, task_id);
+        assert!(AppId::from_str("0xab").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_operator_avs_status_from_u256() {
+        assert_eq!(
+            OperatorAvsStatus::from(U256::ZERO),
+            OperatorAvsStatus::UNREGISTERED
+        );
+        assert_eq!(
+            OperatorAvsStatus::from(U256::from(1)),
+            OperatorAvsStatus::REGISTERED
+        );
+        // Statuses this version doesn't know of must not count as registered
+        for value in [U256::from(2), U256::MAX] {
+            assert_eq!(
+                OperatorAvsStatus::from(value),
+                OperatorAvsStatus::UNKNOWN(value)
+            );
+        }
+    }
+
+    #[test]
+    fn test_equal_results_canonicalize_equal() -> Result<()> {
+        let uint256 = ResultType::Uint256.canonicalize("42")?;
+        for raw in ["0042", " 42\n", "0x2a", "0X2A", "0x002A"] {
+            assert_eq!(ResultType::Uint256.canonicalize(raw)?, uint256, "{:?}", raw);
+        }
+        assert_ne!(ResultType::Uint256.canonicalize("43")?, uint256);
+
+        let bytes = ResultType::Bytes.canonicalize("0xdeadbeef")?;
+        assert_eq!(ResultType::Bytes.canonicalize("DEADBEEF")?, bytes);
+
+        let json = ResultType::Json.canonicalize(r#"{"a": 1, "b": [true]}"#)?;
+        assert_eq!(
+            ResultType::Json.canonicalize("{\n  \"b\": [true],\n  \"a\": 1\n}")?,
+            json
+        );
+
+        let text = ResultType::Text.canonicalize("cat\nsitting")?;
+        assert_eq!(ResultType::Text.canonicalize("  cat\r\nsitting\r\n")?, text);
+        assert_ne!(ResultType::Text.canonicalize("Cat\nsitting")?, text);
+        assert_eq!(
+            ResultType::Text.onchain_value(&text),
+            U256::from_be_bytes(keccak256(b"cat\nsitting").0)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_giza_avs_stake_selectors() {
+        use alloy::sol_types::SolCall;
+
+        // Must match the `methodIdentifiers` of the GizaAVS artifact
+        assert_eq!(
+            GizaAVS::operatorStakeCall::SELECTOR,
+            [0xb3, 0x9b, 0xcf, 0x3f]
+        );
+        assert_eq!(
+            GizaAVS::setOperatorStakeCall::SELECTOR,
+            [0x65, 0x9e, 0x37, 0x44]
+        );
+    }
+
+    #[test]
+    fn test_chain_id_round_trip() {
+        for chain in [Chain::Anvil, Chain::Holesky] {
+            assert_eq!(Chain::from_chain_id(chain.chain_id()), Some(chain));
+        }
+        assert_eq!(Chain::from_chain_id(1), None);
+    }
+
+    #[test]
+    fn test_chain_config() -> Result<()> {
+        let config: ChainConfig = toml::from_str(
+            r#"
+            [holesky]
+            http_url = "https://holesky.example.com/"
+            "#,
+        )?;
+        let holesky = &config.0[&Chain::Holesky];
+        assert_eq!(
+            holesky.http_url,
+            Some(Url::parse("https://holesky.example.com/")?)
+        );
+        assert_eq!(holesky.ws_url, None);
+        assert!(!config.0.contains_key(&Chain::Anvil));
+
+        assert!(toml::from_str::<ChainConfig>("[mainnet]").is_err());
+        assert!(toml::from_str::<ChainConfig>("[anvil]\nhttp_url = \"not a url\"").is_err());
+        Ok(())
+    }
+
+    // Serve a mock node answering `eth_getBlockByNumber` with a block of hash `blocks(number)`,
+    // or `null` for `Some(None)`, and an error for `None`, returning its URL
+    async fn mock_node(blocks: fn(u64) -> Option<Option<BlockHash>>) -> Url {
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    loop {
+                        // Headers, of which only the length of the body matters
+                        let mut content_length = 0;
+                        let mut line = String::new();
+                        loop {
+                            line.clear();
+                            if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+                                return;
+                            }
+                            if line == "\r\n" {
+                                break;
+                            }
+                            if let Some((name, value)) = line.split_once(':') {
+                                if name.eq_ignore_ascii_case("content-length") {
+                                    content_length = value.trim().parse().unwrap();
+                                }
+                            }
+                        }
+                        let mut body = vec![0; content_length];
+                        stream.read_exact(&mut body).await.unwrap();
+                        let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+                        let number = request["params"][0].as_str().unwrap();
+                        let number = u64::from_str_radix(&number[2..], 16).unwrap();
+                        let mut response = match blocks(number) {
+                            Some(hash) => serde_json::json!({
+                                "result": hash.map(|hash| {
+                                    let mut block = alloy::rpc::types::Block::<()>::default();
+                                    block.header.number = number;
+                                    block.header.hash = hash;
+                                    block
+                                })
+                            }),
+                            None => serde_json::json!({
+                                "error": { "code": -32000, "message": "unavailable" }
+                            }),
+                        };
+                        response["jsonrpc"] = "2.0".into();
+                        response["id"] = request["id"].clone();
+                        let response = response.to_string();
+                        let written = stream
+                            .get_mut()
+                            .write_all(
+                                format!(
+                                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                                    response.len(),
+                                    response
+                                )
+                                .as_bytes(),
+                            )
+                            .await;
+                        if written.is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+        url
+    }
+
+    fn log(block_number: Option<u64>, block_hash: BlockHash, log_index: u64) -> Log {
+        Log {
+            block_number,
+            block_hash: Some(block_hash),
+            log_index: Some(log_index),
+            ..Default::default()
+        }
+    }
+
+    // Canonical hash of the blocks served by `mock_node`: blocks from 50 on are not found, and
+    // those from 100 on can't be fetched
+    fn canonical_block(number: u64) -> Option<Option<BlockHash>> {
+        match number {
+            ..50 => Some(Some(BlockHash::with_last_byte(number as u8))),
+            50..100 => Some(None),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_confirmation_buffer_push() {
+        let mut buffer = ConfirmationBuffer::new(2);
+        buffer.push("mined", &log(Some(1), BlockHash::with_last_byte(1), 0));
+        buffer.push("reorged", &log(Some(1), BlockHash::with_last_byte(1), 1));
+        buffer.push("pending", &log(None, BlockHash::with_last_byte(1), 2));
+
+        // A removed log only drops the event emitted at its block hash and log index
+        let mut removed = log(Some(1), BlockHash::with_last_byte(1), 1);
+        removed.removed = true;
+        buffer.push("ignored", &removed);
+
+        let events = buffer
+            .unconfirmed
+            .iter()
+            .map(|unconfirmed| unconfirmed.event)
+            .collect::<Vec<_>>();
+        assert_eq!(events, vec!["mined"]);
+    }
+
+    #[tokio::test]
+    async fn test_confirmation_buffer_confirm() -> Result<()> {
+        let provider = ProviderBuilder::new().on_http(mock_node(canonical_block).await);
+        let mut buffer = ConfirmationBuffer::new(2);
+        buffer.push(1, &log(Some(1), BlockHash::with_last_byte(1), 0));
+        buffer.push(2, &log(Some(2), BlockHash::with_last_byte(2), 0));
+
+        // Events are released once `confirmations` blocks are built on top of theirs
+        assert_eq!(buffer.take_confirmed(&provider, 2).await, (vec![], vec![]));
+        assert_eq!(buffer.take_confirmed(&provider, 3).await, (vec![1], vec![]));
+        assert_eq!(buffer.take_confirmed(&provider, 4).await, (vec![2], vec![]));
+        assert!(buffer.unconfirmed.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_confirmation_buffer_reorg() -> Result<()> {
+        let provider = ProviderBuilder::new().on_http(mock_node(canonical_block).await);
+        let mut buffer = ConfirmationBuffer::new(0);
+        buffer.push("canonical", &log(Some(1), BlockHash::with_last_byte(1), 0));
+        buffer.push("reorged", &log(Some(2), BlockHash::repeat_byte(0xff), 0));
+        buffer.push("missing", &log(Some(50), BlockHash::with_last_byte(50), 0));
+        buffer.push(
+            "unavailable",
+            &log(Some(100), BlockHash::with_last_byte(100), 0),
+        );
+
+        // An event whose block was replaced or is gone is reorged, one whose block can't be
+        // fetched waits
+        assert_eq!(
+            buffer.take_confirmed(&provider, 100).await,
+            (vec!["canonical"], vec!["reorged", "missing"])
+        );
+        let events = buffer
+            .unconfirmed
+            .iter()
+            .map(|unconfirmed| unconfirmed.event)
+            .collect::<Vec<_>>();
+        assert_eq!(events, vec!["unavailable"]);
+
+        Ok(())
+    }
 
     #[tokio::test]
     async fn test_task_registry_interaction() -> Result<()> {
@@ -15,6 +15,7 @@ contract GizaAvs is Ownable {
     event OperatorDeregistered(address indexed operator);
     event ClientAppIdRegistered(address indexed operator, bytes32 clientAppId);
     event ClientAppIdDeregistered(address indexed operator, bytes32 clientAppId);
+    event OperatorStakeUpdated(address indexed operator, uint256 stake);
 
     /*//////////////////////////////////////////////////////////////
                               ERROR
@@ -32,6 +33,7 @@ contract GizaAvs is Ownable {
     mapping(address operator => bool isRegistered) public isOperatorRegistered;
     mapping(address operator => mapping(bytes32 clientAppId => bool isRegistered)) public
         operatorClientAppIdRegistrationStatus;
+    mapping(address operator => uint256 stake) public operatorStake;
 
     /*//////////////////////////////////////////////////////////////
                               CONSTRUCTOR
@@ -55,6 +57,17 @@ contract GizaAvs is Ownable {
         avsDirectory.updateAVSMetadataURI(_metadataURI);
     }
 
+    /**
+     * @notice Sets the stake an operator weighs with in stake-weighted consensus
+     * @param operator The address of the operator.
+     * @param stake The stake of the operator, e.g. mirroring its delegated shares in EigenLayer.
+     * @dev only callable by the owner
+     */
+    function setOperatorStake(address operator, uint256 stake) external onlyOwner {
+        operatorStake[operator] = stake;
+        emit OperatorStakeUpdated(operator, stake);
+    }
+
     /**
      * @notice Forwards a call to EigenLayer's AVSDirectory contract to confirm operator registration with the AVS
      * @param operator The address of the operator to register.
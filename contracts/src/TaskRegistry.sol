@@ -79,6 +79,26 @@ contract TaskRegistry is Ownable {
     }
 
     function respondToTask(bytes32 taskId, TaskStatus status, uint256 taskResult) external onlyAggregatorNode {
+        _respondToTask(taskId, status, taskResult);
+    }
+
+    function respondToTasks(bytes32[] calldata taskIds, TaskStatus[] calldata statuses, uint256[] calldata taskResults)
+        external
+        onlyAggregatorNode
+    {
+        // Check that every task has a status and a result
+        if (statuses.length != taskIds.length || taskResults.length != taskIds.length) revert InvalidTaskOperation();
+
+        for (uint256 i = 0; i < taskIds.length; i++) {
+            _respondToTask(taskIds[i], statuses[i], taskResults[i]);
+        }
+    }
+
+    /*//////////////////////////////////////////////////////////////
+                              INTERNAL
+    //////////////////////////////////////////////////////////////*/
+
+    function _respondToTask(bytes32 taskId, TaskStatus status, uint256 taskResult) internal {
         // Check that status is only Completed or Failed
         if (status == TaskStatus.EMPTY || status == TaskStatus.PENDING) revert InvalidTaskOperation();
 
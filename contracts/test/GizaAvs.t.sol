@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.26;
+
+import {GizaAvs} from "src/GizaAvs.sol";
+import {Ownable} from "src/Ownable.sol";
+import {TestState} from "./TestState.sol";
+
+contract GizaAvsTest is TestState {
+    event OperatorStakeUpdated(address indexed operator, uint256 stake);
+
+    address public avsDirectory = makeAddr("avsDirectory");
+    address public operator = makeAddr("operator");
+
+    GizaAvs public gizaAvs;
+
+    function setUp() public override {
+        super.setUp();
+        gizaAvs = new GizaAvs(owner, avsDirectory, address(clientAppRegistry));
+        vm.label(address(gizaAvs), "gizaAvs");
+    }
+
+    function testOperatorStake_DefaultsToZero() public view {
+        assertEq(gizaAvs.operatorStake(operator), 0);
+    }
+
+    function testSetOperatorStake() public {
+        vm.expectEmit(true, false, false, true);
+        emit OperatorStakeUpdated(operator, 100);
+
+        vm.prank(owner);
+        gizaAvs.setOperatorStake(operator, 100);
+
+        assertEq(gizaAvs.operatorStake(operator), 100);
+    }
+
+    function testSetOperatorStake_Overwrites() public {
+        vm.startPrank(owner);
+        gizaAvs.setOperatorStake(operator, 100);
+        gizaAvs.setOperatorStake(operator, 0);
+        vm.stopPrank();
+
+        assertEq(gizaAvs.operatorStake(operator), 0);
+    }
+
+    function testSetOperatorStake_RevertWhen_NotOwner() public {
+        vm.prank(user);
+        vm.expectRevert(Ownable.Unauthorized.selector);
+        gizaAvs.setOperatorStake(operator, 100);
+    }
+}
@@ -153,4 +153,76 @@ contract TaskRegistryTest is TestState {
         vm.expectRevert(TaskRegistry.InvalidTaskOperation.selector);
         taskRegistry.respondToTask(taskId, TaskRegistry.TaskStatus.COMPLETED, 0);
     }
+
+    function testRespondToTasks() public {
+        bytes32 appId = bytes32(uint256(1));
+        bytes32[] memory taskIds = new bytes32[](2);
+        TaskRegistry.TaskStatus[] memory statuses = new TaskRegistry.TaskStatus[](2);
+        uint256[] memory taskResults = new uint256[](2);
+
+        vm.prank(user);
+        taskRegistry.createTask(appId);
+        taskIds[0] = keccak256(abi.encode(user, appId, block.timestamp));
+
+        vm.warp(block.timestamp + 1);
+        vm.prank(user);
+        taskRegistry.createTask(appId);
+        taskIds[1] = keccak256(abi.encode(user, appId, block.timestamp));
+
+        statuses[0] = TaskRegistry.TaskStatus.COMPLETED;
+        statuses[1] = TaskRegistry.TaskStatus.FAILED;
+        taskResults[0] = 42;
+
+        vm.prank(aggregatorNode);
+        taskRegistry.respondToTasks(taskIds, statuses, taskResults);
+
+        assertEq(uint256(taskRegistry.tasks(taskIds[0])), uint256(TaskRegistry.TaskStatus.COMPLETED));
+        assertEq(uint256(taskRegistry.tasks(taskIds[1])), uint256(TaskRegistry.TaskStatus.FAILED));
+    }
+
+    function testRespondToTasks_RevertWhen_NotAggregatorNode() public {
+        bytes32[] memory taskIds = new bytes32[](0);
+        TaskRegistry.TaskStatus[] memory statuses = new TaskRegistry.TaskStatus[](0);
+        uint256[] memory taskResults = new uint256[](0);
+
+        vm.prank(user);
+        vm.expectRevert(Ownable.Unauthorized.selector);
+        taskRegistry.respondToTasks(taskIds, statuses, taskResults);
+    }
+
+    function testRespondToTasks_RevertWhen_LengthMismatch() public {
+        bytes32 appId = bytes32(uint256(1));
+        bytes32[] memory taskIds = new bytes32[](1);
+        TaskRegistry.TaskStatus[] memory statuses = new TaskRegistry.TaskStatus[](0);
+        uint256[] memory taskResults = new uint256[](1);
+
+        vm.prank(user);
+        taskRegistry.createTask(appId);
+        taskIds[0] = keccak256(abi.encode(user, appId, block.timestamp));
+
+        vm.prank(aggregatorNode);
+        vm.expectRevert(TaskRegistry.InvalidTaskOperation.selector);
+        taskRegistry.respondToTasks(taskIds, statuses, taskResults);
+    }
+
+    function testRespondToTasks_RevertWhen_AlreadyResponded() public {
+        bytes32 appId = bytes32(uint256(1));
+        bytes32[] memory taskIds = new bytes32[](2);
+        TaskRegistry.TaskStatus[] memory statuses = new TaskRegistry.TaskStatus[](2);
+        uint256[] memory taskResults = new uint256[](2);
+
+        vm.prank(user);
+        taskRegistry.createTask(appId);
+        taskIds[0] = keccak256(abi.encode(user, appId, block.timestamp));
+        taskIds[1] = taskIds[0];
+        statuses[0] = TaskRegistry.TaskStatus.COMPLETED;
+        statuses[1] = TaskRegistry.TaskStatus.COMPLETED;
+
+        // The whole batch reverts, leaving the task pending
+        vm.prank(aggregatorNode);
+        vm.expectRevert(TaskRegistry.InvalidTaskOperation.selector);
+        taskRegistry.respondToTasks(taskIds, statuses, taskResults);
+
+        assertEq(uint256(taskRegistry.tasks(taskIds[0])), uint256(TaskRegistry.TaskStatus.PENDING));
+    }
 }
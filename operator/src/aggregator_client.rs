@@ -0,0 +1,427 @@
+use alloy::signers::{local::PrivateKeySigner, SignerSync};
+use alloy_primitives::Signature;
+use contract_bindings::{failure_signing_bytes, response_signing_bytes, TaskId};
+use eyre::{Result, WrapErr};
+use reqwest::Client as HttpClient;
+use serde::Serialize;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::time::sleep;
+use tracing::{debug, error, info, warn};
+
+// Delay before the second readiness probe of the aggregators at startup, doubled after every
+// failed probe
+const AGGREGATOR_WAIT_BASE_DELAY: Duration = Duration::from_secs(1);
+
+// Upper bound on the delay between two readiness probes of the aggregators
+const AGGREGATOR_WAIT_MAX_DELAY: Duration = Duration::from_secs(16);
+
+// Delay before retrying a submission rate limited by an aggregator without a `Retry-After`,
+// doubled after every rate limited attempt
+const RATE_LIMITED_BASE_DELAY: Duration = Duration::from_secs(1);
+
+// Upper bound on the delay before retrying a rate limited submission, `Retry-After` included
+const RATE_LIMITED_MAX_DELAY: Duration = Duration::from_secs(16);
+
+// Number of times a rate limited submission is retried before giving up on the aggregator
+const RATE_LIMITED_MAX_RETRIES: u32 = 5;
+
+#[derive(Serialize)]
+pub struct OperatorResponse {
+    pub(super) task_id: TaskId,
+    pub(super) result: String,
+    pub(super) signature: Signature,
+    // Signature scheme, telling the aggregator how to verify `signature`
+    pub(super) version: u8,
+    // Exit code and run time (in milliseconds) of the container that produced `result`, absent
+    // when no container ran. Not signed, they only let the aggregator flag suspicious runs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) exit_code: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) duration_ms: Option<u64>,
+    // Unix time (in seconds) the response was signed at, letting the aggregator detect clock
+    // skew. Not signed either.
+    pub(super) timestamp: u64,
+}
+
+// Report that the operator couldn't produce a result for a task, so the aggregator doesn't wait
+// for its response
+#[derive(Serialize)]
+pub struct OperatorFailure {
+    task_id: TaskId,
+    reason: String,
+    // Signature of the task id and the reason
+    signature: Signature,
+}
+
+/// `AggregatorClient` signs the outcome of the operator's tasks and posts it to every configured
+/// aggregator.
+#[derive(Clone)]
+pub(super) struct AggregatorClient {
+    /// Base URLs of the aggregators, each outcome is sent to all of them.
+    aggregator_urls: Vec<String>,
+    /// Shared client for the aggregator API, cloning it reuses the same connection pool.
+    http_client: HttpClient,
+    /// Key responses and failures are signed with.
+    ecdsa_signer: PrivateKeySigner,
+    /// Signature scheme of the submitted responses.
+    response_version: u8,
+}
+
+impl AggregatorClient {
+    /// Creates a client posting to `aggregator_urls` with `http_client`.
+    pub fn new(
+        aggregator_urls: Vec<String>,
+        http_client: HttpClient,
+        ecdsa_signer: PrivateKeySigner,
+        response_version: u8,
+    ) -> Self {
+        Self {
+            aggregator_urls,
+            http_client,
+            ecdsa_signer,
+            response_version,
+        }
+    }
+
+    /// Signs `result` as the response of the operator to task `task_id`.
+    ///
+    /// # Errors
+    /// Fails when the response version is unsupported or signing fails.
+    pub fn sign_response(&self, task_id: TaskId, result: &str) -> Result<OperatorResponse> {
+        Ok(OperatorResponse {
+            task_id,
+            result: result.to_string(),
+            signature: self.ecdsa_signer.sign_message_sync(
+                &response_signing_bytes(self.response_version, task_id, result)
+                    .ok_or_else(|| eyre::eyre!("Unsupported response version"))?,
+            )?,
+            version: self.response_version,
+            exit_code: None,
+            duration_ms: None,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Sends a response to every aggregator at once.
+    ///
+    /// # Errors
+    /// Fails when no aggregator accepted it.
+    pub async fn submit_response(&self, response: &OperatorResponse) -> Result<()> {
+        self.submit_to_aggregators("submit_task", response).await
+    }
+
+    /// Tells the aggregators that the operator couldn't produce a result for `task_id`, so they
+    /// stop waiting for its response. Failing to do so is only logged, as the aggregators
+    /// eventually time the task out.
+    pub async fn report_failure(&self, task_id: TaskId, reason: &str) {
+        let failure = match self
+            .ecdsa_signer
+            .sign_message_sync(&failure_signing_bytes(task_id, reason))
+        {
+            Ok(signature) => OperatorFailure {
+                task_id,
+                reason: reason.to_string(),
+                signature,
+            },
+            Err(e) => {
+                error!(
+                    "Failed to sign the failure of task \x1b[1;33m{:?}\x1b[0m: {:?}",
+                    task_id, e
+                );
+                return;
+            }
+        };
+
+        match self.submit_to_aggregators("submit_failure", &failure).await {
+            Ok(()) => info!(
+                "Reported the failure of task \x1b[1;33m{:?}\x1b[0m to the aggregator",
+                task_id
+            ),
+            Err(e) => error!(
+                "Failed to report the failure of task \x1b[1;33m{:?}\x1b[0m: {:?}",
+                task_id, e
+            ),
+        }
+    }
+
+    /// Probes the `/ready` endpoint of every aggregator with exponential backoff until one of
+    /// them is ready or `timeout` elapsed. Never fails: an aggregator that is still down only
+    /// gets a warning, as it may be expected to start after the operator.
+    pub async fn wait_for_aggregators(&self, timeout: Duration) {
+        let started = Instant::now();
+        let mut attempt = 1;
+        let mut delay = AGGREGATOR_WAIT_BASE_DELAY;
+
+        loop {
+            let probes = futures::future::join_all(self.aggregator_urls.iter().map(
+                |aggregator_url| async move {
+                    let ready_url = format!("{}/ready", aggregator_url);
+                    match self.http_client.get(&ready_url).send().await {
+                        Ok(res) if res.status().is_success() => Ok(()),
+                        Ok(res) => Err(format!("{} returned {}", ready_url, res.status())),
+                        Err(e) => Err(format!("{} is unreachable: {}", ready_url, e)),
+                    }
+                },
+            ))
+            .await;
+
+            if probes.iter().any(Result::is_ok) {
+                info!("Aggregator ready after {:?}", started.elapsed());
+                return;
+            }
+
+            let remaining = timeout.saturating_sub(started.elapsed());
+            if remaining.is_zero() {
+                warn!(
+                    "No aggregator ready after {:?} ({} attempts), starting anyway: results are dropped until one is up",
+                    started.elapsed(),
+                    attempt
+                );
+                return;
+            }
+
+            for error in probes.into_iter().filter_map(Result::err) {
+                debug!("{}", error);
+            }
+            warn!(
+                "No aggregator ready (attempt {}), retrying in {:?}",
+                attempt,
+                delay.min(remaining)
+            );
+            sleep(delay.min(remaining)).await;
+            attempt += 1;
+            delay = (delay * 2).min(AGGREGATOR_WAIT_MAX_DELAY);
+        }
+    }
+
+    // Post `body` to `path` of every aggregator at once, it counts as submitted if at least one
+    // of them accepts it
+    async fn submit_to_aggregators<T: Serialize>(&self, path: &str, body: &T) -> Result<()> {
+        let outcomes = futures::future::join_all(
+            self.aggregator_urls
+                .iter()
+                .map(|aggregator_url| self.submit_to(aggregator_url, path, body)),
+        )
+        .await;
+
+        let mut accepted = 0;
+        for (aggregator_url, outcome) in self.aggregator_urls.iter().zip(outcomes) {
+            match outcome {
+                Ok(()) => {
+                    accepted += 1;
+                    debug!(
+                        "Aggregator {} accepted the {} request",
+                        aggregator_url, path
+                    );
+                }
+                Err(e) => warn!("Aggregator {} failed: {:?}", aggregator_url, e),
+            }
+        }
+
+        match accepted {
+            0 => Err(eyre::eyre!(
+                "No aggregator accepted the {} request ({} tried)",
+                path,
+                self.aggregator_urls.len()
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // Post `body` to `path` of the aggregator at `aggregator_url`, retrying while it doesn't
+    // know the task yet, and backing off while it rate limits us
+    async fn submit_to<T: Serialize>(
+        &self,
+        aggregator_url: &str,
+        path: &str,
+        body: &T,
+    ) -> Result<()> {
+        let submit_url = format!("{}/{}", aggregator_url, path);
+        let mut retry_count = 0;
+        const MAX_RETRIES: u32 = 3;
+        let mut rate_limited_count = 0;
+        let mut rate_limited_delay = RATE_LIMITED_BASE_DELAY;
+
+        loop {
+            let res = self
+                .http_client
+                .post(&submit_url)
+                .json(body)
+                .send()
+                .await
+                .wrap_err_with(|| format!("Error posting to {}", submit_url))?;
+
+            if res.status().is_success() {
+                return Ok(());
+            } else if res.status() == reqwest::StatusCode::NOT_FOUND && retry_count < MAX_RETRIES {
+                retry_count += 1;
+                warn!(
+                    "Received 404 error. Retrying in 1 second... (Attempt {}/{})",
+                    retry_count, MAX_RETRIES
+                );
+                sleep(Duration::from_secs(1)).await;
+            } else if res.status() == reqwest::StatusCode::TOO_MANY_REQUESTS
+                && rate_limited_count < RATE_LIMITED_MAX_RETRIES
+            {
+                rate_limited_count += 1;
+                // The aggregator knows best when it has room again
+                let delay = res
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse().ok())
+                    .map(Duration::from_secs)
+                    .unwrap_or(rate_limited_delay)
+                    .min(RATE_LIMITED_MAX_DELAY);
+                warn!(
+                    "Rate limited by {}, retrying in {:?} (Attempt {}/{})",
+                    aggregator_url, delay, rate_limited_count, RATE_LIMITED_MAX_RETRIES
+                );
+                sleep(delay).await;
+                rate_limited_delay = (rate_limited_delay * 2).min(RATE_LIMITED_MAX_DELAY);
+            } else {
+                return Err(eyre::eyre!(
+                    "Failed to post to {}. Status: {}",
+                    submit_url,
+                    res.status()
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use alloy_primitives::B256;
+    use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+    use std::sync::{Arc, Mutex};
+    use tokio::net::TcpListener;
+
+    // Requests received by a mock aggregator, as the path they were posted to and their body
+    pub type Received = Arc<Mutex<Vec<(String, serde_json::Value)>>>;
+
+    // Serve a mock aggregator accepting every submission, returning its URL and the requests it
+    // receives
+    pub async fn mock_aggregator() -> (String, Received) {
+        let received = Received::default();
+        let record = |path: &'static str| {
+            move |State(received): State<Received>, Json(body): Json<serde_json::Value>| async move {
+                received.lock().unwrap().push((path.to_string(), body));
+                StatusCode::OK
+            }
+        };
+        let app = Router::new()
+            .route("/submit_task", post(record("submit_task")))
+            .route("/submit_failure", post(record("submit_failure")))
+            .with_state(received.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (url, received)
+    }
+
+    fn test_client(aggregator_url: String) -> AggregatorClient {
+        AggregatorClient::new(
+            vec![aggregator_url],
+            HttpClient::new(),
+            PrivateKeySigner::random(),
+            contract_bindings::RESPONSE_VERSION_ECDSA_TASK_BOUND,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_report_failure() -> Result<()> {
+        let (url, received) = mock_aggregator().await;
+        let client = test_client(url);
+        let task_id = TaskId(B256::repeat_byte(1));
+
+        // A failure goes to its own endpoint, signed so the aggregator can tell who sent it
+        client.report_failure(task_id, "image pull failed").await;
+        let received = received.lock().unwrap().clone();
+        assert_eq!(received.len(), 1);
+        let (path, body) = &received[0];
+        assert_eq!(path, "submit_failure");
+        assert_eq!(body["reason"], "image pull failed");
+        let signature: Signature = serde_json::from_value(body["signature"].clone())?;
+        assert_eq!(
+            signature
+                .recover_address_from_msg(failure_signing_bytes(task_id, "image pull failed"))?,
+            client.ecdsa_signer.address()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_submit_response() -> Result<()> {
+        let (url, received) = mock_aggregator().await;
+        let client = test_client(url);
+
+        client
+            .submit_response(&client.sign_response(TaskId(B256::repeat_byte(1)), "42")?)
+            .await?;
+        let received = received.lock().unwrap().clone();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].0, "submit_task");
+        assert_eq!(received[0].1["result"], "42");
+
+        // Nothing accepted it when every aggregator is unreachable
+        let unreachable = test_client("http://127.0.0.1:1".to_string());
+        assert!(unreachable
+            .submit_response(&unreachable.sign_response(TaskId(B256::repeat_byte(1)), "42")?)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_submission() -> Result<()> {
+        use axum::{http::header::RETRY_AFTER, response::IntoResponse};
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        // An aggregator rate limiting the first `limited` submissions it receives
+        async fn rate_limiting_aggregator(limited: u32) -> (String, Arc<AtomicU32>) {
+            let attempts = Arc::new(AtomicU32::new(0));
+            let counter = attempts.clone();
+            let app = Router::new().route(
+                "/submit_task",
+                post(move || async move {
+                    match counter.fetch_add(1, Ordering::Relaxed) < limited {
+                        true => {
+                            (StatusCode::TOO_MANY_REQUESTS, [(RETRY_AFTER, "0")]).into_response()
+                        }
+                        false => StatusCode::OK.into_response(),
+                    }
+                }),
+            );
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(async move { axum::serve(listener, app).await });
+            (url, attempts)
+        }
+
+        // A rate limited submission is retried once the aggregator has room again
+        let (url, attempts) = rate_limiting_aggregator(2).await;
+        let client = test_client(url);
+        let response = client.sign_response(TaskId(B256::repeat_byte(1)), "42")?;
+        client.submit_response(&response).await?;
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+
+        // Until it gives up on an aggregator that keeps rate limiting it
+        let (url, attempts) = rate_limiting_aggregator(u32::MAX).await;
+        let client = test_client(url);
+        assert!(client.submit_response(&response).await.is_err());
+        assert_eq!(
+            attempts.load(Ordering::Relaxed),
+            RATE_LIMITED_MAX_RETRIES + 1
+        );
+
+        Ok(())
+    }
+}
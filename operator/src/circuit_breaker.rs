@@ -0,0 +1,151 @@
+use contract_bindings::AppId;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Failures of a single client app since its last success.
+#[derive(Debug, Default)]
+struct AppFailures {
+    /// Tasks of the app that failed in a row.
+    consecutive: u32,
+    /// When the breaker of the app closes again, `None` while it is closed.
+    open_until: Option<Instant>,
+}
+
+/// `CircuitBreaker` stops running the tasks of client apps that keep failing, e.g. because
+/// their image can't be pulled or always exits with an error, so they don't take the resources
+/// of the other apps.
+///
+/// The breaker of an app opens after `threshold` consecutive failures of its tasks, and closes
+/// again once `cooldown` has elapsed, or when the app's image is prefetched successfully. A
+/// success resets the count of failures.
+#[derive(Debug)]
+pub(super) struct CircuitBreaker {
+    /// Consecutive failures that open the breaker of an app.
+    threshold: u32,
+    /// Time the tasks of an app are skipped for once its breaker opened.
+    cooldown: Duration,
+    /// Failures of the apps that failed since their last success.
+    apps: Mutex<HashMap<AppId, AppFailures>>,
+}
+
+impl CircuitBreaker {
+    /// Creates a breaker opening after `threshold` consecutive failures for `cooldown`.
+    ///
+    /// # Returns
+    /// `None` when `threshold` is 0, disabling the breaker.
+    pub fn new(threshold: u32, cooldown: Duration) -> Option<Self> {
+        (threshold > 0).then(|| Self {
+            threshold,
+            cooldown,
+            apps: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Time the tasks of an app are skipped for once its breaker opened.
+    pub fn cooldown(&self) -> Duration {
+        self.cooldown
+    }
+
+    /// Returns how long the breaker of `app_id` stays open at `now`, `None` when its tasks may
+    /// run. A breaker whose cooldown elapsed is closed, with its failures forgotten.
+    pub fn open_for(&self, app_id: &AppId, now: Instant) -> Option<Duration> {
+        let mut apps = self.apps.lock().unwrap();
+        let open_until = apps.get(app_id)?.open_until?;
+        if now >= open_until {
+            apps.remove(app_id);
+            return None;
+        }
+        Some(open_until - now)
+    }
+
+    /// Records a failed task of `app_id` at `now`.
+    ///
+    /// # Returns
+    /// `true` when the failure opened the breaker of the app.
+    pub fn record_failure(&self, app_id: AppId, now: Instant) -> bool {
+        let mut apps = self.apps.lock().unwrap();
+        let failures = apps.entry(app_id).or_default();
+        failures.consecutive += 1;
+        if failures.open_until.is_none() && failures.consecutive >= self.threshold {
+            failures.open_until = Some(now + self.cooldown);
+            return true;
+        }
+        false
+    }
+
+    /// Records a successful task of `app_id`, resetting its failures.
+    pub fn record_success(&self, app_id: &AppId) {
+        self.apps.lock().unwrap().remove(app_id);
+    }
+
+    /// Closes the breaker of `app_id`, e.g. once its image was prefetched successfully.
+    ///
+    /// # Returns
+    /// `true` when the breaker was open.
+    pub fn reset(&self, app_id: &AppId) -> bool {
+        self.apps
+            .lock()
+            .unwrap()
+            .remove(app_id)
+            .is_some_and(|failures| failures.open_until.is_some())
+    }
+
+    /// Returns the client apps whose breaker is open at `now`.
+    pub fn open_apps(&self, now: Instant) -> Vec<AppId> {
+        self.apps
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, failures)| failures.open_until.is_some_and(|until| until > now))
+            .map(|(app_id, _)| *app_id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::FixedBytes;
+
+    #[test]
+    fn test_circuit_breaker() {
+        let cooldown = Duration::from_secs(60);
+        assert!(CircuitBreaker::new(0, cooldown).is_none());
+
+        let breaker = CircuitBreaker::new(3, cooldown).unwrap();
+        let failing_app = AppId(FixedBytes::repeat_byte(1));
+        let other_app = AppId(FixedBytes::repeat_byte(2));
+        let now = Instant::now();
+
+        // A success in between resets the count
+        assert!(!breaker.record_failure(failing_app, now));
+        assert!(!breaker.record_failure(failing_app, now));
+        breaker.record_success(&failing_app);
+        assert!(!breaker.record_failure(failing_app, now));
+        assert!(!breaker.record_failure(failing_app, now));
+        assert_eq!(breaker.open_for(&failing_app, now), None);
+
+        // The third failure in a row opens the breaker of that app only
+        assert!(breaker.record_failure(failing_app, now));
+        assert!(!breaker.record_failure(failing_app, now));
+        assert!(!breaker.record_failure(other_app, now));
+        assert_eq!(breaker.open_for(&failing_app, now), Some(cooldown));
+        assert_eq!(breaker.open_for(&other_app, now), None);
+        assert_eq!(breaker.open_apps(now), vec![failing_app]);
+
+        // It closes once the cooldown elapsed, with its failures forgotten
+        let later = now + cooldown;
+        assert_eq!(breaker.open_for(&failing_app, later), None);
+        assert!(!breaker.record_failure(failing_app, later));
+
+        // Or when reset
+        breaker.record_failure(failing_app, later);
+        assert!(breaker.record_failure(failing_app, later));
+        assert!(breaker.reset(&failing_app));
+        assert_eq!(breaker.open_for(&failing_app, later), None);
+        assert!(!breaker.reset(&failing_app));
+    }
+}
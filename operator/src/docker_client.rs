@@ -1,12 +1,28 @@
 use bollard::{
-    container::Config, container::CreateContainerOptions, container::LogsOptions,
-    container::StartContainerOptions, container::WaitContainerOptions, image::CreateImageOptions,
-    Docker,
+    container::Config,
+    container::CreateContainerOptions,
+    container::KillContainerOptions,
+    container::ListContainersOptions,
+    container::LogsOptions,
+    container::RemoveContainerOptions,
+    container::StartContainerOptions,
+    container::WaitContainerOptions,
+    errors::Error as DockerError,
+    image::CreateImageOptions,
+    models::{CreateImageInfo, HostConfig},
+    Docker, API_DEFAULT_VERSION,
 };
-use eyre::Result;
+use contract_bindings::TaskId;
+use eyre::{Result, WrapErr};
 use futures::StreamExt;
+use rand::Rng;
 use regex::Regex;
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::time::{sleep, timeout, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::operator_config::{DockerHost, LogTruncation, RunOptions};
 
 /// `DockerImageMetadata` holds metadata for a Docker image.
 pub struct DockerImageMetadata {
@@ -14,6 +30,266 @@ pub struct DockerImageMetadata {
     pub repository: String,
     /// The tag of the Docker image.
     pub tag: String,
+    /// Digest of the manifest the tag resolved to, once pinned. A pinned image is pulled and
+    /// run by digest, so it is the exact image that was verified even if the tag moves.
+    pub digest: Option<String>,
+}
+
+/// Registry of images whose repository doesn't name one.
+const DEFAULT_REGISTRY: &str = "docker.io";
+
+/// Legacy host of Docker Hub, naming the same registry as `DEFAULT_REGISTRY`.
+const DOCKER_HUB_INDEX: &str = "index.docker.io";
+
+impl DockerImageMetadata {
+    /// Returns the reference the image is pulled and run by: `repository@digest` once pinned,
+    /// `repository:tag` otherwise.
+    pub fn reference(&self) -> String {
+        match &self.digest {
+            Some(digest) => format!("{}@{}", self.repository, digest),
+            None => format!("{}:{}", self.repository, self.tag),
+        }
+    }
+
+    /// Returns the repository qualified with the registry it is pulled from, following the
+    /// Docker reference rules: a first path component containing a `.` or a `:`, or equal to
+    /// `localhost`, is a registry host, otherwise the image comes from Docker Hub. Official
+    /// Docker Hub images are qualified with their `library` namespace, so every way of naming
+    /// the same image compares equal.
+    ///
+    /// # Example
+    /// `giza/app` is `docker.io/giza/app`, `nginx` and `index.docker.io/nginx` are
+    /// `docker.io/library/nginx`, and `ghcr.io/giza/app` is left as is.
+    pub fn qualified_repository(&self) -> String {
+        let repository = self.repository.to_lowercase();
+        let (registry, path) = match repository.split_once('/') {
+            Some((host, path)) if host.contains(['.', ':']) || host == "localhost" => (host, path),
+            _ => (DEFAULT_REGISTRY, repository.as_str()),
+        };
+
+        match registry {
+            DEFAULT_REGISTRY | DOCKER_HUB_INDEX if !path.contains('/') => {
+                format!("{}/library/{}", DEFAULT_REGISTRY, path)
+            }
+            DEFAULT_REGISTRY | DOCKER_HUB_INDEX => format!("{}/{}", DEFAULT_REGISTRY, path),
+            _ => repository.clone(),
+        }
+    }
+
+    /// Whether the image is pulled from one of `registries`, each a registry host optionally
+    /// followed by a path prefix, such as `docker.io` or `ghcr.io/giza`. Prefixes match whole
+    /// path components, so `ghcr.io/giza` doesn't allow `ghcr.io/gizatech`. Docker Hub may be
+    /// named `index.docker.io` too, and its official images are matched under
+    /// `docker.io/library`.
+    ///
+    /// # Returns
+    /// `true` when `registries` is empty, which allows every registry.
+    pub fn is_from_registry(&self, registries: &[String]) -> bool {
+        let repository = self.qualified_repository();
+        registries.is_empty()
+            || registries.iter().any(|registry| {
+                let registry = registry.trim().trim_end_matches('/').to_lowercase();
+                let registry = match registry.strip_prefix(DOCKER_HUB_INDEX) {
+                    Some(path) => format!("{}{}", DEFAULT_REGISTRY, path),
+                    None => registry,
+                };
+                repository
+                    .strip_prefix(&registry)
+                    .is_some_and(|rest| rest.starts_with('/'))
+            })
+    }
+}
+
+/// `ContainerRun` is the outcome of running a task container to completion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerRun {
+    /// The output of the container, truncated to the configured size.
+    pub stdout: String,
+    /// The exit code of the container.
+    pub exit_code: i64,
+    /// Time from the start of the container to its exit.
+    pub duration: Duration,
+}
+
+/// `ContainerLimits` bounds the time and resources a task container may use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContainerLimits {
+    /// Time the container may run before it is stopped, `None` to wait indefinitely.
+    pub timeout: Option<Duration>,
+    /// Memory (in bytes) the container may use, without swap, `None` for no limit.
+    pub memory: Option<i64>,
+    /// CPU quota of the container, in billionths of a CPU, `None` for no limit.
+    pub nano_cpus: Option<i64>,
+}
+
+/// `RunError` is why a task container didn't run to a successful exit.
+#[derive(Debug)]
+pub enum RunError {
+    /// The container outlived its timeout and was stopped, on SIGTERM or with SIGKILL once it
+    /// ignored SIGTERM for `killed_after`.
+    TimedOut {
+        timeout: Duration,
+        killed_after: Option<Duration>,
+    },
+    /// The container exited with a non-zero code, its run is kept for the caller to act on.
+    NonZeroExit(ContainerRun),
+    /// The task was cancelled before its container exited.
+    Cancelled,
+    /// Docker failed to create, start, wait for or clean up the container.
+    Docker(eyre::Report),
+}
+
+impl std::fmt::Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunError::TimedOut {
+                timeout,
+                killed_after: None,
+            } => write!(
+                f,
+                "Container timed out after {:?} and exited on SIGTERM",
+                timeout
+            ),
+            RunError::TimedOut {
+                timeout,
+                killed_after: Some(grace_period),
+            } => write!(
+                f,
+                "Container timed out after {:?} and was killed after ignoring SIGTERM for {:?}",
+                timeout, grace_period
+            ),
+            RunError::NonZeroExit(run) => {
+                write!(f, "Container exited with code {}", run.exit_code)
+            }
+            RunError::Cancelled => write!(f, "Task cancelled before its container exited"),
+            RunError::Docker(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for RunError {}
+
+impl From<DockerError> for RunError {
+    fn from(e: DockerError) -> Self {
+        RunError::Docker(e.into())
+    }
+}
+
+impl From<eyre::Report> for RunError {
+    fn from(e: eyre::Report) -> Self {
+        RunError::Docker(e)
+    }
+}
+
+/// Timeout (in seconds) of requests to the Docker daemon.
+const DOCKER_TIMEOUT_SECS: u64 = 120;
+
+/// Connects to the Docker daemon at `docker_host`, or through the unix socket at `sock_path`
+/// when no remote daemon is configured.
+pub(super) fn connect(sock_path: &str, docker_host: Option<&DockerHost>) -> Result<Docker> {
+    let docker = match docker_host {
+        None => Docker::connect_with_socket(sock_path, DOCKER_TIMEOUT_SECS, API_DEFAULT_VERSION)?,
+        Some(DockerHost::Tcp(address)) => {
+            Docker::connect_with_http(address, DOCKER_TIMEOUT_SECS, API_DEFAULT_VERSION)?
+        }
+        Some(DockerHost::Tls { address, cert_path }) => Docker::connect_with_ssl(
+            address,
+            &cert_path.join("key.pem"),
+            &cert_path.join("cert.pem"),
+            &cert_path.join("ca.pem"),
+            DOCKER_TIMEOUT_SECS,
+            API_DEFAULT_VERSION,
+        )?,
+    };
+    Ok(docker)
+}
+
+/// Network mode of task containers without network access.
+const NETWORK_MODE_NONE: &str = "none";
+/// Network mode of task containers allowed to reach the network.
+const NETWORK_MODE_BRIDGE: &str = "bridge";
+
+/// Directory of task containers backed by a tmpfs, as their root filesystem is read-only.
+const SCRATCH_DIR: &str = "/tmp";
+/// Default size (in MiB) of the scratch tmpfs of task containers.
+const DEFAULT_TMP_SIZE_MB: u64 = 64;
+
+/// Label holding the address of the operator that created a task container.
+const OPERATOR_LABEL: &str = "avsthon.operator";
+/// Label holding the id of the task a container ran for.
+const TASK_LABEL: &str = "avsthon.task";
+/// Prefix of the name of task containers.
+const CONTAINER_NAME_PREFIX: &str = "avsthon-";
+
+/// Builds the name of the container of run `run` of `task_id` on the image of `metadata`, for
+/// the operator `operator_address`. Names are unique per operator, task and run, so tasks can
+/// run side by side and containers kept for inspection don't block the next ones.
+///
+/// # Example
+/// `ghcr.io/giza/app:latest` gives `avsthon-<operator>-ghcr.io-giza-app-<task_id>-0`.
+fn container_name(
+    operator_address: &str,
+    metadata: &DockerImageMetadata,
+    task_id: TaskId,
+    run: u32,
+) -> String {
+    // Docker names only allow `[a-zA-Z0-9_.-]`
+    let repository = metadata.repository.to_lowercase().replace(
+        |c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '.',
+        "-",
+    );
+    format!(
+        "{}{}-{}-{}-{}",
+        CONTAINER_NAME_PREFIX, operator_address, repository, task_id, run
+    )
+}
+
+/// Builds the configuration of a task container running the image of `metadata`, with the
+/// overrides of `options` applied, the memory and CPU quota of `limits` and labelled with
+/// `operator_address` and `task_id`. The container has a read-only root filesystem and a tmpfs
+/// scratch directory unless `options` say otherwise.
+fn container_config(
+    metadata: &DockerImageMetadata,
+    options: &RunOptions,
+    limits: &ContainerLimits,
+    operator_address: &str,
+    task_id: TaskId,
+) -> Config<String> {
+    let network_mode = if options.network_access {
+        NETWORK_MODE_BRIDGE
+    } else {
+        NETWORK_MODE_NONE
+    };
+
+    Config {
+        tty: Some(true),
+        attach_stdin: Some(true),
+        image: Some(metadata.reference()),
+        cmd: options.cmd.clone(),
+        entrypoint: options.entrypoint.clone(),
+        working_dir: options.working_dir.clone(),
+        labels: Some(HashMap::from([
+            (OPERATOR_LABEL.to_string(), operator_address.to_string()),
+            (TASK_LABEL.to_string(), task_id.to_string()),
+        ])),
+        host_config: Some(HostConfig {
+            network_mode: Some(network_mode.to_string()),
+            readonly_rootfs: Some(!options.writable_rootfs),
+            tmpfs: match options.tmp_size_mb.unwrap_or(DEFAULT_TMP_SIZE_MB) {
+                0 => None,
+                size_mb => Some(HashMap::from([(
+                    SCRATCH_DIR.to_string(),
+                    format!("rw,noexec,nosuid,size={}m", size_mb),
+                )])),
+            },
+            // Swap is capped at the memory limit, i.e. none on top of it
+            memory: limits.memory,
+            memory_swap: limits.memory,
+            nano_cpus: limits.nano_cpus,
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
 }
 
 /// `DockerClient` is a wrapper around the `Docker` struct provided by the `bollard` crate.
@@ -22,8 +298,188 @@ pub struct DockerImageMetadata {
 pub(super) struct DockerClient {
     /// The underlying `Docker` client, wrapped in an `Arc` for shared ownership across threads.
     docker: Arc<Docker>,
-    /// The machine ID of the Docker client (Here it is eth addressof the operator)
-    machine_id: String,
+    /// Address of the operator running the containers, used to label and name them.
+    operator_address: String,
+    /// Maximum number of bytes of container output kept in memory.
+    max_log_size: usize,
+    /// Which part of the container output is kept when it exceeds `max_log_size`.
+    log_truncation: LogTruncation,
+    /// Default time and resources task containers may use.
+    limits: ContainerLimits,
+    /// Time a timed out container is given to exit after SIGTERM, before it is sent SIGKILL.
+    stop_grace_period: Duration,
+    /// Maximum number of attempts made to pull an image.
+    pull_max_attempts: u32,
+    /// Time an image pull may go without any progress before it is cancelled, `None` to wait
+    /// indefinitely.
+    pull_stall_timeout: Option<Duration>,
+    /// Minimum time between two logs of the progress of an image pull, `None` to not log it.
+    pull_progress_interval: Option<Duration>,
+    /// Platform images are pulled and containers run for, so every operator runs the same
+    /// architecture whatever its host.
+    platform: String,
+    /// Whether exited containers are left behind for inspection instead of being removed.
+    keep_containers: bool,
+}
+
+/// Delay before the first image pull retry, doubled after every failed attempt.
+const PULL_BASE_DELAY: Duration = Duration::from_secs(1);
+/// Upper bound on the delay between image pull attempts.
+const PULL_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Progress of a single image layer, as reported by the Docker daemon.
+#[derive(Debug, Default)]
+struct LayerProgress {
+    /// Bytes of the layer downloaded so far.
+    downloaded: u64,
+    /// Size of the layer, 0 until its download starts.
+    size: u64,
+    /// Whether the layer is extracted or was already present locally.
+    complete: bool,
+}
+
+/// `PullProgress` aggregates the progress an image pull reports layer by layer.
+#[derive(Debug, Default)]
+struct PullProgress {
+    /// Every layer of the image reported so far, by id.
+    layers: HashMap<String, LayerProgress>,
+}
+
+impl PullProgress {
+    /// Records a progress update of the pull stream. Updates not about a layer, such as the
+    /// initial `Pulling from ...` line, are ignored.
+    fn record(&mut self, info: &CreateImageInfo) {
+        let (Some(id), Some(status)) = (&info.id, &info.status) else {
+            return;
+        };
+        if !matches!(
+            status.as_str(),
+            "Pulling fs layer"
+                | "Waiting"
+                | "Downloading"
+                | "Verifying Checksum"
+                | "Download complete"
+                | "Extracting"
+                | "Pull complete"
+                | "Already exists"
+        ) {
+            return;
+        }
+
+        let layer = self.layers.entry(id.clone()).or_default();
+        match status.as_str() {
+            "Downloading" => {
+                if let Some(detail) = &info.progress_detail {
+                    layer.downloaded = detail.current.unwrap_or_default().max(0) as u64;
+                    layer.size = detail.total.unwrap_or_default().max(0) as u64;
+                }
+            }
+            "Download complete" => layer.downloaded = layer.size,
+            "Pull complete" | "Already exists" => layer.complete = true,
+            _ => {}
+        }
+    }
+
+    /// Summarizes the progress as layers completed and MiB downloaded.
+    fn summary(&self) -> String {
+        const MIB: f64 = 1024.0 * 1024.0;
+        let complete = self.layers.values().filter(|layer| layer.complete).count();
+        let downloaded: u64 = self.layers.values().map(|layer| layer.downloaded).sum();
+        let size: u64 = self.layers.values().map(|layer| layer.size).sum();
+        format!(
+            "{}/{} layers complete, {:.1}/{:.1} MiB downloaded",
+            complete,
+            self.layers.len(),
+            downloaded as f64 / MIB,
+            size as f64 / MIB
+        )
+    }
+}
+
+/// `PullError` classifies a failed image pull attempt.
+enum PullError {
+    /// A transient failure (network, registry 5xx) worth retrying.
+    Retryable(eyre::Report),
+    /// A failure that retrying won't fix, such as an image that doesn't exist.
+    Permanent(eyre::Report),
+}
+
+impl From<DockerError> for PullError {
+    fn from(e: DockerError) -> Self {
+        match e {
+            DockerError::DockerResponseServerError { status_code, .. } if status_code < 500 => {
+                PullError::Permanent(eyre::eyre!("Error pulling image: {:?}", e))
+            }
+            e => PullError::Retryable(eyre::eyre!("Error pulling image: {:?}", e)),
+        }
+    }
+}
+
+/// `LogBuffer` accumulates container output while keeping at most `max_size` bytes of it,
+/// so that a chatty container cannot exhaust the operator's memory.
+struct LogBuffer {
+    /// The bytes kept so far.
+    bytes: Vec<u8>,
+    /// Maximum number of bytes kept.
+    max_size: usize,
+    /// Which part of the output is kept.
+    truncation: LogTruncation,
+    /// Number of bytes dropped so far.
+    dropped: usize,
+}
+
+impl LogBuffer {
+    fn new(max_size: usize, truncation: LogTruncation) -> Self {
+        Self {
+            bytes: Vec::new(),
+            max_size,
+            truncation,
+            dropped: 0,
+        }
+    }
+
+    /// Appends a chunk of output, dropping whatever falls outside the size limit.
+    fn push(&mut self, chunk: &[u8]) {
+        match self.truncation {
+            LogTruncation::Head => {
+                let kept = chunk.len().min(self.max_size - self.bytes.len());
+                self.bytes.extend_from_slice(&chunk[..kept]);
+                self.dropped += chunk.len() - kept;
+            }
+            LogTruncation::Tail => {
+                self.bytes.extend_from_slice(chunk);
+                // Trim lazily so that the buffer is not shifted on every chunk
+                if self.bytes.len() > 2 * self.max_size {
+                    self.trim_front();
+                }
+            }
+        }
+    }
+
+    /// Returns the kept output, with a marker where output was dropped.
+    fn finish(mut self) -> String {
+        if self.truncation == LogTruncation::Tail {
+            self.trim_front();
+        }
+
+        let output = String::from_utf8_lossy(&self.bytes).into_owned();
+        match (self.dropped, self.truncation) {
+            (0, _) => output,
+            (dropped, LogTruncation::Head) => {
+                format!("{}\n[... truncated {} bytes]", output, dropped)
+            }
+            (dropped, LogTruncation::Tail) => {
+                format!("[... truncated {} bytes]\n{}", dropped, output)
+            }
+        }
+    }
+
+    /// Drops the oldest bytes beyond `max_size`.
+    fn trim_front(&mut self) {
+        let excess = self.bytes.len().saturating_sub(self.max_size);
+        self.bytes.drain(..excess);
+        self.dropped += excess;
+    }
 }
 
 impl DockerClient {
@@ -31,108 +487,365 @@ impl DockerClient {
     ///
     /// # Arguments
     /// * `docker` - An `Arc<Docker>` object representing the Docker client.
+    /// * `operator_address` - Address of the operator, used to label and name its containers.
+    /// * `max_log_size` - Maximum number of bytes of container output kept in memory.
+    /// * `log_truncation` - Which part of the output is kept when it exceeds `max_log_size`.
+    /// * `limits` - Default time, memory and CPU quota of task containers.
+    /// * `stop_grace_period` - Time a timed out container is given to exit after SIGTERM.
+    /// * `pull_max_attempts` - Maximum number of attempts made to pull an image.
+    /// * `pull_stall_timeout` - Time a pull may go without progress before it is cancelled, if any.
+    /// * `pull_progress_interval` - Minimum time between two pull progress logs, if logged.
+    /// * `platform` - Platform images are pulled and containers run for, e.g. `linux/amd64`.
+    /// * `keep_containers` - Whether exited containers are kept for inspection.
     ///
     /// # Returns
     /// A new instance of `DockerClient`.
-    pub fn new(docker: Arc<Docker>, machine_id: String) -> Self {
-        Self { docker, machine_id }
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        docker: Arc<Docker>,
+        operator_address: String,
+        max_log_size: usize,
+        log_truncation: LogTruncation,
+        limits: ContainerLimits,
+        stop_grace_period: Duration,
+        pull_max_attempts: u32,
+        pull_stall_timeout: Option<Duration>,
+        pull_progress_interval: Option<Duration>,
+        platform: String,
+        keep_containers: bool,
+    ) -> Self {
+        Self {
+            docker,
+            operator_address,
+            max_log_size,
+            log_truncation,
+            limits,
+            stop_grace_period,
+            pull_max_attempts,
+            pull_stall_timeout,
+            pull_progress_interval,
+            platform,
+            keep_containers,
+        }
     }
 
     /// Pulls a Docker image from the repository and tag specified in the `DockerImageMetadata`.
     ///
     /// This method streams the image download progress and handles any errors encountered during
-    /// the process.
+    /// the process. Transient failures (network errors, registry 5xx) are retried up to
+    /// `pull_max_attempts` times with exponential backoff and jitter, while permanent ones (such
+    /// as an image that doesn't exist) fail immediately.
+    ///
+    /// An attempt is cancelled when the daemon reports no progress for `pull_stall_timeout`, so
+    /// a stalled registry connection doesn't hang forever. The timeout is reset by every
+    /// progress update, so large images that keep downloading are never cut off. The progress
+    /// is logged at debug level at most once every `pull_progress_interval`.
     ///
     /// # Arguments
     /// * `metadata` - A reference to `DockerImageMetadata` of the image to pull.
     ///
     /// # Errors
-    /// Returns an `eyre::Result<()>` if the image pull encounters any errors or the stream reports an error.
+    /// Returns an `eyre::Result<()>` with the last error if the image pull fails permanently or
+    /// every attempt fails.
     ///
     /// # Example
-    /// ```rust
-    /// let metadata = DockerImageMetadata { repository: "hello-world".to_string(), tag: "latest".to_string() };
+    /// ```rust,ignore
+    /// let metadata = DockerImageMetadata { repository: "hello-world".to_string(), tag: "latest".to_string(), digest: None };
     /// docker_client.pull_image(&metadata).await?;
     /// ```
     pub async fn pull_image(&self, metadata: &DockerImageMetadata) -> Result<()> {
+        let mut attempt = 1;
+        let mut delay = PULL_BASE_DELAY;
+
+        loop {
+            match self.pull_image_once(metadata).await {
+                Ok(()) => return Ok(()),
+                Err(PullError::Permanent(e)) => return Err(e),
+                Err(PullError::Retryable(e)) if attempt >= self.pull_max_attempts => return Err(e),
+                Err(PullError::Retryable(e)) => {
+                    // Jitter spreads out retries from operators hitting the registry at once
+                    let jitter = rand::thread_rng().gen_range(0..=delay.as_millis() as u64 / 2);
+                    let wait = delay + Duration::from_millis(jitter);
+                    warn!(
+                        "Failed to pull {}:{} (attempt {}/{}), retrying in {:?}: {:?}",
+                        metadata.repository, metadata.tag, attempt, self.pull_max_attempts, wait, e
+                    );
+                    sleep(wait).await;
+                    attempt += 1;
+                    delay = (delay * 2).min(PULL_MAX_DELAY);
+                }
+            }
+        }
+    }
+
+    /// Makes a single attempt at pulling an image, classifying any failure as retryable or not.
+    async fn pull_image_once(&self, metadata: &DockerImageMetadata) -> Result<(), PullError> {
         // Download the image if we don't have it
         let options = CreateImageOptions {
             from_image: metadata.repository.clone(),
-            tag: metadata.tag.clone(),
+            // The daemon pulls by digest when given one as the tag
+            tag: metadata
+                .digest
+                .clone()
+                .unwrap_or_else(|| metadata.tag.clone()),
+            platform: self.platform.clone(),
             ..Default::default()
         };
 
         // Request the image
         let mut stream = self.docker.create_image(Some(options), None, None);
+        let mut progress = PullProgress::default();
+        let mut progress_logged = Instant::now();
+
+        // Process the stream, giving up once it stalls
+        loop {
+            let next = match self.pull_stall_timeout {
+                Some(stall_timeout) => {
+                    timeout(stall_timeout, stream.next()).await.map_err(|_| {
+                        PullError::Retryable(eyre::eyre!(
+                            "Image pull made no progress for {:?}",
+                            stall_timeout
+                        ))
+                    })?
+                }
+                None => stream.next().await,
+            };
+            let Some(result) = next else {
+                break;
+            };
 
-        // Process the stream
-        while let Some(result) = stream.next().await {
             match result {
                 Ok(build_info) => {
                     if let Some(error) = build_info.error {
-                        return Err(eyre::eyre!("Error pulling image: {:?}", error));
+                        let report = eyre::eyre!("Error pulling image: {:?}", error);
+                        // The registry reports missing images in the stream rather than as a 404
+                        return Err(
+                            if error.contains("not found") || error.contains("manifest unknown") {
+                                PullError::Permanent(report)
+                            } else {
+                                PullError::Retryable(report)
+                            },
+                        );
+                    }
+
+                    progress.record(&build_info);
+                    if let Some(interval) = self.pull_progress_interval {
+                        if progress_logged.elapsed() >= interval {
+                            debug!("Pulling {}: {}", metadata.reference(), progress.summary());
+                            progress_logged = Instant::now();
+                        }
                     }
                 }
-                Err(e) => {
-                    return Err(eyre::eyre!("Error pulling image: {:?}", e));
-                }
+                Err(e) => return Err(e.into()),
             }
         }
 
         Ok(())
     }
 
-    /// Runs a Docker image and retrieves the output logs.
+    /// Runs a Docker image with the configured limits and retrieves the output logs, as
+    /// `run_image_with_limits` does. The `timeout_secs` of `options` replaces the configured
+    /// container timeout.
+    ///
+    /// # Returns
+    /// A `Result<ContainerRun>` containing the container's output logs, exit code and run
+    /// duration if successful. Containers exiting with a non-zero code are not an error, the
+    /// code is reported for the caller to act on.
+    ///
+    /// # Errors
+    /// Returns the `RunError` the run failed with for any other reason: `TimedOut`,
+    /// `Cancelled` or `Docker`.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let metadata = DockerImageMetadata { repository: "hello-world".to_string(), tag: "latest".to_string(), digest: None };
+    /// let run = docker_client.run_image(&metadata, &RunOptions::default(), TaskId::default(), 0, &CancellationToken::new()).await?;
+    /// println!("Container output: {}", run.stdout);
+    /// ```
+    pub async fn run_image(
+        &self,
+        metadata: &DockerImageMetadata,
+        options: &RunOptions,
+        task_id: TaskId,
+        run: u32,
+        cancel: &CancellationToken,
+    ) -> Result<ContainerRun, RunError> {
+        let limits = ContainerLimits {
+            timeout: options
+                .timeout_secs
+                .map(Duration::from_secs)
+                .or(self.limits.timeout),
+            ..self.limits
+        };
+
+        match self
+            .run_image_with_limits(metadata, options, task_id, run, cancel, &limits)
+            .await
+        {
+            // The output of an app exiting with an error is still reported, with its exit code
+            Ok(container_run) | Err(RunError::NonZeroExit(container_run)) => Ok(container_run),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Runs a Docker image within `limits` and retrieves the output logs.
     ///
     /// This method creates a container from the specified image, starts it, waits for it to exit,
-    /// retrieves the logs, and then removes the container.
+    /// retrieves the logs, and then removes the container. At most `max_log_size` bytes of the
+    /// logs are kept; when the output is larger, the head or tail is kept depending on
+    /// `log_truncation` and a `[... truncated N bytes]` marker is added. The container is
+    /// named `avsthon-<operator>-<repository>-<task_id>-<run>` and labelled
+    /// `avsthon.operator=<address>`, so containers of different operators sharing a host can be
+    /// told apart, and `avsthon.task=<task_id>`. A container left behind under the same name,
+    /// e.g. by a crash, is removed before the new one is created.
+    ///
+    /// With `keep_containers`, the container is kept for inspection instead, including timed
+    /// out and cancelled ones once stopped.
+    ///
+    /// A container still running after the `timeout` of `limits` is stopped in two stages: it
+    /// is sent SIGTERM so well-behaved images can flush their output, then SIGKILL if it is
+    /// still running after `stop_grace_period`, and is force-removed either way. A container
+    /// whose task is cancelled through `cancel` is killed and removed right away. Either is
+    /// only stopped with `keep_containers`. A container
+    /// going over the `memory` of `limits` is killed by the kernel, and exits with a non-zero
+    /// code.
     ///
     /// # Arguments
     /// * `metadata` - A reference to `DockerImageMetadata` of the image to run.
+    /// * `options` - The `cmd`, `entrypoint` and `working_dir` overrides, image defaults are
+    ///   kept for unset fields. The container has no network unless `network_access` is set.
+    ///   Its `timeout_secs` is ignored in favour of `limits`.
+    /// * `task_id` - Id of the task the container runs for, which it is named and labelled
+    ///   with.
+    /// * `run` - Index of the run, so every run of a task gets a container of its own.
+    /// * `cancel` - Token of the task the container runs for, cancelled to abandon the task.
+    /// * `limits` - Time, memory and CPU quota of the container.
     ///
     /// # Returns
-    /// A `Result<String>` containing the container's output logs if successful.
+    /// A `ContainerRun` containing the container's output logs, exit code and run duration if
+    /// the container exited with code 0.
     ///
     /// # Errors
-    /// Returns an `eyre::Result<String>` if any step (container creation, start, wait, log retrieval, or container removal) fails.
-    ///
-    /// # Example
-    /// ```rust
-    /// let metadata = DockerImageMetadata { repository: "hello-world".to_string(), tag: "latest".to_string() };
-    /// let output = docker_client.run_image(&metadata).await?;
-    /// println!("Container output: {}", output);
-    /// ```
-    pub async fn run_image(&self, metadata: &DockerImageMetadata) -> Result<String> {
+    /// * `RunError::TimedOut` if the container outlived its timeout, stating whether it exited
+    ///   on SIGTERM or had to be killed.
+    /// * `RunError::NonZeroExit` if the container exited with a non-zero code, with its run.
+    /// * `RunError::Cancelled` if the task was cancelled.
+    /// * `RunError::Docker` if any step (container creation, start, wait, log retrieval, or
+    ///   container removal) fails.
+    pub async fn run_image_with_limits(
+        &self,
+        metadata: &DockerImageMetadata,
+        options: &RunOptions,
+        task_id: TaskId,
+        run: u32,
+        cancel: &CancellationToken,
+        limits: &ContainerLimits,
+    ) -> Result<ContainerRun, RunError> {
+        if cancel.is_cancelled() {
+            return Err(RunError::Cancelled);
+        }
+
         // Create a container from the image
+        let name = container_name(&self.operator_address, metadata, task_id, run);
         let container_opts = CreateContainerOptions {
-            name: format!("test-{}", self.machine_id),
-            ..Default::default()
+            name: name.clone(),
+            platform: Some(self.platform.clone()),
         };
 
-        let container_conf: Config<String> = Config {
-            tty: Some(true),
-            attach_stdin: Some(true),
-            image: Some(metadata.repository.clone()),
-            ..Default::default()
-        };
+        let container_conf =
+            container_config(metadata, options, limits, &self.operator_address, task_id);
 
-        let container = self
+        let container = match self
             .docker
-            .create_container(Some(container_opts), container_conf)
-            .await?;
+            .create_container(Some(container_opts.clone()), container_conf.clone())
+            .await
+        {
+            // The name is ours alone, so the container holding it is a leftover of this task
+            Err(DockerError::DockerResponseServerError {
+                status_code: 409, ..
+            }) => {
+                warn!("Removing leftover container {}", name);
+                let remove_opts = RemoveContainerOptions {
+                    force: true,
+                    ..Default::default()
+                };
+                self.docker
+                    .remove_container(&name, Some(remove_opts))
+                    .await
+                    .map_err(|e| {
+                        eyre::eyre!("Container name {} is taken and can't be freed: {}", name, e)
+                    })?;
+                self.docker
+                    .create_container(Some(container_opts), container_conf)
+                    .await?
+            }
+            container => container?,
+        };
 
         // Start the created container
         self.docker
             .start_container(&container.id, None::<StartContainerOptions<String>>)
             .await?;
+        let started_at = Instant::now();
+
+        // Wait for the container to exit, stopping it once it runs for too long or its task is
+        // cancelled
+        let exit_code = tokio::select! {
+            exit_code = self.wait_for_exit_or_timeout(&container.id, limits.timeout) => exit_code?,
+            _ = cancel.cancelled() => {
+                self.stop_cancelled_container(&container.id).await;
+                return Err(RunError::Cancelled);
+            }
+        };
+        let duration = started_at.elapsed();
+
+        // Get the logs from the exited container
+        let log_opts = LogsOptions::<String> {
+            stdout: true,
+            ..Default::default()
+        };
+
+        let mut logs = self.docker.logs(&container.id, Some(log_opts));
+
+        let mut output = LogBuffer::new(self.max_log_size, self.log_truncation);
+
+        // Chunks are streamed into the bounded buffer, so only the kept bytes stay in memory
+        while let Some(log) = logs.next().await {
+            output.push(&log?.into_bytes());
+        }
+
+        // Remove the exited container, or keep it for inspection
+        if self.keep_containers {
+            info!("Kept container {} for inspection", name);
+        } else {
+            self.docker.remove_container(&container.id, None).await?;
+        }
+
+        let container_run = ContainerRun {
+            stdout: output.finish(),
+            exit_code,
+            duration,
+        };
+        match exit_code {
+            0 => Ok(container_run),
+            _ => Err(RunError::NonZeroExit(container_run)),
+        }
+    }
 
-        // Wait for the container to exit
+    /// Waits for a container to stop running.
+    ///
+    /// # Returns
+    /// The exit code of the container.
+    ///
+    /// # Errors
+    /// Returns an `eyre::Result<i64>` if Docker fails to report the container's exit.
+    async fn wait_for_exit(&self, id: &str) -> Result<i64> {
         let wait_opts = WaitContainerOptions {
             condition: "not-running",
-            ..Default::default()
         };
 
-        let mut wait_stream = self.docker.wait_container(&container.id, Some(wait_opts));
+        let mut wait_stream = self.docker.wait_container(id, Some(wait_opts));
+        let mut exit_code = 0;
 
         while let Some(result) = wait_stream.next().await {
             match result {
@@ -140,31 +853,210 @@ impl DockerClient {
                     if let Some(error) = wait_info.error {
                         return Err(eyre::eyre!("Error waiting for container: {:?}", error));
                     }
+                    exit_code = wait_info.status_code;
                 }
+                // bollard reports non-zero exit codes as errors, but the container did exit
+                Err(DockerError::DockerContainerWaitError { code, .. }) => exit_code = code,
                 Err(e) => {
                     return Err(eyre::eyre!("Error waiting for container: {:?}", e));
                 }
             }
         }
 
-        // Get the logs from the exited container
-        let log_opts = LogsOptions::<String> {
-            stdout: true,
+        Ok(exit_code)
+    }
+
+    /// Waits for a container to stop running, stopping it once it outlives `container_timeout`,
+    /// if any.
+    ///
+    /// # Returns
+    /// The exit code of the container.
+    ///
+    /// # Errors
+    /// Returns a `RunError::Docker` if Docker fails to report the container's exit, or a
+    /// `RunError::TimedOut` if the container timed out.
+    async fn wait_for_exit_or_timeout(
+        &self,
+        id: &str,
+        container_timeout: Option<Duration>,
+    ) -> Result<i64, RunError> {
+        match container_timeout {
+            None => Ok(self.wait_for_exit(id).await?),
+            Some(container_timeout) => {
+                match timeout(container_timeout, self.wait_for_exit(id)).await {
+                    Ok(exit_code) => Ok(exit_code?),
+                    Err(_) => Err(self.stop_runaway_container(id, container_timeout).await),
+                }
+            }
+        }
+    }
+
+    /// Kills and removes the container of a cancelled task right away, as its output will be
+    /// discarded anyway. With `keep_containers`, it is only killed.
+    async fn stop_cancelled_container(&self, id: &str) {
+        if !self.keep_containers {
+            info!("Task cancelled, removing container {}", id);
+            self.force_remove_container(id).await;
+            return;
+        }
+
+        info!("Task cancelled, killing container {}", id);
+        let sigkill = KillContainerOptions { signal: "SIGKILL" };
+        if let Err(e) = self.docker.kill_container(id, Some(sigkill)).await {
+            warn!("Failed to send SIGKILL to container {}: {:?}", id, e);
+        }
+        info!("Kept container {} for inspection", id);
+    }
+
+    /// Force-removes a container stopped before it exited, logging rather than failing when it
+    /// can't be removed.
+    async fn force_remove_container(&self, id: &str) {
+        let remove_opts = RemoveContainerOptions {
+            force: true,
             ..Default::default()
         };
+        if let Err(e) = self.docker.remove_container(id, Some(remove_opts)).await {
+            warn!("Failed to remove container {}: {:?}", id, e);
+        }
+    }
 
-        let mut logs = self.docker.logs(&container.id, Some(log_opts));
+    /// Stops a container that outlived `container_timeout`: SIGTERM first, then SIGKILL if it
+    /// is still running after `stop_grace_period`, and force-removes it unless
+    /// `keep_containers` is set.
+    ///
+    /// # Returns
+    /// The `RunError::TimedOut` reported for the run, stating which stage stopped the container.
+    async fn stop_runaway_container(&self, id: &str, container_timeout: Duration) -> RunError {
+        warn!(
+            "Container {} still running after {:?}, sending SIGTERM",
+            id, container_timeout
+        );
+        let sigterm = KillContainerOptions { signal: "SIGTERM" };
+        let terminated = match self.docker.kill_container(id, Some(sigterm)).await {
+            Ok(()) => timeout(self.stop_grace_period, self.wait_for_exit(id))
+                .await
+                .is_ok(),
+            Err(e) => {
+                warn!("Failed to send SIGTERM to container {}: {:?}", id, e);
+                false
+            }
+        };
 
-        let mut output = String::new();
+        let killed_after = if terminated {
+            None
+        } else {
+            warn!(
+                "Container {} still running {:?} after SIGTERM, sending SIGKILL",
+                id, self.stop_grace_period
+            );
+            let sigkill = KillContainerOptions { signal: "SIGKILL" };
+            if let Err(e) = self.docker.kill_container(id, Some(sigkill)).await {
+                warn!("Failed to send SIGKILL to container {}: {:?}", id, e);
+            }
+            Some(self.stop_grace_period)
+        };
 
-        while let Some(log) = logs.next().await {
-            output.push_str(&log?.to_string());
+        if self.keep_containers {
+            info!("Kept container {} for inspection", id);
+        } else {
+            self.force_remove_container(id).await;
+        }
+
+        RunError::TimedOut {
+            timeout: container_timeout,
+            killed_after,
         }
+    }
+
+    /// Resolves the local image of `metadata` to its content-addressed id, which changes
+    /// whenever the tag is pulled again and points to another image.
+    ///
+    /// # Errors
+    /// Returns an `eyre::Result<String>` if the image isn't available locally.
+    pub async fn image_id(&self, metadata: &DockerImageMetadata) -> Result<String> {
+        let reference = metadata.reference();
+        self.docker
+            .inspect_image(&reference)
+            .await?
+            .id
+            .ok_or_else(|| eyre::eyre!("Image {} has no id", reference))
+    }
+
+    /// Resolves the tag of `metadata` to the digest of its manifest in the registry, pinning
+    /// the image so the one verified is the one pulled and run.
+    ///
+    /// # Errors
+    /// Returns an `eyre::Result<DockerImageMetadata>` if the registry can't be queried or
+    /// doesn't report a digest.
+    pub async fn pin_image(&self, metadata: &DockerImageMetadata) -> Result<DockerImageMetadata> {
+        let reference = format!("{}:{}", metadata.repository, metadata.tag);
+        let digest = self
+            .docker
+            .inspect_registry_image(&reference, None)
+            .await
+            .wrap_err_with(|| format!("Failed to resolve the digest of {}", reference))?
+            .descriptor
+            .digest
+            .ok_or_else(|| eyre::eyre!("Registry reported no digest for {}", reference))?;
 
-        // Remove the exited container
-        self.docker.remove_container(&container.id, None).await?;
+        Ok(DockerImageMetadata {
+            repository: metadata.repository.clone(),
+            tag: metadata.tag.clone(),
+            digest: Some(digest),
+        })
+    }
+
+    /// Checks that the Docker daemon is reachable.
+    ///
+    /// # Errors
+    /// Returns an `eyre::Result<()>` if the daemon doesn't answer the ping.
+    pub async fn ping(&self) -> Result<()> {
+        self.docker.ping().await?;
+        Ok(())
+    }
 
-        Ok(output)
+    /// Whether exited containers are kept for inspection instead of being removed.
+    pub fn keeps_containers(&self) -> bool {
+        self.keep_containers
+    }
+
+    /// Removes the task containers left behind by this operator, e.g. after a crash mid-task.
+    ///
+    /// Containers are found by their `avsthon.operator=<address>` label, so those of other
+    /// operators sharing the host are left alone. This includes the containers kept for
+    /// inspection with `keep_containers`, unless `running_only` is set.
+    ///
+    /// # Errors
+    /// Returns an `eyre::Result<()>` if the containers cannot be listed or removed.
+    pub async fn remove_orphaned_containers(&self, running_only: bool) -> Result<()> {
+        let list_opts = ListContainersOptions {
+            all: !running_only,
+            filters: HashMap::from([(
+                "label".to_string(),
+                vec![format!("{}={}", OPERATOR_LABEL, self.operator_address)],
+            )]),
+            ..Default::default()
+        };
+
+        let containers = self.docker.list_containers(Some(list_opts)).await?;
+
+        for container in containers {
+            let Some(id) = container.id else {
+                continue;
+            };
+            let remove_opts = RemoveContainerOptions {
+                force: true,
+                ..Default::default()
+            };
+            self.docker.remove_container(&id, Some(remove_opts)).await?;
+            info!(
+                "Removed orphaned container {} ({:?})",
+                id,
+                container.names.unwrap_or_default()
+            );
+        }
+
+        Ok(())
     }
 
     /// Extracts the Docker image metadata (repository and tag) from a DockerHub URL.
@@ -182,7 +1074,7 @@ impl DockerClient {
     /// Returns an `eyre::Result<DockerImageMetadata>` if the URL does not contain valid repository or tag information.
     ///
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let metadata = docker_client.image_metadata("https://hub.docker.com/layers/library/hello-world/latest/images/sha256:e2fc4e5")?;
     /// println!("Repository: {}, Tag: {}", metadata.repository, metadata.tag);
     /// ```
@@ -203,8 +1095,528 @@ impl DockerClient {
             ));
         }
 
-        let metadata = DockerImageMetadata { repository, tag };
+        let metadata = DockerImageMetadata {
+            repository,
+            tag,
+            digest: None,
+        };
 
         Ok(metadata)
     }
 }
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use bollard::models::ProgressDetail;
+
+    #[test]
+    fn test_container_name() {
+        let metadata = DockerImageMetadata {
+            repository: "ghcr.io/Giza/app:v1".to_string(),
+            tag: "latest".to_string(),
+            digest: None,
+        };
+        let task_id = TaskId::default();
+
+        let name = container_name("0xabc", &metadata, task_id, 1);
+        assert_eq!(
+            name,
+            format!("avsthon-0xabc-ghcr.io-giza-app-v1-{}-1", task_id)
+        );
+        assert!(name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c)));
+
+        // Every run of a task gets a container of its own
+        assert_ne!(name, container_name("0xabc", &metadata, task_id, 2));
+    }
+
+    #[test]
+    fn test_pull_progress() {
+        let update = |id: &str, status: &str, current: i64, total: i64| CreateImageInfo {
+            id: Some(id.to_string()),
+            status: Some(status.to_string()),
+            progress_detail: Some(ProgressDetail {
+                current: Some(current),
+                total: Some(total),
+            }),
+            ..Default::default()
+        };
+        let mut progress = PullProgress::default();
+
+        progress.record(&update("latest", "Pulling from library/app", 0, 0));
+        progress.record(&update("a", "Already exists", 0, 0));
+        progress.record(&update("b", "Pulling fs layer", 0, 0));
+        progress.record(&update("c", "Downloading", 1024 * 1024, 4 * 1024 * 1024));
+        assert_eq!(
+            progress.summary(),
+            "1/3 layers complete, 1.0/4.0 MiB downloaded"
+        );
+
+        progress.record(&update("c", "Download complete", 0, 0));
+        progress.record(&update("c", "Pull complete", 0, 0));
+        assert_eq!(
+            progress.summary(),
+            "2/3 layers complete, 4.0/4.0 MiB downloaded"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pull_stall_timeout() {
+        use axum::{body::Body, Router};
+
+        // Serve a daemon pulling images in `updates` progress updates `interval` apart, which
+        // then hangs rather than completing the pull when `stalls`
+        async fn mock_pull(interval: Duration, updates: usize, stalls: bool) -> DockerHost {
+            let app = Router::new().fallback(move || async move {
+                Body::from_stream(futures::stream::unfold(0, move |update| async move {
+                    if update == updates {
+                        if stalls {
+                            std::future::pending::<()>().await;
+                        }
+                        return None;
+                    }
+                    sleep(interval).await;
+                    let line = serde_json::json!({ "id": "a", "status": "Downloading" });
+                    Some((
+                        Ok::<_, std::convert::Infallible>(format!("{}\n", line)),
+                        update + 1,
+                    ))
+                }))
+            });
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap().to_string();
+            tokio::spawn(async move { axum::serve(listener, app).await });
+            DockerHost::Tcp(address)
+        }
+        let metadata = DockerImageMetadata {
+            repository: "hello-world".to_string(),
+            tag: "latest".to_string(),
+            digest: None,
+        };
+        let stall_timeout = Duration::from_millis(200);
+
+        // Only the time between two updates counts, not the length of the whole pull
+        for (updates, stalls, pulled) in [(10, false, true), (2, true, false)] {
+            let docker_host = mock_pull(Duration::from_millis(50), updates, stalls).await;
+            let client = DockerClient::new(
+                Arc::new(connect("", Some(&docker_host)).unwrap()),
+                "0x0".to_string(),
+                1024,
+                LogTruncation::default(),
+                ContainerLimits::default(),
+                Duration::from_millis(10),
+                1,
+                Some(stall_timeout),
+                None,
+                "linux/amd64".to_string(),
+                false,
+            );
+
+            let pull = client.pull_image(&metadata).await;
+            assert_eq!(pull.is_ok(), pulled, "{:?}", pull);
+            if let Err(e) = pull {
+                assert!(e.to_string().contains("made no progress"), "{}", e);
+            }
+        }
+    }
+
+    #[test]
+    fn test_registry_allowlist() {
+        let image = |repository: &str| DockerImageMetadata {
+            repository: repository.to_string(),
+            tag: "latest".to_string(),
+            digest: None,
+        };
+        let registries = vec!["docker.io/library".to_string(), "ghcr.io/giza/".to_string()];
+
+        assert_eq!(
+            image("giza/app").qualified_repository(),
+            "docker.io/giza/app"
+        );
+        assert_eq!(
+            image("localhost:5000/app").qualified_repository(),
+            "localhost:5000/app"
+        );
+
+        // Official images are named the same whichever way they are written
+        for repository in [
+            "nginx",
+            "library/nginx",
+            "docker.io/nginx",
+            "docker.io/library/nginx",
+            "index.docker.io/nginx",
+            "NGINX",
+        ] {
+            assert_eq!(
+                image(repository).qualified_repository(),
+                "docker.io/library/nginx",
+                "{}",
+                repository
+            );
+        }
+
+        assert!(image("hello-world").is_from_registry(&registries));
+        assert!(image("library/hello-world").is_from_registry(&registries));
+        assert!(image("ghcr.io/giza/app").is_from_registry(&registries));
+        assert!(!image("giza/app").is_from_registry(&registries));
+        assert!(!image("ghcr.io/gizatech/app").is_from_registry(&registries));
+        assert!(!image("evil.io/library/hello-world").is_from_registry(&registries));
+        assert!(image("evil.io/app").is_from_registry(&[]));
+        assert!(image("nginx").is_from_registry(&["index.docker.io/library".to_string()]));
+    }
+
+    fn host_config(options: &RunOptions) -> HostConfig {
+        let metadata = DockerImageMetadata {
+            repository: "hello-world".to_string(),
+            tag: "latest".to_string(),
+            digest: None,
+        };
+        container_config(
+            &metadata,
+            options,
+            &ContainerLimits::default(),
+            "0x0",
+            TaskId::default(),
+        )
+        .host_config
+        .unwrap_or_default()
+    }
+
+    fn network_mode(options: &RunOptions) -> Option<String> {
+        host_config(options).network_mode
+    }
+
+    #[test]
+    fn test_container_network_mode() {
+        assert_eq!(
+            network_mode(&RunOptions::default()).as_deref(),
+            Some(NETWORK_MODE_NONE)
+        );
+
+        let options = RunOptions {
+            network_access: true,
+            ..Default::default()
+        };
+        assert_eq!(network_mode(&options).as_deref(), Some(NETWORK_MODE_BRIDGE));
+    }
+
+    #[test]
+    fn test_container_filesystem() {
+        let default = host_config(&RunOptions::default());
+        assert_eq!(default.readonly_rootfs, Some(true));
+        assert_eq!(
+            default.tmpfs,
+            Some(HashMap::from([(
+                SCRATCH_DIR.to_string(),
+                "rw,noexec,nosuid,size=64m".to_string()
+            )]))
+        );
+
+        let options = RunOptions {
+            writable_rootfs: true,
+            tmp_size_mb: Some(0),
+            ..Default::default()
+        };
+        let opted_out = host_config(&options);
+        assert_eq!(opted_out.readonly_rootfs, Some(false));
+        assert_eq!(opted_out.tmpfs, None);
+    }
+
+    #[test]
+    fn test_pinned_image_reference() {
+        let mut metadata = DockerImageMetadata {
+            repository: "giza/app".to_string(),
+            tag: "latest".to_string(),
+            digest: None,
+        };
+        assert_eq!(metadata.reference(), "giza/app:latest");
+
+        // A pinned image is run by digest rather than by the tag it was resolved from
+        metadata.digest = Some("sha256:e2fc4e5".to_string());
+        assert_eq!(metadata.reference(), "giza/app@sha256:e2fc4e5");
+        let config = container_config(
+            &metadata,
+            &RunOptions::default(),
+            &ContainerLimits::default(),
+            "0x0",
+            TaskId::default(),
+        );
+        assert_eq!(config.image.as_deref(), Some("giza/app@sha256:e2fc4e5"));
+    }
+
+    #[test]
+    fn test_container_limits() {
+        let metadata = DockerImageMetadata {
+            repository: "hello-world".to_string(),
+            tag: "latest".to_string(),
+            digest: None,
+        };
+        let limits = ContainerLimits {
+            timeout: Some(Duration::from_secs(60)),
+            memory: Some(256 * 1024 * 1024),
+            nano_cpus: Some(1_500_000_000),
+        };
+        let limited = container_config(
+            &metadata,
+            &RunOptions::default(),
+            &limits,
+            "0x0",
+            TaskId::default(),
+        )
+        .host_config
+        .unwrap_or_default();
+        assert_eq!(limited.memory, Some(256 * 1024 * 1024));
+        assert_eq!(limited.memory_swap, limited.memory);
+        assert_eq!(limited.nano_cpus, Some(1_500_000_000));
+
+        let unlimited = host_config(&RunOptions::default());
+        assert_eq!(
+            (unlimited.memory, unlimited.memory_swap, unlimited.nano_cpus),
+            (None, None, None)
+        );
+    }
+
+    // Serve a mock Docker daemon whose containers run until stopped, or until sent SIGTERM when
+    // `exits_on_sigterm`, recording the method, path and query of every request it gets
+    pub(crate) async fn mock_daemon(
+        exits_on_sigterm: bool,
+    ) -> (DockerHost, Arc<std::sync::Mutex<Vec<String>>>) {
+        use axum::{
+            http::{Method, StatusCode, Uri},
+            response::IntoResponse,
+            Json, Router,
+        };
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        let terminated = Arc::new(AtomicBool::new(false));
+        let app = Router::new().fallback(move |method: Method, uri: Uri| {
+            let requests = recorded.clone();
+            let terminated = terminated.clone();
+            async move {
+                let path = uri.path().to_string();
+                requests.lock().unwrap().push(match uri.query() {
+                    Some(query) => format!("{} {}?{}", method, path, query),
+                    None => format!("{} {}", method, path),
+                });
+                if path.ends_with("/containers/create") {
+                    (
+                        StatusCode::CREATED,
+                        Json(serde_json::json!({ "Id": "runaway", "Warnings": [] })),
+                    )
+                        .into_response()
+                } else if path.ends_with("/wait") {
+                    while !terminated.load(Ordering::Relaxed) {
+                        sleep(Duration::from_millis(5)).await;
+                    }
+                    Json(serde_json::json!({ "StatusCode": 143 })).into_response()
+                } else {
+                    if path.ends_with("/kill")
+                        && exits_on_sigterm
+                        && uri.query() == Some("signal=SIGTERM")
+                    {
+                        terminated.store(true, Ordering::Relaxed);
+                    }
+                    StatusCode::NO_CONTENT.into_response()
+                }
+            }
+        });
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (DockerHost::Tcp(address), requests)
+    }
+
+    #[tokio::test]
+    async fn test_stopped_containers_kept_or_removed() {
+        let metadata = DockerImageMetadata {
+            repository: "hello-world".to_string(),
+            tag: "latest".to_string(),
+            digest: None,
+        };
+
+        for keep_containers in [false, true] {
+            let (docker_host, requests) = mock_daemon(false).await;
+            let client = DockerClient::new(
+                Arc::new(connect("", Some(&docker_host)).unwrap()),
+                "0x0".to_string(),
+                1024,
+                LogTruncation::default(),
+                ContainerLimits::default(),
+                Duration::from_millis(10),
+                1,
+                None,
+                None,
+                "linux/amd64".to_string(),
+                keep_containers,
+            );
+            let removed = || {
+                requests
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .any(|request| request.starts_with("DELETE"))
+            };
+
+            // A timed out container is stopped, and only reaped when containers aren't kept
+            let limits = ContainerLimits {
+                timeout: Some(Duration::from_millis(50)),
+                ..ContainerLimits::default()
+            };
+            let run = client
+                .run_image_with_limits(
+                    &metadata,
+                    &RunOptions::default(),
+                    TaskId::default(),
+                    0,
+                    &CancellationToken::new(),
+                    &limits,
+                )
+                .await;
+            assert!(matches!(run, Err(RunError::TimedOut { .. })));
+            assert_eq!(removed(), !keep_containers);
+            requests.lock().unwrap().clear();
+
+            // So is the container of a cancelled task
+            let cancel = CancellationToken::new();
+            let cancel_later = cancel.clone();
+            tokio::spawn(async move {
+                sleep(Duration::from_millis(50)).await;
+                cancel_later.cancel();
+            });
+            let run = client
+                .run_image_with_limits(
+                    &metadata,
+                    &RunOptions::default(),
+                    TaskId::default(),
+                    1,
+                    &cancel,
+                    &ContainerLimits::default(),
+                )
+                .await;
+            assert!(matches!(run, Err(RunError::Cancelled)));
+            assert_eq!(removed(), !keep_containers);
+            // A kept container is killed rather than left running
+            let killed = requests
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|request| request.contains("/kill"));
+            assert_eq!(killed, keep_containers);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_runaway_container_stop_stages() {
+        let metadata = DockerImageMetadata {
+            repository: "hello-world".to_string(),
+            tag: "latest".to_string(),
+            digest: None,
+        };
+        let grace_period = Duration::from_millis(100);
+        let limits = ContainerLimits {
+            timeout: Some(Duration::from_millis(50)),
+            ..ContainerLimits::default()
+        };
+
+        // Containers exiting on SIGTERM are left to flush their output, the others are killed
+        for (exits_on_sigterm, killed_after) in [(true, None), (false, Some(grace_period))] {
+            let (docker_host, requests) = mock_daemon(exits_on_sigterm).await;
+            let client = DockerClient::new(
+                Arc::new(connect("", Some(&docker_host)).unwrap()),
+                "0x0".to_string(),
+                1024,
+                LogTruncation::default(),
+                ContainerLimits::default(),
+                grace_period,
+                1,
+                None,
+                None,
+                "linux/amd64".to_string(),
+                false,
+            );
+
+            let run = client
+                .run_image_with_limits(
+                    &metadata,
+                    &RunOptions::default(),
+                    TaskId::default(),
+                    0,
+                    &CancellationToken::new(),
+                    &limits,
+                )
+                .await;
+            match run {
+                Err(RunError::TimedOut {
+                    timeout,
+                    killed_after: stage,
+                }) => assert_eq!((timeout, stage), (Duration::from_millis(50), killed_after)),
+                _ => panic!("expected a timeout, got {:?}", run.map(|run| run.exit_code)),
+            }
+            let signals = requests
+                .lock()
+                .unwrap()
+                .iter()
+                .filter_map(|request| {
+                    request
+                        .split_once("/kill?signal=")
+                        .map(|(_, signal)| signal.to_string())
+                })
+                .collect::<Vec<_>>();
+            let expected = match killed_after {
+                None => vec!["SIGTERM"],
+                Some(_) => vec!["SIGTERM", "SIGKILL"],
+            };
+            assert_eq!(signals, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_container_timeout_override() {
+        let metadata = DockerImageMetadata {
+            repository: "hello-world".to_string(),
+            tag: "latest".to_string(),
+            digest: None,
+        };
+        let (docker_host, _) = mock_daemon(true).await;
+        let client = DockerClient::new(
+            Arc::new(connect("", Some(&docker_host)).unwrap()),
+            "0x0".to_string(),
+            1024,
+            LogTruncation::default(),
+            ContainerLimits {
+                timeout: Some(Duration::from_secs(3600)),
+                ..ContainerLimits::default()
+            },
+            Duration::from_millis(10),
+            1,
+            None,
+            None,
+            "linux/amd64".to_string(),
+            false,
+        );
+
+        // The app's own timeout replaces the configured one
+        let options = RunOptions {
+            timeout_secs: Some(1),
+            ..Default::default()
+        };
+        let run = client
+            .run_image(
+                &metadata,
+                &options,
+                TaskId::default(),
+                0,
+                &CancellationToken::new(),
+            )
+            .await;
+        match run {
+            Err(RunError::TimedOut { timeout, .. }) => assert_eq!(timeout, Duration::from_secs(1)),
+            _ => panic!("expected a timeout, got {:?}", run.map(|run| run.exit_code)),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use contract_bindings::AppId;
+use eyre::{eyre, Result, WrapErr};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+use tokio::process::Command;
+
+use crate::docker_client::DockerImageMetadata;
+
+/// `ImageVerifier` checks the cosign signature of client app images, so that tampered or
+/// unsigned images are never pulled or run.
+#[derive(Clone, Debug)]
+pub(super) struct ImageVerifier {
+    /// Path of the `cosign` binary.
+    cosign_path: String,
+    /// Public key each client app's image must be signed with.
+    keys: HashMap<AppId, PathBuf>,
+    /// Pinned images already verified for each client app, by digest. A digest names a single
+    /// image, so its signature only needs checking once.
+    verified: Arc<Mutex<HashSet<(AppId, String)>>>,
+}
+
+impl ImageVerifier {
+    pub fn new(cosign_path: String, keys: HashMap<AppId, PathBuf>) -> Self {
+        Self {
+            cosign_path,
+            keys,
+            verified: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Verifies the signature of the image of `client_app_id` with `cosign verify`, against
+    /// the public key configured for the app. A pinned image is verified by digest, and only
+    /// the first time that digest is seen for the app.
+    ///
+    /// # Errors
+    /// Returns an error if no key is configured for the app, if `cosign` can't be run, or if
+    /// the image is unsigned or its signature doesn't match the key.
+    pub async fn verify(&self, client_app_id: &AppId, image: &DockerImageMetadata) -> Result<()> {
+        let key = self.keys.get(client_app_id).ok_or_else(|| {
+            eyre!(
+                "No image signing key configured for ClientApp {:?}",
+                client_app_id
+            )
+        })?;
+        let verified = image
+            .digest
+            .as_ref()
+            .map(|digest| (*client_app_id, digest.clone()));
+        if verified
+            .as_ref()
+            .is_some_and(|verified| self.verified.lock().unwrap().contains(verified))
+        {
+            return Ok(());
+        }
+        let reference = image.reference();
+
+        let output = Command::new(&self.cosign_path)
+            .arg("verify")
+            .arg("--key")
+            .arg(key)
+            .arg(&reference)
+            .output()
+            .await
+            .wrap_err_with(|| format!("Failed to run {}", self.cosign_path))?;
+
+        if !output.status.success() {
+            return Err(eyre!(
+                "Signature verification of {} failed: {}",
+                reference,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        if let Some(verified) = verified {
+            self.verified.lock().unwrap().insert(verified);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image() -> DockerImageMetadata {
+        DockerImageMetadata {
+            repository: "library/hello-world".to_string(),
+            tag: "latest".to_string(),
+            digest: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_image() {
+        let app_id = AppId::default();
+        let keys = HashMap::from([(app_id, PathBuf::from("cosign.pub"))]);
+
+        // `true` and `false` stand in for a cosign accepting and rejecting the signature
+        let accepting = ImageVerifier::new("true".to_string(), keys.clone());
+        assert!(accepting.verify(&app_id, &image()).await.is_ok());
+
+        let rejecting = ImageVerifier::new("false".to_string(), keys);
+        assert!(rejecting.verify(&app_id, &image()).await.is_err());
+
+        // Apps without a key are rejected rather than run unverified
+        let no_keys = ImageVerifier::new("true".to_string(), HashMap::new());
+        assert!(no_keys.verify(&app_id, &image()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_verified_digests_are_cached() {
+        let app_id = AppId::default();
+        let keys = HashMap::from([(app_id, PathBuf::from("cosign.pub"))]);
+        let pinned = |digest: &str| DockerImageMetadata {
+            digest: Some(digest.to_string()),
+            ..image()
+        };
+
+        let accepting = ImageVerifier::new("true".to_string(), keys);
+        assert!(accepting
+            .verify(&app_id, &pinned("sha256:aa"))
+            .await
+            .is_ok());
+
+        // A rejecting cosign shows whether the signature is checked again: not for a digest
+        // already verified, but for a new one and for unpinned images
+        let rejecting = ImageVerifier {
+            cosign_path: "false".to_string(),
+            ..accepting
+        };
+        assert!(rejecting
+            .verify(&app_id, &pinned("sha256:aa"))
+            .await
+            .is_ok());
+        assert!(rejecting
+            .verify(&app_id, &pinned("sha256:bb"))
+            .await
+            .is_err());
+        assert!(rejecting.verify(&app_id, &image()).await.is_err());
+    }
+}
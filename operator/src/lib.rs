@@ -1,45 +1,77 @@
+mod aggregator_client;
+mod circuit_breaker;
 mod docker_client;
-mod operator_config;
+mod image_verifier;
+pub mod operator_config;
+mod result_cache;
+mod salt_store;
+mod status_server;
 
+use aggregator_client::AggregatorClient;
+pub use aggregator_client::{OperatorFailure, OperatorResponse};
 use alloy::{
+    eips::BlockNumberOrTag,
     network::{Ethereum, EthereumWallet},
     providers::{
         fillers::{
             BlobGasFiller, ChainIdFiller, FillProvider, GasFiller, JoinFill, NonceFiller,
             WalletFiller,
         },
-        Identity, IpcConnect, ProviderBuilder, RootProvider,
+        Identity, Provider, ProviderBuilder, RootProvider,
     },
     pubsub::PubSubFrontend,
-    signers::{local::PrivateKeySigner, Signer, SignerSync},
+    rpc::types::Log,
+    signers::{local::PrivateKeySigner, Signer},
+    sol_types::SolValue,
     transports::http::{Client, Http},
 };
-use alloy_primitives::{Address, FixedBytes, Signature, U256};
-use bollard::{Docker, API_DEFAULT_VERSION};
+use alloy_primitives::{keccak256, Address, FixedBytes, B256, U256};
+use circuit_breaker::CircuitBreaker;
 use contract_bindings::{
     AVSDirectory::AVSDirectoryInstance,
-    Chain,
-    ClientAppRegistry::ClientAppRegistryInstance,
+    AppId, Chain,
+    ClientAppRegistry::{self, ClientAppRegistryInstance},
+    ConfirmationBuffer,
     GizaAVS::GizaAVSInstance,
     ISignatureUtils::SignatureWithSaltAndExpiry,
+    OperatorAvsStatus, ResultTypes, TaskId,
     TaskRegistry::{self, TaskRegistryInstance},
-    AVS_DIRECTORY_ADDRESS, CLIENT_APP_REGISTRY_ADDRESS, GIZA_AVS_ADDRESS, TASK_REGISTRY_ADDRESS,
+    TaskTimings, AVS_DIRECTORY_ADDRESS, CLIENT_APP_REGISTRY_ADDRESS, GIZA_AVS_ADDRESS,
+    TASK_REGISTRY_ADDRESS,
 };
-use docker_client::DockerClient;
+use docker_client::{ContainerLimits, ContainerRun, DockerClient, DockerImageMetadata, RunError};
 use eyre::{Result, WrapErr};
 use futures::StreamExt;
-use operator_config::OperatorConfig;
+use image_verifier::ImageVerifier;
+use operator_config::{OnImagePullFailure, OperatorConfig, RunOptions};
 use reqwest::Client as HttpClient;
-use serde::Serialize;
-use std::time::Duration;
-use std::{str::FromStr, sync::Arc};
+use result_cache::{ResultCache, ResultCacheKey};
+use salt_store::SaltStore;
+use status_server::OperatorStats;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{
+    path::PathBuf,
+    str::FromStr,
+    sync::{atomic::Ordering, Arc, Mutex},
+};
 use tokio::{
     self,
-    sync::mpsc::{self, Receiver, Sender},
+    signal::unix::{signal, SignalKind},
+    sync::{
+        mpsc::{self, Receiver, Sender, WeakSender},
+        Mutex as AsyncMutex,
+    },
     task::JoinHandle,
-    time::sleep,
+    time::{sleep, timeout},
 };
-use tracing::{error, info, warn};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, info_span, warn, Instrument};
+
+// Crate version and the git commit it was built from
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const GIT_SHA: &str = env!("GIT_SHA");
 
 pub type HttpProviderWithSigner = Arc<
     FillProvider<
@@ -59,50 +91,122 @@ pub type HttpProviderWithSigner = Arc<
 // Adjust this based on your expected load and system resources
 const QUEUE_CAPACITY: usize = 100;
 
-#[derive(Serialize)]
-pub struct OperatorResponse {
-    task_id: FixedBytes<32>,
-    result: String,
-    signature: Signature,
+// Block client app registrations are queried from
+const CLIENT_APP_REGISTRY_FROM_BLOCK: u64 = 2577255;
+
+// Interval of the TCP keep-alive probes on connections to the aggregator
+const HTTP_TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+
+// Delay before the first retry of a failed client app metadata call, doubled after every
+// failed attempt
+const METADATA_RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+// Number of times a task is requeued when its client app metadata can't be fetched
+const MAX_TASK_REQUEUES: u32 = 3;
+
+// Delay before a requeued task is processed again
+const TASK_REQUEUE_DELAY: Duration = Duration::from_secs(30);
+
+// A task event waiting to be processed, along with the time it was received
+struct QueuedTask {
+    task: TaskRegistry::TaskRequested,
+    // Block the task was requested in, `None` if the node didn't tell
+    block_number: Option<u64>,
+    received_at: Instant,
+    // Number of times the task was requeued after a transient failure
+    requeues: u32,
+}
+
+// Queue of the confirmed tasks, held by the task processor while it runs so that the tasks it
+// didn't start can be reported on shutdown
+type TaskQueue = Arc<AsyncMutex<Receiver<QueuedTask>>>;
+
+// A task being processed, which can be cancelled to stop its container and abandon its
+// submission
+struct RunningTask {
+    client_app_id: AppId,
+    cancel: CancellationToken,
+}
+
+// Stops tracking a running task once its processing is over, however it ended
+struct RunningTaskGuard {
+    task_id: TaskId,
+    running_tasks: Arc<Mutex<HashMap<TaskId, RunningTask>>>,
+}
+
+impl Drop for RunningTaskGuard {
+    fn drop(&mut self) {
+        self.running_tasks.lock().unwrap().remove(&self.task_id);
+    }
 }
 
 #[derive(Clone)]
 pub struct Operator {
     operator_address: Address,
-    aggregator_url: String,
+    // Name every log line is tagged with, the operator address when none is configured
+    name: String,
+    // Signs the outcome of tasks and posts it to the aggregators
+    aggregator: AggregatorClient,
     pubsub_provider: Arc<RootProvider<PubSubFrontend>>,
     http_provider: HttpProviderWithSigner,
     ecdsa_signer: PrivateKeySigner,
     docker: DockerClient,
+    result_types: ResultTypes,
+    local_runs: u32,
+    client_app_allowlist: HashSet<AppId>,
+    // Registries client app images may be pulled from, empty to allow every registry
+    registry_allowlist: Vec<String>,
+    // Whether startup fails when no registered client app is served
+    require_client_apps: bool,
+    // Blocks a task event must be buried under before the task is processed
+    task_confirmations: u64,
+    run_options: HashMap<AppId, RunOptions>,
+    metadata_max_attempts: u32,
+    // Verifies client app images before they are pulled or run, `None` when disabled
+    image_verifier: Option<ImageVerifier>,
+    on_image_pull_failure: OnImagePullFailure,
+    // Time without events or blocks after which the listener checks that its subscriptions are
+    // alive, `None` when disabled
+    listener_watchdog: Option<Duration>,
+    // Time startup waits for an aggregator to be ready, `None` when it doesn't wait
+    aggregator_wait_timeout: Option<Duration>,
+    // Age above which a task is skipped rather than run, `None` when disabled
+    max_task_age: Option<Duration>,
+    // Client apps whose image failed to pull and hasn't been pulled since
+    unpulled_apps: Arc<Mutex<HashSet<AppId>>>,
+    // Tasks being processed, by id
+    running_tasks: Arc<Mutex<HashMap<TaskId, RunningTask>>>,
+    // Cancelled when the operator is asked to stop, after which no queued task is started
+    shutdown: CancellationToken,
+    // Time the running task is given to finish on shutdown before it is abandoned
+    shutdown_drain_timeout: Duration,
+    // Whether a new task of a client app cancels its older ones
+    cancel_superseded_tasks: bool,
+    // Latest confirmed task of each client app, tracked when superseded tasks are cancelled
+    latest_tasks: Arc<Mutex<HashMap<AppId, TaskId>>>,
+    // Address of the health and stats server, `None` when disabled
+    status_server_addr: Option<SocketAddr>,
+    // Token required to prefetch images through the status server, loopback only when `None`
+    status_admin_token: Option<String>,
+    stats: Arc<OperatorStats>,
+    // Where the salts used to sign AVS registrations are persisted, opened only on registration
+    // so that commands which don't register don't need a writable configuration directory
+    salt_store_path: PathBuf,
+    // Outputs of deterministic client apps reused by identical tasks, `None` when disabled
+    result_cache: Option<Arc<ResultCache>>,
+    // Skips the tasks of client apps that keep failing, `None` when disabled
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
 }
 
 impl Operator {
-    pub async fn new(private_key: &str, chain: Chain) -> Result<Self> {
-        // Load operator configuration
-        let config = OperatorConfig::from_env(private_key);
-
+    pub async fn new(chain: Chain, config: OperatorConfig) -> Result<Self> {
+        let container_memory = config.container_memory_bytes();
         let ecdsa_signer = config.ecdsa_signer;
         let operator_address = ecdsa_signer.address();
         let wallet = EthereumWallet::from(ecdsa_signer.clone());
 
         //Create PubSubProvider
-        let pubsub_provider: Arc<RootProvider<PubSubFrontend>> = match chain {
-            Chain::Anvil => {
-                let ipc = IpcConnect::new("/tmp/anvil.ipc".to_string());
-                ProviderBuilder::new()
-                    .on_ipc(ipc)
-                    .await
-                    .wrap_err("Failed to create Anvil IPC provider")?
-            }
-            Chain::Holesky => ProviderBuilder::new()
-                .on_ws(alloy::providers::WsConnect {
-                    url: chain.ws_url().to_string(),
-                    auth: None,
-                })
-                .await
-                .wrap_err("Failed to create Holesky WebSocket provider")?,
-        }
-        .into();
+        let pubsub_provider = Arc::new(chain.connect_pubsub().await?);
 
         //Create HttpProvider
         let rpc_url = chain.http_url();
@@ -114,44 +218,273 @@ impl Operator {
                 .on_http(rpc_url),
         );
 
-        let docker_connection = Arc::new(Docker::connect_with_socket(
-            config.docker_sock_path.as_str(),
-            120,
-            API_DEFAULT_VERSION,
+        let docker_connection = Arc::new(docker_client::connect(
+            &config.docker_sock_path,
+            config.docker_host.as_ref(),
         )?);
 
-        let docker = DockerClient::new(docker_connection, operator_address.to_string());
+        let docker = DockerClient::new(
+            docker_connection,
+            operator_address.to_string(),
+            config.max_container_log_size,
+            config.container_log_truncation,
+            ContainerLimits {
+                timeout: (config.container_timeout_secs > 0)
+                    .then(|| Duration::from_secs(config.container_timeout_secs)),
+                memory: container_memory,
+                nano_cpus: (config.container_cpus > 0.0)
+                    .then_some((config.container_cpus * 1_000_000_000.0) as i64),
+            },
+            Duration::from_secs(config.container_stop_grace_secs),
+            config.image_pull_max_attempts,
+            (config.image_pull_stall_timeout_secs > 0)
+                .then(|| Duration::from_secs(config.image_pull_stall_timeout_secs)),
+            (config.image_pull_progress_interval_secs > 0)
+                .then(|| Duration::from_secs(config.image_pull_progress_interval_secs)),
+            config.container_platform,
+            config.keep_containers,
+        );
+        if config.keep_containers {
+            warn!("Keeping exited task containers for debugging, they accumulate until the next startup");
+        }
+
+        let mut http_client = HttpClient::builder()
+            .pool_max_idle_per_host(config.http_pool_max_idle)
+            .pool_idle_timeout(Duration::from_secs(config.http_pool_idle_timeout_secs))
+            .tcp_keepalive(HTTP_TCP_KEEPALIVE);
+        if let Some(ca_cert_path) = &config.aggregator_ca_cert_path {
+            let ca_cert = std::fs::read(ca_cert_path)
+                .wrap_err_with(|| format!("Failed to read {}", ca_cert_path.display()))?;
+            http_client = http_client.add_root_certificate(
+                reqwest::Certificate::from_pem(&ca_cert)
+                    .wrap_err("Failed to parse the aggregator CA certificate")?,
+            );
+        }
+        let http_client = http_client
+            .build()
+            .wrap_err("Failed to create HTTP client")?;
 
         Ok(Self {
             operator_address,
+            name: config
+                .operator_name
+                .unwrap_or_else(|| operator_address.to_string()),
             pubsub_provider,
             http_provider,
+            aggregator: AggregatorClient::new(
+                config.aggregator_urls,
+                http_client,
+                ecdsa_signer.clone(),
+                config.response_version,
+            ),
             ecdsa_signer,
             docker,
-            aggregator_url: config.aggregator_url,
+            result_types: config.result_types,
+            local_runs: config.local_runs,
+            client_app_allowlist: config.client_app_allowlist.into_iter().collect(),
+            registry_allowlist: config.registry_allowlist,
+            require_client_apps: config.require_client_apps,
+            task_confirmations: config
+                .task_confirmations
+                .unwrap_or_else(|| chain.default_task_confirmations()),
+            run_options: config.run_options,
+            metadata_max_attempts: config.metadata_max_attempts,
+            image_verifier: config
+                .verify_image_signatures
+                .then(|| ImageVerifier::new(config.cosign_path, config.image_signing_keys)),
+            on_image_pull_failure: config.on_image_pull_failure,
+            listener_watchdog: (config.listener_watchdog_secs > 0)
+                .then(|| Duration::from_secs(config.listener_watchdog_secs)),
+            aggregator_wait_timeout: (config.aggregator_wait_timeout_secs > 0)
+                .then(|| Duration::from_secs(config.aggregator_wait_timeout_secs)),
+            max_task_age: (config.max_task_age_secs > 0)
+                .then(|| Duration::from_secs(config.max_task_age_secs)),
+            unpulled_apps: Arc::new(Mutex::new(HashSet::new())),
+            running_tasks: Arc::new(Mutex::new(HashMap::new())),
+            shutdown: CancellationToken::new(),
+            shutdown_drain_timeout: Duration::from_secs(config.shutdown_drain_timeout_secs),
+            cancel_superseded_tasks: config.cancel_superseded_tasks,
+            latest_tasks: Arc::new(Mutex::new(HashMap::new())),
+            status_server_addr: config.status_server_addr,
+            status_admin_token: config.status_admin_token,
+            stats: Arc::new(OperatorStats::default()),
+            salt_store_path: config.salt_store_path,
+            result_cache: ResultCache::new(
+                config.result_cache_apps.into_iter().collect(),
+                config.result_cache_size,
+            )
+            .map(Arc::new),
+            circuit_breaker: CircuitBreaker::new(
+                config.circuit_breaker_threshold,
+                Duration::from_secs(config.circuit_breaker_cooldown_secs),
+            )
+            .map(Arc::new),
         })
     }
 
     pub async fn run(&self) -> Result<()> {
+        // Every log line carries the operator name, so the logs of a fleet can be told apart
+        let span = info_span!("operator", name = %self.name);
+        self.start().instrument(span).await
+    }
+
+    async fn start(&self) -> Result<()> {
         info!("Starting operator...");
 
         self.register_operator_in_avs().await?;
 
+        // Containers left behind by a crash would collide with the ones of new tasks. Failing to
+        // remove them only affects the tasks they collide with, so the operator starts anyway.
+        if let Err(e) = self.docker.remove_orphaned_containers(false).await {
+            error!("Failed to remove orphaned containers: {:?}", e);
+        }
+
         self.fetch_client_app().await?;
 
+        // The status server only reports the operator healthy once it waited for an aggregator
+        if let Some(timeout) = self.aggregator_wait_timeout {
+            self.aggregator.wait_for_aggregators(timeout).await;
+        }
+
+        if let Some(addr) = self.status_server_addr {
+            let operator = Arc::new(self.clone());
+            tokio::spawn(
+                async move {
+                    if let Err(e) = status_server::serve(operator, addr).await {
+                        error!("{:?}", e);
+                    }
+                }
+                .in_current_span(),
+            );
+        }
+
         // Create a bounded channel for task communication
         // NOTE: Using a bounded channel helps with backpressure, preventing the event listener from overwhelming the task processor. However, if the
         // channel becomes full, it may block the event listener.
-        let (tx, rx) = mpsc::channel::<TaskRegistry::TaskRequested>(QUEUE_CAPACITY);
+        let (tx, rx) = mpsc::channel::<QueuedTask>(QUEUE_CAPACITY);
+        let task_queue: TaskQueue = Arc::new(AsyncMutex::new(rx));
+
+        // Tasks that failed transiently are sent back to the queue by the processor, through a
+        // weak sender so that the queue still closes once the event listener stops
+        let tx_requeue = tx.downgrade();
 
         // Spawn the event listener task
-        let event_listener = tokio::spawn(self.clone().listen_for_events(tx));
+        let event_listener = tokio::spawn(self.clone().listen_for_events(tx).in_current_span());
 
         // Spawn the task processor
-        let task_processor = tokio::spawn(self.clone().process_tasks(rx));
+        let task_processor = tokio::spawn(
+            self.clone()
+                .process_tasks(task_queue.clone(), tx_requeue)
+                .in_current_span(),
+        );
 
         // Wait for both tasks to complete or handle errors
-        self.handle_tasks(event_listener, task_processor).await?;
+        self.handle_tasks(event_listener, task_processor, task_queue)
+            .await?;
+
+        Ok(())
+    }
+
+    // Pull the images of every served client app and return, without registering in the AVS or
+    // listening for tasks, so operators can warm their image cache before going live. Fails when
+    // an image couldn't be pulled.
+    pub async fn prefetch(&self) -> Result<()> {
+        let span = info_span!("operator", name = %self.name);
+        async {
+            self.fetch_client_app().await?;
+
+            let unpulled_apps = self.unpulled_apps.lock().unwrap().clone();
+            if !unpulled_apps.is_empty() {
+                return Err(eyre::eyre!(
+                    "Failed to pull the images of ClientApps {:?}",
+                    unpulled_apps
+                ));
+            }
+
+            info!(
+                "Pulled the images of {} ClientApps",
+                self.stats.cached_images.lock().unwrap().len()
+            );
+            Ok(())
+        }
+        .instrument(span)
+        .await
+    }
+
+    // Print the operator's registration in GizaAVS and EigenLayer, and its opt-in status for
+    // the client apps it serves. Only reads from the chain: without an allowlist, every
+    // registered client app is looked up from the registry events, which takes a while.
+    pub async fn status(&self) -> Result<()> {
+        let giza_avs = GizaAVSInstance::new(GIZA_AVS_ADDRESS, self.http_provider.clone());
+        let avs_directory =
+            AVSDirectoryInstance::new(AVS_DIRECTORY_ADDRESS, self.http_provider.clone());
+        let client_app_registry =
+            ClientAppRegistryInstance::new(CLIENT_APP_REGISTRY_ADDRESS, self.http_provider.clone());
+
+        let is_operator_registered = giza_avs
+            .isOperatorRegistered(self.operator_address)
+            .call()
+            .await?
+            .isRegistered;
+
+        let avs_operator_status: OperatorAvsStatus = avs_directory
+            .avsOperatorStatus(GIZA_AVS_ADDRESS, self.operator_address)
+            .call()
+            .await?
+            ._0
+            .into();
+
+        let clients_list = if self.client_app_allowlist.is_empty() {
+            client_app_registry
+                .ClientAppRegistered_filter()
+                .from_block(CLIENT_APP_REGISTRY_FROM_BLOCK)
+                .query()
+                .await?
+                .into_iter()
+                .map(|(client_app_id, _)| AppId::from(client_app_id.clientAppId))
+                .collect::<Vec<_>>()
+        } else {
+            let mut clients_list = self
+                .client_app_allowlist
+                .iter()
+                .copied()
+                .collect::<Vec<_>>();
+            clients_list.sort();
+            clients_list
+        };
+
+        let registration = |registered: bool| match registered {
+            true => "registered",
+            false => "not registered",
+        };
+
+        println!("Operator {:?}", self.operator_address);
+        println!("  GizaAVS:      {}", registration(is_operator_registered));
+        match avs_operator_status {
+            OperatorAvsStatus::UNKNOWN(value) => {
+                println!("  AVSDirectory: unknown status {}", value)
+            }
+            status => println!(
+                "  AVSDirectory: {}",
+                registration(status == OperatorAvsStatus::REGISTERED)
+            ),
+        }
+
+        for client_app_id in clients_list {
+            let is_opted_in = giza_avs
+                .operatorClientAppIdRegistrationStatus(self.operator_address, client_app_id.into())
+                .call()
+                .await?
+                .isRegistered;
+            println!(
+                "  Client app {:?}: {}",
+                client_app_id,
+                match is_opted_in {
+                    true => "opted in",
+                    false => "not opted in",
+                }
+            );
+        }
 
         Ok(())
     }
@@ -173,10 +506,24 @@ impl Operator {
             return Ok(());
         }
 
-        let salt = FixedBytes::<32>::from_str(
-            "0x2ef06b8bbad022ca2dd29795902ceb588d06d1cfd10cb6e687db0dbb837865e9",
-        )
-        .unwrap();
+        // A registration signed with a salt already spent on-chain reverts, so every attempt
+        // gets a salt never handed out before, regenerated if an unknown attempt spent it
+        let salt_store = SaltStore::open(&self.salt_store_path)?;
+        let salt = loop {
+            let salt = salt_store.new_salt()?;
+            let is_spent = avs_directory
+                .operatorSaltIsSpent(self.operator_address, salt)
+                .call()
+                .await?
+                ._0;
+            if !is_spent {
+                break salt;
+            }
+            warn!(
+                "Registration salt {:?} already spent, generating another",
+                salt
+            );
+        };
         let expiry = U256::from(1779248899);
 
         // Eigenlayer provide a view function to calculate the digest hash that needs to be signed
@@ -231,7 +578,7 @@ impl Operator {
         .unwrap();
 
         let is_client_app_registered = giza_avs
-            .operatorClientAppIdRegistrationStatus(self.operator_address, client_app_id.clone())
+            .operatorClientAppIdRegistrationStatus(self.operator_address, client_app_id)
             .call()
             .await?
             .isRegistered;
@@ -250,7 +597,7 @@ impl Operator {
         info!("Operator successfully opted-in for Client app {:?}", tx);
 
         let is_client_app_registered = giza_avs
-            .operatorClientAppIdRegistrationStatus(self.operator_address, client_app_id.clone())
+            .operatorClientAppIdRegistrationStatus(self.operator_address, client_app_id)
             .call()
             .await?
             .isRegistered;
@@ -272,191 +619,1591 @@ impl Operator {
 
         let clients_list = client_app_registry
             .ClientAppRegistered_filter()
-            .from_block(2577255)
+            .from_block(CLIENT_APP_REGISTRY_FROM_BLOCK)
             .query()
             .await?
             .into_iter()
-            .map(|(client_app_id, _)| client_app_id.clientAppId)
+            .map(|(client_app_id, _)| AppId::from(client_app_id.clientAppId))
             .collect::<Vec<_>>();
 
+        // An operator serving no app idles silently, which usually means it looks at the wrong
+        // place rather than at an empty registry
+        if !clients_list
+            .iter()
+            .any(|client_app_id| self.serves_client_app(client_app_id))
+        {
+            let reason = match clients_list.len() {
+                0 => format!(
+                    "No ClientApp is registered in the ClientAppRegistry at {:?} since block {} on chain {}, check the chain, the block registrations are queried from and the registry address",
+                    CLIENT_APP_REGISTRY_ADDRESS,
+                    CLIENT_APP_REGISTRY_FROM_BLOCK,
+                    self.http_provider
+                        .get_chain_id()
+                        .await
+                        .map_or_else(|_| "unknown".to_string(), |chain_id| chain_id.to_string())
+                ),
+                registered => format!(
+                    "None of the {} registered ClientApps is in the allowlist, check CLIENT_APP_ALLOWLIST",
+                    registered
+                ),
+            };
+            if self.require_client_apps {
+                return Err(eyre::eyre!(reason));
+            }
+            warn!("{}, the operator will not process any task", reason);
+        }
+
         // Download the Docker images of the client apps
-        for client_app_id in &clients_list {
-            info!("Getting metadata of ClientApp: {:?}", client_app_id);
+        for client_app_id in clients_list {
+            if !self.serves_client_app(&client_app_id) {
+                debug!(
+                    "Skipping ClientApp not in the allowlist: {:?}",
+                    client_app_id
+                );
+                continue;
+            }
 
-            let app_metadata = match client_app_registry
-                .getClientAppMetadata(client_app_id.clone())
-                .call()
-                .await
-            {
-                Ok(metadata) => metadata._0,
-                _ => {
-                    error!("Error getting client app metadata");
-                    continue;
-                }
-            };
+            if let Err(e) = self.prefetch_client_app(client_app_id).await {
+                error!("{:?}", e);
+            }
+        }
 
-            info!("Getting image from: {:?}", app_metadata.dockerUrl);
+        Ok(())
+    }
 
-            let image_metadata = match self.docker.image_metadata(app_metadata.dockerUrl.as_str()) {
-                Ok(metadata) => metadata,
-                _ => {
-                    error!("Error getting image metadata");
-                    continue;
-                }
-            };
+    // Pull the image of a client app, after verifying it. A successful pull closes the circuit
+    // breaker of the app, so a fixed image is run again without waiting for the cooldown.
+    pub(crate) async fn prefetch_client_app(&self, client_app_id: AppId) -> Result<()> {
+        info!("Getting metadata of ClientApp: {:?}", client_app_id);
 
-            match self.docker.pull_image(&image_metadata).await {
-                Err(e) => {
-                    error!("Error pulling image: {:?}", e);
-                    continue;
-                }
-                _ => info!(
-                    "Pulled successfully image: {:?}:{:?}",
-                    image_metadata.repository, image_metadata.tag
-                ),
-            }
+        let app_metadata = self
+            .client_app_metadata(client_app_id)
+            .await
+            .wrap_err("Error getting client app metadata")?
+            .ok_or_else(|| eyre::eyre!("ClientApp {:?} has no metadata", client_app_id))?;
+
+        info!("Getting image from: {:?}", app_metadata.dockerUrl);
+
+        let image_metadata = self
+            .docker
+            .image_metadata(app_metadata.dockerUrl.as_str())
+            .wrap_err("Error getting image metadata")?;
+
+        let image_metadata = self
+            .verify_image(&client_app_id, image_metadata)
+            .await
+            .wrap_err_with(|| format!("Not pulling image of ClientApp {:?}", client_app_id))?;
+
+        if let Err(e) = self.docker.pull_image(&image_metadata).await {
+            self.unpulled_apps.lock().unwrap().insert(client_app_id);
+            return Err(e).wrap_err_with(|| {
+                format!(
+                    "Error pulling image of ClientApp {:?}, handling its tasks with {:?}",
+                    client_app_id, self.on_image_pull_failure
+                )
+            });
+        }
+
+        info!(
+            "Pulled successfully image: {:?}",
+            image_metadata.reference()
+        );
+        self.unpulled_apps.lock().unwrap().remove(&client_app_id);
+        self.stats
+            .cached_images
+            .lock()
+            .unwrap()
+            .insert(client_app_id);
+        if self
+            .circuit_breaker
+            .as_ref()
+            .is_some_and(|circuit_breaker| circuit_breaker.reset(&client_app_id))
+        {
+            info!(
+                "Closed the circuit breaker of ClientApp {:?} after a successful prefetch",
+                client_app_id
+            );
         }
 
         Ok(())
     }
 
-    async fn listen_for_events(self, tx: Sender<TaskRegistry::TaskRequested>) -> Result<()> {
+    async fn listen_for_events(self, tx: Sender<QueuedTask>) -> Result<()> {
         let task_registry =
             TaskRegistryInstance::new(TASK_REGISTRY_ADDRESS, self.pubsub_provider.clone());
+        let mut unconfirmed = ConfirmationBuffer::new(self.task_confirmations);
 
-        // Create a stream of events from the the TaskRequested filter, will block until there an incoming event
-        let mut stream = task_registry
-            .TaskRequested_filter()
-            .subscribe()
-            .await
-            .wrap_err("Failed to subscribe to TaskRegistry events")?
-            .into_stream();
-
-        info!("Subscribed to TaskRegistry events. Waiting for events...");
-
-        while let Some(log) = stream.next().await {
-            match log {
-                Ok(event) => {
-                    // Send the task to the processing queue
-                    // NOTE: If the channel is full, this will block until there's space.
-                    // Consider using `try_send` or implementing a timeout mechanism
-                    // to prevent indefinite blocking.
-                    if let Err(e) = tx.send(event.0).await {
-                        error!("Error sending task to queue: {:?}", e);
-                        // TODO(eduponz): Implement retry logic or error handling strategy here
+        // Latest block known to the listener, compared against the chain by the watchdog and
+        // backfilled from after resubscribing
+        let mut last_block: Option<u64> = None;
+        // Events of the blocks the backfill may return again, by block hash and log index
+        let mut seen_events: HashMap<(B256, u64), u64> = HashMap::new();
+
+        // Subscriptions are rebuilt whenever they end or the watchdog finds them dead
+        loop {
+            // Create a stream of events from the the TaskRequested filter, will block until there an incoming event
+            let mut stream = task_registry
+                .TaskRequested_filter()
+                .subscribe()
+                .await
+                .wrap_err("Failed to subscribe to TaskRegistry events")?
+                .into_stream();
+
+            // New blocks tell how deep the buffered events are
+            let mut blocks = self
+                .pubsub_provider
+                .subscribe_blocks()
+                .await
+                .wrap_err("Failed to subscribe to new blocks")?
+                .into_stream();
+
+            // Events emitted while resubscribing only show up in the logs. The new subscription
+            // is already up, so the backfill overlaps with it rather than leaving a gap.
+            if let Some(from_block) = last_block {
+                match self.missed_events(from_block).await {
+                    Ok(events) => {
+                        info!(
+                            "Backfilled {} TaskRegistry events since block {}",
+                            events.len(),
+                            from_block
+                        );
+                        for (task, log) in events {
+                            self.handle_event(&tx, &mut unconfirmed, &mut seen_events, task, log)
+                                .await;
+                        }
+                    }
+                    Err(e) => error!(
+                        "Failed to backfill TaskRegistry events since block {}: {:?}",
+                        from_block, e
+                    ),
+                }
+            }
+            last_block = self
+                .http_provider
+                .get_block_number()
+                .await
+                .ok()
+                .or(last_block);
+
+            info!(
+                "Subscribed to TaskRegistry events, processing them after {} confirmations. Waiting for events...",
+                self.task_confirmations
+            );
+
+            loop {
+                tokio::select! {
+                    log = stream.next() => match log {
+                        Some(Ok((task, log))) => {
+                            self.handle_event(&tx, &mut unconfirmed, &mut seen_events, task, log)
+                                .await;
+                        }
+                        Some(Err(e)) => error!("Error receiving event: {:?}", e),
+                        None => {
+                            warn!("TaskRegistry event subscription ended, resubscribing");
+                            break;
+                        }
+                    },
+                    block = blocks.next() => {
+                        let Some(block) = block else {
+                            warn!("Block subscription ended, resubscribing");
+                            break;
+                        };
+                        last_block = Some(block.header.number);
+                        // Only events of the latest block can be returned again by a backfill
+                        seen_events.retain(|_, block_number| *block_number >= block.header.number);
+                        let (confirmed, reorged) = unconfirmed
+                            .take_confirmed(&*self.pubsub_provider, block.header.number)
+                            .await;
+                        for queued_task in reorged {
+                            warn!(
+                                "Discarding task reorged out before confirmation: \x1b[1;33m{:?}\x1b[0m",
+                                queued_task.task.taskId
+                            );
+                        }
+                        for queued_task in confirmed {
+                            self.supersede_tasks(&queued_task.task);
+                            Self::queue_task(&tx, queued_task).await;
+                        }
+                    }
+                    // Nothing arrived for a whole interval: the chain is either idle or the
+                    // subscriptions silently died, which only the chain moving on tells apart
+                    _ = sleep(self.listener_watchdog.unwrap_or_default()), if self.listener_watchdog.is_some() => {
+                        match self.http_provider.get_block_number().await {
+                            Ok(block_number) if last_block.is_some_and(|last| block_number > last) => {
+                                warn!(
+                                    "No events or blocks received for {:?} while the chain reached block {}, resubscribing",
+                                    self.listener_watchdog.unwrap_or_default(),
+                                    block_number
+                                );
+                                break;
+                            }
+                            Ok(_) => debug!("No new blocks, the chain is idle"),
+                            Err(e) => warn!("Failed to probe the chain for new blocks: {:?}", e),
+                        }
                     }
                 }
-                Err(e) => error!("Error receiving event: {:?}", e),
             }
         }
+    }
 
-        Ok(())
+    // TaskRequested events emitted since block `from_block`, which the subscriptions may have
+    // missed while they were down
+    async fn missed_events(
+        &self,
+        from_block: u64,
+    ) -> Result<Vec<(TaskRegistry::TaskRequested, Log)>> {
+        TaskRegistryInstance::new(TASK_REGISTRY_ADDRESS, self.http_provider.clone())
+            .TaskRequested_filter()
+            .from_block(from_block)
+            .query()
+            .await
+            .wrap_err("Failed to query TaskRegistry events")
     }
 
-    async fn process_tasks(self, mut rx: Receiver<TaskRegistry::TaskRequested>) -> Result<()> {
-        let client_app_registry =
-            ClientAppRegistryInstance::new(CLIENT_APP_REGISTRY_ADDRESS, self.http_provider.clone());
-        let http_client = HttpClient::new();
+    // Queue the task of a TaskRequested event, or buffer it until confirmed. Events already
+    // handled, as returned again by a backfill, are skipped.
+    async fn handle_event(
+        &self,
+        tx: &Sender<QueuedTask>,
+        unconfirmed: &mut ConfirmationBuffer<QueuedTask>,
+        seen_events: &mut HashMap<(B256, u64), u64>,
+        task: TaskRegistry::TaskRequested,
+        log: Log,
+    ) {
+        if let (Some(block_hash), Some(block_number)) = (log.block_hash, log.block_number) {
+            let event = (block_hash, log.log_index.unwrap_or_default());
+            // A removed log undoes the event, the same event may be mined again afterwards
+            if log.removed {
+                seen_events.remove(&event);
+            } else if seen_events.insert(event, block_number).is_some() {
+                debug!(
+                    "Skipping already received task: \x1b[1;33m{:?}\x1b[0m",
+                    task.taskId
+                );
+                return;
+            }
+            self.stats.record_event_block(block_number);
+        }
+
+        let queued_task = QueuedTask {
+            task,
+            block_number: log.block_number,
+            received_at: Instant::now(),
+            requeues: 0,
+        };
+        if self.task_confirmations == 0 {
+            self.supersede_tasks(&queued_task.task);
+            Self::queue_task(tx, queued_task).await;
+        } else {
+            unconfirmed.push(queued_task, &log);
+        }
+    }
+
+    // Send a confirmed task to the processing queue
+    async fn queue_task(tx: &Sender<QueuedTask>, queued_task: QueuedTask) {
+        // NOTE: If the channel is full, this will block until there's space.
+        // Consider using `try_send` or implementing a timeout mechanism
+        // to prevent indefinite blocking.
+        if let Err(e) = tx.send(queued_task).await {
+            error!("Error sending task to queue: {:?}", e);
+            // TODO(eduponz): Implement retry logic or error handling strategy here
+        }
+    }
+
+    // Record `task` as the latest one of its client app and cancel the older ones still running,
+    // when superseded tasks are cancelled. Older tasks still queued are skipped when dequeued.
+    fn supersede_tasks(&self, task: &TaskRegistry::TaskRequested) {
+        if !self.cancel_superseded_tasks {
+            return;
+        }
+
+        let client_app_id = AppId::from(task.taskRequest.appId);
+        let task_id = TaskId::from(task.taskId);
+        self.latest_tasks
+            .lock()
+            .unwrap()
+            .insert(client_app_id, task_id);
+
+        for (running_task_id, running_task) in self.running_tasks.lock().unwrap().iter() {
+            if running_task.client_app_id == client_app_id && *running_task_id != task_id {
+                info!(
+                    "Cancelling task \x1b[1;33m{:?}\x1b[0m, superseded by \x1b[1;33m{:?}\x1b[0m",
+                    running_task_id, task_id
+                );
+                running_task.cancel.cancel();
+            }
+        }
+    }
+
+    // Time elapsed since the block a task was requested in, `None` if the block or its timestamp
+    // can't be fetched
+    async fn task_age(&self, block_number: Option<u64>) -> Option<Duration> {
+        let block = self
+            .http_provider
+            .get_block_by_number(BlockNumberOrTag::Number(block_number?), false)
+            .await
+            .inspect_err(|e| warn!("Failed to fetch block {:?}: {:?}", block_number, e))
+            .ok()??;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+        Some(now.saturating_sub(Duration::from_secs(block.header.timestamp)))
+    }
+
+    // Whether a newer task of the same client app was confirmed since `task_id`
+    fn is_superseded(&self, client_app_id: &AppId, task_id: &TaskId) -> bool {
+        self.latest_tasks
+            .lock()
+            .unwrap()
+            .get(client_app_id)
+            .is_some_and(|latest| latest != task_id)
+    }
+
+    // Cancel a running task, e.g. through the status server: its container is killed and
+    // removed, and a failure is reported for it instead of a result. Returns whether the task
+    // was running.
+    pub(crate) fn cancel_task(&self, task_id: TaskId) -> bool {
+        match self.running_tasks.lock().unwrap().get(&task_id) {
+            Some(running_task) => {
+                info!("Cancelling task \x1b[1;33m{:?}\x1b[0m", task_id);
+                running_task.cancel.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Track a task as running until the returned guard is dropped, returning the token that
+    // cancels it
+    fn start_task(
+        &self,
+        task_id: TaskId,
+        client_app_id: AppId,
+    ) -> (CancellationToken, RunningTaskGuard) {
+        let cancel = CancellationToken::new();
+        self.running_tasks.lock().unwrap().insert(
+            task_id,
+            RunningTask {
+                client_app_id,
+                cancel: cancel.clone(),
+            },
+        );
+        let guard = RunningTaskGuard {
+            task_id,
+            running_tasks: self.running_tasks.clone(),
+        };
+        (cancel, guard)
+    }
+
+    async fn process_tasks(
+        self,
+        task_queue: TaskQueue,
+        tx_requeue: WeakSender<QueuedTask>,
+    ) -> Result<()> {
+        let mut rx = task_queue.lock_owned().await;
+        loop {
+            // Queued tasks are left alone once the operator is shutting down
+            let queued_task = tokio::select! {
+                biased;
+                _ = self.shutdown.cancelled() => break,
+                queued_task = rx.recv() => queued_task,
+            };
+            let Some(QueuedTask {
+                task,
+                block_number,
+                received_at,
+                requeues,
+            }) = queued_task
+            else {
+                break;
+            };
+
+            let client_app_id = AppId::from(task.taskRequest.appId);
+
+            if !self.serves_client_app(&client_app_id) {
+                debug!(
+                    "Skipping task \x1b[1;33m{:?}\x1b[0m for ClientApp not in the allowlist: {:?}",
+                    task.taskId, client_app_id
+                );
+                continue;
+            }
+
+            if let Some(max_task_age) = self.max_task_age {
+                match self.task_age(block_number).await {
+                    Some(age) if age > max_task_age => {
+                        info!(
+                            "Skipping stale task \x1b[1;33m{:?}\x1b[0m requested {:?} ago, above the maximum task age of {:?}",
+                            task.taskId, age, max_task_age
+                        );
+                        self.aggregator
+                            .report_failure(task.taskId.into(), "task too old")
+                            .await;
+                        continue;
+                    }
+                    Some(_) => {}
+                    None => warn!(
+                        "Unknown age of task \x1b[1;33m{:?}\x1b[0m, processing it anyway",
+                        task.taskId
+                    ),
+                }
+            }
+
+            if let Some(open_for) = self.circuit_breaker.as_ref().and_then(|circuit_breaker| {
+                circuit_breaker.open_for(&client_app_id, Instant::now())
+            }) {
+                warn!(
+                    "Skipping task \x1b[1;33m{:?}\x1b[0m, the circuit breaker of ClientApp {:?} is open for another {:?}",
+                    task.taskId, client_app_id, open_for
+                );
+                self.aggregator
+                    .report_failure(task.taskId.into(), "client app circuit breaker open")
+                    .await;
+                continue;
+            }
+
+            // Tracked before checking for newer tasks, so one confirmed in between cancels it
+            let (cancel, _running_task) = self.start_task(task.taskId.into(), client_app_id);
+
+            if self.is_superseded(&client_app_id, &task.taskId.into()) {
+                info!(
+                    "Skipping task \x1b[1;33m{:?}\x1b[0m superseded by a newer task of ClientApp {:?}",
+                    task.taskId, client_app_id
+                );
+                self.aggregator
+                    .report_failure(task.taskId.into(), "superseded by a newer task")
+                    .await;
+                continue;
+            }
 
-        while let Some(task) = rx.recv().await {
             info!("Processing task: \x1b[1;33m{:?}\x1b[0m", task);
 
-            let client_app_id = task.taskRequest.appId;
+            let mut timings = TaskTimings::start_at(received_at);
+            timings.record("queued");
 
             info!("Getting metadata of ClientApp: {:?}", client_app_id);
 
-            let app_metadata = match client_app_registry
-                .getClientAppMetadata(client_app_id)
-                .call()
-                .await
-            {
-                Ok(metadata) => metadata._0,
-                _ => {
-                    error!("Error getting client app metadata");
+            let app_metadata = match self.client_app_metadata(client_app_id).await {
+                Ok(Some(metadata)) => metadata,
+                Ok(None) => {
+                    error!(
+                        "Skipping task \x1b[1;33m{:?}\x1b[0m for unknown ClientApp: {:?}",
+                        task.taskId, client_app_id
+                    );
+                    self.aggregator
+                        .report_failure(task.taskId.into(), "unknown client app")
+                        .await;
+                    continue;
+                }
+                // The RPC is flaky rather than the app unknown, so try the task again later
+                Err(e) if requeues < MAX_TASK_REQUEUES => {
+                    warn!(
+                        "Requeueing task \x1b[1;33m{:?}\x1b[0m in {:?} ({}/{}): {:?}",
+                        task.taskId,
+                        TASK_REQUEUE_DELAY,
+                        requeues + 1,
+                        MAX_TASK_REQUEUES,
+                        e
+                    );
+                    let queued_task = QueuedTask {
+                        task,
+                        block_number,
+                        received_at,
+                        requeues: requeues + 1,
+                    };
+                    let tx_requeue = tx_requeue.clone();
+                    tokio::spawn(
+                        async move {
+                            sleep(TASK_REQUEUE_DELAY).await;
+                            match tx_requeue.upgrade() {
+                                Some(tx) => Self::queue_task(&tx, queued_task).await,
+                                None => warn!(
+                                    "Task queue closed, dropping requeued task \x1b[1;33m{:?}\x1b[0m",
+                                    queued_task.task.taskId
+                                ),
+                            }
+                        }
+                        .in_current_span(),
+                    );
+                    continue;
+                }
+                Err(e) => {
+                    error!(
+                        "Dropping task \x1b[1;33m{:?}\x1b[0m after {} requeues: {:?}",
+                        task.taskId, MAX_TASK_REQUEUES, e
+                    );
+                    self.aggregator
+                        .report_failure(task.taskId.into(), "client app metadata unavailable")
+                        .await;
                     continue;
                 }
             };
 
             let image_metadata = match self.docker.image_metadata(app_metadata.dockerUrl.as_str()) {
                 Ok(metadata) => metadata,
-                _ => {
-                    error!("Error getting image metadata");
+                Err(e) => {
+                    error!(
+                        "Skipping task \x1b[1;33m{:?}\x1b[0m, error getting image metadata: {:?}",
+                        task.taskId, e
+                    );
+                    self.aggregator
+                        .report_failure(task.taskId.into(), &format!("invalid image: {}", e))
+                        .await;
                     continue;
                 }
             };
 
-            info!(
-                "Running image: {:?}:{:?}",
-                image_metadata.repository, image_metadata.tag
-            );
+            timings.record("image_resolved");
+
+            let image_metadata = match self.verify_image(&client_app_id, image_metadata).await {
+                Ok(image_metadata) => image_metadata,
+                Err(e) => {
+                    error!(
+                        "Skipping task \x1b[1;33m{:?}\x1b[0m with unverified image: {:?}",
+                        task.taskId, e
+                    );
+                    self.record_app_failure(client_app_id);
+                    self.aggregator
+                        .report_failure(task.taskId.into(), &format!("unverified image: {}", e))
+                        .await;
+                    continue;
+                }
+            };
 
-            match self.docker.run_image(&image_metadata).await {
-                Ok(result) => {
+            info!("Running image: {:?}", image_metadata.reference());
+
+            let unpulled = self.unpulled_apps.lock().unwrap().contains(&client_app_id);
+            if unpulled {
+                match self.on_image_pull_failure {
+                    OnImagePullFailure::Skip => {
+                        error!(
+                            "Skipping task \x1b[1;33m{:?}\x1b[0m, the image of ClientApp {:?} failed to pull",
+                            task.taskId, client_app_id
+                        );
+                        self.record_app_failure(client_app_id);
+                        continue;
+                    }
+                    OnImagePullFailure::SubmitFailure => {
+                        warn!(
+                            "Reporting a failure for task \x1b[1;33m{:?}\x1b[0m, the image of ClientApp {:?} failed to pull",
+                            task.taskId, client_app_id
+                        );
+                        self.record_app_failure(client_app_id);
+                        self.aggregator
+                            .report_failure(task.taskId.into(), "image pull failed")
+                            .await;
+                        continue;
+                    }
+                    OnImagePullFailure::RetryAtTask => {
+                        if let Err(e) = self.docker.pull_image(&image_metadata).await {
+                            error!(
+                                "Skipping task \x1b[1;33m{:?}\x1b[0m, error pulling image: {:?}",
+                                task.taskId, e
+                            );
+                            self.record_app_failure(client_app_id);
+                            self.aggregator
+                                .report_failure(
+                                    task.taskId.into(),
+                                    &format!("image pull failed: {}", e),
+                                )
+                                .await;
+                            continue;
+                        }
+                        self.unpulled_apps.lock().unwrap().remove(&client_app_id);
+                        self.stats
+                            .cached_images
+                            .lock()
+                            .unwrap()
+                            .insert(client_app_id);
+                        timings.record("image_pulled");
+                    }
+                }
+            }
+
+            // A pinned image is run by digest, which isn't local yet when the tag moved since
+            // the image was pulled
+            if image_metadata.digest.is_some()
+                && self.docker.image_id(&image_metadata).await.is_err()
+            {
+                if let Err(e) = self.docker.pull_image(&image_metadata).await {
+                    error!(
+                        "Skipping task \x1b[1;33m{:?}\x1b[0m, error pulling image: {:?}",
+                        task.taskId, e
+                    );
+                    self.record_app_failure(client_app_id);
+                    self.aggregator
+                        .report_failure(task.taskId.into(), &format!("image pull failed: {}", e))
+                        .await;
+                    continue;
+                }
+                timings.record("image_pulled");
+            }
+
+            // A task cancelled while its metadata was fetched or its image pulled isn't run
+            if cancel.is_cancelled() {
+                info!(
+                    "Abandoning cancelled task \x1b[1;33m{:?}\x1b[0m",
+                    task.taskId
+                );
+                self.aggregator
+                    .report_failure(task.taskId.into(), "task cancelled")
+                    .await;
+                continue;
+            }
+
+            let run_options = self
+                .run_options
+                .get(&client_app_id)
+                .cloned()
+                .unwrap_or_default();
+
+            // Identical tasks of deterministic apps reuse the output of an earlier run
+            let cache_key = self
+                .result_cache_key(
+                    &client_app_id,
+                    &task.taskRequest,
+                    &run_options,
+                    &image_metadata,
+                )
+                .await;
+            let cached_run = cache_key
+                .as_ref()
+                .and_then(|key| self.result_cache.as_ref()?.get(key));
+
+            let container_run = match cached_run {
+                Some(run) => {
                     info!(
-                        "Processed task: \x1b[1;33m{:?}\x1b[0m. Result: {:?}",
-                        task, result
+                        "Reusing the cached result of ClientApp {:?} for task \x1b[1;33m{:?}\x1b[0m",
+                        client_app_id, task.taskId
                     );
-                    let signed_result = self.ecdsa_signer.sign_message_sync(&result.as_bytes())?;
-                    let response = OperatorResponse {
-                        task_id: task.taskId,
-                        result,
-                        signature: signed_result,
-                    };
+                    Ok(run)
+                }
+                None => {
+                    self.run_image_locally(
+                        &image_metadata,
+                        &run_options,
+                        task.taskId.into(),
+                        &cancel,
+                    )
+                    .await
+                }
+            };
 
-                    // Send the response to the aggregator with retry logic
-                    let submit_url = format!("{}/submit_task", self.aggregator_url);
-                    let mut retry_count = 0;
-                    const MAX_RETRIES: u32 = 3;
-
-                    loop {
-                        match http_client.post(&submit_url).json(&response).send().await {
-                            Ok(res) => {
-                                if res.status().is_success() {
-                                    info!("Successfully submitted task result to aggregator");
-                                    break;
-                                } else if res.status() == reqwest::StatusCode::NOT_FOUND
-                                    && retry_count < MAX_RETRIES
-                                {
-                                    retry_count += 1;
-                                    warn!("Received 404 error. Retrying in 1 second... (Attempt {}/{})", retry_count, MAX_RETRIES);
-                                    sleep(Duration::from_secs(1)).await;
-                                } else {
-                                    error!(
-                                        "Failed to submit task result. Status: {}",
-                                        res.status()
-                                    );
-                                    break;
-                                }
-                            }
+            match container_run {
+                Ok(ContainerRun {
+                    stdout: result,
+                    exit_code,
+                    duration,
+                }) => {
+                    timings.record("container_ran");
+
+                    info!(
+                        "Processed task: \x1b[1;33m{:?}\x1b[0m. Result: {:?} (exit code {}, ran for {:?})",
+                        task, result, exit_code, duration
+                    );
+
+                    // Don't sign output that doesn't match what the client app should produce
+                    let result_type = self.result_types.get(&client_app_id);
+                    if let Err(e) = result_type.canonicalize(&result) {
+                        error!("Result is not a valid {:?}: {:?}", result_type, e);
+                        self.record_app_failure(client_app_id);
+                        self.aggregator
+                            .report_failure(
+                                task.taskId.into(),
+                                &format!("result is not a valid {:?}", result_type),
+                            )
+                            .await;
+                        continue;
+                    }
+
+                    // The output of an app exiting with an error is still submitted, but the
+                    // run counts as a failure of the app
+                    match exit_code {
+                        0 => self.record_app_success(&client_app_id),
+                        _ => self.record_app_failure(client_app_id),
+                    }
+
+                    if let (Some(result_cache), Some(cache_key)) = (&self.result_cache, cache_key) {
+                        result_cache.put(
+                            cache_key,
+                            ContainerRun {
+                                stdout: result.clone(),
+                                exit_code,
+                                duration,
+                            },
+                        );
+                    }
+
+                    // The task may have been cancelled after its container exited
+                    if cancel.is_cancelled() {
+                        info!(
+                            "Abandoning cancelled task \x1b[1;33m{:?}\x1b[0m",
+                            task.taskId
+                        );
+                        self.aggregator
+                            .report_failure(task.taskId.into(), "task cancelled")
+                            .await;
+                        continue;
+                    }
+
+                    // A response that can't be signed is dropped on its own, it must not stop
+                    // the processor
+                    let mut response =
+                        match self.aggregator.sign_response(task.taskId.into(), &result) {
+                            Ok(response) => response,
                             Err(e) => {
-                                error!("Error submitting task result: {:?}", e);
-                                break;
+                                error!(
+                                    "Failed to sign the result of task \x1b[1;33m{:?}\x1b[0m: {:?}",
+                                    task.taskId, e
+                                );
+                                continue;
                             }
+                        };
+                    response.exit_code = Some(exit_code);
+                    response.duration_ms = Some(duration.as_millis() as u64);
+                    timings.record("signed");
+
+                    match self.aggregator.submit_response(&response).await {
+                        Ok(()) => {
+                            info!("Successfully submitted task result to aggregator");
+                            self.stats.tasks_processed.fetch_add(1, Ordering::Relaxed);
+                            timings.record("submitted");
+                            info!(
+                                "Task \x1b[1;33m{:?}\x1b[0m timings: {}",
+                                task.taskId,
+                                timings.summary()
+                            );
                         }
+                        Err(e) => error!("{:?}", e),
                     }
                 }
-                Err(e) => error!("Error processing task: {:?}", e),
+                Err(e)
+                    if cancel.is_cancelled()
+                        || matches!(e.downcast_ref(), Some(RunError::Cancelled)) =>
+                {
+                    info!(
+                        "Abandoned cancelled task \x1b[1;33m{:?}\x1b[0m: {:?}",
+                        task.taskId, e
+                    );
+                    self.aggregator
+                        .report_failure(task.taskId.into(), "task cancelled")
+                        .await;
+                }
+                // The app ran for too long, which counts against it like a failed run
+                Err(e) if matches!(e.downcast_ref(), Some(RunError::TimedOut { .. })) => {
+                    warn!("Task \x1b[1;33m{:?}\x1b[0m timed out: {}", task.taskId, e);
+                    self.record_app_failure(client_app_id);
+                    self.aggregator
+                        .report_failure(task.taskId.into(), &format!("timed out: {}", e))
+                        .await;
+                }
+                Err(e) => {
+                    error!("Error processing task: {:?}", e);
+                    self.record_app_failure(client_app_id);
+                    self.aggregator
+                        .report_failure(task.taskId.into(), &format!("run failed: {}", e))
+                        .await;
+                }
             }
         }
         Ok(())
     }
 
+    // Key of the output of a task in the result cache, `None` when the task's client app isn't
+    // cached or its image can't be resolved
+    async fn result_cache_key(
+        &self,
+        client_app_id: &AppId,
+        task_request: &TaskRegistry::TaskRequest,
+        run_options: &RunOptions,
+        image_metadata: &DockerImageMetadata,
+    ) -> Option<ResultCacheKey> {
+        if !self.result_cache.as_ref()?.caches(client_app_id) {
+            return None;
+        }
+        // The output of a container reaching the network depends on more than its input
+        if run_options.network_access {
+            debug!(
+                "Not caching the result of ClientApp {:?}, it has network access",
+                client_app_id
+            );
+            return None;
+        }
+        let input_hash = task_input_hash(task_request, run_options);
+
+        match self.docker.image_id(image_metadata).await {
+            Ok(image_id) => Some(ResultCacheKey {
+                app_id: *client_app_id,
+                input_hash,
+                image_id,
+            }),
+            Err(e) => {
+                warn!(
+                    "Not caching the result of ClientApp {:?}, its image can't be resolved: {:?}",
+                    client_app_id, e
+                );
+                None
+            }
+        }
+    }
+
+    // Count a failed task of `client_app_id` towards its circuit breaker
+    fn record_app_failure(&self, client_app_id: AppId) {
+        let Some(circuit_breaker) = &self.circuit_breaker else {
+            return;
+        };
+        if circuit_breaker.record_failure(client_app_id, Instant::now()) {
+            warn!(
+                "ClientApp {:?} keeps failing, opened its circuit breaker: its tasks are skipped for {:?}",
+                client_app_id,
+                circuit_breaker.cooldown()
+            );
+        }
+    }
+
+    // Reset the failures counted towards the circuit breaker of `client_app_id`
+    fn record_app_success(&self, client_app_id: &AppId) {
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            circuit_breaker.record_success(client_app_id);
+        }
+    }
+
+    // Fetch the metadata of a client app, retrying failed calls with exponential backoff.
+    // Returns `None` for an app that isn't registered, which reads as empty metadata.
+    async fn client_app_metadata(
+        &self,
+        client_app_id: AppId,
+    ) -> Result<Option<ClientAppRegistry::ClientAppMetadata>> {
+        let client_app_registry =
+            ClientAppRegistryInstance::new(CLIENT_APP_REGISTRY_ADDRESS, self.http_provider.clone());
+        let mut attempt = 1;
+        let mut delay = METADATA_RETRY_BASE_DELAY;
+
+        loop {
+            match client_app_registry
+                .getClientAppMetadata(client_app_id.into())
+                .call()
+                .await
+            {
+                Ok(metadata) if metadata._0.dockerUrl.is_empty() => return Ok(None),
+                Ok(metadata) => return Ok(Some(metadata._0)),
+                Err(e) if attempt >= self.metadata_max_attempts => {
+                    return Err(e).wrap_err("Failed to get client app metadata")
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to get metadata of ClientApp {:?} (attempt {}/{}), retrying in {:?}: {:?}",
+                        client_app_id, attempt, self.metadata_max_attempts, delay, e
+                    );
+                    sleep(delay).await;
+                    attempt += 1;
+                    delay *= 2;
+                }
+            }
+        }
+    }
+
+    // Check that a client app image comes from an allowed registry, then its signature when
+    // image verification is enabled. The registry check is cheap, so it always runs first.
+    // A verified image is returned pinned to the digest its tag resolved to, for the image
+    // pulled and run to be the one verified even if the tag moves in the meantime.
+    async fn verify_image(
+        &self,
+        client_app_id: &AppId,
+        image_metadata: DockerImageMetadata,
+    ) -> Result<DockerImageMetadata> {
+        if !image_metadata.is_from_registry(&self.registry_allowlist) {
+            return Err(eyre::eyre!(
+                "Registry of {} is not allowed",
+                image_metadata.qualified_repository()
+            ));
+        }
+
+        let Some(image_verifier) = &self.image_verifier else {
+            return Ok(image_metadata);
+        };
+        let pinned = self.docker.pin_image(&image_metadata).await?;
+        image_verifier.verify(client_app_id, &pinned).await?;
+        Ok(pinned)
+    }
+
+    // Whether the operator serves `client_app_id`. An empty allowlist serves every app.
+    fn serves_client_app(&self, client_app_id: &AppId) -> bool {
+        self.client_app_allowlist.is_empty() || self.client_app_allowlist.contains(client_app_id)
+    }
+
+    // Run the image `local_runs` times and return its output only if every run agrees, so that
+    // nondeterministic images are caught before they cause a consensus failure. A run that
+    // fails leaves its `RunError` in the error.
+    async fn run_image_locally(
+        &self,
+        image_metadata: &DockerImageMetadata,
+        run_options: &RunOptions,
+        task_id: TaskId,
+        cancel: &CancellationToken,
+    ) -> Result<ContainerRun> {
+        let output = self
+            .docker
+            .run_image(image_metadata, run_options, task_id, 0, cancel)
+            .await?;
+
+        for run in 1..self.local_runs {
+            let run_output = self
+                .docker
+                .run_image(image_metadata, run_options, task_id, run, cancel)
+                .await?;
+            if (&run_output.stdout, run_output.exit_code) != (&output.stdout, output.exit_code) {
+                return Err(eyre::eyre!(
+                    "Local runs disagree: run 0 returned {:?} (exit code {}), run {} returned {:?} (exit code {})",
+                    output.stdout,
+                    output.exit_code,
+                    run,
+                    run_output.stdout,
+                    run_output.exit_code
+                ));
+            }
+        }
+
+        Ok(output)
+    }
+
     async fn handle_tasks(
         &self,
-        event_listener: JoinHandle<Result<()>>,
-        task_processor: JoinHandle<Result<()>>,
+        mut event_listener: JoinHandle<Result<()>>,
+        mut task_processor: JoinHandle<Result<()>>,
+        task_queue: TaskQueue,
     ) -> Result<()> {
+        let shutdown_signal = Self::shutdown_signal();
         tokio::select! {
-            event_result = event_listener => {
+            event_result = &mut event_listener => {
                 match event_result {
                     Ok(result) => result.wrap_err("Event listener task failed"),
                     Err(e) => Err(eyre::eyre!("Event listener task panicked: {:?}", e)),
                 }
             }
-            _ = task_processor => {
+            _ = &mut task_processor => {
                 warn!("Task processor exited unexpectedly");
                 Err(eyre::eyre!("Task processor exited unexpectedly"))
             }
+            _ = shutdown_signal => {
+                event_listener.abort();
+                self.drain(task_processor, task_queue).await;
+                Ok(())
+            }
+        }
+    }
+
+    // Resolve once the operator is asked to stop, by Ctrl-C or by the SIGTERM of `docker stop`
+    // and service managers. SIGTERM is listened for from the call on, not from the first poll.
+    fn shutdown_signal() -> impl std::future::Future<Output = ()> {
+        let terminate = signal(SignalKind::terminate());
+        let terminate = async {
+            match terminate {
+                Ok(mut terminate) => {
+                    terminate.recv().await;
+                }
+                Err(e) => {
+                    error!("Failed to listen for SIGTERM: {:?}", e);
+                    std::future::pending::<()>().await;
+                }
+            }
+        };
+        let interrupt = async {
+            if let Err(e) = tokio::signal::ctrl_c().await {
+                error!("Failed to listen for Ctrl-C: {:?}", e);
+                std::future::pending::<()>().await;
+            }
+        };
+
+        async {
+            tokio::select! {
+                _ = terminate => {}
+                _ = interrupt => {}
+            }
+        }
+    }
+
+    // Stop starting queued tasks and give the running one `shutdown_drain_timeout` to finish and
+    // submit its result. A task still running after that is abandoned and its container killed
+    // and removed, so a hung container can't block the exit. The aggregators are told about the
+    // abandoned task and the queued ones, so they don't wait for them until they time out.
+    async fn drain(&self, mut task_processor: JoinHandle<Result<()>>, task_queue: TaskQueue) {
+        info!(
+            "Shutting down, waiting up to {:?} for running tasks to finish",
+            self.shutdown_drain_timeout
+        );
+        self.shutdown.cancel();
+
+        if timeout(self.shutdown_drain_timeout, &mut task_processor)
+            .await
+            .is_ok()
+        {
+            self.report_queued_tasks(&task_queue).await;
+            info!("Operator stopped");
+            return;
+        }
+
+        let abandoned_tasks: Vec<TaskId> =
+            self.running_tasks.lock().unwrap().keys().copied().collect();
+        task_processor.abort();
+        // The queue is only released once the aborted processor is dropped
+        let _ = task_processor.await;
+        for task_id in &abandoned_tasks {
+            warn!(
+                "Abandoning task \x1b[1;33m{:?}\x1b[0m still running after {:?}",
+                task_id, self.shutdown_drain_timeout
+            );
         }
+        futures::future::join_all(abandoned_tasks.iter().map(|task_id| {
+            self.aggregator
+                .report_failure(*task_id, "task abandoned on shutdown")
+        }))
+        .await;
+        self.report_queued_tasks(&task_queue).await;
+
+        // Aborting the processor leaves its container behind, the exited containers kept for
+        // inspection are left for the next startup
+        if let Err(e) = self
+            .docker
+            .remove_orphaned_containers(self.docker.keeps_containers())
+            .await
+        {
+            error!(
+                "Failed to remove the containers of abandoned tasks: {:?}",
+                e
+            );
+        }
+        info!("Operator stopped");
+    }
+
+    // Report every task still queued on shutdown, which will never be started
+    async fn report_queued_tasks(&self, task_queue: &TaskQueue) {
+        let mut rx = task_queue.lock().await;
+        rx.close();
+        let mut queued_tasks = Vec::new();
+        while let Ok(queued_task) = rx.try_recv() {
+            let client_app_id = AppId::from(queued_task.task.taskRequest.appId);
+            if self.serves_client_app(&client_app_id) {
+                queued_tasks.push(TaskId::from(queued_task.task.taskId));
+            }
+        }
+        if queued_tasks.is_empty() {
+            return;
+        }
+
+        warn!(
+            "Reporting {} queued tasks not started before shutdown",
+            queued_tasks.len()
+        );
+        futures::future::join_all(queued_tasks.iter().map(|task_id| {
+            self.aggregator
+                .report_failure(*task_id, "operator shutting down")
+        }))
+        .await;
+    }
+}
+
+// Hash of everything a task container is given: tasks carry no input besides their request, so
+// the run options the container is started with are part of it
+fn task_input_hash(task_request: &TaskRegistry::TaskRequest, run_options: &RunOptions) -> B256 {
+    let run_options = serde_json::to_vec(run_options).unwrap_or_default();
+    keccak256([task_request.abi_encode(), run_options].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{
+        pubsub::{ConnectionHandle, PubSubConnect},
+        rpc::client::ClientBuilder,
+        transports::TransportResult,
+    };
+
+    // Node that accepts the subscription connection but never answers, for tests that don't
+    // reach the chain
+    struct SilentNode;
+
+    impl PubSubConnect for SilentNode {
+        fn is_local(&self) -> bool {
+            true
+        }
+
+        async fn connect(&self) -> TransportResult<ConnectionHandle> {
+            let (handle, interface) = ConnectionHandle::new();
+            tokio::spawn(async move {
+                let _interface = interface;
+                std::future::pending::<()>().await
+            });
+            Ok(handle)
+        }
+    }
+
+    // HTTP provider signing with `ecdsa_signer`, for the node at `url`
+    fn test_http_provider(ecdsa_signer: &PrivateKeySigner, url: &str) -> HttpProviderWithSigner {
+        Arc::new(
+            ProviderBuilder::new()
+                .with_recommended_fillers()
+                .wallet(EthereumWallet::from(ecdsa_signer.clone()))
+                .on_http(url.parse().unwrap()),
+        )
+    }
+
+    // Serve a mock node answering each request with the result `answer` gives for its method,
+    // returning its URL. Methods without a result are unsupported.
+    async fn mock_rpc(
+        answer: impl Fn(&str) -> Option<serde_json::Value> + Clone + Send + Sync + 'static,
+    ) -> String {
+        use axum::{routing::post, Json, Router};
+
+        let app = Router::new().route(
+            "/",
+            post(move |Json(request): Json<serde_json::Value>| async move {
+                let mut response = match answer(request["method"].as_str().unwrap_or_default()) {
+                    Some(result) => serde_json::json!({ "result": result }),
+                    None => {
+                        serde_json::json!({ "error": { "code": -32601, "message": "unsupported" } })
+                    }
+                };
+                response["jsonrpc"] = "2.0".into();
+                response["id"] = request["id"].clone();
+                Json(response)
+            }),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    // Serve a mock node answering every `eth_call` with `metadata` as the metadata of the
+    // ClientApp, after calling `on_call`, and returning its URL
+    async fn mock_node(
+        metadata: ClientAppRegistry::ClientAppMetadata,
+        on_call: impl Fn() + Clone + Send + Sync + 'static,
+    ) -> String {
+        use alloy::sol_types::SolCall;
+
+        let result = alloy_primitives::hex::encode_prefixed(
+            ClientAppRegistry::getClientAppMetadataCall::abi_encode_returns(&(metadata,)),
+        );
+        mock_rpc(move |method| {
+            (method == "eth_call").then(|| {
+                on_call();
+                result.clone().into()
+            })
+        })
+        .await
+    }
+
+    // Operator submitting to the aggregator at `aggregator_url`, with default settings and no
+    // reachable node or Docker daemon
+    pub(super) async fn test_operator(aggregator_url: String) -> Operator {
+        let ecdsa_signer = PrivateKeySigner::random();
+        let http_provider = test_http_provider(&ecdsa_signer, "http://127.0.0.1:1");
+        let pubsub_provider = Arc::new(RootProvider::new(
+            ClientBuilder::default().pubsub(SilentNode).await.unwrap(),
+        ));
+        let docker = DockerClient::new(
+            Arc::new(
+                docker_client::connect(
+                    "",
+                    Some(&operator_config::DockerHost::Tcp("127.0.0.1:1".to_string())),
+                )
+                .unwrap(),
+            ),
+            ecdsa_signer.address().to_string(),
+            1024,
+            Default::default(),
+            ContainerLimits::default(),
+            Duration::from_secs(1),
+            1,
+            None,
+            None,
+            "linux/amd64".to_string(),
+            false,
+        );
+
+        Operator {
+            operator_address: ecdsa_signer.address(),
+            name: "test".to_string(),
+            aggregator: AggregatorClient::new(
+                vec![aggregator_url],
+                HttpClient::new(),
+                ecdsa_signer.clone(),
+                contract_bindings::RESPONSE_VERSION_ECDSA_TASK_BOUND,
+            ),
+            pubsub_provider,
+            http_provider,
+            ecdsa_signer,
+            docker,
+            result_types: Default::default(),
+            local_runs: 1,
+            client_app_allowlist: HashSet::new(),
+            registry_allowlist: Vec::new(),
+            require_client_apps: false,
+            task_confirmations: 0,
+            run_options: HashMap::new(),
+            metadata_max_attempts: 1,
+            image_verifier: None,
+            on_image_pull_failure: OnImagePullFailure::default(),
+            listener_watchdog: None,
+            aggregator_wait_timeout: None,
+            max_task_age: None,
+            unpulled_apps: Arc::new(Mutex::new(HashSet::new())),
+            running_tasks: Arc::new(Mutex::new(HashMap::new())),
+            shutdown: CancellationToken::new(),
+            shutdown_drain_timeout: Duration::from_secs(1),
+            cancel_superseded_tasks: false,
+            latest_tasks: Arc::new(Mutex::new(HashMap::new())),
+            status_server_addr: None,
+            status_admin_token: None,
+            stats: Arc::new(OperatorStats::default()),
+            salt_store_path: std::env::temp_dir()
+                .join(format!("operator_salts_{}.json", std::process::id())),
+            result_cache: None,
+            circuit_breaker: None,
+        }
+    }
+
+    fn queued_task(task_byte: u8, app_id: AppId) -> QueuedTask {
+        QueuedTask {
+            task: TaskRegistry::TaskRequested {
+                taskId: B256::repeat_byte(task_byte),
+                taskRequest: TaskRegistry::TaskRequest {
+                    appId: app_id.into(),
+                },
+            },
+            block_number: None,
+            received_at: Instant::now(),
+            requeues: 0,
+        }
+    }
+
+    // Run `queued_tasks` through the task processor of `operator` until the queue is empty
+    async fn process(operator: &Operator, queued_tasks: Vec<QueuedTask>) -> Result<()> {
+        let (tx, rx) = mpsc::channel(queued_tasks.len().max(1));
+        for queued_task in queued_tasks {
+            tx.send(queued_task).await?;
+        }
+        // The processor's own sender doesn't keep the queue open
+        let tx_requeue = tx.downgrade();
+        drop(tx);
+        operator
+            .clone()
+            .process_tasks(Arc::new(AsyncMutex::new(rx)), tx_requeue)
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_skipped_tasks_are_reported() -> Result<()> {
+        let (url, received) = aggregator_client::tests::mock_aggregator().await;
+        let mut operator = test_operator(url).await;
+        let served = AppId(B256::repeat_byte(0xaa));
+        let not_served = AppId(B256::repeat_byte(0xbb));
+        operator.client_app_allowlist = HashSet::from([served]);
+        operator.cancel_superseded_tasks = true;
+
+        // A newer task of the app was confirmed since, the older one is reported rather than
+        // left for the aggregator to time out. Tasks of apps not served aren't reported.
+        operator.supersede_tasks(&queued_task(2, served).task);
+        process(
+            &operator,
+            vec![queued_task(1, served), queued_task(3, not_served)],
+        )
+        .await?;
+
+        let received = received.lock().unwrap().clone();
+        assert_eq!(received.len(), 1);
+        let (path, body) = &received[0];
+        assert_eq!(path, "submit_failure");
+        assert_eq!(
+            body["task_id"],
+            serde_json::to_value(TaskId(B256::repeat_byte(1)))?
+        );
+        assert_eq!(body["reason"], "superseded by a newer task");
+
+        Ok(())
+    }
+
+    // Task ids and reasons of the failures received by a mock aggregator, sorted by task id
+    fn reported_failures(received: &aggregator_client::tests::Received) -> Vec<(String, String)> {
+        let mut failures = received
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(path, _)| path == "submit_failure")
+            .map(|(_, body)| {
+                (
+                    body["task_id"].as_str().unwrap().to_string(),
+                    body["reason"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+        failures.sort();
+        failures
+    }
+
+    #[tokio::test]
+    async fn test_drain() -> Result<()> {
+        let (url, received) = aggregator_client::tests::mock_aggregator().await;
+        let mut operator = test_operator(url).await;
+        operator.shutdown_drain_timeout = Duration::from_millis(100);
+        let app_id = AppId(B256::repeat_byte(0xaa));
+        let task_id = |byte| format!("{:?}", B256::repeat_byte(byte));
+
+        // A processor stuck on a running task is abandoned, and the tasks it didn't start are
+        // reported along with it
+        let (tx, rx) = mpsc::channel(2);
+        tx.send(queued_task(2, app_id)).await?;
+        tx.send(queued_task(3, app_id)).await?;
+        let task_queue: TaskQueue = Arc::new(AsyncMutex::new(rx));
+        let queue_held = Arc::new(tokio::sync::Notify::new());
+        let task_processor = tokio::spawn({
+            let operator = operator.clone();
+            let task_queue = task_queue.clone();
+            let queue_held = queue_held.clone();
+            async move {
+                let _rx = task_queue.lock_owned().await;
+                let _running = operator.start_task(TaskId(B256::repeat_byte(1)), app_id);
+                queue_held.notify_one();
+                std::future::pending::<Result<()>>().await
+            }
+        });
+        queue_held.notified().await;
+        operator.drain(task_processor, task_queue).await;
+        assert!(operator.shutdown.is_cancelled());
+        assert!(operator.running_tasks.lock().unwrap().is_empty());
+        assert_eq!(
+            reported_failures(&received),
+            vec![
+                (task_id(1), "task abandoned on shutdown".to_string()),
+                (task_id(2), "operator shutting down".to_string()),
+                (task_id(3), "operator shutting down".to_string()),
+            ]
+        );
+
+        // A processor that stops in time leaves only the queued tasks to report
+        let (url, received) = aggregator_client::tests::mock_aggregator().await;
+        let operator = test_operator(url).await;
+        let (tx, rx) = mpsc::channel(1);
+        tx.send(queued_task(4, app_id)).await?;
+        let task_queue: TaskQueue = Arc::new(AsyncMutex::new(rx));
+        let tx_requeue = tx.downgrade();
+        operator.shutdown.cancel();
+        let task_processor = tokio::spawn(
+            operator
+                .clone()
+                .process_tasks(task_queue.clone(), tx_requeue),
+        );
+        operator.drain(task_processor, task_queue).await;
+        assert_eq!(
+            reported_failures(&received),
+            vec![(task_id(4), "operator shutting down".to_string())]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_signal() {
+        // SIGTERM is caught from the call on, even before the future is polled
+        let shutdown_signal = Operator::shutdown_signal();
+        let status = std::process::Command::new("kill")
+            .args(["-TERM", &std::process::id().to_string()])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        timeout(Duration::from_secs(5), shutdown_signal)
+            .await
+            .expect("SIGTERM should resolve the shutdown signal");
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_task_is_reported() -> Result<()> {
+        let (url, received) = aggregator_client::tests::mock_aggregator().await;
+        let mut operator = test_operator(url).await;
+        let app_id = AppId(B256::repeat_byte(0xaa));
+
+        // The task is cancelled while its metadata is fetched, so it is reported rather than run
+        let metadata = ClientAppRegistry::ClientAppMetadata {
+            name: "app".to_string(),
+            description: String::new(),
+            logoUrl: String::new(),
+            dockerUrl: "https://hub.docker.com/layers/giza/app/latest/images/sha256:e2fc4e5"
+                .to_string(),
+        };
+        let node_url = mock_node(metadata, {
+            let operator = operator.clone();
+            move || {
+                operator.cancel_task(TaskId(B256::repeat_byte(1)));
+            }
+        })
+        .await;
+        operator.http_provider = test_http_provider(&operator.ecdsa_signer, &node_url);
+        process(&operator, vec![queued_task(1, app_id)]).await?;
+
+        assert_eq!(
+            reported_failures(&received),
+            vec![(
+                format!("{:?}", B256::repeat_byte(1)),
+                "task cancelled".to_string()
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_task_is_reported() -> Result<()> {
+        let (url, received) = aggregator_client::tests::mock_aggregator().await;
+        let mut operator = test_operator(url).await;
+        let app_id = AppId(B256::repeat_byte(0xaa));
+
+        // The container of the task never exits, so it is stopped once it times out
+        let metadata = ClientAppRegistry::ClientAppMetadata {
+            name: "app".to_string(),
+            description: String::new(),
+            logoUrl: String::new(),
+            dockerUrl: "https://hub.docker.com/layers/giza/app/latest/images/sha256:e2fc4e5"
+                .to_string(),
+        };
+        let node_url = mock_node(metadata, || {}).await;
+        operator.http_provider = test_http_provider(&operator.ecdsa_signer, &node_url);
+        let (docker_host, _requests) = docker_client::tests::mock_daemon(false).await;
+        operator.docker = DockerClient::new(
+            Arc::new(docker_client::connect("", Some(&docker_host))?),
+            operator.operator_address.to_string(),
+            1024,
+            Default::default(),
+            ContainerLimits {
+                timeout: Some(Duration::from_millis(50)),
+                ..ContainerLimits::default()
+            },
+            Duration::from_millis(10),
+            1,
+            None,
+            None,
+            "linux/amd64".to_string(),
+            false,
+        );
+        process(&operator, vec![queued_task(1, app_id)]).await?;
+
+        let failures = reported_failures(&received);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, format!("{:?}", B256::repeat_byte(1)));
+        assert!(
+            failures[0].1.starts_with("timed out: "),
+            "{}",
+            failures[0].1
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backfill_skips_received_events() -> Result<()> {
+        use alloy::sol_types::SolEvent;
+
+        let task = queued_task(1, AppId(B256::repeat_byte(0xaa))).task;
+        let log = Log {
+            inner: alloy_primitives::Log {
+                address: TASK_REGISTRY_ADDRESS,
+                data: task.encode_log_data(),
+            },
+            block_hash: Some(B256::repeat_byte(0xbb)),
+            block_number: Some(7),
+            log_index: Some(0),
+            ..Default::default()
+        };
+        let logs = serde_json::to_value(vec![log.clone()])?;
+        let url = mock_rpc(move |method| (method == "eth_getLogs").then(|| logs.clone())).await;
+
+        let mut operator = test_operator("http://127.0.0.1:1".to_string()).await;
+        operator.http_provider = test_http_provider(&operator.ecdsa_signer, &url);
+
+        // The backfill returns the event already received through the subscription, which is
+        // queued only once
+        let missed = operator.missed_events(7).await?;
+        assert_eq!(missed.len(), 1);
+        assert_eq!(missed[0].0.taskId, task.taskId);
+
+        let (tx, mut rx) = mpsc::channel(4);
+        let mut unconfirmed = ConfirmationBuffer::new(0);
+        let mut seen_events = HashMap::new();
+        operator
+            .handle_event(&tx, &mut unconfirmed, &mut seen_events, task, log)
+            .await;
+        for (task, log) in missed {
+            operator
+                .handle_event(&tx, &mut unconfirmed, &mut seen_events, task, log)
+                .await;
+        }
+        drop(tx);
+
+        assert_eq!(
+            rx.recv().await.map(|queued| queued.task.taskId),
+            Some(B256::repeat_byte(1))
+        );
+        assert!(rx.recv().await.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_status_checks_allowlist() -> Result<()> {
+        // The node answers every `eth_call` with `true` and doesn't support event queries, so
+        // the status only succeeds without scanning the registered client apps
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let url = mock_rpc({
+            let calls = calls.clone();
+            move |method| {
+                (method == "eth_call").then(|| {
+                    calls.fetch_add(1, Ordering::Relaxed);
+                    alloy_primitives::hex::encode_prefixed(U256::from(1).to_be_bytes::<32>()).into()
+                })
+            }
+        })
+        .await;
+
+        let mut operator = test_operator("http://127.0.0.1:1".to_string()).await;
+        operator.http_provider = test_http_provider(&operator.ecdsa_signer, &url);
+        operator.client_app_allowlist = HashSet::from([
+            AppId(B256::repeat_byte(0xaa)),
+            AppId(B256::repeat_byte(0xbb)),
+        ]);
+
+        operator.status().await?;
+        // Both registrations, then the opt-in of each allowed app
+        assert_eq!(calls.load(Ordering::Relaxed), 4);
+
+        // Without an allowlist, every registered client app is looked up from the events
+        operator.client_app_allowlist.clear();
+        assert!(operator.status().await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_task_input_hash() {
+        let request = queued_task(1, AppId(B256::repeat_byte(0xaa)))
+            .task
+            .taskRequest;
+        let other_request = queued_task(1, AppId(B256::repeat_byte(0xbb)))
+            .task
+            .taskRequest;
+        let run_options = RunOptions::default();
+        let other_run_options = RunOptions {
+            cmd: Some(vec!["--other".to_string()]),
+            ..RunOptions::default()
+        };
+
+        assert_eq!(
+            task_input_hash(&request, &run_options),
+            task_input_hash(&request, &run_options)
+        );
+        assert_ne!(
+            task_input_hash(&request, &run_options),
+            task_input_hash(&other_request, &run_options)
+        );
+        assert_ne!(
+            task_input_hash(&request, &run_options),
+            task_input_hash(&request, &other_run_options)
+        );
     }
 }
@@ -1,9 +1,13 @@
+use contract_bindings::Chain;
 use eyre::Result;
-use operator::Operator;
-use std::env;
+use operator::{operator_config::OperatorConfig, Operator, GIT_SHA, VERSION};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
 use time::macros::format_description;
-use tracing::error;
-use tracing_subscriber::fmt;
+use tracing::{error, info};
+use tracing_subscriber::{fmt, EnvFilter};
 
 fn init_tracing() {
     // Initialize the tracing subscriber with custom filter and format
@@ -21,26 +25,110 @@ fn init_tracing() {
 
     tracing_subscriber::fmt()
         .event_format(format)
-        .with_max_level(tracing::Level::INFO)
+        // `RUST_LOG=debug` shows the debug logs, such as the progress of image pulls
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
         .init();
+
+    info!("Operator {} ({})", VERSION, GIT_SHA);
+}
+
+// Remove `<flag> <path>` (e.g. `--config <path>`) from the arguments, returning the path if
+// present
+fn take_path_flag(args: &mut Vec<String>, flag: &str) -> Option<PathBuf> {
+    let index = args.iter().position(|arg| arg == flag)?;
+    if index + 1 >= args.len() {
+        return None;
+    }
+    let path = args.remove(index + 1);
+    args.remove(index);
+    Some(path.into())
+}
+
+// Remove `flag` from the arguments, returning whether it was present
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let Some(index) = args.iter().position(|arg| arg == flag) else {
+        return false;
+    };
+    args.remove(index);
+    true
+}
+
+// Load the operator configuration, from the `--config` file if one was given. The private key
+// is only needed when no keystore is configured. `--keep-containers` turns on
+// `keep_containers` whatever the configuration says. The configuration is validated before it is
+// returned, so every misconfiguration is reported before the operator connects to anything.
+fn load_config(
+    config_path: Option<&Path>,
+    keep_containers: bool,
+    chain: &Chain,
+    private_key: Option<&str>,
+) -> Result<OperatorConfig> {
+    let mut config = match config_path {
+        Some(path) => OperatorConfig::from_file(path, chain, private_key)?,
+        None => OperatorConfig::from_env(chain, private_key)?,
+    };
+    config.keep_containers |= keep_containers;
+    config.validate()?;
+    Ok(config)
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     init_tracing();
 
-    let args: Vec<String> = env::args().collect();
-    match args.len() {
-        3 => {
-            // Correct number of arguments, continue with the private key
-            let private_key = args[1].clone();
-            let chain = args[2].clone().into();
-            let operator = Operator::new(&private_key, chain).await?;
+    let mut args: Vec<String> = env::args().collect();
+    let config_path = take_path_flag(&mut args, "--config");
+    if let Some(chain_config_path) = take_path_flag(&mut args, "--chain-config") {
+        Chain::from_config(&chain_config_path)?.install()?;
+    }
+    let keep_containers = take_flag(&mut args, "--keep-containers");
+
+    match args.as_slice() {
+        [_, command, private_key @ .., chain] if command == "status" && private_key.len() <= 1 => {
+            // Read-only report of the operator's on-chain standing
+            let chain = Chain::from(chain.clone());
+            let private_key = private_key.first().map(String::as_str);
+            let config = load_config(config_path.as_deref(), keep_containers, &chain, private_key)?;
+            let operator = Operator::new(chain, config).await?;
+            operator.status().await
+        }
+        [_, command, private_key @ .., chain]
+            if command == "prefetch" && private_key.len() <= 1 =>
+        {
+            // Warm the image cache without registering or processing tasks
+            let chain = Chain::from(chain.clone());
+            let private_key = private_key.first().map(String::as_str);
+            let config = load_config(config_path.as_deref(), keep_containers, &chain, private_key)?;
+            config.validate_docker()?;
+            let operator = Operator::new(chain, config).await?;
+            operator.prefetch().await
+        }
+        [_, private_key @ .., chain] if private_key.len() <= 1 => {
+            // Correct number of arguments, the private key is optional with a keystore
+            let chain = Chain::from(chain.clone());
+            let private_key = private_key.first().map(String::as_str);
+            let config = load_config(config_path.as_deref(), keep_containers, &chain, private_key)?;
+            config.validate_docker()?;
+            config.validate_salt_store()?;
+            let operator = Operator::new(chain, config).await?;
             operator.run().await
         }
         _ => {
-            error!("Usage: {} <private_key> <chain>", args[0]);
-            error!("Both the private key and chain are expected as arguments");
+            error!(
+                "Usage: {} [--config <path>] [--chain-config <path>] [--keep-containers] [<private_key>] <chain>",
+                args[0]
+            );
+            error!(
+                "       {} [--config <path>] [--chain-config <path>] status [<private_key>] <chain>",
+                args[0]
+            );
+            error!(
+                "       {} [--config <path>] [--chain-config <path>] prefetch [<private_key>] <chain>",
+                args[0]
+            );
+            error!("The private key can only be omitted when OPERATOR_KEYSTORE_PATH is set");
             std::process::exit(1);
         }
     }
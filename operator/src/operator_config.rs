@@ -1,33 +1,569 @@
 use alloy::signers::local::PrivateKeySigner;
-use dirs::home_dir;
+use contract_bindings::{
+    response_signing_bytes, AppId, Chain, ResultTypes, TaskId, RESPONSE_VERSION_ECDSA_TASK_BOUND,
+};
+use dirs::{config_dir, home_dir};
 use dotenv::dotenv;
-use std::env;
+use eyre::{eyre, Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    env, fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use tracing::warn;
+
+/// Default URL of the aggregator.
+const DEFAULT_AGGREGATOR_URL: &str = "http://0.0.0.0:8080";
+/// Default number of times each task image is run locally.
+const DEFAULT_LOCAL_RUNS: u32 = 1;
+/// Default maximum number of attempts made to pull a client app image.
+const DEFAULT_IMAGE_PULL_MAX_ATTEMPTS: u32 = 5;
+/// Default time (in seconds) an image pull may go without progress before it is cancelled.
+const DEFAULT_IMAGE_PULL_STALL_TIMEOUT_SECS: u64 = 120;
+/// Default minimum time (in seconds) between two logs of the progress of an image pull.
+const DEFAULT_IMAGE_PULL_PROGRESS_INTERVAL_SECS: u64 = 10;
+/// Default maximum number of attempts made to fetch the metadata of a client app.
+const DEFAULT_METADATA_MAX_ATTEMPTS: u32 = 3;
+/// Default maximum number of idle connections kept open to the aggregator.
+const DEFAULT_HTTP_POOL_MAX_IDLE: usize = 8;
+/// Default time (in seconds) an idle connection to the aggregator is kept open.
+const DEFAULT_HTTP_POOL_IDLE_TIMEOUT_SECS: u64 = 90;
+/// Default time (in seconds) a task container may run before it is stopped.
+const DEFAULT_CONTAINER_TIMEOUT_SECS: u64 = 300;
+/// Default upper bound (in seconds) on the container timeout of a client app.
+const DEFAULT_MAX_CONTAINER_TIMEOUT_SECS: u64 = 3600;
+/// Default time (in seconds) a task container is given to exit after SIGTERM.
+const DEFAULT_CONTAINER_STOP_GRACE_SECS: u64 = 10;
+/// Default memory limit (in MiB) of task containers (unlimited).
+const DEFAULT_CONTAINER_MEMORY_MB: u64 = 0;
+/// Default number of CPUs task containers may use (unlimited).
+const DEFAULT_CONTAINER_CPUS: f64 = 0.0;
+/// Default time (in seconds) running tasks are given to finish on shutdown.
+const DEFAULT_SHUTDOWN_DRAIN_TIMEOUT_SECS: u64 = 30;
+/// Default time (in seconds) without events or blocks before the event subscriptions are checked.
+const DEFAULT_LISTENER_WATCHDOG_SECS: u64 = 120;
+/// Default time (in seconds) the operator waits for an aggregator at startup (no wait).
+const DEFAULT_AGGREGATOR_WAIT_TIMEOUT_SECS: u64 = 0;
+/// Default maximum age (in seconds) of a task the operator processes (no limit).
+const DEFAULT_MAX_TASK_AGE_SECS: u64 = 0;
+/// Default signature scheme of the responses, which binds them to their task.
+const DEFAULT_RESPONSE_VERSION: u8 = RESPONSE_VERSION_ECDSA_TASK_BOUND;
+/// Default platform images are pulled and containers run for.
+const DEFAULT_CONTAINER_PLATFORM: &str = "linux/amd64";
+/// Default maximum number of task outputs kept in the result cache.
+const DEFAULT_RESULT_CACHE_SIZE: usize = 256;
+/// Default number of consecutive failures of a client app that open its circuit breaker
+/// (disabled).
+const DEFAULT_CIRCUIT_BREAKER_THRESHOLD: u32 = 0;
+/// Default time (in seconds) the tasks of a client app are skipped once its breaker opened.
+const DEFAULT_CIRCUIT_BREAKER_COOLDOWN_SECS: u64 = 300;
+/// Default maximum number of bytes of container output kept in memory (64 KiB).
+const DEFAULT_MAX_CONTAINER_LOG_SIZE: usize = 64 * 1024;
+/// Default of whether a new task of a client app cancels its older ones.
+const DEFAULT_CANCEL_SUPERSEDED_TASKS: bool = false;
+/// Default for keeping exited task containers for inspection (removed).
+const DEFAULT_KEEP_CONTAINERS: bool = false;
+/// Smallest memory limit (in MiB) Docker accepts for a container.
+const MIN_CONTAINER_MEMORY_MB: u64 = 6;
+/// Smallest CPU quota Docker accepts for a container.
+const MIN_CONTAINER_CPUS: f64 = 0.01;
+/// File name of the registration salt store, under the operator's configuration directory.
+const SALT_STORE_FILE_NAME: &str = "registration_salts.json";
+/// Default path of the `cosign` binary, looked up in `PATH`.
+const DEFAULT_COSIGN_PATH: &str = "cosign";
+
+/// Which part of the container output is kept when it exceeds the size limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogTruncation {
+    /// Keep the first bytes of the output.
+    Head,
+    /// Keep the last bytes of the output, where results are usually printed.
+    #[default]
+    Tail,
+}
+
+impl FromStr for LogTruncation {
+    type Err = eyre::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "head" => Ok(LogTruncation::Head),
+            "tail" => Ok(LogTruncation::Tail),
+            other => Err(eyre::eyre!("Unknown log truncation: {:?}", other)),
+        }
+    }
+}
+
+/// What happens to the tasks of a client app whose image failed to pull on startup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnImagePullFailure {
+    /// Skip the tasks of the app.
+    Skip,
+    /// Report a failure to the aggregator for each task, so it doesn't wait on this operator.
+    SubmitFailure,
+    /// Pull the image again when a task comes in, and run the task if the pull succeeds.
+    #[default]
+    RetryAtTask,
+}
+
+impl FromStr for OnImagePullFailure {
+    type Err = eyre::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "skip" => Ok(OnImagePullFailure::Skip),
+            "submit_failure" => Ok(OnImagePullFailure::SubmitFailure),
+            "retry_at_task" => Ok(OnImagePullFailure::RetryAtTask),
+            other => Err(eyre::eyre!(
+                "Unknown image pull failure behavior: {:?}",
+                other
+            )),
+        }
+    }
+}
+
+/// A remote Docker daemon, reached over TCP rather than through the local socket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DockerHost {
+    /// Plain TCP, at `<host>:<port>`.
+    Tcp(String),
+    /// TCP secured with TLS, at `<host>:<port>`, authenticating with the `key.pem`,
+    /// `cert.pem` and `ca.pem` files of `cert_path`.
+    Tls { address: String, cert_path: PathBuf },
+}
+
+/// Overrides applied to a client app's container when it is run. Unset fields keep the
+/// image defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RunOptions {
+    /// Command run by the container, replacing the image's `CMD`.
+    pub cmd: Option<Vec<String>>,
+    /// Entrypoint of the container, replacing the image's `ENTRYPOINT`.
+    pub entrypoint: Option<Vec<String>>,
+    /// Working directory of the container, replacing the image's `WORKDIR`.
+    pub working_dir: Option<String>,
+    /// Whether the container may reach the network. Task containers run without any network
+    /// by default, as live data would make results differ between operators.
+    pub network_access: bool,
+    /// Whether the container may write to its image filesystem. Task containers run with a
+    /// read-only root filesystem by default, and get a tmpfs at `/tmp` for scratch space.
+    pub writable_rootfs: bool,
+    /// Size (in MiB) of the tmpfs mounted at `/tmp`, `0` to mount none. Defaults to 64 MiB.
+    pub tmp_size_mb: Option<u64>,
+    /// Time (in seconds) the container may run before it is sent SIGTERM, replacing
+    /// `container_timeout_secs`. Must be between 1 and `max_container_timeout_secs`.
+    pub timeout_secs: Option<u64>,
+}
+
+/// Values read from a TOML configuration file. Every field is optional: missing fields fall
+/// back to the built-in defaults, and environment variables override whatever is set here.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    keystore_path: Option<PathBuf>,
+    operator_name: Option<String>,
+    docker_sock_path: Option<String>,
+    docker_host: Option<String>,
+    docker_tls_verify: Option<bool>,
+    docker_cert_path: Option<PathBuf>,
+    aggregator_url: Option<String>,
+    aggregator_urls: Option<Vec<String>>,
+    aggregator_ca_cert_path: Option<PathBuf>,
+    result_types: Option<ResultTypes>,
+    max_container_log_size: Option<usize>,
+    container_log_truncation: Option<LogTruncation>,
+    container_timeout_secs: Option<u64>,
+    max_container_timeout_secs: Option<u64>,
+    container_stop_grace_secs: Option<u64>,
+    container_memory_mb: Option<u64>,
+    container_cpus: Option<f64>,
+    shutdown_drain_timeout_secs: Option<u64>,
+    container_platform: Option<String>,
+    local_runs: Option<u32>,
+    client_app_allowlist: Option<Vec<AppId>>,
+    registry_allowlist: Option<Vec<String>>,
+    require_client_apps: Option<bool>,
+    result_cache_apps: Option<Vec<AppId>>,
+    result_cache_size: Option<usize>,
+    circuit_breaker_threshold: Option<u32>,
+    circuit_breaker_cooldown_secs: Option<u64>,
+    image_pull_max_attempts: Option<u32>,
+    image_pull_stall_timeout_secs: Option<u64>,
+    image_pull_progress_interval_secs: Option<u64>,
+    on_image_pull_failure: Option<OnImagePullFailure>,
+    metadata_max_attempts: Option<u32>,
+    http_pool_max_idle: Option<usize>,
+    http_pool_idle_timeout_secs: Option<u64>,
+    task_confirmations: Option<u64>,
+    listener_watchdog_secs: Option<u64>,
+    aggregator_wait_timeout_secs: Option<u64>,
+    max_task_age_secs: Option<u64>,
+    response_version: Option<u8>,
+    cancel_superseded_tasks: Option<bool>,
+    keep_containers: Option<bool>,
+    status_server_addr: Option<SocketAddr>,
+    status_admin_token: Option<String>,
+    salt_store_path: Option<PathBuf>,
+    run_options: Option<HashMap<AppId, RunOptions>>,
+    verify_image_signatures: Option<bool>,
+    cosign_path: Option<String>,
+    image_signing_keys: Option<HashMap<AppId, PathBuf>>,
+}
 
 /// `OperatorConfig` represents the configuration for the operator service.
 ///
 /// This struct holds the following configuration:
 /// - `docker_sock_path`: The path to the Docker socket file (docker.sock).
+/// - `docker_host`: The remote Docker daemon to use instead of the socket, if any.
 /// - `ecdsa_signer`: The ECDSA signer for cryptographic operations.
+/// - `operator_name`: The name the operator's logs are tagged with.
+/// - `result_types`: The expected result type of each client app.
+/// - `max_container_log_size` / `container_log_truncation`: How much container output is kept.
+/// - `container_timeout_secs` / `container_stop_grace_secs`: How runaway containers are stopped.
+/// - `container_memory_mb` / `container_cpus`: The resources task containers may use.
+/// - `shutdown_drain_timeout_secs`: How long running tasks may delay shutdown.
+/// - `container_platform`: The platform images are pulled and containers run for.
+/// - `local_runs`: How many times each task image is run before its result is submitted.
+/// - `client_app_allowlist`: The client apps served by the operator.
+/// - `registry_allowlist`: The registries client app images may be pulled from.
+/// - `require_client_apps`: Whether startup fails when no registered client app is served.
+/// - `result_cache_apps` / `result_cache_size`: Which task outputs are reused, and how many.
+/// - `circuit_breaker_threshold` / `circuit_breaker_cooldown_secs`: When the tasks of a failing
+///   client app are skipped, and for how long.
+/// - `image_pull_max_attempts`: How many times a failing image pull is attempted.
+/// - `image_pull_stall_timeout_secs`: How long an image pull may go without progress.
+/// - `image_pull_progress_interval_secs`: How often the progress of an image pull is logged.
+/// - `on_image_pull_failure`: What happens to the tasks of apps whose image failed to pull.
+/// - `metadata_max_attempts`: How many times a failing client app metadata call is attempted.
+/// - `http_pool_max_idle` / `http_pool_idle_timeout_secs`: Connection pooling to the aggregator.
+/// - `task_confirmations`: How deep a task event must be before it is processed.
+/// - `listener_watchdog_secs`: How long the event listener may go without events or blocks.
+/// - `aggregator_wait_timeout_secs`: How long startup waits for an aggregator to be ready.
+/// - `max_task_age_secs`: How old a task may be and still be processed.
+/// - `response_version`: The scheme responses are signed with.
+/// - `cancel_superseded_tasks`: Whether a new task of a client app cancels its older ones.
+/// - `keep_containers`: Whether exited task containers are kept for debugging.
+/// - `status_server_addr`: Where the health and stats endpoints are served, if anywhere.
+/// - `status_admin_token`: The bearer token required by the prefetch endpoint.
+/// - `salt_store_path`: Where the salts of AVS registrations are persisted.
+/// - `run_options`: The container overrides of each client app.
+/// - `verify_image_signatures` / `cosign_path` / `image_signing_keys`: How client app images
+///   are verified before being pulled or run.
 ///
 /// The configuration is loaded from environment variables, with defaults based
 /// on the operating system (macOS/Linux). It optionally loads values from a
-/// `.env` file if one exists.
+/// `.env` file if one exists, and from a TOML file when built with `from_file`.
+/// Environment variables always take precedence over the file.
 #[derive(Debug)]
 pub struct OperatorConfig {
     /// The path to the Docker socket file (docker.sock).
     /// - Defaults to `$HOME/.colima/docker.sock` on macOS.
     /// - Defaults to `/var/run/docker.sock` on Linux.
-    /// - Can be overridden by the `DOCKER_SOCK_PATH` environment variable.
+    /// - Can be overridden by the `DOCKER_SOCK_PATH` environment variable, or by a
+    ///   `unix://<path>` `DOCKER_HOST`.
+    /// - Must point to an existing unix socket when a command using Docker runs, unless
+    ///   `docker_host` is set.
     pub docker_sock_path: String,
 
-    /// The URL of the aggregator.
-    pub aggregator_url: String,
+    /// The remote Docker daemon used instead of the socket, read from a `tcp://`, `http://`
+    /// or `https://` `DOCKER_HOST` environment variable (or `docker_host` in the
+    /// configuration file).
+    /// - TLS is used for `https://` hosts, or when `DOCKER_TLS_VERIFY` is set.
+    /// - TLS certificates are read from `DOCKER_CERT_PATH`, defaulting to `$HOME/.docker`.
+    pub docker_host: Option<DockerHost>,
+
+    /// The URLs of the aggregators results are submitted to. Results are submitted to every
+    /// aggregator at once, and count as submitted when at least one accepts them.
+    /// - Defaults to `http://0.0.0.0:8080`.
+    /// - Can be overridden by the `AGGREGATOR_URLS` environment variable, as a comma separated
+    ///   list, or by the `AGGREGATOR_URL` environment variable for a single aggregator.
+    pub aggregator_urls: Vec<String>,
+
+    /// PEM certificate trusted in addition to the system roots when talking to the aggregator
+    /// over HTTPS, e.g. for an aggregator serving a self-signed certificate.
+    /// - Can be overridden by the `AGGREGATOR_CA_CERT_PATH` environment variable.
+    pub aggregator_ca_cert_path: Option<PathBuf>,
 
     /// The ECDSA signer used for cryptographic operations.
-    /// - Currently initialized with a hardcoded private key.
-    /// - In production, this should be securely loaded from an environment variable
-    ///   or a secure key management system.
+    /// - Decrypted from the JSON keystore (Web3 Secret Storage) at `OPERATOR_KEYSTORE_PATH`
+    ///   (or `keystore_path` in the configuration file), with the passphrase in the
+    ///   `OPERATOR_KEYSTORE_PASSWORD` environment variable.
+    /// - Without a keystore, the raw private key given on the command line is used, which is
+    ///   only accepted on Anvil.
     pub ecdsa_signer: PrivateKeySigner,
+
+    /// Display name of the operator, attached to every log line so the logs of an operator
+    /// fleet can be told apart once aggregated.
+    /// - Defaults to the operator address.
+    /// - Can be overridden by the `OPERATOR_NAME` environment variable.
+    pub operator_name: Option<String>,
+
+    /// The `ResultType` produced by each client app, used to validate container output
+    /// before it is signed and submitted.
+    /// - Apps not listed default to `uint256`.
+    /// - Read from the `CLIENT_APP_RESULT_TYPES` environment variable as a comma separated
+    ///   list of `<app_id>:<uint256|bytes|json|text>` pairs, or from a `[result_types]` table
+    ///   in the configuration file.
+    pub result_types: ResultTypes,
+
+    /// Maximum number of bytes of container output kept in memory.
+    /// - Output beyond this size is dropped and replaced by a truncation marker.
+    /// - Defaults to 64 KiB.
+    /// - Can be overridden by the `MAX_CONTAINER_LOG_SIZE` environment variable.
+    pub max_container_log_size: usize,
+
+    /// Which part of the container output is kept once it exceeds `max_container_log_size`.
+    /// - Defaults to `tail`.
+    /// - Can be overridden by the `CONTAINER_LOG_TRUNCATION` environment variable
+    ///   (`head` or `tail`).
+    pub container_log_truncation: LogTruncation,
+
+    /// Time (in seconds) a task container may run before it is sent SIGTERM.
+    /// - Defaults to 300 seconds, `0` lets containers run indefinitely.
+    /// - Can be overridden by the `CONTAINER_TIMEOUT_SECS` environment variable.
+    pub container_timeout_secs: u64,
+
+    /// Upper bound (in seconds) on the `timeout_secs` a client app's `run_options` may set, so
+    /// a misconfigured override can't hold the task processor for hours.
+    /// - Defaults to 3600 seconds.
+    /// - Can be overridden by the `MAX_CONTAINER_TIMEOUT_SECS` environment variable.
+    pub max_container_timeout_secs: u64,
+
+    /// Time (in seconds) a timed out container is given to exit after SIGTERM, so it can flush
+    /// its output, before it is sent SIGKILL.
+    /// - Defaults to 10 seconds.
+    /// - Can be overridden by the `CONTAINER_STOP_GRACE_SECS` environment variable.
+    pub container_stop_grace_secs: u64,
+
+    /// Memory limit (in MiB) of task containers, which are killed by the kernel when they go
+    /// over it. Containers get no swap on top of it.
+    /// - Defaults to 0 (unlimited), otherwise at least 6 MiB.
+    /// - Can be overridden by the `CONTAINER_MEMORY_MB` environment variable.
+    pub container_memory_mb: u64,
+
+    /// Number of CPUs task containers may use, e.g. `1.5`. Containers are throttled rather
+    /// than stopped when they reach it.
+    /// - Defaults to 0 (unlimited), otherwise at least 0.01 and at most the number of CPUs of
+    ///   the host when the Docker daemon is local.
+    /// - Can be overridden by the `CONTAINER_CPUS` environment variable.
+    pub container_cpus: f64,
+
+    /// Time (in seconds) the running task is given to finish and submit its result once the
+    /// operator is asked to stop (Ctrl-C or SIGTERM). Queued tasks aren't started meanwhile.
+    /// Containers still running after it are killed and removed, and their tasks abandoned.
+    /// - Defaults to 30 seconds, `0` abandons running tasks right away.
+    /// - Can be overridden by the `SHUTDOWN_DRAIN_TIMEOUT_SECS` environment variable.
+    pub shutdown_drain_timeout_secs: u64,
+
+    /// Platform client app images are pulled and their containers run for. Operators on
+    /// different architectures (e.g. Apple Silicon and x86 servers) would otherwise run
+    /// different builds of the image, or the same one under emulation, and may disagree.
+    /// - Defaults to `linux/amd64`.
+    /// - Can be overridden by the `CONTAINER_PLATFORM` environment variable.
+    pub container_platform: String,
+
+    /// Number of times each task image is run locally. The result is only submitted if every
+    /// run produced the same output, which catches nondeterministic images early.
+    /// - Defaults to 1 (a single run). Values below 1 are treated as 1.
+    /// - Can be overridden by the `LOCAL_RUNS` environment variable.
+    pub local_runs: u32,
+
+    /// The client app ids the operator serves. Images of other apps are not pulled and their
+    /// tasks are skipped.
+    /// - An empty list (the default) serves every registered client app.
+    /// - Read from the `CLIENT_APP_ALLOWLIST` environment variable as a comma separated list
+    ///   of app ids, or from `client_app_allowlist` in the configuration file.
+    pub client_app_allowlist: Vec<AppId>,
+
+    /// The registries client app images may be pulled from, each a registry host optionally
+    /// followed by a path prefix (e.g. `docker.io`, `ghcr.io/giza`). Images from other
+    /// registries are not pulled and their tasks are skipped. Images without a registry host
+    /// come from `docker.io`.
+    /// - An empty list (the default) allows every registry.
+    /// - Read from the `REGISTRY_ALLOWLIST` environment variable as a comma separated list, or
+    ///   from `registry_allowlist` in the configuration file.
+    pub registry_allowlist: Vec<String>,
+
+    /// Whether the operator fails on startup when no registered client app is served, either
+    /// because none is registered or because none is in the allowlist. This usually points at
+    /// the wrong chain or registry, and otherwise leaves the operator idle with only a warning.
+    /// - Defaults to `false`.
+    /// - Can be overridden by the `REQUIRE_CLIENT_APPS` environment variable.
+    pub require_client_apps: bool,
+
+    /// The deterministic client apps whose task outputs are cached, so an identical task
+    /// (same app, same request, same image) reuses the output instead of running the
+    /// container again. The output is signed again for every task. The client app metadata
+    /// doesn't say whether an app is deterministic, so apps must be opted in here.
+    /// - Empty by default, caching nothing.
+    /// - Read from the `RESULT_CACHE_APPS` environment variable as a comma separated list of
+    ///   app ids, or from `result_cache_apps` in the configuration file.
+    pub result_cache_apps: Vec<AppId>,
+
+    /// Maximum number of task outputs cached, the least recently used ones are evicted first.
+    /// - Defaults to 256, `0` disables the cache.
+    /// - Can be overridden by the `RESULT_CACHE_SIZE` environment variable.
+    pub result_cache_size: usize,
+
+    /// Number of tasks of a client app failing in a row (image pull, run or result failures,
+    /// and non-zero exit codes) after which the app's tasks are skipped for
+    /// `circuit_breaker_cooldown_secs`, rather than wasting resources on an app that keeps
+    /// failing. A successful prefetch of the app's image through the status server resumes them.
+    /// - Defaults to 0, which never skips tasks.
+    /// - Can be overridden by the `CIRCUIT_BREAKER_THRESHOLD` environment variable.
+    pub circuit_breaker_threshold: u32,
+
+    /// Time (in seconds) the tasks of a client app are skipped once its circuit breaker opened.
+    /// - Defaults to 300 seconds.
+    /// - Can be overridden by the `CIRCUIT_BREAKER_COOLDOWN_SECS` environment variable.
+    pub circuit_breaker_cooldown_secs: u64,
+
+    /// Maximum number of attempts made to pull a client app image. Transient failures are
+    /// retried with exponential backoff, permanent ones (image not found) are not.
+    /// - Defaults to 5. Values below 1 are treated as 1.
+    /// - Can be overridden by the `IMAGE_PULL_MAX_ATTEMPTS` environment variable.
+    pub image_pull_max_attempts: u32,
+
+    /// Time (in seconds) an image pull may go without any progress from the Docker daemon
+    /// before the attempt is cancelled and retried. Every progress update resets the timeout,
+    /// so large images that keep downloading aren't cut off.
+    /// - Defaults to 120 seconds, `0` waits indefinitely.
+    /// - Can be overridden by the `IMAGE_PULL_STALL_TIMEOUT_SECS` environment variable.
+    pub image_pull_stall_timeout_secs: u64,
+
+    /// Minimum time (in seconds) between two debug logs of the progress of an image pull
+    /// (layers complete and bytes downloaded), so long pulls of large images show they are
+    /// still moving without flooding the logs.
+    /// - Defaults to 10 seconds, `0` disables the progress logs.
+    /// - Can be overridden by the `IMAGE_PULL_PROGRESS_INTERVAL_SECS` environment variable.
+    pub image_pull_progress_interval_secs: u64,
+
+    /// What happens to the tasks of a client app whose image failed to pull on startup:
+    /// `skip` them, `submit_failure` reports so the aggregator isn't left waiting, or
+    /// `retry_at_task` the pull before running each task.
+    /// - Defaults to `retry_at_task`.
+    /// - Can be overridden by the `ON_IMAGE_PULL_FAILURE` environment variable.
+    pub on_image_pull_failure: OnImagePullFailure,
+
+    /// Maximum number of attempts made to fetch the metadata of a client app. Failed calls are
+    /// retried with exponential backoff, and a task whose metadata still can't be fetched is
+    /// requeued rather than dropped.
+    /// - Defaults to 3. Values below 1 are treated as 1.
+    /// - Can be overridden by the `METADATA_MAX_ATTEMPTS` environment variable.
+    pub metadata_max_attempts: u32,
+
+    /// Maximum number of idle connections kept open to the aggregator for reuse.
+    /// - Defaults to 8.
+    /// - Can be overridden by the `HTTP_POOL_MAX_IDLE` environment variable.
+    pub http_pool_max_idle: usize,
+
+    /// Time (in seconds) an idle connection to the aggregator is kept open before being closed.
+    /// - Defaults to 90 seconds.
+    /// - Can be overridden by the `HTTP_POOL_IDLE_TIMEOUT_SECS` environment variable.
+    pub http_pool_idle_timeout_secs: u64,
+
+    /// Number of blocks a `TaskRequested` event must be buried under before the task is
+    /// processed. Events reorged out before reaching that depth are discarded.
+    /// - Defaults to the chain's `Chain::default_task_confirmations` (0 on Anvil).
+    /// - Can be overridden by the `TASK_CONFIRMATIONS` environment variable.
+    pub task_confirmations: Option<u64>,
+
+    /// Time (in seconds) the event listener may go without any `TaskRequested` event or new
+    /// block before the chain is probed. If the chain has moved on in the meantime, the
+    /// subscriptions silently died and are rebuilt; otherwise the chain is just idle.
+    /// - Defaults to 120 seconds, `0` disables the watchdog.
+    /// - Can be overridden by the `LISTENER_WATCHDOG_SECS` environment variable.
+    pub listener_watchdog_secs: u64,
+
+    /// Maximum time (in seconds) the operator waits at startup for one of its aggregators to
+    /// be ready before listening for tasks, so it doesn't run tasks whose results would be
+    /// dropped. Aggregators are probed with exponential backoff; once the time is up a warning
+    /// is logged and the operator starts anyway, for aggregators expected to start later.
+    /// - Defaults to 0, which doesn't wait.
+    /// - Can be overridden by the `AGGREGATOR_WAIT_TIMEOUT_SECS` environment variable.
+    pub aggregator_wait_timeout_secs: u64,
+
+    /// Maximum time (in seconds) since a task was requested, measured from the timestamp of
+    /// the block of its `TaskRequested` event, for the task to be processed. Older tasks,
+    /// such as those picked up after a long outage, are skipped without running a container.
+    /// Tasks whose block can't be fetched are processed.
+    /// - Defaults to 0, which processes tasks of any age.
+    /// - Can be overridden by the `MAX_TASK_AGE_SECS` environment variable.
+    pub max_task_age_secs: u64,
+
+    /// Signature scheme of the responses submitted to the aggregator. Version 1 signs the task
+    /// id along with the result, so a response can't be replayed for another task. Version 0
+    /// only signs the result, for aggregators predating version 1.
+    /// - Defaults to 1.
+    /// - Can be overridden by the `RESPONSE_VERSION` environment variable.
+    pub response_version: u8,
+
+    /// Whether a new task of a client app cancels its older ones: the container of a running
+    /// task is killed and no result is submitted for it, and queued tasks are skipped. Only
+    /// tasks of the same client app supersede each other.
+    /// - Defaults to `false`.
+    /// - Can be overridden by the `CANCEL_SUPERSEDED_TASKS` environment variable.
+    pub cancel_superseded_tasks: bool,
+
+    /// Whether task containers are left behind for post-mortem debugging instead of being
+    /// removed, labelled `avsthon.task=<task_id>` and named after their task. Timed out and
+    /// cancelled containers are stopped and kept as well. They
+    /// accumulate with every task, so this is only meant for debugging; they are removed on
+    /// the next startup.
+    /// - Defaults to `false`.
+    /// - Can be overridden by the `KEEP_CONTAINERS` environment variable, or set with the
+    ///   `--keep-containers` flag.
+    pub keep_containers: bool,
+
+    /// Address the status server binds to, serving `GET /health`, `GET /stats` and
+    /// `POST /prefetch/<app_id>`.
+    /// - Disabled by default.
+    /// - Can be overridden by the `STATUS_SERVER_ADDR` environment variable, e.g.
+    ///   `127.0.0.1:9090`. Binding every interface (`0.0.0.0:9090`) exposes the health and stats
+    ///   endpoints, prefetching still requires `status_admin_token`.
+    pub status_server_addr: Option<SocketAddr>,
+
+    /// Bearer token required by `POST /prefetch/<app_id>`, which pulls images on demand.
+    /// - When unset, the endpoint only accepts requests from the loopback interface.
+    /// - Can be overridden by the `STATUS_ADMIN_TOKEN` environment variable.
+    pub status_admin_token: Option<String>,
+
+    /// Path of the JSON file persisting the salts AVS registrations were signed with, so that
+    /// a registration retried after a failure or a restart never reuses one.
+    /// - Defaults to `avsthon/registration_salts.json` under the user's configuration
+    ///   directory (`$HOME/.config` on Linux, `$HOME/Library/Application Support` on macOS).
+    /// - Can be overridden by the `SALT_STORE_PATH` environment variable.
+    pub salt_store_path: PathBuf,
+
+    /// The `cmd`, `entrypoint` and `working_dir` overrides applied to each client app's
+    /// container, so one base image can be reused with different commands, whether the
+    /// container gets network access and a writable filesystem, and how long it may run.
+    /// - Apps not listed run with the image defaults, without network and with a read-only
+    ///   root filesystem.
+    /// - Read from the `[run_options."<app_id>"]` tables of the configuration file.
+    pub run_options: HashMap<AppId, RunOptions>,
+
+    /// Whether the cosign signature of client app images is verified before they are pulled or
+    /// run. Tasks of apps whose image is unsigned, signed with another key, or has no key in
+    /// `image_signing_keys` are skipped.
+    /// - Defaults to `false`.
+    /// - Can be overridden by the `VERIFY_IMAGE_SIGNATURES` environment variable.
+    pub verify_image_signatures: bool,
+
+    /// Path of the `cosign` binary used to verify image signatures.
+    /// - Defaults to `cosign`, looked up in `PATH`.
+    /// - Can be overridden by the `COSIGN_PATH` environment variable.
+    pub cosign_path: String,
+
+    /// The cosign public key each client app's image must be signed with.
+    /// - Read from the `[image_signing_keys]` table of the configuration file.
+    pub image_signing_keys: HashMap<AppId, PathBuf>,
 }
 
 impl OperatorConfig {
@@ -38,49 +574,772 @@ impl OperatorConfig {
     /// - Set the Docker socket path based on the `DOCKER_SOCK_PATH` environment
     ///   variable or use the platform-specific default path.
     ///
+    /// # Errors
+    /// Returns an error if an environment variable has an invalid value.
+    ///
     /// # Example
     ///
-    /// ```rust
-    /// let config = OperatorConfig::from_env();
+    /// ```rust,no_run
+    /// use contract_bindings::Chain;
+    /// use operator::operator_config::OperatorConfig;
+    ///
+    /// let config = OperatorConfig::from_env(&Chain::Anvil, Some("<private_key>")).unwrap();
     /// println!("Docker socket path: {}", config.docker_sock_path);
     /// ```
-    pub(super) fn from_env(private_key: &str) -> Self {
+    pub fn from_env(chain: &Chain, private_key: Option<&str>) -> Result<Self> {
+        Self::load(chain, private_key, FileConfig::default())
+    }
+
+    /// Constructs a new `OperatorConfig` from the TOML file at `path`, layered under the
+    /// environment variables.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed, or if an environment variable has
+    /// an invalid value.
+    pub fn from_file(path: &Path, chain: &Chain, private_key: Option<&str>) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+        let file_config = toml::from_str(&contents)
+            .wrap_err_with(|| format!("Failed to parse {}", path.display()))?;
+
+        Self::load(chain, private_key, file_config)
+    }
+
+    /// Layers environment variables over the file values over the built-in defaults.
+    fn load(chain: &Chain, private_key: Option<&str>, file_config: FileConfig) -> Result<Self> {
         // Load environment variables from .env file if present
         dotenv().ok();
 
-        let docker_sock_path = Self::get_docker_sock_path();
+        // Same semantics as the Docker CLI: any non-empty DOCKER_TLS_VERIFY enables TLS
+        let docker_tls_verify = env::var("DOCKER_TLS_VERIFY")
+            .ok()
+            .map(|value| !value.is_empty())
+            .or(file_config.docker_tls_verify)
+            .unwrap_or(false);
+        let docker_cert_path = env::var("DOCKER_CERT_PATH")
+            .ok()
+            .map(PathBuf::from)
+            .or(file_config.docker_cert_path)
+            .unwrap_or_else(|| Path::new(&Self::get_home_dir()).join(".docker"));
+        let docker_host_url = env::var("DOCKER_HOST").ok().or(file_config.docker_host);
+        let docker_host = match &docker_host_url {
+            Some(url) => Self::parse_docker_host(url, docker_tls_verify, docker_cert_path)?,
+            None => None,
+        };
+
+        let docker_sock_path = env::var("DOCKER_SOCK_PATH")
+            .ok()
+            .or(file_config.docker_sock_path)
+            .or_else(|| {
+                docker_host_url
+                    .as_deref()
+                    .and_then(|url| url.strip_prefix("unix://"))
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(Self::get_docker_sock_path);
+
+        let keystore_path = env::var("OPERATOR_KEYSTORE_PATH")
+            .ok()
+            .map(PathBuf::from)
+            .or(file_config.keystore_path);
+        let ecdsa_signer = Self::load_signer(chain, private_key, keystore_path)?;
+
+        let operator_name = env::var("OPERATOR_NAME").ok().or(file_config.operator_name);
+
+        let aggregator_urls = env::var("AGGREGATOR_URLS")
+            .ok()
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|url| !url.is_empty())
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .or_else(|| env::var("AGGREGATOR_URL").ok().map(|url| vec![url]))
+            .or(file_config.aggregator_urls)
+            .or(file_config.aggregator_url.map(|url| vec![url]))
+            .filter(|urls| !urls.is_empty())
+            .unwrap_or_else(|| vec![DEFAULT_AGGREGATOR_URL.to_string()]);
+
+        let aggregator_ca_cert_path = env::var("AGGREGATOR_CA_CERT_PATH")
+            .ok()
+            .map(PathBuf::from)
+            .or(file_config.aggregator_ca_cert_path);
+
+        let result_types = Self::get_result_types(file_config.result_types.unwrap_or_default());
+
+        let max_container_log_size = Self::env_or(
+            "MAX_CONTAINER_LOG_SIZE",
+            file_config
+                .max_container_log_size
+                .unwrap_or(DEFAULT_MAX_CONTAINER_LOG_SIZE),
+        )?;
+
+        let container_log_truncation = Self::env_or(
+            "CONTAINER_LOG_TRUNCATION",
+            file_config.container_log_truncation.unwrap_or_default(),
+        )?;
+
+        let container_timeout_secs = Self::env_or(
+            "CONTAINER_TIMEOUT_SECS",
+            file_config
+                .container_timeout_secs
+                .unwrap_or(DEFAULT_CONTAINER_TIMEOUT_SECS),
+        )?;
+
+        let max_container_timeout_secs = Self::env_or(
+            "MAX_CONTAINER_TIMEOUT_SECS",
+            file_config
+                .max_container_timeout_secs
+                .unwrap_or(DEFAULT_MAX_CONTAINER_TIMEOUT_SECS),
+        )?;
+
+        let container_stop_grace_secs = Self::env_or(
+            "CONTAINER_STOP_GRACE_SECS",
+            file_config
+                .container_stop_grace_secs
+                .unwrap_or(DEFAULT_CONTAINER_STOP_GRACE_SECS),
+        )?;
+
+        let container_memory_mb = Self::env_or(
+            "CONTAINER_MEMORY_MB",
+            file_config
+                .container_memory_mb
+                .unwrap_or(DEFAULT_CONTAINER_MEMORY_MB),
+        )?;
+
+        let container_cpus = Self::env_or(
+            "CONTAINER_CPUS",
+            file_config.container_cpus.unwrap_or(DEFAULT_CONTAINER_CPUS),
+        )?;
+
+        let shutdown_drain_timeout_secs = Self::env_or(
+            "SHUTDOWN_DRAIN_TIMEOUT_SECS",
+            file_config
+                .shutdown_drain_timeout_secs
+                .unwrap_or(DEFAULT_SHUTDOWN_DRAIN_TIMEOUT_SECS),
+        )?;
 
-        let ecdsa_signer: PrivateKeySigner = private_key
-            .parse()
-            .expect("Failed to parse ECDSA private key");
+        let container_platform = env::var("CONTAINER_PLATFORM")
+            .ok()
+            .or(file_config.container_platform)
+            .unwrap_or_else(|| DEFAULT_CONTAINER_PLATFORM.to_string());
 
-        let aggregator_url = "http://0.0.0.0:8080".to_string();
+        let local_runs = Self::env_or(
+            "LOCAL_RUNS",
+            file_config.local_runs.unwrap_or(DEFAULT_LOCAL_RUNS),
+        )?
+        .max(1);
 
-        Self {
+        let http_pool_max_idle = Self::env_or(
+            "HTTP_POOL_MAX_IDLE",
+            file_config
+                .http_pool_max_idle
+                .unwrap_or(DEFAULT_HTTP_POOL_MAX_IDLE),
+        )?;
+
+        let http_pool_idle_timeout_secs = Self::env_or(
+            "HTTP_POOL_IDLE_TIMEOUT_SECS",
+            file_config
+                .http_pool_idle_timeout_secs
+                .unwrap_or(DEFAULT_HTTP_POOL_IDLE_TIMEOUT_SECS),
+        )?;
+
+        let client_app_allowlist = Self::get_app_ids(
+            "CLIENT_APP_ALLOWLIST",
+            file_config.client_app_allowlist.unwrap_or_default(),
+        );
+
+        let registry_allowlist = env::var("REGISTRY_ALLOWLIST")
+            .ok()
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(|registry| registry.trim().to_string())
+                    .filter(|registry| !registry.is_empty())
+                    .collect()
+            })
+            .or(file_config.registry_allowlist)
+            .unwrap_or_default();
+
+        let require_client_apps = Self::env_or(
+            "REQUIRE_CLIENT_APPS",
+            file_config.require_client_apps.unwrap_or(false),
+        )?;
+
+        let result_cache_apps = Self::get_app_ids(
+            "RESULT_CACHE_APPS",
+            file_config.result_cache_apps.unwrap_or_default(),
+        );
+
+        let result_cache_size = Self::env_or(
+            "RESULT_CACHE_SIZE",
+            file_config
+                .result_cache_size
+                .unwrap_or(DEFAULT_RESULT_CACHE_SIZE),
+        )?;
+
+        let circuit_breaker_threshold = Self::env_or(
+            "CIRCUIT_BREAKER_THRESHOLD",
+            file_config
+                .circuit_breaker_threshold
+                .unwrap_or(DEFAULT_CIRCUIT_BREAKER_THRESHOLD),
+        )?;
+
+        let circuit_breaker_cooldown_secs = Self::env_or(
+            "CIRCUIT_BREAKER_COOLDOWN_SECS",
+            file_config
+                .circuit_breaker_cooldown_secs
+                .unwrap_or(DEFAULT_CIRCUIT_BREAKER_COOLDOWN_SECS),
+        )?;
+
+        let image_pull_max_attempts = Self::env_or(
+            "IMAGE_PULL_MAX_ATTEMPTS",
+            file_config
+                .image_pull_max_attempts
+                .unwrap_or(DEFAULT_IMAGE_PULL_MAX_ATTEMPTS),
+        )?
+        .max(1);
+
+        let image_pull_stall_timeout_secs = Self::env_or(
+            "IMAGE_PULL_STALL_TIMEOUT_SECS",
+            file_config
+                .image_pull_stall_timeout_secs
+                .unwrap_or(DEFAULT_IMAGE_PULL_STALL_TIMEOUT_SECS),
+        )?;
+
+        let image_pull_progress_interval_secs = Self::env_or(
+            "IMAGE_PULL_PROGRESS_INTERVAL_SECS",
+            file_config
+                .image_pull_progress_interval_secs
+                .unwrap_or(DEFAULT_IMAGE_PULL_PROGRESS_INTERVAL_SECS),
+        )?;
+
+        let on_image_pull_failure = Self::env_or(
+            "ON_IMAGE_PULL_FAILURE",
+            file_config.on_image_pull_failure.unwrap_or_default(),
+        )?;
+
+        let metadata_max_attempts = Self::env_or(
+            "METADATA_MAX_ATTEMPTS",
+            file_config
+                .metadata_max_attempts
+                .unwrap_or(DEFAULT_METADATA_MAX_ATTEMPTS),
+        )?
+        .max(1);
+
+        let task_confirmations = env::var("TASK_CONFIRMATIONS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .or(file_config.task_confirmations);
+
+        let verify_image_signatures = Self::env_or(
+            "VERIFY_IMAGE_SIGNATURES",
+            file_config.verify_image_signatures.unwrap_or(false),
+        )?;
+
+        let cosign_path = env::var("COSIGN_PATH")
+            .ok()
+            .or(file_config.cosign_path)
+            .unwrap_or_else(|| DEFAULT_COSIGN_PATH.to_string());
+
+        let listener_watchdog_secs = Self::env_or(
+            "LISTENER_WATCHDOG_SECS",
+            file_config
+                .listener_watchdog_secs
+                .unwrap_or(DEFAULT_LISTENER_WATCHDOG_SECS),
+        )?;
+
+        let aggregator_wait_timeout_secs = Self::env_or(
+            "AGGREGATOR_WAIT_TIMEOUT_SECS",
+            file_config
+                .aggregator_wait_timeout_secs
+                .unwrap_or(DEFAULT_AGGREGATOR_WAIT_TIMEOUT_SECS),
+        )?;
+
+        let max_task_age_secs = Self::env_or(
+            "MAX_TASK_AGE_SECS",
+            file_config
+                .max_task_age_secs
+                .unwrap_or(DEFAULT_MAX_TASK_AGE_SECS),
+        )?;
+
+        let response_version = Self::env_or(
+            "RESPONSE_VERSION",
+            file_config
+                .response_version
+                .unwrap_or(DEFAULT_RESPONSE_VERSION),
+        )?;
+
+        let cancel_superseded_tasks = Self::env_or(
+            "CANCEL_SUPERSEDED_TASKS",
+            file_config
+                .cancel_superseded_tasks
+                .unwrap_or(DEFAULT_CANCEL_SUPERSEDED_TASKS),
+        )?;
+
+        let keep_containers = Self::env_or(
+            "KEEP_CONTAINERS",
+            file_config
+                .keep_containers
+                .unwrap_or(DEFAULT_KEEP_CONTAINERS),
+        )?;
+
+        let status_server_addr = env::var("STATUS_SERVER_ADDR")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .or(file_config.status_server_addr);
+        let status_admin_token = env::var("STATUS_ADMIN_TOKEN")
+            .ok()
+            .or(file_config.status_admin_token);
+
+        let salt_store_path = env::var("SALT_STORE_PATH")
+            .ok()
+            .map(PathBuf::from)
+            .or(file_config.salt_store_path)
+            .unwrap_or_else(Self::get_salt_store_path);
+
+        Ok(Self {
             docker_sock_path,
-            aggregator_url,
+            docker_host,
+            aggregator_urls,
+            aggregator_ca_cert_path,
             ecdsa_signer,
+            operator_name,
+            result_types,
+            max_container_log_size,
+            container_log_truncation,
+            container_timeout_secs,
+            max_container_timeout_secs,
+            container_stop_grace_secs,
+            container_memory_mb,
+            container_cpus,
+            shutdown_drain_timeout_secs,
+            container_platform,
+            local_runs,
+            client_app_allowlist,
+            registry_allowlist,
+            require_client_apps,
+            result_cache_apps,
+            result_cache_size,
+            circuit_breaker_threshold,
+            circuit_breaker_cooldown_secs,
+            image_pull_max_attempts,
+            image_pull_stall_timeout_secs,
+            image_pull_progress_interval_secs,
+            on_image_pull_failure,
+            metadata_max_attempts,
+            http_pool_max_idle,
+            http_pool_idle_timeout_secs,
+            task_confirmations,
+            listener_watchdog_secs,
+            aggregator_wait_timeout_secs,
+            max_task_age_secs,
+            response_version,
+            cancel_superseded_tasks,
+            keep_containers,
+            status_server_addr,
+            status_admin_token,
+            salt_store_path,
+            run_options: file_config.run_options.unwrap_or_default(),
+            verify_image_signatures,
+            cosign_path,
+            image_signing_keys: file_config.image_signing_keys.unwrap_or_default(),
+        })
+    }
+
+    /// Returns the memory limit of task containers in bytes.
+    ///
+    /// # Returns
+    /// `None` when containers are unlimited, or when the limit doesn't fit in the `i64` Docker
+    /// takes, which `validate` rejects.
+    pub fn container_memory_bytes(&self) -> Option<i64> {
+        if self.container_memory_mb == 0 {
+            return None;
         }
+        self.container_memory_mb
+            .checked_mul(1024 * 1024)
+            .and_then(|bytes| i64::try_from(bytes).ok())
     }
 
-    /// Determines the Docker socket path, using the following logic:
-    /// - If `DOCKER_SOCK_PATH` is set in the environment, it is used.
-    /// - Otherwise, the default path is chosen based on the operating system.
-    ///   - macOS: `$HOME/.colima/docker.sock`
-    ///   - Linux: `/var/run/docker.sock`
+    /// Checks the invariants of the configuration before anything is started, so that every
+    /// misconfiguration is reported at once rather than as a failure midway through startup.
+    ///
+    /// # Errors
+    /// Returns an error listing every problem found.
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+
+        if self.aggregator_urls.is_empty() {
+            problems.push("At least one aggregator URL is required".to_string());
+        }
+        for aggregator_url in &self.aggregator_urls {
+            match reqwest::Url::parse(aggregator_url) {
+                Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+                Ok(_) => problems.push(format!(
+                    "Aggregator URL {} must be an http:// or https:// URL",
+                    aggregator_url
+                )),
+                Err(e) => {
+                    problems.push(format!("Invalid aggregator URL {}: {}", aggregator_url, e))
+                }
+            }
+        }
+        if let Some(operator_name) = &self.operator_name {
+            if operator_name.trim().is_empty() {
+                problems.push("operator_name is set but empty".to_string());
+            }
+        }
+        if self
+            .status_admin_token
+            .as_deref()
+            .is_some_and(|token| token.trim().is_empty())
+        {
+            problems.push("status_admin_token is set but empty".to_string());
+        }
+        if !self.container_platform.contains('/') {
+            problems.push(format!(
+                "Invalid container_platform {:?}, expected <os>/<arch> such as linux/amd64",
+                self.container_platform
+            ));
+        }
+        if response_signing_bytes(self.response_version, TaskId::default(), "").is_none() {
+            problems.push(format!(
+                "Unsupported response_version: {}",
+                self.response_version
+            ));
+        }
+
+        for (name, value) in [
+            ("local_runs", self.local_runs),
+            ("image_pull_max_attempts", self.image_pull_max_attempts),
+            ("metadata_max_attempts", self.metadata_max_attempts),
+        ] {
+            if value == 0 {
+                problems.push(format!("{} must be positive", name));
+            }
+        }
+        if self.max_container_log_size == 0 {
+            problems.push("max_container_log_size must be positive".to_string());
+        }
+        if self.container_memory_mb > 0 {
+            if self.container_memory_mb < MIN_CONTAINER_MEMORY_MB {
+                problems.push(format!(
+                    "container_memory_mb must be 0 or at least {} MiB, got {}",
+                    MIN_CONTAINER_MEMORY_MB, self.container_memory_mb
+                ));
+            } else if self.container_memory_bytes().is_none() {
+                problems.push(format!(
+                    "container_memory_mb is too large, got {}",
+                    self.container_memory_mb
+                ));
+            }
+        }
+        if !self.container_cpus.is_finite() || self.container_cpus < 0.0 {
+            problems.push(format!(
+                "container_cpus must be 0 or positive, got {}",
+                self.container_cpus
+            ));
+        } else if self.container_cpus > 0.0 {
+            if self.container_cpus < MIN_CONTAINER_CPUS {
+                problems.push(format!(
+                    "container_cpus must be 0 or at least {}, got {}",
+                    MIN_CONTAINER_CPUS, self.container_cpus
+                ));
+            }
+            // The CPUs of a remote daemon's host are unknown
+            let host_cpus = std::thread::available_parallelism().map_or(0, usize::from);
+            if self.docker_host.is_none() && host_cpus > 0 && self.container_cpus > host_cpus as f64
+            {
+                problems.push(format!(
+                    "container_cpus exceeds the {} CPUs of the host, got {}",
+                    host_cpus, self.container_cpus
+                ));
+            }
+        }
+        if !self.result_cache_apps.is_empty() && self.result_cache_size == 0 {
+            problems.push("result_cache_size must be positive to cache results".to_string());
+        }
+        if self.circuit_breaker_threshold > 0 && self.circuit_breaker_cooldown_secs == 0 {
+            problems.push(
+                "circuit_breaker_cooldown_secs must be positive when the circuit breaker is enabled"
+                    .to_string(),
+            );
+        }
+        for (app_id, options) in &self.run_options {
+            match options.timeout_secs {
+                Some(timeout_secs)
+                    if timeout_secs == 0 || timeout_secs > self.max_container_timeout_secs =>
+                {
+                    problems.push(format!(
+                        "Container timeout of {:?} must be between 1 and {} seconds, got {}",
+                        app_id, self.max_container_timeout_secs, timeout_secs
+                    ))
+                }
+                _ => {}
+            }
+        }
+
+        let mut required_paths: Vec<&Path> = self
+            .aggregator_ca_cert_path
+            .iter()
+            .map(PathBuf::as_path)
+            .collect();
+        if let Some(DockerHost::Tls { cert_path, .. }) = &self.docker_host {
+            required_paths.push(cert_path);
+        }
+        if self.verify_image_signatures {
+            required_paths.extend(self.image_signing_keys.values().map(PathBuf::as_path));
+        }
+        for path in required_paths {
+            if !path.exists() {
+                problems.push(format!("{} does not exist", path.display()));
+            }
+        }
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(eyre!(
+                "Invalid configuration, {} problem(s) found:\n  - {}",
+                problems.len(),
+                problems.join("\n  - ")
+            )),
+        }
+    }
+
+    /// Checks that the salt store can be created, which only matters to the commands registering
+    /// the operator: `status` and `prefetch` don't need a writable configuration directory.
+    ///
+    /// # Errors
+    /// Returns an error if the nearest existing directory of the salt store isn't writable.
+    pub fn validate_salt_store(&self) -> Result<()> {
+        // The directories of the salt store are created on registration, so only the nearest one
+        // that already exists must be writable
+        let Some(ancestor) = self
+            .salt_store_path
+            .ancestors()
+            .skip(1)
+            .find(|ancestor| ancestor.as_os_str().is_empty() || ancestor.exists())
+        else {
+            return Ok(());
+        };
+        let writable = match ancestor.as_os_str().is_empty() {
+            true => Path::new("."),
+            false => ancestor,
+        }
+        .metadata()
+        .is_ok_and(|metadata| metadata.is_dir() && !metadata.permissions().readonly());
+        match writable {
+            true => Ok(()),
+            false => Err(eyre!(
+                "{} is not a writable directory, the salt store {} can't be created",
+                ancestor.display(),
+                self.salt_store_path.display()
+            )),
+        }
+    }
+
+    /// Loads the operator's signer from the JSON keystore at `keystore_path` when one is set,
+    /// unlocked with the `OPERATOR_KEYSTORE_PASSWORD` environment variable, or from the raw
+    /// `private_key` otherwise.
+    ///
+    /// # Errors
+    /// Returns an error if the keystore cannot be decrypted, or if no keystore is set on a
+    /// chain other than Anvil, where raw private keys are refused.
+    fn load_signer(
+        chain: &Chain,
+        private_key: Option<&str>,
+        keystore_path: Option<PathBuf>,
+    ) -> Result<PrivateKeySigner> {
+        if let Some(keystore_path) = keystore_path {
+            if private_key.is_some() {
+                warn!("Ignoring the private key argument, the keystore is used instead");
+            }
+            let password = env::var("OPERATOR_KEYSTORE_PASSWORD")
+                .wrap_err("OPERATOR_KEYSTORE_PASSWORD must be set to unlock the keystore")?;
+            return PrivateKeySigner::decrypt_keystore(&keystore_path, password).wrap_err_with(
+                || format!("Failed to decrypt keystore {}", keystore_path.display()),
+            );
+        }
+
+        match (chain, private_key) {
+            (Chain::Anvil, Some(private_key)) => private_key
+                .parse()
+                .wrap_err("Failed to parse ECDSA private key"),
+            (Chain::Anvil, None) => Err(eyre!(
+                "Either a private key or OPERATOR_KEYSTORE_PATH is required"
+            )),
+            (chain, _) => Err(eyre!(
+                "OPERATOR_KEYSTORE_PATH must be set on {:?}, raw private keys are only accepted on Anvil",
+                chain
+            )),
+        }
+    }
+
+    /// Reads and parses the environment variable `key`, falling back to `default` when it is
+    /// unset.
+    ///
+    /// # Errors
+    /// Returns an error if the variable is set but cannot be parsed.
+    fn env_or<T: FromStr>(key: &str, default: T) -> Result<T> {
+        match env::var(key) {
+            Ok(value) => value
+                .parse()
+                .map_err(|_| eyre!("Invalid value for {}: {:?}", key, value)),
+            Err(_) => Ok(default),
+        }
+    }
+
+    /// Parses the client app result types from the `CLIENT_APP_RESULT_TYPES` environment
+    /// variable, falling back to `default` (the file value, or an empty registry where every
+    /// app produces a `uint256`).
+    ///
+    /// # Returns
+    /// A `ResultTypes` registry keyed by client app id.
+    fn get_result_types(default: ResultTypes) -> ResultTypes {
+        match env::var("CLIENT_APP_RESULT_TYPES") {
+            Ok(value) => value.parse().unwrap_or_else(|e| {
+                warn!("Ignoring invalid CLIENT_APP_RESULT_TYPES: {:?}", e);
+                default
+            }),
+            Err(_) => default,
+        }
+    }
+
+    /// Parses a comma separated list of client app ids from the environment variable `key`,
+    /// such as the `CLIENT_APP_ALLOWLIST`, falling back to `default` (the file value, or an
+    /// empty list) when it is unset or invalid.
+    ///
+    /// # Returns
+    /// The list of client app ids.
+    fn get_app_ids(key: &str, default: Vec<AppId>) -> Vec<AppId> {
+        match env::var(key) {
+            Ok(value) => value
+                .split(',')
+                .filter(|app_id| !app_id.trim().is_empty())
+                .map(|app_id| AppId::from_str(app_id.trim()))
+                .collect::<Result<_, _>>()
+                .unwrap_or_else(|e| {
+                    warn!("Ignoring invalid {}: {:?}", key, e);
+                    default
+                }),
+            Err(_) => default,
+        }
+    }
+
+    /// Determines the default Docker socket path based on the operating system:
+    /// - macOS: `$HOME/.colima/docker.sock`
+    /// - Linux: `/var/run/docker.sock`
     ///
     /// # Returns
     /// A `String` representing the Docker socket path.
     fn get_docker_sock_path() -> String {
-        let default_path = if cfg!(target_os = "macos") {
+        if cfg!(target_os = "macos") {
             let home_dir = Self::get_home_dir();
-            String::from(format!("{}/.colima/docker.sock", home_dir))
+            format!("{}/.colima/docker.sock", home_dir)
         } else {
             String::from("/var/run/docker.sock")
-        };
+        }
+    }
 
-        // Override with DOCKER_SOCK_PATH if available
-        env::var("DOCKER_SOCK_PATH").unwrap_or(default_path)
+    /// Parses a `DOCKER_HOST` URL, using TLS for `https://` URLs or when `tls_verify` is set.
+    ///
+    /// # Returns
+    /// The remote daemon to connect to, or `None` for a `unix://` URL, which only overrides
+    /// the socket path.
+    ///
+    /// # Errors
+    /// Returns an error if the URL scheme isn't supported.
+    fn parse_docker_host(
+        url: &str,
+        tls_verify: bool,
+        cert_path: PathBuf,
+    ) -> Result<Option<DockerHost>> {
+        if url.starts_with("unix://") {
+            return Ok(None);
+        }
+
+        let tcp_address = url
+            .strip_prefix("tcp://")
+            .or_else(|| url.strip_prefix("http://"));
+        match (tcp_address, url.strip_prefix("https://")) {
+            (Some(address), _) if !tls_verify => Ok(Some(DockerHost::Tcp(address.to_string()))),
+            (Some(address), _) | (None, Some(address)) => Ok(Some(DockerHost::Tls {
+                address: address.to_string(),
+                cert_path,
+            })),
+            (None, None) => Err(eyre!(
+                "Unsupported DOCKER_HOST {:?}, expected a unix://, tcp://, http:// or https:// URL",
+                url
+            )),
+        }
+    }
+
+    /// Checks that the Docker socket can be reached, which only matters to the commands using
+    /// Docker: `status` runs without a Docker daemon.
+    ///
+    /// # Errors
+    /// Returns an error naming the socket path if it doesn't exist or isn't a unix socket, unless
+    /// a remote daemon is used.
+    pub fn validate_docker(&self) -> Result<()> {
+        match self.docker_host {
+            Some(_) => Ok(()),
+            None => Self::validate_docker_sock_path(&self.docker_sock_path),
+        }
+    }
+
+    /// Checks that `path` is a unix socket, so that a missing Docker daemon is reported with a
+    /// hint rather than as an opaque connection error.
+    ///
+    /// # Errors
+    /// Returns an error naming `path` if it doesn't exist or isn't a socket.
+    fn validate_docker_sock_path(path: &str) -> Result<()> {
+        let metadata = fs::metadata(path).map_err(|e| {
+            eyre!(
+                "Docker socket not found at {} ({}). Make sure Docker is running (`colima start` \
+                 on macOS), or set DOCKER_SOCK_PATH to the socket of your Docker daemon",
+                path,
+                e
+            )
+        })?;
+
+        if !Self::is_socket(&metadata) {
+            return Err(eyre!(
+                "{} is not a unix socket. Set DOCKER_SOCK_PATH to the socket of your Docker \
+                 daemon (e.g. /var/run/docker.sock, or $HOME/.colima/docker.sock on macOS)",
+                path
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Whether `metadata` is the one of a unix socket.
+    #[cfg(unix)]
+    fn is_socket(metadata: &fs::Metadata) -> bool {
+        use std::os::unix::fs::FileTypeExt;
+
+        metadata.file_type().is_socket()
+    }
+
+    /// Other platforms reach Docker through named pipes, so only the path's existence is checked.
+    #[cfg(not(unix))]
+    fn is_socket(_metadata: &fs::Metadata) -> bool {
+        true
+    }
+
+    /// Determines the default path of the registration salt store.
+    ///
+    /// The store lives under the user's configuration directory, or under the home directory
+    /// when that cannot be determined.
+    ///
+    /// # Returns
+    /// A `PathBuf` ending in `avsthon/registration_salts.json`.
+    fn get_salt_store_path() -> PathBuf {
+        config_dir()
+            .unwrap_or_else(|| PathBuf::from(Self::get_home_dir()))
+            .join("avsthon")
+            .join(SALT_STORE_FILE_NAME)
     }
 
     /// Retrieves the user's home directory.
@@ -97,3 +1356,258 @@ impl OperatorConfig {
             .unwrap_or_else(|| env::var("HOME").unwrap_or(".".to_string()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // First account of Anvil
+    const PRIVATE_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    // A valid configuration, reaching Docker over TCP so no socket is needed
+    fn test_config() -> OperatorConfig {
+        OperatorConfig::load(
+            &Chain::Anvil,
+            Some(PRIVATE_KEY),
+            FileConfig {
+                docker_host: Some("tcp://127.0.0.1:2375".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_validate() {
+        test_config().validate().unwrap();
+
+        // Each problem, along with a change to a valid configuration that triggers it
+        type Invalidate = fn(&mut OperatorConfig);
+        let cases: Vec<(&str, Invalidate)> = vec![
+            ("At least one aggregator URL is required", |config| {
+                config.aggregator_urls.clear()
+            }),
+            ("must be an http:// or https:// URL", |config| {
+                config.aggregator_urls = vec!["ftp://aggregator".to_string()]
+            }),
+            ("Invalid aggregator URL", |config| {
+                config.aggregator_urls = vec!["aggregator".to_string()]
+            }),
+            ("operator_name is set but empty", |config| {
+                config.operator_name = Some(" ".to_string())
+            }),
+            ("status_admin_token is set but empty", |config| {
+                config.status_admin_token = Some(String::new())
+            }),
+            (
+                "container_memory_mb must be 0 or at least 6 MiB",
+                |config| config.container_memory_mb = 1,
+            ),
+            ("container_memory_mb is too large", |config| {
+                config.container_memory_mb = u64::MAX
+            }),
+            ("container_cpus must be 0 or at least 0.01", |config| {
+                config.container_cpus = 0.001
+            }),
+            ("CPUs of the host", |config| {
+                config.docker_host = None;
+                config.container_cpus = 1e6;
+            }),
+            ("Invalid container_platform", |config| {
+                config.container_platform = "linux".to_string()
+            }),
+            ("Unsupported response_version", |config| {
+                config.response_version = u8::MAX
+            }),
+            ("local_runs must be positive", |config| {
+                config.local_runs = 0
+            }),
+            ("image_pull_max_attempts must be positive", |config| {
+                config.image_pull_max_attempts = 0
+            }),
+            ("metadata_max_attempts must be positive", |config| {
+                config.metadata_max_attempts = 0
+            }),
+            ("max_container_log_size must be positive", |config| {
+                config.max_container_log_size = 0
+            }),
+            ("container_cpus must be 0 or positive", |config| {
+                config.container_cpus = -1.0
+            }),
+            ("result_cache_size must be positive", |config| {
+                config.result_cache_apps = vec![AppId::default()];
+                config.result_cache_size = 0;
+            }),
+            ("circuit_breaker_cooldown_secs must be positive", |config| {
+                config.circuit_breaker_threshold = 1;
+                config.circuit_breaker_cooldown_secs = 0;
+            }),
+            ("Container timeout of", |config| {
+                config.run_options.insert(
+                    AppId::default(),
+                    RunOptions {
+                        timeout_secs: Some(0),
+                        ..Default::default()
+                    },
+                );
+            }),
+            ("must be between 1 and 3600 seconds, got 3601", |config| {
+                config.run_options.insert(
+                    AppId::default(),
+                    RunOptions {
+                        timeout_secs: Some(3601),
+                        ..Default::default()
+                    },
+                );
+            }),
+            ("does not exist", |config| {
+                config.aggregator_ca_cert_path = Some(PathBuf::from("/nonexistent/ca.pem"))
+            }),
+        ];
+        for (problem, invalidate) in cases {
+            let mut config = test_config();
+            invalidate(&mut config);
+            let error = config.validate().unwrap_err().to_string();
+            assert!(
+                error.contains(problem),
+                "expected {:?} in {:?}",
+                problem,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_docker_host() -> Result<()> {
+        let cert_path = PathBuf::from("/certs");
+        let parse = |url: &str, tls_verify: bool| {
+            OperatorConfig::parse_docker_host(url, tls_verify, cert_path.clone())
+        };
+        let tls = |address: &str| {
+            Some(DockerHost::Tls {
+                address: address.to_string(),
+                cert_path: cert_path.clone(),
+            })
+        };
+
+        // A unix socket only overrides the socket path
+        assert_eq!(parse("unix:///var/run/docker.sock", true)?, None);
+        assert_eq!(
+            parse("tcp://docker:2375", false)?,
+            Some(DockerHost::Tcp("docker:2375".to_string()))
+        );
+        assert_eq!(
+            parse("http://docker:2375", false)?,
+            Some(DockerHost::Tcp("docker:2375".to_string()))
+        );
+
+        // TLS is used for https:// hosts, and for TCP ones when DOCKER_TLS_VERIFY is set
+        assert_eq!(parse("https://docker:2376", false)?, tls("docker:2376"));
+        assert_eq!(parse("tcp://docker:2376", true)?, tls("docker:2376"));
+
+        let error = parse("ssh://user@docker", false).unwrap_err().to_string();
+        assert!(error.contains("Unsupported DOCKER_HOST"), "{}", error);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_env_or() -> Result<()> {
+        assert_eq!(OperatorConfig::env_or("OPERATOR_TEST_ENV_OR", 7u64)?, 7);
+        env::set_var("OPERATOR_TEST_ENV_OR", "42");
+        assert_eq!(OperatorConfig::env_or("OPERATOR_TEST_ENV_OR", 7u64)?, 42);
+
+        // A value that doesn't parse fails the configuration rather than using the default
+        env::set_var("OPERATOR_TEST_ENV_OR", "forty-two");
+        let error = OperatorConfig::env_or("OPERATOR_TEST_ENV_OR", 7u64)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("Invalid value for OPERATOR_TEST_ENV_OR: \"forty-two\""),
+            "{}",
+            error
+        );
+        env::remove_var("OPERATOR_TEST_ENV_OR");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_signer() -> Result<()> {
+        // Raw private keys are only accepted on Anvil
+        let signer = OperatorConfig::load_signer(&Chain::Anvil, Some(PRIVATE_KEY), None)?;
+        assert!(OperatorConfig::load_signer(&Chain::Anvil, None, None).is_err());
+        let error = OperatorConfig::load_signer(&Chain::Holesky, Some(PRIVATE_KEY), None)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("OPERATOR_KEYSTORE_PATH must be set"),
+            "{}",
+            error
+        );
+
+        // A keystore is used on every chain, over any private key given
+        let dir = env::temp_dir().join(format!("operator_keystore_{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let (_, name) = PrivateKeySigner::encrypt_keystore(
+            &dir,
+            &mut rand::thread_rng(),
+            signer.credential().to_bytes(),
+            "passphrase",
+            None,
+        )?;
+        env::set_var("OPERATOR_KEYSTORE_PASSWORD", "passphrase");
+        let keystore =
+            OperatorConfig::load_signer(&Chain::Holesky, Some("0x01"), Some(dir.join(&name)))?;
+        assert_eq!(keystore.address(), signer.address());
+
+        env::set_var("OPERATOR_KEYSTORE_PASSWORD", "wrong");
+        let error = OperatorConfig::load_signer(&Chain::Holesky, None, Some(dir.join(&name)))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Failed to decrypt keystore"), "{}", error);
+        env::remove_var("OPERATOR_KEYSTORE_PASSWORD");
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_salt_store() {
+        // A salt store in directories yet to be created is fine
+        let mut config = test_config();
+        config.salt_store_path = env::temp_dir().join("avsthon_missing/nested/salts.json");
+        config.validate_salt_store().unwrap();
+
+        // Only registering needs the salt store, other commands run without a writable directory
+        config.salt_store_path = env::current_exe().unwrap().join("salts.json");
+        config.validate().unwrap();
+        let error = config.validate_salt_store().unwrap_err().to_string();
+        assert!(error.contains("is not a writable directory"), "{}", error);
+    }
+
+    #[test]
+    fn test_validate_docker() {
+        // A remote daemon needs no socket
+        let mut config = test_config();
+        config.docker_sock_path = "/nonexistent/docker.sock".to_string();
+        config.validate_docker().unwrap();
+
+        // The socket is only required by the commands using Docker
+        config.docker_host = None;
+        config.validate().unwrap();
+        let error = config.validate_docker().unwrap_err().to_string();
+        assert!(error.contains("Docker socket not found at /nonexistent/docker.sock"));
+
+        config.docker_sock_path = env::current_exe().unwrap().display().to_string();
+        let error = config.validate_docker().unwrap_err().to_string();
+        assert!(error.contains("is not a unix socket"), "{}", error);
+
+        let socket_path = env::temp_dir().join(format!("docker_{}.sock", std::process::id()));
+        let _ = fs::remove_file(&socket_path);
+        let _listener = std::os::unix::net::UnixListener::bind(&socket_path).unwrap();
+        config.docker_sock_path = socket_path.display().to_string();
+        config.validate_docker().unwrap();
+        fs::remove_file(&socket_path).unwrap();
+    }
+}
@@ -0,0 +1,134 @@
+use alloy_primitives::B256;
+use contract_bindings::AppId;
+use lru::LruCache;
+use std::{collections::HashSet, num::NonZeroUsize, sync::Mutex};
+
+use crate::docker_client::ContainerRun;
+
+/// `ResultCacheKey` identifies the output of a task: the same client app, run on the same input
+/// with the same image, always produces the same output when the app is deterministic.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct ResultCacheKey {
+    /// The client app the task was requested for.
+    pub app_id: AppId,
+    /// keccak256 of the task input: the ABI encoded task request, along with the run options
+    /// the container is started with.
+    pub input_hash: B256,
+    /// Content-addressed id of the local image the task ran with, which changes whenever the
+    /// tag is moved to another image.
+    pub image_id: String,
+}
+
+/// `ResultCache` keeps the output of the latest tasks of deterministic client apps, so that
+/// identical tasks requested again reuse it instead of running the container once more.
+///
+/// Only apps opted in are cached, as the output of a nondeterministic app must be computed for
+/// every task. Only outputs of runs exiting with code 0 and valid for the app's result type are
+/// stored, and they are signed again for each task that reuses them.
+#[derive(Debug)]
+pub(super) struct ResultCache {
+    /// Client apps whose results may be reused.
+    apps: HashSet<AppId>,
+    /// The cached outputs, least recently used first out.
+    entries: Mutex<LruCache<ResultCacheKey, ContainerRun>>,
+}
+
+impl ResultCache {
+    /// Creates a cache of up to `capacity` outputs of the `apps` client apps.
+    ///
+    /// # Returns
+    /// `None` when `capacity` is 0 or no app is opted in, disabling the cache.
+    pub fn new(apps: HashSet<AppId>, capacity: usize) -> Option<Self> {
+        let capacity = NonZeroUsize::new(capacity).filter(|_| !apps.is_empty())?;
+        Some(Self {
+            apps,
+            entries: Mutex::new(LruCache::new(capacity)),
+        })
+    }
+
+    /// Whether the results of `app_id` may be reused.
+    pub fn caches(&self, app_id: &AppId) -> bool {
+        self.apps.contains(app_id)
+    }
+
+    /// Returns the output cached for `key`, if any.
+    pub fn get(&self, key: &ResultCacheKey) -> Option<ContainerRun> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    /// Caches the output of the task identified by `key`, if its app is opted in and the run
+    /// exited with code 0. A failed run may succeed when retried, so it is never reused.
+    pub fn put(&self, key: ResultCacheKey, run: ContainerRun) {
+        if self.caches(&key.app_id) && run.exit_code == 0 {
+            self.entries.lock().unwrap().put(key, run);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::FixedBytes;
+    use std::time::Duration;
+
+    fn key(app_id: AppId, image_id: &str) -> ResultCacheKey {
+        ResultCacheKey {
+            app_id,
+            input_hash: B256::repeat_byte(1),
+            image_id: image_id.to_string(),
+        }
+    }
+
+    fn run(stdout: &str) -> ContainerRun {
+        ContainerRun {
+            stdout: stdout.to_string(),
+            exit_code: 0,
+            duration: Duration::from_millis(10),
+        }
+    }
+
+    #[test]
+    fn test_result_cache() {
+        let cached_app = AppId(FixedBytes::repeat_byte(1));
+        let other_app = AppId(FixedBytes::repeat_byte(2));
+        assert!(ResultCache::new(HashSet::from([cached_app]), 0).is_none());
+        assert!(ResultCache::new(HashSet::new(), 2).is_none());
+
+        let cache = ResultCache::new(HashSet::from([cached_app]), 2).unwrap();
+
+        // Apps not opted in are never cached
+        cache.put(key(other_app, "sha256:a"), run("1"));
+        assert_eq!(cache.get(&key(other_app, "sha256:a")), None);
+
+        // Nor are failed runs
+        cache.put(
+            key(cached_app, "sha256:a"),
+            ContainerRun {
+                exit_code: 1,
+                ..run("1")
+            },
+        );
+        assert_eq!(cache.get(&key(cached_app, "sha256:a")), None);
+
+        // Another input is another entry
+        cache.put(key(cached_app, "sha256:a"), run("1"));
+        let other_input = ResultCacheKey {
+            input_hash: B256::repeat_byte(2),
+            ..key(cached_app, "sha256:a")
+        };
+        assert_eq!(cache.get(&other_input), None);
+
+        // A new image invalidates the outputs of the previous one
+        cache.put(key(cached_app, "sha256:a"), run("1"));
+        assert_eq!(cache.get(&key(cached_app, "sha256:a")), Some(run("1")));
+        assert_eq!(cache.get(&key(cached_app, "sha256:b")), None);
+
+        // The least recently used output is evicted first
+        cache.put(key(cached_app, "sha256:b"), run("2"));
+        cache.get(&key(cached_app, "sha256:a"));
+        cache.put(key(cached_app, "sha256:c"), run("3"));
+        assert_eq!(cache.get(&key(cached_app, "sha256:a")), Some(run("1")));
+        assert_eq!(cache.get(&key(cached_app, "sha256:b")), None);
+        assert_eq!(cache.get(&key(cached_app, "sha256:c")), Some(run("3")));
+    }
+}
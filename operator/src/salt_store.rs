@@ -0,0 +1,103 @@
+use alloy_primitives::B256;
+use eyre::{Result, WrapErr};
+use rand::Rng;
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// `SaltStore` persists the salts the operator signed AVS registrations with, so that a
+/// registration retried after a crash or a partial failure never reuses a salt that may already
+/// have been spent on-chain, which would make it revert.
+///
+/// Every new salt is written to disk before it is handed out, through a temporary file and a
+/// rename, so a crash right after a registration is broadcast still leaves its salt recorded.
+#[derive(Debug)]
+pub(super) struct SaltStore {
+    /// Location of the JSON file backing the store.
+    path: PathBuf,
+    /// In-memory copy of the persisted salts.
+    salts: Mutex<HashSet<B256>>,
+}
+
+impl SaltStore {
+    /// Opens the store at `path`, loading its contents if the file already exists. Missing
+    /// parent directories are created.
+    ///
+    /// # Errors
+    /// Returns an error if the file exists but cannot be read or parsed, or if its directory
+    /// cannot be created.
+    pub fn open(path: &Path) -> Result<Self> {
+        let salts = if path.exists() {
+            let contents =
+                fs::read(path).wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+            serde_json::from_slice(&contents)
+                .wrap_err_with(|| format!("Failed to parse {}", path.display()))?
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .wrap_err_with(|| format!("Failed to create {}", parent.display()))?;
+            }
+            HashSet::new()
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            salts: Mutex::new(salts),
+        })
+    }
+
+    /// Generates a random salt that was never handed out before, and persists it.
+    ///
+    /// # Errors
+    /// Returns an error if the salt cannot be persisted, in which case it must not be used.
+    pub fn new_salt(&self) -> Result<B256> {
+        let mut salts = self.salts.lock().unwrap();
+        let salt = loop {
+            let salt = B256::from(rand::thread_rng().gen::<[u8; 32]>());
+            if salts.insert(salt) {
+                break salt;
+            }
+        };
+        self.flush(&salts)?;
+        Ok(salt)
+    }
+
+    /// Atomically replaces the file on disk with `salts`.
+    fn flush(&self, salts: &HashSet<B256>) -> Result<()> {
+        let contents = serde_json::to_vec(salts)?;
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, contents)
+            .wrap_err_with(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &self.path)
+            .wrap_err_with(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::Result;
+
+    #[test]
+    fn test_salts_survive_restarts() -> Result<()> {
+        let path = std::env::temp_dir()
+            .join(format!("salt_store_{}", std::process::id()))
+            .join("salts.json");
+
+        let store = SaltStore::open(&path)?;
+        let first = store.new_salt()?;
+        let second = store.new_salt()?;
+        assert_ne!(first, second);
+
+        // A restarted operator knows every salt handed out before
+        let reopened = SaltStore::open(&path)?;
+        let salts = reopened.salts.lock().unwrap().clone();
+        fs::remove_dir_all(path.parent().unwrap())?;
+
+        assert_eq!(salts, HashSet::from([first, second]));
+        Ok(())
+    }
+}
@@ -0,0 +1,311 @@
+use alloy_primitives::keccak256;
+use axum::{
+    extract::{ConnectInfo, Path, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    routing::{get, post},
+    Json, Router,
+};
+use contract_bindings::{AppId, TaskId};
+use eyre::{Result, WrapErr};
+use serde_json::json;
+use std::{
+    collections::HashSet,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+use tokio::net::TcpListener;
+use tracing::info;
+
+use crate::{Operator, GIT_SHA, VERSION};
+
+// Counters of the operator's activity since it started, served by the status server
+#[derive(Default)]
+pub(super) struct OperatorStats {
+    // Tasks whose result was accepted by an aggregator
+    pub tasks_processed: AtomicU64,
+    // Block of the latest `TaskRequested` event received, 0 until the first one
+    pub last_event_block: AtomicU64,
+    // Client apps whose image was pulled
+    pub cached_images: Mutex<HashSet<AppId>>,
+}
+
+impl OperatorStats {
+    // Record the block of a `TaskRequested` event, events may arrive out of order
+    pub fn record_event_block(&self, block_number: u64) {
+        self.last_event_block
+            .fetch_max(block_number, Ordering::Relaxed);
+    }
+}
+
+// Serve the health and stats endpoints of the operator on `addr`, so orchestration can probe
+// it the same way it probes the aggregator, along with the manual prefetch of a client app and
+// cancellation of a task, which require the admin token or a request from the loopback interface.
+// The operator is shared by every request rather than cloned for each of them.
+pub(super) async fn serve(operator: Arc<Operator>, addr: SocketAddr) -> Result<()> {
+    let app = Router::new()
+        .route("/health", get(handle_health))
+        .route("/stats", get(handle_stats))
+        .route("/prefetch/:app_id", post(handle_prefetch))
+        .route("/cancel/:task_id", post(handle_cancel))
+        .with_state(operator);
+
+    let listener = TcpListener::bind(addr)
+        .await
+        .wrap_err_with(|| format!("Failed to bind the status server to {}", addr))?;
+
+    info!("Status server listening on {}", addr);
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .wrap_err("Status server error")
+}
+
+// Handler for GET /health endpoint, the operator can't run tasks without Docker
+async fn handle_health(
+    State(operator): State<Arc<Operator>>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let docker = operator.docker.ping().await.is_ok();
+    let status = match docker {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    (
+        status,
+        Json(json!({
+            "healthy": docker,
+            "docker": docker,
+            "version": VERSION,
+            "git_sha": GIT_SHA,
+        })),
+    )
+}
+
+// Handler for GET /stats endpoint
+async fn handle_stats(State(operator): State<Arc<Operator>>) -> Json<serde_json::Value> {
+    let stats = &operator.stats;
+    // Probed before locking anything, the guards must not be held across the await
+    let docker_connected = operator.docker.ping().await.is_ok();
+    let tasks_in_flight = operator.running_tasks.lock().unwrap().len();
+    let images_cached = stats.cached_images.lock().unwrap().len();
+    let circuit_breakers_open = operator
+        .circuit_breaker
+        .as_ref()
+        .map(|circuit_breaker| circuit_breaker.open_apps(Instant::now()))
+        .unwrap_or_default();
+    let last_event_block = match stats.last_event_block.load(Ordering::Relaxed) {
+        0 => None,
+        block_number => Some(block_number),
+    };
+
+    Json(json!({
+        "tasks_processed": stats.tasks_processed.load(Ordering::Relaxed),
+        "tasks_in_flight": tasks_in_flight,
+        "last_event_block": last_event_block,
+        "images_cached": images_cached,
+        "docker_connected": docker_connected,
+        "circuit_breakers_open": circuit_breakers_open,
+    }))
+}
+
+// Handler for POST /prefetch/:app_id endpoint, pulling the image of a client app again, e.g.
+// once it was fixed, which also resumes its tasks if its circuit breaker is open
+async fn handle_prefetch(
+    State(operator): State<Arc<Operator>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(app_id): Path<AppId>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if !authorized(&headers, peer, operator.status_admin_token.as_deref()) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": "Unauthorized" })),
+        );
+    }
+    if !operator.serves_client_app(&app_id) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "ClientApp not in the allowlist" })),
+        );
+    }
+
+    match operator.prefetch_client_app(app_id).await {
+        Ok(()) => (StatusCode::OK, Json(json!({ "app_id": app_id }))),
+        Err(e) => (
+            StatusCode::BAD_GATEWAY,
+            Json(json!({ "app_id": app_id, "error": format!("{:#}", e) })),
+        ),
+    }
+}
+
+// Handler for POST /cancel/:task_id endpoint, cancelling a running task, which is then reported
+// as failed to the aggregator
+async fn handle_cancel(
+    State(operator): State<Arc<Operator>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(task_id): Path<TaskId>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if !authorized(&headers, peer, operator.status_admin_token.as_deref()) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": "Unauthorized" })),
+        );
+    }
+
+    match operator.cancel_task(task_id) {
+        true => (StatusCode::OK, Json(json!({ "task_id": task_id }))),
+        false => (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "Task not running" })),
+        ),
+    }
+}
+
+// Whether a request may trigger a prefetch or a cancellation: it must carry the admin token when one is set, or
+// come from the operator's own host otherwise
+fn authorized(headers: &HeaderMap, peer: SocketAddr, expected: Option<&str>) -> bool {
+    let Some(expected) = expected else {
+        return peer.ip().is_loopback();
+    };
+
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| tokens_match(token, expected))
+}
+
+// Compare a token with the expected one in a time independent of where they differ. Hashing
+// both first also hides the length of the expected token.
+fn tokens_match(token: &str, expected: &str) -> bool {
+    let (token, expected) = (keccak256(token), keccak256(expected));
+    token
+        .iter()
+        .zip(expected.iter())
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::B256;
+    use axum::http::HeaderValue;
+
+    #[tokio::test]
+    async fn test_health_and_stats() {
+        // The test operator's Docker daemon is unreachable
+        let operator =
+            Arc::new(crate::tests::test_operator("http://127.0.0.1:1".to_string()).await);
+
+        let (status, Json(health)) = handle_health(State(operator.clone())).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(health["healthy"], false);
+        assert_eq!(health["version"], VERSION);
+
+        let Json(stats) = handle_stats(State(operator.clone())).await;
+        assert_eq!(stats["tasks_processed"], 0);
+        assert_eq!(stats["tasks_in_flight"], 0);
+        assert!(stats["last_event_block"].is_null());
+        assert_eq!(stats["docker_connected"], false);
+
+        // Events may arrive out of order, only the latest block is reported
+        operator.stats.record_event_block(12);
+        operator.stats.record_event_block(10);
+        operator
+            .stats
+            .tasks_processed
+            .fetch_add(1, Ordering::Relaxed);
+        operator
+            .stats
+            .cached_images
+            .lock()
+            .unwrap()
+            .insert(AppId::default());
+        let (_token, _running) =
+            operator.start_task(TaskId(B256::repeat_byte(1)), AppId::default());
+
+        let Json(stats) = handle_stats(State(operator)).await;
+        assert_eq!(stats["tasks_processed"], 1);
+        assert_eq!(stats["tasks_in_flight"], 1);
+        assert_eq!(stats["last_event_block"], 12);
+        assert_eq!(stats["images_cached"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_authorization() {
+        let mut operator = crate::tests::test_operator("http://127.0.0.1:1".to_string()).await;
+        // Authorized requests for an app outside the allowlist stop short of pulling anything
+        operator.client_app_allowlist = HashSet::from([AppId::from(B256::repeat_byte(1))]);
+        let app_id = AppId::from(B256::repeat_byte(2));
+        let local: SocketAddr = "127.0.0.1:40000".parse().unwrap();
+        let remote: SocketAddr = "203.0.113.7:40000".parse().unwrap();
+        let bearer = |token: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+            );
+            headers
+        };
+        let prefetch = |operator: &Operator, peer: SocketAddr, headers: HeaderMap| {
+            handle_prefetch(
+                State(Arc::new(operator.clone())),
+                ConnectInfo(peer),
+                headers,
+                Path(app_id),
+            )
+        };
+
+        // Without a token, only the loopback interface may prefetch
+        let (status, _) = prefetch(&operator, remote, HeaderMap::new()).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = prefetch(&operator, local, HeaderMap::new()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // With one, every request must carry it, local ones included
+        operator.status_admin_token = Some("secret".to_string());
+        let (status, _) = prefetch(&operator, local, HeaderMap::new()).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = prefetch(&operator, remote, bearer("guess")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = prefetch(&operator, remote, bearer("secret")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_cancel() {
+        let mut operator = crate::tests::test_operator("http://127.0.0.1:1".to_string()).await;
+        operator.status_admin_token = Some("secret".to_string());
+        let task_id = TaskId(B256::repeat_byte(1));
+        let remote: SocketAddr = "203.0.113.7:40000".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        let cancel = |headers: HeaderMap| {
+            handle_cancel(
+                State(Arc::new(operator.clone())),
+                ConnectInfo(remote),
+                headers,
+                Path(task_id),
+            )
+        };
+
+        // Only running tasks can be cancelled, by authorized requests
+        let (status, _) = cancel(headers.clone()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (token, _running) = operator.start_task(task_id, AppId::default());
+        let (status, _) = cancel(HeaderMap::new()).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert!(!token.is_cancelled());
+        let (status, _) = cancel(headers).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(token.is_cancelled());
+    }
+}